@@ -0,0 +1,114 @@
+use std::{path::PathBuf, time::Duration};
+use clap::Args;
+use packster_core::{
+    Result, Error,
+    application::path::Absolute,
+    packaging::application::{PackageCacheRequest, PruneCacheRequest, VerifyCacheRequest},
+};
+use crate::{config::to_cache_path, lock::LockArgs, parse::{parse_duration, parse_size, try_from_current_dir}};
+
+#[derive(Args)]
+pub struct CacheArg {
+    /// Directory where package files are cached, keyed by checksum
+    #[arg(long, value_name = "DIRECTORY", env = "PACKSTER_CACHE", value_parser=try_from_current_dir)]
+    pub cache: Option<Absolute<PathBuf>>,
+}
+
+impl CacheArg {
+    pub fn to_cache_path(&self) -> Result<Absolute<PathBuf>> {
+        match &self.cache {
+            Some(cache) => Ok(cache.clone()),
+            None => Ok(to_cache_path()?),
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct CacheLimitArgs {
+    #[command(flatten)]
+    pub cache: CacheArg,
+    /// Size the cache is pruned to once packages are stored, least recently used first
+    #[arg(long, value_name = "SIZE", env = "PACKSTER_CACHE_MAX_SIZE", value_parser=parse_size)]
+    pub cache_max_size: Option<u64>,
+}
+
+impl CacheLimitArgs {
+    pub fn to_prune_request(&self) -> Result<Option<PruneCacheRequest>> {
+        match self.cache_max_size {
+            Some(max_size) => Ok(Some(PruneCacheRequest::new(self.cache.to_cache_path()?).with_max_size(max_size))),
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ListCacheCommand {
+    #[command(flatten)]
+    pub cache: CacheArg,
+}
+
+impl TryFrom<ListCacheCommand> for PackageCacheRequest {
+    type Error = Error;
+    fn try_from(command: ListCacheCommand) -> Result<PackageCacheRequest> {
+        Ok(PackageCacheRequest::new(command.cache.to_cache_path()?))
+    }
+}
+
+#[derive(Args)]
+pub struct PruneCacheCommand {
+    #[command(flatten)]
+    pub cache: CacheArg,
+    /// Remove packages unused for this duration, as a number followed by s, m, h, d or w
+    #[arg(long, value_name = "DURATION", value_parser=parse_duration)]
+    pub older_than: Option<Duration>,
+    /// Remove the least recently used packages until the cache fits in this size, in bytes or followed by K, M or G
+    #[arg(long, value_name = "SIZE", value_parser=parse_size)]
+    pub max_size: Option<u64>,
+    /// List the packages which would be removed, without removing them
+    #[arg(long)]
+    pub dry_run: bool,
+    #[command(flatten)]
+    pub lock: LockArgs,
+}
+
+impl TryFrom<PruneCacheCommand> for PruneCacheRequest {
+    type Error = Error;
+    fn try_from(command: PruneCacheCommand) -> Result<PruneCacheRequest> {
+        let mut request = PruneCacheRequest::new(command.cache.to_cache_path()?);
+        if let Some(older_than) = command.older_than {
+            request = request.with_unused_for(older_than);
+        }
+        if let Some(max_size) = command.max_size {
+            request = request.with_max_size(max_size);
+        }
+        Ok(request)
+    }
+}
+
+#[derive(Args)]
+pub struct VerifyCacheCommand {
+    #[command(flatten)]
+    pub cache: CacheArg,
+    /// Remove the damaged packages from the cache
+    #[arg(long)]
+    pub remove: bool,
+    #[command(flatten)]
+    pub lock: LockArgs,
+}
+
+impl TryFrom<VerifyCacheCommand> for VerifyCacheRequest {
+    type Error = Error;
+    fn try_from(command: VerifyCacheCommand) -> Result<VerifyCacheRequest> {
+        Ok(VerifyCacheRequest::new(command.cache.to_cache_path()?))
+    }
+}
+
+/* Elapsed seconds in their largest whole unit */
+pub fn to_age(seconds: u64) -> String {
+    match seconds {
+        seconds if seconds >= 24 * 60 * 60 => format!("{}d", seconds / (24 * 60 * 60)),
+        seconds if seconds >= 60 * 60 => format!("{}h", seconds / (60 * 60)),
+        seconds if seconds >= 60 => format!("{}m", seconds / 60),
+        seconds => format!("{seconds}s"),
+    }
+}
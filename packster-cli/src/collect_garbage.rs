@@ -0,0 +1,21 @@
+use clap::Args;
+use packster_core::{Result, Error, packaging::application::CollectGarbageRequest};
+use crate::{location::LocationArg, lock::LockArgs};
+
+#[derive(Args)]
+pub struct CollectGarbageCommand {
+    #[command(flatten)]
+    pub location: LocationArg,
+    /// List the directories which would be removed, without removing them
+    #[arg(long)]
+    pub dry_run: bool,
+    #[command(flatten)]
+    pub lock: LockArgs,
+}
+
+impl TryFrom<CollectGarbageCommand> for CollectGarbageRequest {
+    type Error = Error;
+    fn try_from(command: CollectGarbageCommand) -> Result<CollectGarbageRequest> {
+        Ok(CollectGarbageRequest::new(command.location.to_location_path()?))
+    }
+}
@@ -0,0 +1,32 @@
+use std::{env, path::PathBuf};
+use packster_core::{application::path::Absolute, packaging::{PACKAGE_CACHE_DIRECTORY_NAME, USER_CONFIG_DIRECTORY_NAME, USER_CONFIG_NAME}};
+
+/* The user config lives in the XDG config directory, falling back to the current directory */
+pub fn to_user_config_path() -> std::io::Result<Absolute<PathBuf>> {
+    let config_directory = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .filter(|path| path.is_absolute());
+    Ok(
+        match config_directory {
+            Some(config_directory) => Absolute::assume_absolute(config_directory.join(USER_CONFIG_DIRECTORY_NAME).join(USER_CONFIG_NAME)),
+            None => Absolute::assume_absolute(env::current_dir()?.join(USER_CONFIG_NAME)),
+        }
+    )
+}
+
+/* Downloaded packages are kept in the XDG cache directory, falling back to the current directory */
+pub fn to_cache_path() -> std::io::Result<Absolute<PathBuf>> {
+    let cache_directory = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .filter(|path| path.is_absolute());
+    Ok(
+        match cache_directory {
+            Some(cache_directory) => Absolute::assume_absolute(cache_directory.join(USER_CONFIG_DIRECTORY_NAME)),
+            None => Absolute::assume_absolute(env::current_dir()?.join(PACKAGE_CACHE_DIRECTORY_NAME)),
+        }
+    )
+}
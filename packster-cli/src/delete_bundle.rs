@@ -0,0 +1,21 @@
+use std::path::PathBuf;
+use clap::Args;
+use packster_core::{Result, Error, application::path::Absolute, packaging::application::DeleteBundleRequest};
+use crate::{location::LocationArg, lock::LockArgs, parse::try_from_current_dir};
+
+#[derive(Args)]
+pub struct DeleteBundleCommand {
+    #[arg(value_parser=try_from_current_dir)]
+    pub bundle_file: Absolute<PathBuf>,
+    #[command(flatten)]
+    pub location: LocationArg,
+    #[command(flatten)]
+    pub lock: LockArgs,
+}
+
+impl TryFrom<DeleteBundleCommand> for DeleteBundleRequest {
+    type Error = Error;
+    fn try_from(command: DeleteBundleCommand) -> Result<DeleteBundleRequest> {
+        Ok(DeleteBundleRequest::new(command.bundle_file, command.location.to_location_path()?))
+    }
+}
@@ -1,18 +1,38 @@
 use std::path::PathBuf;
 use clap::Args;
-use packster_core::{application::path::Absolute, packaging::application::DeployRequest};
-use crate::parse::try_from_current_dir;
+use packster_core::{Result, Error, application::path::Absolute, domain::entity::ParameterValue, packaging::application::{CachePackageRequest, DeployRequest}};
+use crate::{cache::CacheLimitArgs, location::LocationArg, lock::LockArgs, parse::{parse_parameter, try_from_current_dir}};
 
 #[derive(Args)]
 pub struct DeployFileCommand {
     #[arg(value_parser=try_from_current_dir)]
     pub package_file: Absolute<PathBuf>,
-    #[arg(value_parser=try_from_current_dir)]
-    pub location_directory: Absolute<PathBuf>,
+    #[command(flatten)]
+    pub location: LocationArg,
+    #[arg(short, long = "parameter", value_name = "KEY=VALUE", value_parser=parse_parameter)]
+    pub parameters: Vec<(String, ParameterValue)>,
+    #[command(flatten)]
+    pub cache: CacheLimitArgs,
+    #[command(flatten)]
+    pub lock: LockArgs,
 }
 
-impl From<DeployFileCommand> for DeployRequest {
-    fn from(command: DeployFileCommand) -> DeployRequest {
-        DeployRequest::new(command.package_file, command.location_directory)
+impl TryFrom<DeployFileCommand> for DeployRequest {
+    type Error = Error;
+    fn try_from(command: DeployFileCommand) -> Result<DeployRequest> {
+        Ok(
+            DeployRequest::new(
+                command.package_file,
+                command.location.to_location_path()?,
+                command.parameters.into_iter().collect(),
+            )
+        )
     }
-}
\ No newline at end of file
+}
+
+impl TryFrom<&DeployFileCommand> for CachePackageRequest {
+    type Error = Error;
+    fn try_from(command: &DeployFileCommand) -> Result<CachePackageRequest> {
+        Ok(CachePackageRequest::new(command.package_file.clone(), command.cache.cache.to_cache_path()?))
+    }
+}
@@ -0,0 +1,17 @@
+use std::path::PathBuf;
+use clap::Args;
+use packster_core::{application::path::Absolute, packaging::application::IndexSourceRequest};
+use crate::parse::try_from_current_dir;
+
+#[derive(Args)]
+pub struct IndexSourceCommand {
+    /// Directory containing package files
+    #[arg(value_parser=try_from_current_dir)]
+    pub source_directory: Absolute<PathBuf>,
+}
+
+impl From<IndexSourceCommand> for IndexSourceRequest {
+    fn from(command: IndexSourceCommand) -> IndexSourceRequest {
+        IndexSourceRequest::new(command.source_directory)
+    }
+}
@@ -1,16 +1,33 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, str::FromStr};
 use clap::Args;
-use packster_core::{application::path::Absolute, packaging::application::InitLocationRequest};
-use crate::parse::try_from_current_dir;
+use packster_core::{Result, Error, application::path::Absolute, domain::entity::Identifier, packaging::application::{InitLocationRequest, LocationRegistration}};
+use crate::{config::to_user_config_path, lock::LockArgs, parse::try_from_current_dir};
 
 #[derive(Args)]
 pub struct InitLocationCommand {
     #[arg(value_parser=try_from_current_dir, default_value=".")]
     pub location_directory: Absolute<PathBuf>,
+    /// Register the location in the user config under this name
+    #[arg(long)]
+    pub name: Option<String>,
+    /// Use the registered location when no location is given
+    #[arg(long, requires = "name")]
+    pub default: bool,
+    #[command(flatten)]
+    pub lock: LockArgs,
 }
 
-impl From<InitLocationCommand> for InitLocationRequest {
-    fn from(init_location_command: InitLocationCommand) -> InitLocationRequest {
-        InitLocationRequest::new(init_location_command.location_directory)
+impl TryFrom<InitLocationCommand> for InitLocationRequest {
+    type Error = Error;
+    fn try_from(command: InitLocationCommand) -> Result<InitLocationRequest> {
+        let request = InitLocationRequest::new(command.location_directory);
+        Ok(
+            match command.name {
+                Some(name) => request.with_registration(
+                    LocationRegistration::new(to_user_config_path()?, Identifier::from_str(&name)?, command.default)
+                ),
+                None => request,
+            }
+        )
     }
 }
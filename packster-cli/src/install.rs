@@ -0,0 +1,53 @@
+use std::{collections::BTreeSet, str::FromStr};
+use clap::Args;
+use packster_core::{
+    Result, Error,
+    application::operation::Operation,
+    domain::entity::{PackageRequirement, ParameterValue},
+    packaging::application::{InstallRequest, UserConfigRequest},
+};
+use packster_infrastructure::{ConfiguredSource, StdFileSystem, Toml};
+use crate::{cache::CacheLimitArgs, config::to_user_config_path, location::LocationArg, lock::LockArgs, parse::parse_parameter};
+
+#[derive(Args)]
+pub struct InstallCommand {
+    /// Package to install, as identifier@version-requirement
+    pub requirement: String,
+    /// Location directory, or name of a location registered in the user config, the default location being used when omitted
+    #[arg(long, value_name = "LOCATION", env = "PACKSTER_LOCATION")]
+    pub location: Option<String>,
+    /// Only install packages carrying this label
+    #[arg(long = "label", value_name = "LABEL")]
+    pub labels: Vec<String>,
+    /// Parameter of the installed package, dependencies being deployed with their defaults
+    #[arg(short, long = "parameter", value_name = "KEY=VALUE", value_parser=parse_parameter)]
+    pub parameters: Vec<(String, ParameterValue)>,
+    #[command(flatten)]
+    pub cache: CacheLimitArgs,
+    #[command(flatten)]
+    pub lock: LockArgs,
+}
+
+impl TryFrom<&InstallCommand> for InstallRequest {
+    type Error = Error;
+    fn try_from(command: &InstallCommand) -> Result<InstallRequest> {
+        Ok(
+            InstallRequest::new(
+                PackageRequirement::from_str(&command.requirement)?,
+                LocationArg { location: command.location.clone() }.to_location_path()?,
+                command.cache.cache.to_cache_path()?,
+            )
+            .with_labels(command.labels.iter().cloned().collect::<BTreeSet<_>>())
+        )
+    }
+}
+
+/* Sources are queried in the order they were registered */
+pub fn to_registered_sources() -> Result<Vec<ConfiguredSource>> {
+    Operation::new(UserConfigRequest::new(to_user_config_path()?))
+        .parse_user_config(&StdFileSystem, &Toml)?
+        .as_user_config()
+        .iter_sources()
+        .map(ConfiguredSource::new)
+        .collect()
+}
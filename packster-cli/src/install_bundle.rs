@@ -0,0 +1,21 @@
+use std::path::PathBuf;
+use clap::Args;
+use packster_core::{Result, Error, application::path::Absolute, packaging::application::InstallBundleRequest};
+use crate::{location::LocationArg, lock::LockArgs, parse::try_from_current_dir};
+
+#[derive(Args)]
+pub struct InstallBundleCommand {
+    #[arg(value_parser=try_from_current_dir)]
+    pub bundle_file: Absolute<PathBuf>,
+    #[command(flatten)]
+    pub location: LocationArg,
+    #[command(flatten)]
+    pub lock: LockArgs,
+}
+
+impl TryFrom<InstallBundleCommand> for InstallBundleRequest {
+    type Error = Error;
+    fn try_from(command: InstallBundleCommand) -> Result<InstallBundleRequest> {
+        Ok(InstallBundleRequest::new(command.bundle_file, command.location.to_location_path()?))
+    }
+}
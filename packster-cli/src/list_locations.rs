@@ -0,0 +1,13 @@
+use clap::Args;
+use packster_core::{Result, Error, packaging::application::UserConfigRequest};
+use crate::config::to_user_config_path;
+
+#[derive(Args)]
+pub struct ListLocationsCommand {}
+
+impl TryFrom<ListLocationsCommand> for UserConfigRequest {
+    type Error = Error;
+    fn try_from(_: ListLocationsCommand) -> Result<UserConfigRequest> {
+        Ok(UserConfigRequest::new(to_user_config_path()?))
+    }
+}
@@ -0,0 +1,41 @@
+use std::{path::{Path, PathBuf}, str::FromStr};
+use clap::Args;
+use packster_core::{
+    Error, Result,
+    application::{operation::Operation, path::Absolute},
+    domain::entity::Identifier,
+    packaging::application::UserConfigRequest,
+};
+use packster_infrastructure::{StdFileSystem, Toml};
+use crate::{config::to_user_config_path, parse::to_absolute_path};
+
+#[derive(Args)]
+pub struct LocationArg {
+    /// Location directory, or name of a location registered in the user config, the default location being used when omitted
+    #[arg(value_name = "LOCATION", env = "PACKSTER_LOCATION")]
+    pub location: Option<String>,
+}
+
+impl LocationArg {
+    /* A registered name takes precedence over a directory of the same name */
+    pub fn to_location_path(&self) -> Result<Absolute<PathBuf>> {
+        let parse_user_config = || Operation::new(UserConfigRequest::new(to_user_config_path()?))
+            .parse_user_config(&StdFileSystem, &Toml);
+        let path = match &self.location {
+            Some(location) => match Identifier::from_str(location) {
+                Ok(name) => parse_user_config()?
+                    .as_user_config()
+                    .get_location(&name)
+                    .map(Path::to_path_buf)
+                    .unwrap_or_else(|| PathBuf::from(location)),
+                Err(_) => PathBuf::from(location),
+            },
+            None => parse_user_config()?
+                .as_user_config()
+                .get_default_location()
+                .map(|(_, path)| path.to_path_buf())
+                .ok_or(Error::LocationNotGiven)?,
+        };
+        Ok(to_absolute_path(&path)?)
+    }
+}
@@ -0,0 +1,44 @@
+use clap::{Args, ValueEnum};
+use packster_core::{Result, Error, packaging::{application::LocationEnvironmentRequest, domain::Shell}};
+use crate::location::LocationArg;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ShellFormat {
+    Bash,
+    Zsh,
+    Fish,
+    Posix,
+    Json,
+}
+
+impl ShellFormat {
+    /* JSON describes the variables, leaving their activation to the caller */
+    pub fn to_shell(self) -> Option<Shell> {
+        match self {
+            ShellFormat::Bash => Some(Shell::Bash),
+            ShellFormat::Zsh => Some(Shell::Zsh),
+            ShellFormat::Fish => Some(Shell::Fish),
+            ShellFormat::Posix => Some(Shell::Posix),
+            ShellFormat::Json => None,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct LocationEnvironmentCommand {
+    #[command(flatten)]
+    pub location: LocationArg,
+    /// Shell to print the environment script for
+    #[arg(long, value_enum, default_value_t = ShellFormat::Posix)]
+    pub shell: ShellFormat,
+    /// Print the script restoring the variables replaced by the activation
+    #[arg(long)]
+    pub deactivate: bool,
+}
+
+impl TryFrom<LocationEnvironmentCommand> for LocationEnvironmentRequest {
+    type Error = Error;
+    fn try_from(command: LocationEnvironmentCommand) -> Result<LocationEnvironmentRequest> {
+        Ok(LocationEnvironmentRequest::new(command.location.to_location_path()?))
+    }
+}
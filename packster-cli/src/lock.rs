@@ -0,0 +1,15 @@
+use std::time::Duration;
+use clap::Args;
+
+#[derive(Args)]
+pub struct LockArgs {
+    /// Seconds to wait for the locks held by another packster process to be released
+    #[arg(long, value_name = "SECONDS", default_value_t = 0)]
+    pub wait: u64,
+}
+
+impl LockArgs {
+    pub fn to_wait_duration(&self) -> Duration {
+        Duration::from_secs(self.wait)
+    }
+}
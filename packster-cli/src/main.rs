@@ -2,21 +2,40 @@
 #![forbid(unsafe_code)]
 #![warn(clippy::all)]
 
+use std::{path::Path, time::Duration};
 use clap::{Args, Parser, Subcommand};
 use packster_core::{
-    application::operation::{AsChecksum, Operation},
-    packaging::application::*,
-    packaging::domain::*,
-    Result,
+    application::{operation::{AsChecksum, Operation}, path::Absolute, port::{Clock, Lock, Locker, Serializer}},
+    packaging::{application::*, domain::{AsLocation, AsPackage, CachedPackage, Deployment, DeploymentSelector}},
+    Error, Result,
 };
-use packster_infrastructure::{ Json, Sha2Digester, StdFileSystem, TarballArchiver, Toml, UniqidIdentifierGenerator };
+use packster_infrastructure::{ DirectorySource, FileLocker, Json, ProcessExecutor, Sha2Digester, StdFileSystem, SystemClock, TarballArchiver, Toml, UniqidIdentifierGenerator };
 
+mod cache;
+mod collect_garbage;
+mod config;
+mod delete_bundle;
 mod deploy_file;
+mod index_source;
 mod init_location;
+mod install;
+mod install_bundle;
+mod list_locations;
+mod location_environment;
+mod location;
+mod lock;
+mod migrate_location;
 mod pack;
 mod parse;
+mod publish;
+mod register_source;
+mod repair_location;
+mod search_source;
 mod show_location;
 mod undeploy;
+mod upgrade;
+mod use_deployment;
+mod verify_location;
 
 pub const CRATE_VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -44,8 +63,15 @@ struct ProjectArgs {
 #[derive(Subcommand)]
 enum LocationCommand {
     Init(init_location::InitLocationCommand),
+    List(list_locations::ListLocationsCommand),
     Undeploy(undeploy::UndeployCommand),
     Show(show_location::ShowLocationCommand),
+    Migrate(migrate_location::MigrateLocationCommand),
+    Verify(verify_location::VerifyLocationCommand),
+    Gc(collect_garbage::CollectGarbageCommand),
+    Repair(repair_location::RepairLocationCommand),
+    Use(use_deployment::UseDeploymentCommand),
+    Env(location_environment::LocationEnvironmentCommand),
 }
 
 #[derive(Args)]
@@ -57,6 +83,10 @@ struct LocationArgs {
 #[derive(Subcommand)]
 enum PackageCommand {
     Deploy(deploy_file::DeployFileCommand),
+    Upgrade(upgrade::UpgradeCommand),
+    Install(install::InstallCommand),
+    Publish(publish::PublishCommand),
+    Unpublish(publish::UnpublishCommand),
 }
 
 #[derive(Args)]
@@ -65,11 +95,53 @@ struct PackageArgs {
     command: PackageCommand,
 }
 
+#[derive(Subcommand)]
+enum BundleCommand {
+    Install(install_bundle::InstallBundleCommand),
+    Delete(delete_bundle::DeleteBundleCommand),
+}
+
+#[derive(Args)]
+struct BundleArgs {
+    #[command(subcommand)]
+    command: BundleCommand,
+}
+
+#[derive(Subcommand)]
+enum SourceCommand {
+    Search(search_source::SearchSourceCommand),
+    List(search_source::ListSourceCommand),
+    Index(index_source::IndexSourceCommand),
+    Add(register_source::RegisterSourceCommand),
+}
+
+#[derive(Args)]
+struct SourceArgs {
+    #[command(subcommand)]
+    command: SourceCommand,
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    List(cache::ListCacheCommand),
+    Prune(cache::PruneCacheCommand),
+    Verify(cache::VerifyCacheCommand),
+}
+
+#[derive(Args)]
+struct CacheArgs {
+    #[command(subcommand)]
+    command: CacheCommand,
+}
+
 #[derive(Subcommand)]
 enum Scope {
     Project(ProjectArgs),
     Location(LocationArgs),
     Package(PackageArgs),
+    Bundle(BundleArgs),
+    Source(SourceArgs),
+    Cache(CacheArgs),
 }
 
 #[derive(Parser)]
@@ -91,38 +163,181 @@ impl CommandLine {
             Scope::Project(ProjectArgs { command }) => match command {
                 ProjectCommand::Pack(pack_command) => Operation::new(pack_command.into())
                     .parse_project(&StdFileSystem, &Toml)?
+                    .execute_pack_hooks(&ProcessExecutor)?
                     .generate_unique_identity(&UniqidIdentifierGenerator::default())
                     .archive(&StdFileSystem, &TarballArchiver)?
                     .digest(&StdFileSystem, &Sha2Digester::Sha256)?
-                    .finalize(&StdFileSystem, CRATE_VERSION)
+                    .finalize(&StdFileSystem, &Json, CRATE_VERSION)
                     .map(|operation| {
                         println!("Package created : {}", operation.as_state().to_file_name())
                     })?,
             },
             Scope::Location(LocationArgs { command }) => match command {
                 LocationCommand::Init(init_location_command) => {
-                    Operation::new(init_location_command.into())
-                        .initialize_lockfile(&StdFileSystem, &Json)
+                    let wait = init_location_command.lock.to_wait_duration();
+                    Operation::new(InitLocationRequest::try_from(init_location_command)?)
+                        .probe_location_registration(&StdFileSystem, &Toml)?
+                        .create_location_directory(&StdFileSystem)?
+                        .lock_location(&FileLocker, wait)?
+                        .initialize_lockfile(&StdFileSystem, &Json)?
+                        .register_location(&StdFileSystem, &Toml, &Toml, &FileLocker, wait)
                         .map(|op| {
                             println!(
                                 "Empty deployment created at : {}",
                                 op.as_location_path().to_string_lossy()
+                            );
+                            if let Some(registration) = op.as_registration() {
+                                println!("Location registered as {}", registration.as_name())
+                            }
+                        })?
+                }
+                LocationCommand::List(list_locations_command) => {
+                    let operation = Operation::new(UserConfigRequest::try_from(list_locations_command)?)
+                        .parse_user_config(&StdFileSystem, &Toml)?;
+                    let config = operation.as_user_config();
+                    if config.iter_locations().next().is_none() {
+                        println!("No location registered")
+                    }
+                    config.iter_locations().for_each(|(name, path)| {
+                        if config.is_default_location(name) {
+                            println!("{name} {} (default)", path.to_string_lossy())
+                        } else {
+                            println!("{name} {}", path.to_string_lossy())
+                        }
+                    })
+                }
+                LocationCommand::Migrate(migrate_location_command) => {
+                    let wait = migrate_location_command.lock.to_wait_duration();
+                    Operation::new(MigrateLocationRequest::try_from(migrate_location_command)?)
+                        .lock_location(&FileLocker, wait)?
+                        .parse_location_lockfile(&StdFileSystem, &Json)?
+                        .persist_location_lockfile(&StdFileSystem, &Json)
+                        .map(|operation| {
+                            if operation.as_previous_schema_version() == operation.as_schema_version() {
+                                println!("Location lockfile already at schema version {}", operation.as_schema_version())
+                            } else {
+                                println!(
+                                    "Location lockfile migrated from schema version {} to {}",
+                                    operation.as_previous_schema_version(),
+                                    operation.as_schema_version()
+                                )
+                            }
+                        })?
+                }
+                LocationCommand::Verify(verify_location_command) => {
+                    let json = verify_location_command.json;
+                    let operation = Operation::new(VerifyLocationRequest::try_from(verify_location_command)?)
+                        .parse_location_lockfile(&StdFileSystem, &Json)?
+                        .verify_location(&StdFileSystem, &Sha2Digester::Sha256)?;
+                    let report = operation.as_report();
+                    if json {
+                        println!("{}", Json.serialize(report)?)
+                    } else if report.is_healthy() {
+                        println!("Location {} is healthy", operation.as_location_path().to_string_lossy())
+                    } else {
+                        report.as_issues().iter().for_each(|issue| println!("{issue}"))
+                    }
+                    if !report.is_healthy() {
+                        return Err(Error::LocationVerificationFailed(report.as_issues().len()));
+                    }
+                }
+                LocationCommand::Gc(collect_garbage_command) => {
+                    let wait = collect_garbage_command.lock.to_wait_duration();
+                    let dry_run = collect_garbage_command.dry_run;
+                    let operation = Operation::new(CollectGarbageRequest::try_from(collect_garbage_command)?)
+                        .lock_location(&FileLocker, wait)?
+                        .parse_location_lockfile(&StdFileSystem, &Json)?
+                        .find_orphan_directories(&StdFileSystem, &Sha2Digester::Sha256)?;
+                    if dry_run {
+                        operation.as_orphan_paths().iter().for_each(|path| println!("Would remove {}", path.to_string_lossy()))
+                    } else {
+                        operation
+                            .remove_orphan_directories(&StdFileSystem)?
+                            .as_removed_paths()
+                            .iter()
+                            .for_each(|path| println!("Removed {}", path.to_string_lossy()))
+                    }
+                }
+                LocationCommand::Repair(repair_location_command) => {
+                    let wait = repair_location_command.lock.to_wait_duration();
+                    let dry_run = repair_location_command.dry_run;
+                    let cache_request = PackageCacheRequest::new(repair_location_command.cache.to_cache_path()?);
+                    let operation = Operation::new(RepairLocationRequest::try_from(repair_location_command)?)
+                        .lock_location(&FileLocker, wait)?;
+                    // The cache is kept from pruning while packages are extracted from it
+                    let _cache_lock = Operation::new(cache_request).lock_cache(&StdFileSystem, &FileLocker, wait)?;
+                    let operation = operation
+                        .parse_location_lockfile(&StdFileSystem, &Json)?
+                        .find_damaged_deployments(&StdFileSystem, &Json, &Sha2Digester::Sha256)?;
+                    let print_repair = |prefix: &str, repair: &DeploymentRepair| {
+                        println!(
+                            "{prefix} deployment {} of {} {} from {}",
+                            repair.as_deployment_id(),
+                            repair.as_package().as_identifier(),
+                            repair.as_package().as_version(),
+                            repair.as_package_path().to_string_lossy()
+                        )
+                    };
+                    if dry_run {
+                        operation.as_repairs().iter().for_each(|repair| print_repair("Would repair", repair))
+                    } else {
+                        let operation = operation
+                            .repair_deployments(&StdFileSystem, &TarballArchiver, &Sha2Digester::Sha256)?
+                            .persist_location_lockfile(&StdFileSystem, &Json)?
+                            .generate_location_shims(&StdFileSystem)?;
+                        operation.as_repairs().iter().for_each(|repair| print_repair("Repaired", repair));
+                        operation.as_unrecoverable_files().iter().for_each(|issue| println!("{issue}, it can not be recovered"))
+                    }
+                }
+                LocationCommand::Use(use_deployment_command) => {
+                    let wait = use_deployment_command.lock.to_wait_duration();
+                    Operation::new(UseDeploymentRequest::try_from(use_deployment_command)?)
+                        .lock_location(&FileLocker, wait)?
+                        .parse_location_lockfile(&StdFileSystem, &Json)?
+                        .activate_deployment()?
+                        .persist_location_lockfile(&StdFileSystem, &Json)?
+                        .generate_location_shims(&StdFileSystem)
+                        .map(|operation| {
+                            let deployment = operation.as_deployment();
+                            println!(
+                                "Package {} {} is now active as {}",
+                                deployment.as_ref().as_identifier(),
+                                deployment.as_version(),
+                                deployment.as_id()
                             )
                         })?
                 }
+                LocationCommand::Env(location_environment_command) => {
+                    let shell = location_environment_command.shell.to_shell();
+                    let deactivate = location_environment_command.deactivate;
+                    let operation = Operation::new(LocationEnvironmentRequest::try_from(location_environment_command)?)
+                        .parse_location_lockfile(&StdFileSystem, &Json)?
+                        .resolve_location_environment()?;
+                    let environment = operation.as_environment();
+                    match shell {
+                        Some(shell) if deactivate => print!("{}", shell.to_deactivation_script(environment)),
+                        Some(shell) => print!("{}", shell.to_activation_script(environment)),
+                        None => println!("{}", Json.serialize(environment)?),
+                    }
+                }
                 LocationCommand::Show(show_location_command) => {
-                    Operation::new(ShowLocationRequest::from(show_location_command))
+                    Operation::new(ShowLocationRequest::try_from(show_location_command)?)
                         .parse_location_lockfile(&StdFileSystem, &Json)
                         .map(|operation| {
-                            if operation.as_location().iter().next().is_some() {
-                                operation.as_location().iter().for_each(|deployment| {
+                            let deployments = operation.list_deployments();
+                            if !deployments.is_empty() {
+                                deployments.into_iter().for_each(|deployment| {
                                     let package = deployment.as_ref();
-                                    println!(
-                                        "{} {} {}",
-                                        package.as_identifier(),
-                                        package.as_version(),
-                                        package.as_checksum().to_string()
-                                    )
+                                    let mut columns = vec![
+                                        package.as_identifier().to_string(),
+                                        package.as_version().to_string(),
+                                        deployment.as_id().to_string(),
+                                    ];
+                                    columns.extend(deployment.as_parameters().iter().map(|(key, value)| format!("{key}={value}")));
+                                    if operation.as_location().is_active(deployment.as_id()) {
+                                        columns.push(String::from("(active)"));
+                                    }
+                                    println!("{}", columns.join(" "))
                                 })
                             } else {
                                 print!("Location contains no deployments")
@@ -130,44 +345,401 @@ impl CommandLine {
                         })?
                 }
                 LocationCommand::Undeploy(undeploy_command) => {
-                    Operation::new(UndeployRequest::try_from(undeploy_command)?)
+                    let wait = undeploy_command.lock.to_wait_duration();
+                    undeploy_package(UndeployRequest::try_from(undeploy_command)?, &FileLocker, wait)?
+                }
+            },
+            Scope::Package(PackageArgs { command }) => match command {
+                PackageCommand::Deploy(deploy_file_command) => {
+                    let wait = deploy_file_command.lock.to_wait_duration();
+                    let prune_request = deploy_file_command.cache.to_prune_request()?;
+                    Operation::new(CachePackageRequest::try_from(&deploy_file_command)?)
+                        .parse_package_metadata(&StdFileSystem, &Json)?
+                        .lock_cache(&StdFileSystem, &FileLocker, wait)?
+                        .parse_package_cache(&StdFileSystem, &Json)?
+                        .store_package(&StdFileSystem, &Sha2Digester::Sha256, &SystemClock)?
+                        .persist_package_cache(&StdFileSystem, &Json)?;
+                    deploy_package(DeployRequest::try_from(deploy_file_command)?, &FileLocker, wait)?;
+                    if let Some(prune_request) = prune_request {
+                        prune_cache(prune_request, false, wait)?
+                    }
+                }
+                PackageCommand::Install(install_command) => {
+                    let wait = install_command.lock.to_wait_duration();
+                    let prune_request = install_command.cache.to_prune_request()?;
+                    let sources = install::to_registered_sources()?;
+                    let operation = Operation::new(InstallRequest::try_from(&install_command)?);
+                    // Planning and every deployment happen under the same lock of the location
+                    let location_lock = FileLocker.lock(operation.to_location_lock_path(), wait)?;
+                    let operation = operation
+                        .lock_location(&location_lock, wait)?
+                        .parse_location_lockfile(&StdFileSystem, &Json)?
+                        .plan_installation(&StdFileSystem, &sources)?
+                        .lock_cache(&StdFileSystem, &FileLocker, wait)?
+                        .parse_package_cache(&StdFileSystem, &Json)?
+                        .download_packages(&StdFileSystem, &sources, &SystemClock)?
+                        .persist_package_cache(&StdFileSystem, &Json)?;
+                    let active_deployments: Vec<DeploymentSelector> = operation.as_location().iter_active_deployments().map(DeploymentSelector::from).collect();
+                    let package_paths = operation.as_package_paths();
+                    let requests = package_paths.iter().enumerate().map(|(index, package_path)| {
+                        let parameters = if index + 1 == package_paths.len() {
+                            install_command.parameters.iter().cloned().collect()
+                        } else {
+                            Default::default()
+                        };
+                        DeployRequest::new(package_path.clone(), operation.as_location_path().to_absolute_path(), parameters)
+                    });
+                    deploy_packages(requests, operation.as_location_path(), &location_lock, &active_deployments)?;
+                    // Pruning locks the cache in turn
+                    drop(operation);
+                    drop(location_lock);
+                    if let Some(prune_request) = prune_request {
+                        prune_cache(prune_request, false, wait)?
+                    }
+                }
+                PackageCommand::Upgrade(upgrade_command) => {
+                    let wait = upgrade_command.lock.to_wait_duration();
+                    Operation::new(UpgradeRequest::try_from(upgrade_command)?)
+                        .lock_location(&FileLocker, wait)?
+                        .parse_package_metadata(&StdFileSystem, &Json)?
                         .parse_location_lockfile(&StdFileSystem, &Json)?
-                        .probe_package_already_deployed_in_location()?
+                        .find_previous_deployment()?
+                        .identify_deployment(&Sha2Digester::Sha256)?
+                        .probe_package_not_deployed_in_location()?
+                        .probe_dependencies_deployed_in_location()?
+                        .probe_no_resource_conflict()?
+                        .validate_package_checksum(&StdFileSystem, &Sha2Digester::Sha256)?
                         .guess_deployment_path()
-                        .remove_deployment_from_location()
+                        .extract_package(&StdFileSystem, &TarballArchiver)?
+                        .execute_upgrade_hooks(&StdFileSystem, &ProcessExecutor)?
+                        .index_deployment_files(&StdFileSystem, &Sha2Digester::Sha256)?
+                        .execute_previous_undeploy_hooks(&StdFileSystem, &ProcessExecutor)?
+                        .replace_deployment_in_location(CRATE_VERSION)?
                         .persist_location_lockfile(&StdFileSystem, &Json)?
-                        .delete_deployment_directory(&StdFileSystem)
+                        .generate_location_shims(&StdFileSystem)?
+                        .delete_previous_deployment_directory(&StdFileSystem)
                         .map(|operation| {
+                            let deployment = operation.as_deployment();
+                            let previous_deployment = operation.as_previous_deployment();
                             println!(
-                                "Deployment {} undeployed from location {}",
-                                operation.as_checksum().to_string(),
-                                operation.as_location_path().to_string_lossy()
+                                "Package {} upgraded from {} to {} as {}",
+                                deployment.as_ref().as_identifier(),
+                                previous_deployment.as_version(),
+                                deployment.as_version(),
+                                deployment.as_id()
+                            );
+                            if operation.is_previous_deployment_kept() {
+                                println!(
+                                    "Deployment {} is kept, other deployments still depend on it",
+                                    previous_deployment.as_id()
+                                )
+                            }
+                        })?
+                }
+                PackageCommand::Publish(publish_command) => {
+                    Operation::new(publish_command.into())
+                        .parse_package_metadata(&StdFileSystem, &Json)?
+                        .validate_package_checksum(&StdFileSystem, &Sha2Digester::Sha256)?
+                        .index_source(&StdFileSystem, &Json, &Sha2Digester::Sha256)?
+                        .probe_package_publishable()?
+                        .copy_package_to_source(&StdFileSystem)?
+                        .persist_source_index(&StdFileSystem, &Json)
+                        .map(|operation| {
+                            let package = operation.as_package();
+                            if operation.is_already_published() {
+                                println!("Package {} {} is already published", package.as_identifier(), package.as_version())
+                            } else {
+                                println!(
+                                    "Package {} {} published in {}",
+                                    package.as_identifier(),
+                                    package.as_version(),
+                                    operation.as_source_path().to_string_lossy()
+                                )
+                            }
+                        })?
+                }
+                PackageCommand::Unpublish(unpublish_command) => {
+                    Operation::new(UnpublishRequest::try_from(unpublish_command)?)
+                        .index_source(&StdFileSystem, &Json, &Sha2Digester::Sha256)?
+                        .unlist_package()?
+                        .persist_source_index(&StdFileSystem, &Json)?
+                        .delete_package_file(&StdFileSystem)
+                        .map(|operation| {
+                            let package = operation.as_unpublished_package();
+                            println!(
+                                "Package {} {} unpublished from {}",
+                                package.as_identifier(),
+                                package.as_version(),
+                                operation.as_source_path().to_string_lossy()
                             )
                         })?
                 }
             },
-            Scope::Package(PackageArgs { command }) => match command {
-                PackageCommand::Deploy(deploy_file_command) => {
-                    Operation::new(DeployRequest::from(deploy_file_command))
-                        .parse_package_path()?
+            Scope::Bundle(BundleArgs { command }) => match command {
+                BundleCommand::Install(install_bundle_command) => {
+                    let wait = install_bundle_command.lock.to_wait_duration();
+                    Operation::new(InstallBundleRequest::try_from(install_bundle_command)?)
+                        .lock_location(&FileLocker, wait)?
+                        .parse_bundle_manifest(&StdFileSystem, &Toml)?
                         .parse_location_lockfile(&StdFileSystem, &Json)?
-                        .probe_package_not_deployed_in_location()?
-                        .validate_package_checksum(&StdFileSystem, &Sha2Digester::Sha256)?
-                        .guess_deployment_path()
-                        .extract_package(&StdFileSystem, &TarballArchiver)?
-                        .add_deployment_to_location()
-                        .persist_location_lockfile(&StdFileSystem, &Json)
+                        .index_bundle_source(&StdFileSystem, &Json)?
+                        .resolve_bundle(&Sha2Digester::Sha256, CRATE_VERSION)?
+                        .validate_bundle_checksums(&StdFileSystem, &Sha2Digester::Sha256)?
+                        .extract_bundle_packages(&StdFileSystem, &TarballArchiver)?
+                        .execute_bundle_deploy_hooks(&StdFileSystem, &ProcessExecutor)?
+                        .index_bundle_deployment_files(&StdFileSystem, &Sha2Digester::Sha256)?
+                        .persist_location_lockfile(&StdFileSystem, &Json)?
+                        .generate_location_shims(&StdFileSystem)
+                        .map(|operation| {
+                            operation.iter_installed_deployments().for_each(|deployment| {
+                                let package = deployment.as_ref();
+                                println!(
+                                    "Package {} {} deployed",
+                                    package.as_identifier(),
+                                    package.as_version()
+                                )
+                            });
+                            println!(
+                                "Bundle {} installed in {}",
+                                operation.as_bundle().as_identifier(),
+                                operation.as_location_path().to_string_lossy()
+                            )
+                        })?
+                }
+                BundleCommand::Delete(delete_bundle_command) => {
+                    let wait = delete_bundle_command.lock.to_wait_duration();
+                    Operation::new(DeleteBundleRequest::try_from(delete_bundle_command)?)
+                        .lock_location(&FileLocker, wait)?
+                        .parse_bundle_manifest(&StdFileSystem, &Toml)?
+                        .parse_location_lockfile(&StdFileSystem, &Json)?
+                        .probe_bundle_installed_in_location()?
+                        .remove_bundle_from_location()
+                        .execute_bundle_undeploy_hooks(&ProcessExecutor)?
+                        .persist_location_lockfile(&StdFileSystem, &Json)?
+                        .generate_location_shims(&StdFileSystem)?
+                        .delete_bundle_deployment_directories(&StdFileSystem)
                         .map(|operation| {
+                            operation.as_removed_deployments().iter().for_each(|deployment| {
+                                let package = deployment.as_ref();
+                                println!(
+                                    "Package {} {} undeployed",
+                                    package.as_identifier(),
+                                    package.as_version()
+                                )
+                            });
                             println!(
-                                "Package {} deployed in {}",
-                                operation.as_package().as_identifier(),
-                                operation.as_package_path().to_string_lossy()
+                                "Bundle deleted from {}",
+                                operation.as_location_path().to_string_lossy()
                             )
                         })?
                 }
             },
+            Scope::Source(SourceArgs { command }) => match command {
+                SourceCommand::Search(search_source_command) => {
+                    let source = DirectorySource::new(search_source_command.source_directory.to_absolute_path());
+                    print_found_packages(Operation::new((&search_source_command).into()).search_source(&StdFileSystem, &source)?)
+                }
+                SourceCommand::List(list_source_command) => {
+                    let source = DirectorySource::new(list_source_command.source_directory.to_absolute_path());
+                    print_found_packages(Operation::new((&list_source_command).into()).search_source(&StdFileSystem, &source)?)
+                }
+                SourceCommand::Add(register_source_command) => {
+                    let wait = register_source_command.lock.to_wait_duration();
+                    Operation::new(RegisterSourceRequest::try_from(register_source_command)?)
+                        .register_source(&StdFileSystem, &Toml, &Toml, &FileLocker, wait)
+                        .map(|operation| {
+                            if operation.is_new() {
+                                println!("Source {} registered", operation.as_source())
+                            } else {
+                                println!("Source {} is already registered", operation.as_source())
+                            }
+                        })?
+                }
+                SourceCommand::Index(index_source_command) => {
+                    let operation = Operation::new(index_source_command.into())
+                        .index_source(&StdFileSystem, &Json, &Sha2Digester::Sha256)?
+                        .persist_source_index(&StdFileSystem, &Json)?;
+                    operation.as_digested_paths().iter().for_each(|path| println!("Indexed {path}"));
+                    println!("{} package(s) in index", operation.as_index().iter_packages().count())
+                }
+            }
+            Scope::Cache(CacheArgs { command }) => match command {
+                CacheCommand::List(list_cache_command) => {
+                    let operation = Operation::new(PackageCacheRequest::try_from(list_cache_command)?)
+                        .parse_package_cache(&StdFileSystem, &Json)?;
+                    let cache = operation.as_package_cache();
+                    let now = SystemClock.now();
+                    cache.iter_packages().for_each(|package| {
+                        println!(
+                            "{} {} {} {} bytes, used {} ago",
+                            package.as_identifier(),
+                            package.as_version(),
+                            package.as_checksum(),
+                            package.as_size(),
+                            cache::to_age(now.saturating_sub(package.as_last_used_at()))
+                        )
+                    });
+                    println!("{} package(s), {} bytes", cache.iter_packages().count(), cache.to_size())
+                }
+                CacheCommand::Prune(prune_cache_command) => {
+                    let dry_run = prune_cache_command.dry_run;
+                    let wait = prune_cache_command.lock.to_wait_duration();
+                    prune_cache(PruneCacheRequest::try_from(prune_cache_command)?, dry_run, wait)?
+                }
+                CacheCommand::Verify(verify_cache_command) => {
+                    let remove = verify_cache_command.remove;
+                    let wait = verify_cache_command.lock.to_wait_duration();
+                    let operation = Operation::new(VerifyCacheRequest::try_from(verify_cache_command)?)
+                        .lock_cache(&StdFileSystem, &FileLocker, wait)?
+                        .parse_package_cache(&StdFileSystem, &Json)?
+                        .verify_cached_packages(&StdFileSystem, &Sha2Digester::Sha256)?;
+                    operation.as_issues().iter().for_each(|issue| println!("{issue}"));
+                    let issue_count = operation.as_issues().len();
+                    if issue_count == 0 {
+                        println!("Cache {} is healthy", operation.as_cache_path().to_string_lossy())
+                    } else if remove {
+                        operation
+                            .persist_package_cache(&StdFileSystem, &Json)?
+                            .delete_damaged_packages(&StdFileSystem)
+                            .map(|operation| println!("{} damaged package(s) removed", operation.as_issues().len()))?
+                    } else {
+                        return Err(Error::CacheVerificationFailed(issue_count));
+                    }
+                }
+            },
         };
 
         Ok(())
     }
 }
+
+fn deploy_package<L: Locker>(request: DeployRequest, locker: &L, wait: Duration) -> Result<Deployment> {
+    Operation::new(request)
+        .lock_location(locker, wait)?
+        .parse_package_metadata(&StdFileSystem, &Json)?
+        .parse_location_lockfile(&StdFileSystem, &Json)?
+        .identify_deployment(&Sha2Digester::Sha256)?
+        .probe_package_not_deployed_in_location()?
+        .probe_dependencies_deployed_in_location()?
+        .probe_no_resource_conflict()?
+        .validate_package_checksum(&StdFileSystem, &Sha2Digester::Sha256)?
+        .guess_deployment_path()
+        .extract_package(&StdFileSystem, &TarballArchiver)?
+        .execute_deploy_hooks(&StdFileSystem, &ProcessExecutor)?
+        .index_deployment_files(&StdFileSystem, &Sha2Digester::Sha256)?
+        .add_deployment_to_location(CRATE_VERSION)?
+        .persist_location_lockfile(&StdFileSystem, &Json)?
+        .generate_location_shims(&StdFileSystem)
+        .map(|operation| {
+            println!(
+                "Package {} deployed in {} as {}",
+                operation.as_package().as_identifier(),
+                operation.as_location_path().to_string_lossy(),
+                operation.as_deployment().as_id()
+            );
+            operation.as_deployment().clone()
+        })
+}
+
+/* Packages are deployed one by one under the lock of the installation. When one fails, the ones already deployed are undeployed
+ * and the deployments active before the installation are activated again */
+fn deploy_packages<I: IntoIterator<Item = DeployRequest>>(
+    requests: I,
+    location_path: Absolute<&Path>,
+    location_lock: &Lock,
+    active_deployments: &[DeploymentSelector],
+) -> Result<()> {
+    let mut deployments = Vec::new();
+    for request in requests {
+        match deploy_package(request, location_lock, Duration::ZERO) {
+            Ok(deployment) => deployments.push(deployment),
+            Err(error) => {
+                return match undeploy_packages(&deployments, location_path, location_lock, active_deployments) {
+                    Ok(()) => Err(error),
+                    Err(compensation_error) => Err(Error::CompensationFailed { error: Box::new(error), compensation_error: Box::new(compensation_error) }),
+                };
+            }
+        }
+    }
+    Ok(())
+}
+
+fn undeploy_packages(deployments: &[Deployment], location_path: Absolute<&Path>, location_lock: &Lock, active_deployments: &[DeploymentSelector]) -> Result<()> {
+    for deployment in deployments.iter().rev() {
+        let request = UndeployRequest::new(deployment.as_id().clone(), location_path.to_absolute_path()).with_active_reassignment(true);
+        undeploy_package(request, location_lock, Duration::ZERO)?;
+    }
+    let reactivated_deployments = active_deployments.iter()
+        .filter(|selector| deployments.iter().any(|deployment| deployment.as_ref().as_identifier() == selector.as_identifier()));
+    for selector in reactivated_deployments {
+        Operation::new(UseDeploymentRequest::new(location_path.to_absolute_path(), selector.clone()))
+            .lock_location(location_lock, Duration::ZERO)?
+            .parse_location_lockfile(&StdFileSystem, &Json)?
+            .activate_deployment()?
+            .persist_location_lockfile(&StdFileSystem, &Json)?
+            .generate_location_shims(&StdFileSystem)?;
+    }
+    Ok(())
+}
+
+fn undeploy_package<L: Locker>(request: UndeployRequest, locker: &L, wait: Duration) -> Result<()> {
+    Operation::new(request)
+        .lock_location(locker, wait)?
+        .parse_location_lockfile(&StdFileSystem, &Json)?
+        .probe_package_already_deployed_in_location()?
+        .probe_no_dependent_deployments()?
+        .probe_active_deployment_reassignment()?
+        .guess_deployment_path()
+        .execute_undeploy_hooks(&ProcessExecutor)?
+        .remove_deployment_from_location()
+        .persist_location_lockfile(&StdFileSystem, &Json)?
+        .generate_location_shims(&StdFileSystem)?
+        .delete_deployment_directory(&StdFileSystem)
+        .map(|operation| {
+            println!(
+                "Deployment {} undeployed from location {}",
+                operation.as_checksum(),
+                operation.as_location_path().to_string_lossy()
+            );
+            if let Some(deployment) = operation.as_reassigned_deployment() {
+                println!(
+                    "Package {} {} is now active as {}",
+                    deployment.as_ref().as_identifier(),
+                    deployment.as_version(),
+                    deployment.as_id()
+                )
+            }
+        })
+}
+
+/* Orphan directories are left over by an interrupted operation */
+fn prune_cache(request: PruneCacheRequest, dry_run: bool, wait: Duration) -> Result<()> {
+    let operation = Operation::new(request)
+        .lock_cache(&StdFileSystem, &FileLocker, wait)?
+        .parse_package_cache(&StdFileSystem, &Json)?
+        .select_evicted_packages(&StdFileSystem, &SystemClock)?;
+    let print_eviction = |prefix: &str, package: &CachedPackage| {
+        println!("{prefix} {} {} {}", package.as_identifier(), package.as_version(), package.as_checksum())
+    };
+    if dry_run {
+        operation.as_evicted_packages().iter().for_each(|package| print_eviction("Would remove", package));
+        operation.as_orphan_paths().iter().for_each(|path| println!("Would remove {}", path.to_string_lossy()));
+    } else {
+        let operation = operation
+            .persist_package_cache(&StdFileSystem, &Json)?
+            .delete_evicted_packages(&StdFileSystem)?;
+        operation.as_evicted_packages().iter().for_each(|package| print_eviction("Removed", package));
+        operation.as_orphan_paths().iter().for_each(|path| println!("Removed {}", path.to_string_lossy()));
+    }
+    Ok(())
+}
+
+fn print_found_packages(operation: SearchSourceOperation<FoundPackages>) {
+    if operation.as_packages().is_empty() {
+        println!("No package found")
+    }
+    operation.as_packages().iter().for_each(|(identifier, versions)| {
+        let versions: Vec<String> = versions.iter().map(ToString::to_string).collect();
+        println!("{identifier} {}", versions.join(" "))
+    })
+}
@@ -0,0 +1,18 @@
+use clap::Args;
+use packster_core::{Result, Error, packaging::application::MigrateLocationRequest};
+use crate::{location::LocationArg, lock::LockArgs};
+
+#[derive(Args)]
+pub struct MigrateLocationCommand {
+    #[command(flatten)]
+    pub location: LocationArg,
+    #[command(flatten)]
+    pub lock: LockArgs,
+}
+
+impl TryFrom<MigrateLocationCommand> for MigrateLocationRequest {
+    type Error = Error;
+    fn try_from(command: MigrateLocationCommand) -> Result<MigrateLocationRequest> {
+        Ok(MigrateLocationRequest::new(command.location.to_location_path()?))
+    }
+}
@@ -1,8 +1,11 @@
-use std::path::{Path, PathBuf};
-use packster_core::application::path::Absolute;
+use std::{path::{Path, PathBuf}, time::Duration};
+use packster_core::{application::path::Absolute, domain::entity::ParameterValue};
 
 pub fn try_from_current_dir(path: &str) -> std::io::Result<Absolute<PathBuf>> {
-    let path = Path::new(path);
+    to_absolute_path(Path::new(path))
+}
+
+pub fn to_absolute_path(path: &Path) -> std::io::Result<Absolute<PathBuf>> {
     Ok(
         if path.is_relative() {
             Absolute::assume_absolute(std::env::current_dir()?.join(path))
@@ -10,4 +13,48 @@ pub fn try_from_current_dir(path: &str) -> std::io::Result<Absolute<PathBuf>> {
             Absolute::assume_absolute(path.to_path_buf())
         }
     )
-}
\ No newline at end of file
+}
+/* Values are given as strings, the package parameter schema converts them to their declared type */
+pub fn parse_parameter(assignment: &str) -> std::result::Result<(String, ParameterValue), String> {
+    assignment
+        .split_once('=')
+        .map(|(key, value)| (key.to_string(), ParameterValue::String(value.to_string())))
+        .ok_or_else(|| format!("Parameter \"{assignment}\" shall be formatted as key=value"))
+}
+
+/* Durations are given as a number followed by a unit : s, m, h, d or w */
+pub fn parse_duration(duration: &str) -> std::result::Result<Duration, String> {
+    let error = || format!("Duration \"{duration}\" shall be a number followed by s, m, h, d or w");
+    let (position, unit) = duration.char_indices().last().ok_or_else(error)?;
+    let count: u64 = duration[..position].parse().map_err(|_| error())?;
+    let unit_seconds = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return Err(error()),
+    };
+    Ok(Duration::from_secs(count.saturating_mul(unit_seconds)))
+}
+
+/* Sizes are given in bytes, or followed by a binary unit : K, M or G */
+pub fn parse_size(size: &str) -> std::result::Result<u64, String> {
+    let error = || format!("Size \"{size}\" shall be a number of bytes, optionally followed by K, M or G");
+    let (count, unit_bytes) = match size.chars().last() {
+        Some('K') => (&size[..size.len() - 1], 1 << 10),
+        Some('M') => (&size[..size.len() - 1], 1 << 20),
+        Some('G') => (&size[..size.len() - 1], 1 << 30),
+        _ => (size, 1),
+    };
+    count.parse::<u64>().map(|count| count.saturating_mul(unit_bytes)).map_err(|_| error())
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+    assert_eq!(parse_duration("2w"), Ok(Duration::from_secs(2 * 7 * 24 * 60 * 60)));
+    for duration in ["", "s", "5", "5y", "5µ", "3é", "µ"] {
+        assert!(parse_duration(duration).is_err(), "{duration} should not be parsed");
+    }
+}
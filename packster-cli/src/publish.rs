@@ -0,0 +1,46 @@
+use std::{path::PathBuf, str::FromStr};
+use clap::Args;
+use packster_core::{
+    Result, Error,
+    application::path::Absolute,
+    domain::entity::{Identifier, Version},
+    packaging::application::{PublishRequest, UnpublishRequest},
+};
+use crate::parse::try_from_current_dir;
+
+#[derive(Args)]
+pub struct PublishCommand {
+    #[arg(value_parser=try_from_current_dir)]
+    pub package_file: Absolute<PathBuf>,
+    /// Directory containing package files
+    #[arg(value_parser=try_from_current_dir)]
+    pub source_directory: Absolute<PathBuf>,
+}
+
+impl From<PublishCommand> for PublishRequest {
+    fn from(command: PublishCommand) -> PublishRequest {
+        PublishRequest::new(command.package_file, command.source_directory)
+    }
+}
+
+#[derive(Args)]
+pub struct UnpublishCommand {
+    pub identifier: String,
+    pub version: String,
+    /// Directory containing package files
+    #[arg(value_parser=try_from_current_dir)]
+    pub source_directory: Absolute<PathBuf>,
+}
+
+impl TryFrom<UnpublishCommand> for UnpublishRequest {
+    type Error = Error;
+    fn try_from(command: UnpublishCommand) -> Result<UnpublishRequest> {
+        Ok(
+            UnpublishRequest::new(
+                command.source_directory,
+                Identifier::from_str(&command.identifier)?,
+                Version::from_str(&command.version)?,
+            )
+        )
+    }
+}
@@ -0,0 +1,23 @@
+use clap::Args;
+use packster_core::{Result, Error, packaging::application::RegisterSourceRequest};
+use crate::{config::to_user_config_path, lock::LockArgs, parse::try_from_current_dir};
+
+#[derive(Args)]
+pub struct RegisterSourceCommand {
+    /// Directory containing package files, or http(s) url of a source index
+    pub source: String,
+    #[command(flatten)]
+    pub lock: LockArgs,
+}
+
+impl TryFrom<RegisterSourceCommand> for RegisterSourceRequest {
+    type Error = Error;
+    fn try_from(command: RegisterSourceCommand) -> Result<RegisterSourceRequest> {
+        let source = if command.source.starts_with("http://") || command.source.starts_with("https://") {
+            command.source.trim_end_matches('/').to_string()
+        } else {
+            try_from_current_dir(&command.source)?.to_string_lossy().to_string()
+        };
+        Ok(RegisterSourceRequest::new(to_user_config_path()?, source))
+    }
+}
@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+use clap::Args;
+use packster_core::{Result, Error, application::path::Absolute, packaging::application::RepairLocationRequest};
+use crate::{cache::CacheArg, location::LocationArg, lock::LockArgs, parse::try_from_current_dir};
+
+#[derive(Args)]
+pub struct RepairLocationCommand {
+    #[command(flatten)]
+    pub location: LocationArg,
+    /// Package file, or directory containing package files, to extract damaged deployments from
+    #[arg(short, long = "package", value_name = "PATH", value_parser=try_from_current_dir)]
+    pub package_paths: Vec<Absolute<PathBuf>>,
+    #[command(flatten)]
+    pub cache: CacheArg,
+    /// List the deployments which would be repaired, without repairing them
+    #[arg(long)]
+    pub dry_run: bool,
+    #[command(flatten)]
+    pub lock: LockArgs,
+}
+
+impl TryFrom<RepairLocationCommand> for RepairLocationRequest {
+    type Error = Error;
+    fn try_from(command: RepairLocationCommand) -> Result<RepairLocationRequest> {
+        Ok(
+            RepairLocationRequest::new(command.location.to_location_path()?, command.package_paths)
+                .with_cache_directory(command.cache.to_cache_path()?)
+        )
+    }
+}
@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+use clap::Args;
+use packster_core::{application::path::Absolute, packaging::application::SearchSourceRequest};
+use crate::parse::try_from_current_dir;
+
+#[derive(Args)]
+pub struct SearchSourceCommand {
+    /// Part of the identifiers to search for
+    pub pattern: String,
+    /// Directory containing package files
+    #[arg(value_parser=try_from_current_dir)]
+    pub source_directory: Absolute<PathBuf>,
+}
+
+impl From<&SearchSourceCommand> for SearchSourceRequest {
+    fn from(command: &SearchSourceCommand) -> SearchSourceRequest {
+        SearchSourceRequest::new(Some(command.pattern.clone()))
+    }
+}
+
+#[derive(Args)]
+pub struct ListSourceCommand {
+    /// Directory containing package files
+    #[arg(value_parser=try_from_current_dir)]
+    pub source_directory: Absolute<PathBuf>,
+}
+
+impl From<&ListSourceCommand> for SearchSourceRequest {
+    fn from(_: &ListSourceCommand) -> SearchSourceRequest {
+        SearchSourceRequest::new(None)
+    }
+}
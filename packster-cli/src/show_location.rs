@@ -1,15 +1,25 @@
-use std::path::PathBuf;
+use std::str::FromStr;
 use clap::Args;
-use packster_core::{application::path::Absolute, packaging::application::ShowLocationRequest};
-use crate::parse::try_from_current_dir;
+use packster_core::{Result, Error, packaging::application::ShowLocationRequest, domain::entity::PackageRequirement};
+use crate::location::LocationArg;
 
 
 #[derive(Args)]
 pub struct ShowLocationCommand {
-    #[arg(value_parser=try_from_current_dir)]
-    pub location_directory: Absolute<PathBuf>,
+    #[command(flatten)]
+    pub location: LocationArg,
+    #[arg(long)]
+    pub require: Option<String>,
 }
 
-impl From<ShowLocationCommand> for ShowLocationRequest {
-    fn from(value: ShowLocationCommand) -> Self { ShowLocationRequest::new(value.location_directory) }
-}
\ No newline at end of file
+impl TryFrom<ShowLocationCommand> for ShowLocationRequest {
+    type Error = Error;
+    fn try_from(command: ShowLocationCommand) -> Result<Self> {
+        Ok(
+            ShowLocationRequest::new(
+                command.location.to_location_path()?,
+                command.require.as_deref().map(PackageRequirement::from_str).transpose()?
+            )
+        )
+    }
+}
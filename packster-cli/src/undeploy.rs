@@ -1,14 +1,22 @@
-use std::{path::PathBuf, str::FromStr};
+use std::str::FromStr;
 
 use clap::{Args};
-use packster_core::{Result, Error, packaging::application::UndeployRequest, domain::entity::Checksum, application::path::Absolute};
-use crate::parse::try_from_current_dir;
+use packster_core::{Result, Error, packaging::application::UndeployRequest, domain::entity::Checksum};
+use crate::{location::LocationArg, lock::LockArgs};
 
 #[derive(Args)]
 pub struct UndeployCommand {
     pub checksum: String,
-    #[arg(value_parser=try_from_current_dir)]
-    pub location_directory: Absolute<PathBuf>,
+    #[command(flatten)]
+    pub location: LocationArg,
+    /// Activate the newest remaining deployment of the package when the undeployed one is active
+    #[arg(long)]
+    pub reassign: bool,
+    /// Undeploy the deployment even though other deployments depend on it
+    #[arg(long)]
+    pub force: bool,
+    #[command(flatten)]
+    pub lock: LockArgs,
 }
 
 impl TryFrom<UndeployCommand> for UndeployRequest {
@@ -17,8 +25,10 @@ impl TryFrom<UndeployCommand> for UndeployRequest {
         Ok(
             UndeployRequest::new(
                 Checksum::from_str(&command.checksum)?,
-                command.location_directory
+                command.location.to_location_path()?
             )
+            .with_active_reassignment(command.reassign)
+            .with_force(command.force)
         )
     }
 }
\ No newline at end of file
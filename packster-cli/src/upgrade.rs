@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+use clap::Args;
+use packster_core::{Result, Error, application::path::Absolute, domain::entity::ParameterValue, packaging::application::UpgradeRequest};
+use crate::{location::LocationArg, lock::LockArgs, parse::{parse_parameter, try_from_current_dir}};
+
+#[derive(Args)]
+pub struct UpgradeCommand {
+    #[arg(value_parser=try_from_current_dir)]
+    pub package_file: Absolute<PathBuf>,
+    #[command(flatten)]
+    pub location: LocationArg,
+    /// Parameter overriding the one of the upgraded deployment
+    #[arg(short, long = "parameter", value_name = "KEY=VALUE", value_parser=parse_parameter)]
+    pub parameters: Vec<(String, ParameterValue)>,
+    #[command(flatten)]
+    pub lock: LockArgs,
+}
+
+impl TryFrom<UpgradeCommand> for UpgradeRequest {
+    type Error = Error;
+    fn try_from(command: UpgradeCommand) -> Result<UpgradeRequest> {
+        Ok(
+            UpgradeRequest::new(
+                command.package_file,
+                command.location.to_location_path()?,
+                command.parameters.into_iter().collect(),
+            )
+        )
+    }
+}
@@ -0,0 +1,21 @@
+use std::str::FromStr;
+use clap::Args;
+use packster_core::{Result, Error, packaging::{application::UseDeploymentRequest, domain::DeploymentSelector}};
+use crate::{location::LocationArg, lock::LockArgs};
+
+#[derive(Args)]
+pub struct UseDeploymentCommand {
+    /// Deployment to activate, as identifier@version or identifier@checksum
+    pub selector: String,
+    #[command(flatten)]
+    pub location: LocationArg,
+    #[command(flatten)]
+    pub lock: LockArgs,
+}
+
+impl TryFrom<UseDeploymentCommand> for UseDeploymentRequest {
+    type Error = Error;
+    fn try_from(command: UseDeploymentCommand) -> Result<UseDeploymentRequest> {
+        Ok(UseDeploymentRequest::new(command.location.to_location_path()?, DeploymentSelector::from_str(&command.selector)?))
+    }
+}
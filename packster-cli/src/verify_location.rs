@@ -0,0 +1,19 @@
+use clap::Args;
+use packster_core::{Result, Error, packaging::application::VerifyLocationRequest};
+use crate::location::LocationArg;
+
+#[derive(Args)]
+pub struct VerifyLocationCommand {
+    #[command(flatten)]
+    pub location: LocationArg,
+    /// Print the report as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+impl TryFrom<VerifyLocationCommand> for VerifyLocationRequest {
+    type Error = Error;
+    fn try_from(command: VerifyLocationCommand) -> Result<VerifyLocationRequest> {
+        Ok(VerifyLocationRequest::new(command.location.to_location_path()?))
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Result, domain::entity::Checksum};
+use crate::{Error, Result, application::port::FileSystem, domain::entity::Checksum};
 
 pub struct Operation<S, R>{
     pub request: R,
@@ -11,7 +11,6 @@ impl <R> Operation<New, R> {
     pub fn new(request: R) -> Self { Operation { request, state: New } }
 }
 
-//TODO doing rollback operation using same states newtype struct would be super easy and meaningful and stylish cause each state would be described as reversible, or not, or partially, or with different methods :O
 impl <S, R>Operation<S, R> {
     pub fn as_state(&self) -> &S { &self.state }
     pub fn as_mut_state(&mut self) -> &mut S { &mut self.state }
@@ -49,4 +48,26 @@ impl <S, R>From<(S, R)> for Operation<S, R> {
 
 pub trait AsChecksum {
     fn as_checksum(&self) -> &Checksum;
-}
\ No newline at end of file
+}
+
+/* A state declaring how to undo its side effects, along with the ones of its previous states.
+ * Only changes to the filesystem are undone, such as extracted packages : executed hooks can not be undone, and states
+ * changing a location, an index or a cache in memory have nothing to undo, these being only written once persisted */
+pub trait Compensate {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()>;
+}
+
+impl <S: Compensate, R>Operation<S, R> {
+    pub fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> { self.state.compensate(filesystem) }
+
+    /* Compensate the operation when one of its steps failed, the step error being returned */
+    pub (crate) fn compensate_on_error<T, F: FileSystem>(&self, filesystem: &F, result: Result<T>) -> Result<T> {
+        result.map_err(|error| match self.compensate(filesystem) {
+            Ok(()) => error,
+            Err(compensation_error) => Error::CompensationFailed {
+                error: Box::new(error),
+                compensation_error: Box::new(compensation_error),
+            },
+        })
+    }
+}
@@ -1,9 +1,10 @@
-use std::{ io::{Read, Write}, path::Path };
+use std::{ any::Any, collections::BTreeMap, io::{Read, Write}, path::{Path, PathBuf}, sync::Arc, time::{Duration, SystemTime} };
 use serde::{de::DeserializeOwned, ser::Serialize};
 use crate::{
     Error, Result,
     application::path::{Absolute, NormalizedPathBuf},
-    domain::entity::Checksum,
+    domain::entity::{Checksum, Identifier, PackageRequirement, Version},
+    packaging::domain::{IndexedPackage, SourceIndex},
 };
 
 pub trait PathExt {
@@ -40,7 +41,11 @@ pub trait ReadOnlyFileSystem: Sync + Send {
     fn open_read<P: AsRef<Path>>(&self, path: P) -> Result<Box<dyn Read + Send + Sync>>;
     fn walk<'a>(&'a self, target_path: &'a Path)
         -> Box<dyn Iterator<Item = Result<DirEntry>> + 'a>;
+    /* Direct children of a directory, unlike walk which goes down the whole tree */
+    fn read_dir<'a>(&'a self, target_path: &'a Path)
+        -> Box<dyn Iterator<Item = Result<DirEntry>> + 'a>;
     fn file_size<P: AsRef<Path>>(&self, path: P) -> Result<u64>;
+    fn modified_at<P: AsRef<Path>>(&self, path: P) -> Result<SystemTime>;
 }
 
 pub trait FileSystem: ReadOnlyFileSystem {
@@ -70,6 +75,8 @@ pub trait FileSystem: ReadOnlyFileSystem {
         Ok(())
     }
     fn remove_dir_all<P: AsRef<Path>>(&self, path: P) -> Result<()>;
+    fn remove_file<P: AsRef<Path>>(&self, path: P) -> Result<()>;
+    fn set_executable<P: AsRef<Path>>(&self, path: P) -> Result<()>;
 }
 
 pub trait Archiver: Sync + Send {
@@ -84,6 +91,14 @@ pub trait Archiver: Sync + Send {
         filesystem: &F,
         expand_path: Absolute<P1>,
         archive_path: Absolute<P2>,
+    ) -> Result<()> {
+        self.extract_from(filesystem, expand_path, filesystem.open_read(archive_path)?)
+    }
+    fn extract_from<F: FileSystem, P: AsRef<Path>, R: Read>(
+        &self,
+        filesystem: &F,
+        expand_path: Absolute<P>,
+        reader: R,
     ) -> Result<()>;
 }
 
@@ -91,10 +106,82 @@ pub trait Digester: Sync + Send {
     fn generate_checksum<R: Read>(&self, reader: R) -> Result<Checksum>;
 }
 
+/* A place packages can be searched, listed and retrieved from */
+pub trait Source: Sync + Send {
+    /* Directory or url of the source, as displayed */
+    fn to_source_name(&self) -> String;
+    fn index<F: ReadOnlyFileSystem>(&self, filesystem: &F) -> Result<SourceIndex>;
+    /* Retrieves an indexed package in the destination directory, named after the package, returning its path */
+    fn download<F: FileSystem, P: AsRef<Path>>(
+        &self,
+        filesystem: &F,
+        package: &IndexedPackage,
+        destination_directory: Absolute<P>,
+    ) -> Result<Absolute<PathBuf>>;
+
+    fn list_identifiers<F: ReadOnlyFileSystem>(&self, filesystem: &F) -> Result<Vec<Identifier>> {
+        Ok(self.index(filesystem)?.to_identifiers())
+    }
+
+    /* Versions are sorted from the oldest to the newest */
+    fn list_versions<F: ReadOnlyFileSystem>(&self, filesystem: &F, identifier: &Identifier) -> Result<Vec<Version>> {
+        Ok(self.index(filesystem)?.to_versions(identifier))
+    }
+
+    /* Retrieves the newest package matching the requirement in the destination directory, returning its path */
+    fn fetch<F: FileSystem, P: AsRef<Path>>(
+        &self,
+        filesystem: &F,
+        requirement: &PackageRequirement,
+        destination_directory: Absolute<P>,
+    ) -> Result<Absolute<PathBuf>> {
+        let index = self.index(filesystem)?;
+        let package = index.find_latest(requirement)
+            .ok_or_else(|| Error::PackageNotFoundInSource {
+                requirement: requirement.to_string(),
+                source: self.to_source_name(),
+            })?;
+        self.download(filesystem, package, destination_directory)
+    }
+}
+
+pub trait Clock: Sync + Send {
+    /* Seconds elapsed since the unix epoch */
+    fn now(&self) -> u64;
+}
+
 pub trait UniqueIdentifierGenerator: Sync + Send {
     fn generate_identifier(&self) -> String;
 }
 
+/* Exclusive lock held by the current process, released when its last clone is dropped */
+#[derive(Clone)]
+pub struct Lock {
+    path: PathBuf,
+    _guard: Arc<dyn Any + Send + Sync>,
+}
+
+impl Lock {
+    pub fn new<P: AsRef<Path>, G: Any + Send + Sync>(path: P, guard: G) -> Self {
+        Lock { path: path.as_ref().to_path_buf(), _guard: Arc::new(guard) }
+    }
+}
+
+// A held lock is shared with the operations an operation is made of, locking the same path
+impl Locker for Lock {
+    fn lock<P: AsRef<Path>>(&self, path: Absolute<P>, _wait: Duration) -> Result<Lock> {
+        if path.as_ref() != self.path {
+            return Err(Error::LockNotHeld(path.to_path_buf()));
+        }
+        Ok(self.clone())
+    }
+}
+
+pub trait Locker: Sync + Send {
+    /* Waits up to `wait` for the lock to be released by its current owner */
+    fn lock<P: AsRef<Path>>(&self, path: Absolute<P>, wait: Duration) -> Result<Lock>;
+}
+
 pub trait Parser {
     fn parse<S: AsRef<str>, T: DeserializeOwned>(&self, s: S) -> Result<T>;
 }
@@ -102,3 +189,72 @@ pub trait Parser {
 pub trait Serializer {
     fn serialize<T: Serialize>(&self, value: &T) -> Result<String>;
 }
+
+/* A program to execute, with its arguments, environment and working directory */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command {
+    program: String,
+    arguments: Vec<String>,
+    environment: BTreeMap<String, String>,
+    working_directory: Absolute<PathBuf>,
+    timeout: Option<Duration>,
+}
+
+impl Command {
+    pub fn new(program: String, arguments: Vec<String>, working_directory: Absolute<PathBuf>) -> Self {
+        Command {
+            program,
+            arguments,
+            environment: BTreeMap::new(),
+            working_directory,
+            timeout: None,
+        }
+    }
+
+    pub fn with_environment(mut self, environment: BTreeMap<String, String>) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn as_program(&self) -> &str { &self.program }
+    pub fn as_arguments(&self) -> &[String] { &self.arguments }
+    pub fn as_environment(&self) -> &BTreeMap<String, String> { &self.environment }
+    pub fn as_working_directory(&self) -> Absolute<&Path> { self.working_directory.as_absolute_path() }
+    pub fn as_timeout(&self) -> Option<Duration> { self.timeout }
+
+    pub fn to_command_line(&self) -> String {
+        std::iter::once(self.program.as_str())
+            .chain(self.arguments.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/* Captured output of an executed command, the status being absent when the process was terminated by a signal */
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ExecutionOutput {
+    status: Option<i32>,
+    stdout: String,
+    stderr: String,
+}
+
+impl ExecutionOutput {
+    pub fn new(status: Option<i32>, stdout: String, stderr: String) -> Self {
+        ExecutionOutput { status, stdout, stderr }
+    }
+
+    pub fn is_success(&self) -> bool { self.status == Some(0) }
+    pub fn as_status(&self) -> Option<i32> { self.status }
+    pub fn as_stdout(&self) -> &str { &self.stdout }
+    pub fn as_stderr(&self) -> &str { &self.stderr }
+}
+
+pub trait Executor: Sync + Send {
+    /* Fails with Error::ExecutionTimedOut when the command outlives its timeout */
+    fn execute(&self, command: &Command) -> Result<ExecutionOutput>;
+}
@@ -1,23 +1,50 @@
 pub mod entity {
     use core::fmt;
-    use std::str::FromStr;
+    use std::{collections::BTreeMap, str::FromStr};
 
     use serde::{Deserialize, Serialize};
 
     use crate::{Error, Result};
 
+    pub const IDENTIFIER_MAX_LENGTH: usize = 64;
+
     #[cfg(test)]
-    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[serde(try_from = "String", into = "String")]
     pub struct Identifier(pub String);
 
     #[cfg(not(test))]
-    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[serde(try_from = "String", into = "String")]
     pub struct Identifier(String);
 
+    impl Identifier {
+        // ValidCharacter = Numeric | LowercaseLetter | Dash
+        pub fn is_valid_character(character: char) -> bool {
+            character.is_ascii_lowercase() || character.is_ascii_digit() || character == '-'
+        }
+    }
+
     impl FromStr for Identifier {
         type Err = Error;
         fn from_str(s: &str) -> Result<Self> {
-            Ok(Identifier(s.to_string())) //TODO proper identifier validation
+            if s.is_empty() || s.len() > IDENTIFIER_MAX_LENGTH || !s.chars().all(Identifier::is_valid_character) {
+                return Err(Error::InvalidIdentifier(s.to_string()));
+            }
+            Ok(Identifier(s.to_string()))
+        }
+    }
+
+    impl TryFrom<String> for Identifier {
+        type Error = Error;
+        fn try_from(value: String) -> Result<Self> {
+            Identifier::from_str(&value)
+        }
+    }
+
+    impl From<Identifier> for String {
+        fn from(value: Identifier) -> Self {
+            value.0
         }
     }
 
@@ -33,21 +60,23 @@ pub mod entity {
         }
     }
 
-    #[cfg(test)]
-    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
-    pub struct Version(pub String);
-
-    #[cfg(not(test))]
-    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
-    pub struct Version(String);
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[serde(try_from = "String", into = "String")]
+    pub struct Version(semver::Version);
 
     impl Version {
-        pub fn new<S: AsRef<str>>(version_str: S) -> Self {
-            Version(version_str.as_ref().to_owned())
+        pub fn new(major: u64, minor: u64, patch: u64) -> Self {
+            Version(semver::Version::new(major, minor, patch))
         }
 
-        pub fn as_bytes(&self) -> &[u8] {
-            self.0.as_bytes()
+        pub fn major(&self) -> u64 { self.0.major }
+        pub fn minor(&self) -> u64 { self.0.minor }
+        pub fn patch(&self) -> u64 { self.0.patch }
+        pub fn as_pre_release(&self) -> &str { self.0.pre.as_str() }
+        pub fn as_build(&self) -> &str { self.0.build.as_str() }
+
+        pub fn is_pre_release(&self) -> bool {
+            !self.0.pre.is_empty()
         }
     }
 
@@ -61,13 +90,258 @@ pub mod entity {
         type Err = Error;
 
         fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-            Ok(Version(s.to_string())) //TODO enforce semver through Version type ( from_str )
+            semver::Version::parse(s)
+                .map(Version)
+                .map_err(|error| Error::InvalidVersion { version: s.to_string(), error })
         }
     }
 
-    impl AsRef<str> for Version {
-        fn as_ref(&self) -> &str {
-            &self.0
+    impl TryFrom<String> for Version {
+        type Error = Error;
+        fn try_from(value: String) -> Result<Self> {
+            Version::from_str(&value)
+        }
+    }
+
+    impl From<Version> for String {
+        fn from(value: Version) -> Self {
+            value.to_string()
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+    #[serde(try_from = "String", into = "String")]
+    pub struct VersionRequirement(semver::VersionReq);
+
+    impl VersionRequirement {
+        pub fn any() -> Self {
+            VersionRequirement(semver::VersionReq::STAR)
+        }
+
+        pub fn exact(version: &Version) -> Self {
+            VersionRequirement(semver::VersionReq {
+                comparators: vec![semver::Comparator {
+                    op: semver::Op::Exact,
+                    major: version.0.major,
+                    minor: Some(version.0.minor),
+                    patch: Some(version.0.patch),
+                    pre: version.0.pre.clone(),
+                }],
+            })
+        }
+
+        pub fn matches(&self, version: &Version) -> bool {
+            self.0.matches(&version.0)
+        }
+    }
+
+    impl Default for VersionRequirement {
+        fn default() -> Self { VersionRequirement::any() }
+    }
+
+    impl fmt::Display for VersionRequirement {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            self.0.fmt(f)
+        }
+    }
+
+    impl FromStr for VersionRequirement {
+        type Err = Error;
+
+        fn from_str(s: &str) -> Result<Self> {
+            semver::VersionReq::parse(s)
+                .map(VersionRequirement)
+                .map_err(|error| Error::InvalidVersionRequirement { requirement: s.to_string(), error })
+        }
+    }
+
+    impl TryFrom<String> for VersionRequirement {
+        type Error = Error;
+        fn try_from(value: String) -> Result<Self> {
+            VersionRequirement::from_str(&value)
+        }
+    }
+
+    impl From<VersionRequirement> for String {
+        fn from(value: VersionRequirement) -> Self {
+            value.to_string()
+        }
+    }
+
+    /* Identifier constrained by a version requirement, written as `identifier@requirement` ( `identifier` alone stands for any version ) */
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct PackageRequirement {
+        identifier: Identifier,
+        version_requirement: VersionRequirement,
+    }
+
+    impl PackageRequirement {
+        pub fn new(identifier: Identifier, version_requirement: VersionRequirement) -> Self {
+            PackageRequirement { identifier, version_requirement }
+        }
+
+        pub fn as_identifier(&self) -> &Identifier { &self.identifier }
+        pub fn as_version_requirement(&self) -> &VersionRequirement { &self.version_requirement }
+
+        pub fn matches(&self, identifier: &Identifier, version: &Version) -> bool {
+            self.identifier == *identifier && self.version_requirement.matches(version)
+        }
+    }
+
+    impl fmt::Display for PackageRequirement {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}@{}", self.identifier, self.version_requirement)
+        }
+    }
+
+    impl FromStr for PackageRequirement {
+        type Err = Error;
+
+        fn from_str(s: &str) -> Result<Self> {
+            let (identifier, version_requirement) = match s.split_once('@') {
+                Some((identifier, requirement)) => (identifier, VersionRequirement::from_str(requirement)?),
+                None => (s, VersionRequirement::any()),
+            };
+            if identifier.is_empty() {
+                return Err(Error::InvalidPackageRequirement(s.to_string()));
+            }
+            Ok(PackageRequirement::new(Identifier::from_str(identifier)?, version_requirement))
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(untagged)]
+    pub enum ParameterValue {
+        Boolean(bool),
+        Integer(i64),
+        Float(f64),
+        String(String),
+    }
+
+    impl fmt::Display for ParameterValue {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                ParameterValue::Boolean(value) => value.fmt(f),
+                ParameterValue::Integer(value) => value.fmt(f),
+                ParameterValue::Float(value) => value.fmt(f),
+                ParameterValue::String(value) => value.fmt(f),
+            }
+        }
+    }
+
+    impl ParameterValue {
+        pub fn as_type(&self) -> ParameterType {
+            match self {
+                ParameterValue::Boolean(_) => ParameterType::Boolean,
+                ParameterValue::Integer(_) => ParameterType::Integer,
+                ParameterValue::Float(_) => ParameterType::Float,
+                ParameterValue::String(_) => ParameterType::String,
+            }
+        }
+
+        /* Strings ( as given on a command line ) are parsed into the expected type, integers are widened to floats */
+        fn into_type(self, expected: ParameterType) -> Option<ParameterValue> {
+            match (self, expected) {
+                (value, expected) if value.as_type() == expected => Some(value),
+                (ParameterValue::Integer(value), ParameterType::Float) => Some(ParameterValue::Float(value as f64)),
+                (ParameterValue::String(value), ParameterType::Boolean) => value.parse().ok().map(ParameterValue::Boolean),
+                (ParameterValue::String(value), ParameterType::Integer) => value.parse().ok().map(ParameterValue::Integer),
+                (ParameterValue::String(value), ParameterType::Float) => value.parse().ok().map(ParameterValue::Float),
+                _ => None,
+            }
+        }
+
+        fn to_canonical_string(&self) -> String {
+            match self {
+                ParameterValue::Boolean(value) => format!("boolean:{value}"),
+                ParameterValue::Integer(value) => format!("integer:{value}"),
+                ParameterValue::Float(value) => format!("float:{value:?}"),
+                ParameterValue::String(value) => format!("string:{value:?}"),
+            }
+        }
+    }
+
+    pub type Parameters = BTreeMap<String, ParameterValue>;
+
+    /* Unambiguous and stable textual form of parameters, keys being sorted */
+    pub fn to_canonical_parameters(parameters: &Parameters) -> String {
+        parameters.iter()
+            .map(|(key, value)| format!("{key:?}={}\n", value.to_canonical_string()))
+            .collect()
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    #[serde(rename_all = "lowercase")]
+    pub enum ParameterType {
+        Boolean,
+        Integer,
+        Float,
+        String,
+    }
+
+    impl fmt::Display for ParameterType {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                ParameterType::Boolean => "boolean".fmt(f),
+                ParameterType::Integer => "integer".fmt(f),
+                ParameterType::Float => "float".fmt(f),
+                ParameterType::String => "string".fmt(f),
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct ParameterDefinition {
+        #[serde(rename = "type")]
+        parameter_type: ParameterType,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        default: Option<ParameterValue>,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        required: bool,
+    }
+
+    impl ParameterDefinition {
+        pub fn as_type(&self) -> ParameterType { self.parameter_type }
+        pub fn as_default(&self) -> Option<&ParameterValue> { self.default.as_ref() }
+        pub fn is_required(&self) -> bool { self.required }
+    }
+
+    /* Parameters accepted by a package, declared in the [parameters] section of its manifest */
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+    #[serde(transparent)]
+    pub struct ParameterSchema(BTreeMap<String, ParameterDefinition>);
+
+    impl ParameterSchema {
+        pub fn is_empty(&self) -> bool {
+            self.0.is_empty()
+        }
+
+        pub fn iter(&self) -> impl Iterator<Item = (&String, &ParameterDefinition)> {
+            self.0.iter()
+        }
+
+        /* Checks given parameters against the schema and completes them with defaults */
+        pub fn apply(&self, mut parameters: Parameters) -> Result<Parameters> {
+            if let Some(unknown) = parameters.keys().find(|key| !self.0.contains_key(*key)) {
+                return Err(Error::UnknownParameter(unknown.clone()));
+            }
+
+            let mut applied = Parameters::new();
+            for (key, definition) in self.0.iter() {
+                let value = match parameters.remove(key).or_else(|| definition.default.clone()) {
+                    Some(value) => value,
+                    None if definition.required => return Err(Error::MissingParameter(key.clone())),
+                    None => continue,
+                };
+                let value = value.clone().into_type(definition.parameter_type)
+                    .ok_or_else(|| Error::InvalidParameterType {
+                        parameter: key.clone(),
+                        expected: definition.parameter_type.to_string(),
+                        value: value.to_string(),
+                    })?;
+                applied.insert(key.clone(), value);
+            }
+            Ok(applied)
         }
     }
 
@@ -81,9 +355,9 @@ pub mod entity {
         }
     }
 
-    impl ToString for Checksum {
-        fn to_string(&self) -> String {
-            hex::encode(&self.0)
+    impl fmt::Display for Checksum {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            hex::encode(&self.0).fmt(f)
         }
     }
 
@@ -98,4 +372,180 @@ pub mod entity {
             Checksum(value)
         }
     }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        fn test_identifier_accept_valid_input() -> Result<()> {
+            for valid in ["my-package", "a", "0-9", "-", &"a".repeat(IDENTIFIER_MAX_LENGTH)] {
+                assert_eq!(Identifier::from_str(valid)?.as_ref(), valid);
+            }
+            Ok(())
+        }
+
+        #[test]
+        fn test_identifier_reject_invalid_input() {
+            for invalid in ["", "My-Package", "my_package", "my.package", "my package", "pâquet", &"a".repeat(IDENTIFIER_MAX_LENGTH + 1)] {
+                assert!(
+                    matches!(Identifier::from_str(invalid), Err(Error::InvalidIdentifier(_))),
+                    "{invalid} should not be a valid identifier"
+                );
+            }
+        }
+
+        #[test]
+        fn test_version_parse_components() -> Result<()> {
+            let version = Version::from_str("1.2.3-alpha.1+build.5")?;
+
+            assert_eq!(version.major(), 1);
+            assert_eq!(version.minor(), 2);
+            assert_eq!(version.patch(), 3);
+            assert_eq!(version.as_pre_release(), "alpha.1");
+            assert_eq!(version.as_build(), "build.5");
+            assert_eq!(version.to_string(), "1.2.3-alpha.1+build.5");
+            Ok(())
+        }
+
+        #[test]
+        fn test_version_reject_invalid_input() {
+            for invalid in ["v1.2", "1.2", "latest-ish", "", "1.2.3.4", "01.2.3"] {
+                assert!(
+                    matches!(Version::from_str(invalid), Err(Error::InvalidVersion { .. })),
+                    "{invalid} should not be a valid version"
+                );
+            }
+        }
+
+        #[test]
+        fn test_version_ordering() -> Result<()> {
+            let mut versions = ["1.10.0", "1.2.0", "1.2.0-rc.1", "0.9.9", "1.2.0-alpha"]
+                .into_iter()
+                .map(Version::from_str)
+                .collect::<Result<Vec<_>>>()?;
+            versions.sort();
+
+            let sorted: Vec<String> = versions.iter().map(Version::to_string).collect();
+            assert_eq!(sorted, ["0.9.9", "1.2.0-alpha", "1.2.0-rc.1", "1.2.0", "1.10.0"]);
+            Ok(())
+        }
+
+        fn assert_requirement(requirement: &str, matching: &[&str], not_matching: &[&str]) -> Result<()> {
+            let requirement = VersionRequirement::from_str(requirement)?;
+            for version in matching {
+                assert!(requirement.matches(&Version::from_str(version)?), "{requirement} should match {version}");
+            }
+            for version in not_matching {
+                assert!(!requirement.matches(&Version::from_str(version)?), "{requirement} should not match {version}");
+            }
+            Ok(())
+        }
+
+        #[test]
+        fn test_version_requirement_matches() -> Result<()> {
+            assert_requirement("^1.2", &["1.2.0", "1.9.3"], &["1.1.9", "2.0.0"])?;
+            assert_requirement("~1.2.3", &["1.2.3", "1.2.9"], &["1.3.0", "1.2.2"])?;
+            assert_requirement(">=1, <2", &["1.0.0", "1.99.0"], &["0.9.0", "2.0.0"])?;
+            assert_requirement("*", &["0.0.1", "42.0.0"], &[])?;
+            assert_requirement("=1.2.3", &["1.2.3"], &["1.2.4", "1.2.2"])?;
+            Ok(())
+        }
+
+        #[test]
+        fn test_version_requirement_exact() -> Result<()> {
+            let version = Version::from_str("1.2.3-rc.1")?;
+            let requirement = VersionRequirement::exact(&version);
+
+            assert!(requirement.matches(&version));
+            assert!(!requirement.matches(&Version::new(1, 2, 3)));
+            Ok(())
+        }
+
+        #[test]
+        fn test_version_requirement_reject_invalid_input() {
+            assert!(matches!(
+                VersionRequirement::from_str("^^1"),
+                Err(Error::InvalidVersionRequirement { .. })
+            ));
+        }
+
+        #[test]
+        fn test_package_requirement_parse() -> Result<()> {
+            let requirement = PackageRequirement::from_str("my-package@^1")?;
+            assert_eq!(requirement.as_identifier().as_ref(), "my-package");
+            assert!(requirement.matches(&Identifier::from_str("my-package")?, &Version::new(1, 4, 0)));
+            assert!(!requirement.matches(&Identifier::from_str("my-package")?, &Version::new(2, 0, 0)));
+            assert!(!requirement.matches(&Identifier::from_str("other-package")?, &Version::new(1, 4, 0)));
+
+            let any = PackageRequirement::from_str("my-package")?;
+            assert_eq!(any.as_version_requirement(), &VersionRequirement::any());
+
+            assert!(matches!(
+                PackageRequirement::from_str("@^1"),
+                Err(Error::InvalidPackageRequirement(_))
+            ));
+            Ok(())
+        }
+
+        fn schema() -> ParameterSchema {
+            serde_json::from_value(serde_json::json!({
+                "port": { "type": "integer", "default": 80 },
+                "ratio": { "type": "float" },
+                "host": { "type": "string", "required": true }
+            })).unwrap()
+        }
+
+        #[test]
+        fn test_parameter_schema_apply_defaults_and_types() -> Result<()> {
+            let parameters = Parameters::from([
+                ("host".to_string(), ParameterValue::String("example.org".into())),
+                ("ratio".to_string(), ParameterValue::Integer(2)),
+            ]);
+            let applied = schema().apply(parameters)?;
+            assert_eq!(applied.get("port"), Some(&ParameterValue::Integer(80)));
+            assert_eq!(applied.get("ratio"), Some(&ParameterValue::Float(2.0)));
+
+            let parameters = Parameters::from([
+                ("host".to_string(), ParameterValue::String("example.org".into())),
+                ("port".to_string(), ParameterValue::String("8080".into())),
+            ]);
+            let applied = schema().apply(parameters)?;
+            assert_eq!(applied.get("port"), Some(&ParameterValue::Integer(8080)));
+            assert!(!applied.contains_key("ratio"));
+            Ok(())
+        }
+
+        #[test]
+        fn test_parameter_schema_reject_invalid_parameters() {
+            let host = ("host".to_string(), ParameterValue::String("example.org".into()));
+            assert!(matches!(schema().apply(Parameters::new()), Err(Error::MissingParameter(_))));
+            assert!(matches!(
+                schema().apply(Parameters::from([host.clone(), ("user".to_string(), ParameterValue::Boolean(true))])),
+                Err(Error::UnknownParameter(_))
+            ));
+            assert!(matches!(
+                schema().apply(Parameters::from([host, ("port".to_string(), ParameterValue::String("http".into()))])),
+                Err(Error::InvalidParameterType { .. })
+            ));
+        }
+
+        #[test]
+        fn test_canonical_parameters_are_unambiguous() {
+            let parameters = Parameters::from([
+                ("b".to_string(), ParameterValue::String("1\n\"a\"=integer:1".into())),
+                ("a".to_string(), ParameterValue::Integer(1)),
+            ]);
+            let other = Parameters::from([
+                ("b".to_string(), ParameterValue::String("1".into())),
+                ("a".to_string(), ParameterValue::Integer(1)),
+            ]);
+            assert_eq!(to_canonical_parameters(&other), "\"a\"=integer:1\n\"b\"=string:\"1\"\n");
+            assert_ne!(to_canonical_parameters(&parameters), to_canonical_parameters(&other));
+            assert_ne!(
+                to_canonical_parameters(&Parameters::from([("a".to_string(), ParameterValue::Integer(1))])),
+                to_canonical_parameters(&Parameters::from([("a".to_string(), ParameterValue::String("1".into()))]))
+            );
+        }
+    }
 }
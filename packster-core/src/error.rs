@@ -1,8 +1,8 @@
-use std::{fmt,error, path::PathBuf, string::FromUtf8Error};
+use std::{fmt,error, io, path::PathBuf, string::FromUtf8Error, time::Duration};
 
 use hex::FromHexError;
 
-use crate::application::path::Absolute;
+use crate::{application::path::Absolute, domain::entity::IDENTIFIER_MAX_LENGTH};
 
 #[derive(Debug)]
 pub enum Error {
@@ -26,7 +26,51 @@ pub enum Error {
     AlreadyPresentLockfile(PathBuf),
     NoFileNameInPath(PathBuf),
     InvalidUtf8Path(PathBuf),
-    WrongFileNameFormat(String, PathBuf)
+    WrongFileNameFormat(String, PathBuf),
+    InvalidVersion { version: String, error: semver::Error },
+    InvalidVersionRequirement { requirement: String, error: semver::Error },
+    InvalidPackageRequirement(String),
+    InvalidIdentifier(String),
+    Io(io::Error),
+    MissingPackageHeader(PathBuf),
+    InvalidPackageHeader(String, PathBuf),
+    PackageMetadataDoNotMatchFileName { package_path: PathBuf, expected_file_name: String },
+    UnresolvableDependency { dependent: String, requirement: String },
+    BundleNotInstalledInLocation(String),
+    UnknownParameter(String),
+    MissingParameter(String),
+    InvalidParameterType { parameter: String, expected: String, value: String },
+    HookFailed { event: String, command: String, status: Option<i32>, output: String },
+    ExecutionTimedOut { command: String, timeout: Duration },
+    CompensationFailed { error: Box<Error>, compensation_error: Box<Error> },
+    LocationLocked { path: PathBuf, pid: u32, hostname: String },
+    LocationLockedByUnknownOwner(PathBuf),
+    LocationNotInitialized(PathBuf),
+    LockNotHeld(PathBuf),
+    LockfileSchemaTooRecent { path: PathBuf, version: u32, supported_version: u32 },
+    LocationVerificationFailed(usize),
+    RepairPackageNotFound { deployment_id: String, package_id: String, version: String },
+    NoDeploymentToUpgrade(String),
+    UpgradeVersionNotGreater { package_id: String, deployed_version: String, version: String },
+    InvalidDeploymentSelector(String),
+    DeploymentNotFound(String),
+    AmbiguousDeploymentSelector { selector: String, deployment_ids: Vec<String> },
+    ActiveDeploymentUndeployment { deployment_id: String, package_id: String },
+    RequiredDeploymentUndeployment { deployment_id: String, package_id: String, dependents: Vec<String> },
+    InvalidShimName(String),
+    InvalidShimTarget(String),
+    ShimCollision { command: String, package: String, other_package: String },
+    ShimPathOccupied(PathBuf),
+    InvalidEnvironmentVariableName(String),
+    UnknownEnvironmentPlaceholder(String),
+    EnvironmentVariableCollision { variable: String, package: String, other_package: String },
+    ResourceConflict { resource: String, deployment_id: String, package: String, other_deployment_id: String, other_package: String },
+    LocationNameAlreadyRegistered { name: String, path: PathBuf },
+    LocationNotGiven,
+    PackageNotFoundInSource { requirement: String, source: String },
+    PackageVersionAlreadyPublished { package: String, source: String, checksum: String },
+    NoSourceRegistered,
+    CacheVerificationFailed(usize),
 }
 
 impl fmt::Display for Error {
@@ -34,6 +78,7 @@ impl fmt::Display for Error {
         use Error::*;
         match self {
             Infrastructure(error) => write!(f, "Infrastructure error : {error}"),
+            Io(error) => write!(f, "Io error : {error}"),
             Application(error) => write!(f, "Application error : {error}"),
             FromUtf8Error(error) => write!(f, "Utf8 conversion error : {error}"),
             HexadecimalDecodingError(error) => write!(f, "Hexadecimal decoding error : {error}"),
@@ -60,6 +105,121 @@ impl fmt::Display for Error {
             NoFileNameInPath(path) => write!(f, "No filename in path {}", path.to_string_lossy()),
             InvalidUtf8Path(path) => write!(f, "Path {} contains invalid utf8 chacacters", path.to_string_lossy()),
             WrongFileNameFormat(scope, path) => write!(f, "Path {} has a wrong filename formatting : {}", path.to_string_lossy(), scope),
+            InvalidVersion { version, error } => write!(f, "Version \"{version}\" is not a valid semantic version : {error}"),
+            InvalidVersionRequirement { requirement, error } => write!(f, "Version requirement \"{requirement}\" is not valid : {error}"),
+            InvalidPackageRequirement(requirement) => write!(f, "Package requirement \"{requirement}\" shall be formatted as identifier@requirement"),
+            InvalidIdentifier(identifier) => write!(
+                f,
+                "Identifier \"{identifier}\" shall be 1 to {IDENTIFIER_MAX_LENGTH} characters long and only contain lowercase letters, digits and dashes"
+            ),
+            MissingPackageHeader(path) => write!(f, "Package {} has no packster header, it shall be packed again", path.to_string_lossy()),
+            InvalidPackageHeader(scope, path) => write!(f, "Package {} has an invalid header : {}", path.to_string_lossy(), scope),
+            PackageMetadataDoNotMatchFileName { package_path, expected_file_name } => write!(
+                f,
+                "Package {} file name does not match its metadata, expected file name is {}",
+                package_path.to_string_lossy(),
+                expected_file_name
+            ),
+            UnresolvableDependency { dependent, requirement } => write!(f, "No deployed nor available package satisfies {requirement} required by {dependent}"),
+            BundleNotInstalledInLocation(bundle_id) => write!(f, "Bundle {bundle_id} not installed in location"),
+            UnknownParameter(parameter) => write!(f, "Parameter \"{parameter}\" is not declared by the package"),
+            MissingParameter(parameter) => write!(f, "Parameter \"{parameter}\" is required"),
+            InvalidParameterType { parameter, expected, value } => write!(f, "Parameter \"{parameter}\" shall be of type {expected}, got \"{value}\""),
+            HookFailed { event, command, status: Some(status), output } => write!(f, "Hook {event} \"{command}\" failed with exit status {status} :\n{output}"),
+            HookFailed { event, command, status: None, output } => write!(f, "Hook {event} \"{command}\" was terminated :\n{output}"),
+            ExecutionTimedOut { command, timeout } => write!(f, "Command \"{command}\" timed out after {} seconds", timeout.as_secs_f64()),
+            LocationLocked { path, pid, hostname } => write!(
+                f,
+                "Location is locked by process {pid} on {hostname}, remove {} if this process is gone",
+                path.to_string_lossy()
+            ),
+            LocationLockedByUnknownOwner(path) => write!(
+                f,
+                "Location is locked by a process which has not written its identity in {} yet",
+                path.to_string_lossy()
+            ),
+            LocationNotInitialized(path) => write!(f, "Location {} is not initialized, see location init", path.to_string_lossy()),
+            LockNotHeld(path) => write!(f, "Lock {} is not held by the current operation", path.to_string_lossy()),
+            LockfileSchemaTooRecent { path, version, supported_version } => write!(
+                f,
+                "Lockfile {} has schema version {version} while this packster supports up to {supported_version}, packster shall be upgraded",
+                path.to_string_lossy()
+            ),
+            LocationVerificationFailed(issue_count) => write!(f, "Location verification failed with {issue_count} issue(s)"),
+            RepairPackageNotFound { deployment_id, package_id, version } => write!(
+                f,
+                "Deployment {deployment_id} is damaged but no package file of {package_id} {version} was given to repair it"
+            ),
+            NoDeploymentToUpgrade(package_id) => write!(f, "Package {package_id} is not deployed in location, there is nothing to upgrade"),
+            UpgradeVersionNotGreater { package_id, deployed_version, version } => write!(
+                f,
+                "Package {package_id} {version} is not greater than the deployed version {deployed_version}"
+            ),
+            InvalidDeploymentSelector(selector) => write!(
+                f,
+                "Deployment selector \"{selector}\" shall be formatted as identifier@version or identifier@checksum"
+            ),
+            DeploymentNotFound(selector) => write!(f, "No deployment matches {selector} in location"),
+            AmbiguousDeploymentSelector { selector, deployment_ids } => write!(
+                f,
+                "Several deployments match {selector} : {}, select one by its identifier",
+                deployment_ids.join(", ")
+            ),
+            ActiveDeploymentUndeployment { deployment_id, package_id } => write!(
+                f,
+                "Deployment {deployment_id} is the active one of {package_id}, activate another one first or reassign it to the newest remaining one"
+            ),
+            RequiredDeploymentUndeployment { deployment_id, package_id, dependents } => write!(
+                f,
+                "Deployment {deployment_id} of {package_id} is required by {}, undeploy them first or force the undeployment",
+                dependents.join(", ")
+            ),
+            InvalidShimName(name) => write!(
+                f,
+                "Shim name \"{name}\" shall only contain letters, digits, dashes, underscores and dots, and not start with a dot"
+            ),
+            InvalidShimTarget(target) => write!(f, "Shim target \"{target}\" shall be a relative path inside the package"),
+            ShimCollision { command, package, other_package } => write!(
+                f,
+                "Command {command} is claimed by the shims of both {package} and {other_package}"
+            ),
+            ShimPathOccupied(path) => write!(f, "Shim path {} is occupied by a file which is not a shim", path.to_string_lossy()),
+            InvalidEnvironmentVariableName(name) => write!(
+                f,
+                "Environment variable name \"{name}\" shall be made of letters, digits and underscores, PATH and the PACKSTER_ prefix being reserved"
+            ),
+            UnknownEnvironmentPlaceholder(placeholder) => write!(
+                f,
+                "Environment placeholder ${{{placeholder}}} is unknown, use deployment_path, location_path, identifier or version"
+            ),
+            EnvironmentVariableCollision { variable, package, other_package } => write!(
+                f,
+                "Environment variable {variable} is declared by both {package} and {other_package}"
+            ),
+            ResourceConflict { resource, deployment_id, package, other_deployment_id, other_package } => write!(
+                f,
+                "{resource} claimed by deployment {deployment_id} of {package} is already claimed by deployment {other_deployment_id} of {other_package}"
+            ),
+            LocationNameAlreadyRegistered { name, path } => write!(
+                f,
+                "Location name {name} is already registered for {}",
+                path.to_string_lossy()
+            ),
+            LocationNotGiven => write!(
+                f,
+                "No location given, nor set through PACKSTER_LOCATION, and no default location registered"
+            ),
+            PackageNotFoundInSource { requirement, source } => write!(f, "No package matching {requirement} in source {source}"),
+            PackageVersionAlreadyPublished { package, source, checksum } => write!(
+                f,
+                "Package {package} is already published in source {source} with checksum {checksum}, a published version can not be overwritten"
+            ),
+            NoSourceRegistered => write!(f, "No source registered in the user config, register one with packster source add"),
+            CacheVerificationFailed(issue_count) => write!(f, "Cache verification failed with {issue_count} issue(s)"),
+            CompensationFailed { error, compensation_error } => write!(
+                f,
+                "{error}\nThe location could not be restored to its previous state : {compensation_error}"
+            ),
         }
     }
 }
@@ -70,8 +230,12 @@ impl error::Error for Error {
         match self {
             Infrastructure(error) => Some(error.as_ref()),
             Application(error) => Some(error.as_ref()),
+            Io(error) => Some(error),
             FromUtf8Error(error) => Some(error),
             HexadecimalDecodingError(error) => Some(error),
+            InvalidVersion { error, .. } => Some(error),
+            InvalidVersionRequirement { error, .. } => Some(error),
+            CompensationFailed { error, .. } => Some(error.as_ref()),
             _ => None,
         }
     }
@@ -82,6 +246,10 @@ impl From<FromHexError> for Error {
 }
 
 
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self { Error::Io(error) }
+}
+
 impl From<FromUtf8Error> for Error {
     fn from(error: FromUtf8Error) -> Self { Error::FromUtf8Error(error) }
 }
@@ -3,4 +3,12 @@ pub mod domain;
 
 pub const PACKAGE_EXTENSION : &str = "packster";
 pub const LOCKFILE_NAME : &str = "packster.lock";
-pub const PROJECT_MANIFEST_NAME : &str = "packster.toml";
\ No newline at end of file
+pub const LOCATION_LOCK_NAME : &str = ".packster.lck";
+pub const LOCATION_BIN_DIRECTORY_NAME : &str = "bin";
+pub const USER_CONFIG_DIRECTORY_NAME : &str = "packster";
+pub const USER_CONFIG_NAME : &str = "packster-config.toml";
+pub const SOURCE_INDEX_NAME : &str = "packster-index.json";
+pub const PACKAGE_CACHE_DIRECTORY_NAME : &str = ".packster-cache";
+pub const PACKAGE_CACHE_INDEX_NAME : &str = "packster-cache.json";
+pub const PROJECT_MANIFEST_NAME : &str = "packster.toml";
+pub const PACKAGE_HEADER_MAGIC : &[u8] = b"PACKSTER";
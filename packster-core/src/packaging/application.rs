@@ -9,26 +9,99 @@ pub use pack::*;
 mod init_location;
 pub use init_location::*;
 
+mod user_config;
+pub use user_config::*;
+
+mod search_source;
+pub use search_source::*;
+
+mod index_source;
+pub use index_source::*;
+
+mod publish;
+pub use publish::*;
+
+mod unpublish;
+pub use unpublish::*;
+
+mod install;
+pub use install::*;
+
+mod register_source;
+pub use register_source::*;
+
 mod deploy;
 pub use deploy::*;
 
 mod undeploy;
 pub use undeploy::*;
 
+mod upgrade;
+pub use upgrade::*;
+
+mod use_deployment;
+pub use use_deployment::*;
+
 mod show_location;
 pub use show_location::*;
 
-use crate::application::{operation::Operation, path::Absolute};
+mod location_environment;
+pub use location_environment::*;
+
+mod install_bundle;
+pub use install_bundle::*;
+
+mod delete_bundle;
+pub use delete_bundle::*;
+
+mod migrate_location;
+pub use migrate_location::*;
+
+mod verify_location;
+pub use verify_location::*;
+
+mod collect_garbage;
+pub use collect_garbage::*;
+
+mod repair_location;
+pub use repair_location::*;
+
+mod cache_package;
+pub use cache_package::*;
+
+mod package_cache;
+pub use package_cache::*;
+
+mod prune_cache;
+pub use prune_cache::*;
+
+mod verify_cache;
+pub use verify_cache::*;
+
+use crate::{
+    application::{operation::Operation, path::Absolute},
+    domain::entity::Checksum,
+};
 
 use super::{
-    domain::{AsLocation, AsPackage, DeployLocation, Package},
-    LOCKFILE_NAME,
+    domain::{AsBundle, AsDeployment, AsLocation, AsPackage, Bundle, CachedPackage, DeployLocation, Deployment, Package},
+    LOCATION_BIN_DIRECTORY_NAME, LOCATION_LOCK_NAME, LOCKFILE_NAME, PACKAGE_CACHE_INDEX_NAME, SOURCE_INDEX_NAME,
 };
 
 pub trait AsPackagePath {
     fn as_package_path(&self) -> Absolute<&Path>;
 }
 
+pub trait AsBundlePath {
+    fn as_bundle_path(&self) -> Absolute<&Path>;
+}
+
+impl<S: AsBundle, R> AsBundle for Operation<S, R> {
+    fn as_bundle(&self) -> &Bundle {
+        self.as_state().as_bundle()
+    }
+}
+
 // Forward to all operations containing state that implement this trait
 impl<S: AsPackage, R> AsPackage for Operation<S, R> {
     fn as_package(&self) -> &Package {
@@ -36,14 +109,52 @@ impl<S: AsPackage, R> AsPackage for Operation<S, R> {
     }
 }
 
+impl<S: AsDeployment, R> AsDeployment for Operation<S, R> {
+    fn as_deployment(&self) -> &Deployment {
+        self.as_state().as_deployment()
+    }
+}
+
 pub trait AsLocationPath {
     fn as_location_path(&self) -> Absolute<&Path>;
     fn to_location_lockfile_path(&self) -> Absolute<PathBuf> {
         self.as_location_path().join(LOCKFILE_NAME)
     }
+    fn to_location_lock_path(&self) -> Absolute<PathBuf> {
+        self.as_location_path().join(LOCATION_LOCK_NAME)
+    }
+    fn to_deployment_path(&self, deployment_id: &Checksum) -> Absolute<PathBuf> {
+        self.as_location_path().join(deployment_id.to_string())
+    }
+    fn to_location_bin_path(&self) -> Absolute<PathBuf> {
+        self.as_location_path().join(LOCATION_BIN_DIRECTORY_NAME)
+    }
+}
+pub trait AsSourcePath {
+    fn as_source_path(&self) -> Absolute<&Path>;
+    fn to_source_index_path(&self) -> Absolute<PathBuf> {
+        self.as_source_path().join(SOURCE_INDEX_NAME)
+    }
 }
+
 impl<S: AsLocation, R> AsLocation for Operation<S, R> {
     fn as_location(&self) -> &DeployLocation {
         self.as_state().as_location()
     }
 }
+
+pub trait AsCachePath {
+    fn as_cache_path(&self) -> Absolute<&Path>;
+    fn to_cache_index_path(&self) -> Absolute<PathBuf> {
+        self.as_cache_path().join(PACKAGE_CACHE_INDEX_NAME)
+    }
+    fn to_cache_lock_path(&self) -> Absolute<PathBuf> {
+        self.as_cache_path().join(LOCATION_LOCK_NAME)
+    }
+    fn to_cached_package_directory(&self, checksum: &Checksum) -> Absolute<PathBuf> {
+        self.as_cache_path().join(checksum.to_string())
+    }
+    fn to_cached_package_path(&self, package: &CachedPackage) -> Absolute<PathBuf> {
+        self.as_cache_path().join(package.to_path())
+    }
+}
@@ -0,0 +1,139 @@
+use std::{io, path::{Path, PathBuf}};
+use crate::{
+    application::{
+        operation::{Compensate, New, Operation},
+        path::Absolute,
+        port::{Clock, Digester, FileSystem},
+    },
+    packaging::domain::{AsPackage, CachedPackage, Package, PackageCache},
+    Result,
+};
+use super::{validate_checksum, AsCachePath, AsPackagePath, LockedCache, ParsedPackage, ParsedPackageCache, PersistedPackageCache};
+
+pub struct CachePackageRequest {
+    package_path: Absolute<PathBuf>,
+    cache_directory: Absolute<PathBuf>,
+}
+
+impl CachePackageRequest {
+    pub fn new(package_path: Absolute<PathBuf>, cache_directory: Absolute<PathBuf>) -> Self {
+        CachePackageRequest { package_path, cache_directory }
+    }
+}
+
+pub type CachePackageOperation<S> = Operation<S, CachePackageRequest>;
+
+impl<S> AsPackagePath for CachePackageOperation<S> {
+    fn as_package_path(&self) -> Absolute<&Path> {
+        self.as_request().package_path.as_absolute_path()
+    }
+}
+
+impl<S> AsCachePath for CachePackageOperation<S> {
+    fn as_cache_path(&self) -> Absolute<&Path> {
+        self.as_request().cache_directory.as_absolute_path()
+    }
+}
+
+pub struct StoredPackage {
+    previous_state: ParsedPackageCache<LockedCache<ParsedPackage<New>>>,
+    cache: PackageCache,
+    cached_path: Absolute<PathBuf>,
+    is_copied: bool,
+}
+
+impl CachePackageOperation<ParsedPackageCache<LockedCache<ParsedPackage<New>>>> {
+    /* A package already cached is only marked as used, others are copied once their checksum is validated */
+    pub fn store_package<F: FileSystem, D: Digester, C: Clock>(
+        self,
+        filesystem: &F,
+        digester: &D,
+        clock: &C,
+    ) -> Result<CachePackageOperation<StoredPackage>> {
+        let package = self.as_package();
+        let cache = self.as_state().as_ref();
+        if let Some(cached_package) = cache.find(package.as_checksum()) {
+            let cached_path = self.to_cached_package_path(cached_package);
+            if filesystem.is_file(&cached_path) {
+                let cache = cache.with_package(cached_package.used_at(clock.now()));
+                return Self::ok_with_state(
+                    self.request,
+                    StoredPackage {
+                        previous_state: self.state,
+                        cache,
+                        cached_path,
+                        is_copied: false,
+                    },
+                );
+            }
+        }
+
+        validate_checksum(filesystem, digester, self.as_package_path(), package)?;
+        let package_directory = self.to_cached_package_directory(package.as_checksum());
+        let cached_path = package_directory.join(package.to_file_name());
+        if let Err(error) = copy_package_file(filesystem, self.as_package_path(), &package_directory, cached_path.as_absolute_path()) {
+            if filesystem.exists(&package_directory) {
+                filesystem.remove_dir_all(&package_directory)?;
+            }
+            return Err(error);
+        }
+
+        let cached_package = CachedPackage::new(package, filesystem.file_size(&cached_path)?, clock.now());
+        let cache = cache.with_package(cached_package);
+        Self::ok_with_state(
+            self.request,
+            StoredPackage {
+                previous_state: self.state,
+                cache,
+                cached_path,
+                is_copied: true,
+            },
+        )
+    }
+}
+
+/* Copies a package file in its own directory of the cache */
+pub fn copy_package_file<F: FileSystem>(
+    filesystem: &F,
+    package_path: Absolute<&Path>,
+    package_directory: &Absolute<PathBuf>,
+    cached_path: Absolute<&Path>,
+) -> Result<()> {
+    filesystem.create_dir_recursively(package_directory)?;
+    let mut reader = filesystem.open_read(package_path)?;
+    let mut writer = filesystem.open_write(cached_path)?;
+    io::copy(&mut reader, &mut writer)?;
+    Ok(())
+}
+
+impl Compensate for StoredPackage {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        match self.cached_path.parent() {
+            Some(package_directory) if self.is_copied => filesystem.remove_dir_all(package_directory),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl AsRef<PackageCache> for StoredPackage {
+    fn as_ref(&self) -> &PackageCache {
+        &self.cache
+    }
+}
+
+impl AsPackage for StoredPackage {
+    fn as_package(&self) -> &Package {
+        self.previous_state.as_package()
+    }
+}
+
+impl CachePackageOperation<PersistedPackageCache<StoredPackage>> {
+    pub fn as_cached_path(&self) -> Absolute<&Path> {
+        self.as_state().previous_state.cached_path.as_absolute_path()
+    }
+
+    /* The same package was already cached, so nothing was copied */
+    pub fn is_already_cached(&self) -> bool {
+        !self.as_state().previous_state.is_copied
+    }
+}
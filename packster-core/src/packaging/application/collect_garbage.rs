@@ -0,0 +1,82 @@
+use std::path::{Path, PathBuf};
+use crate::{
+    application::{
+        operation::{New, Operation},
+        path::Absolute,
+        port::{Digester, FileSystem, ReadOnlyFileSystem},
+    },
+    Result,
+};
+use super::{find_orphan_deployment_directories, AsLocationPath, LockedLocation, ParsedLocation};
+
+pub struct CollectGarbageRequest {
+    location_path: Absolute<PathBuf>,
+}
+
+impl CollectGarbageRequest {
+    pub fn new(location_path: Absolute<PathBuf>) -> Self {
+        CollectGarbageRequest { location_path }
+    }
+}
+
+pub type CollectGarbageOperation<S> = Operation<S, CollectGarbageRequest>;
+
+impl<S> AsLocationPath for CollectGarbageOperation<S> {
+    fn as_location_path(&self) -> Absolute<&Path> {
+        self.as_request().location_path.as_absolute_path()
+    }
+}
+
+pub struct FoundOrphanDirectories {
+    pub previous_state: ParsedLocation<LockedLocation<New>>,
+    orphan_paths: Vec<Absolute<PathBuf>>,
+}
+
+impl CollectGarbageOperation<ParsedLocation<LockedLocation<New>>> {
+    pub fn find_orphan_directories<F: ReadOnlyFileSystem, D: Digester>(
+        self,
+        filesystem: &F,
+        digester: &D,
+    ) -> Result<CollectGarbageOperation<FoundOrphanDirectories>> {
+        let orphan_paths = find_orphan_deployment_directories(filesystem, digester, self.as_location_path(), &self.as_state().location)?;
+        Self::ok_with_state(
+            self.request,
+            FoundOrphanDirectories {
+                previous_state: self.state,
+                orphan_paths,
+            },
+        )
+    }
+}
+
+// Stopping there is a dry run
+impl CollectGarbageOperation<FoundOrphanDirectories> {
+    pub fn as_orphan_paths(&self) -> &[Absolute<PathBuf>] {
+        &self.as_state().orphan_paths
+    }
+}
+
+pub struct RemovedOrphanDirectories {
+    pub previous_state: FoundOrphanDirectories,
+}
+
+impl CollectGarbageOperation<FoundOrphanDirectories> {
+    /* Orphan directories are referenced by no deployment, there is nothing to compensate */
+    pub fn remove_orphan_directories<F: FileSystem>(self, filesystem: &F) -> Result<CollectGarbageOperation<RemovedOrphanDirectories>> {
+        for orphan_path in self.as_orphan_paths() {
+            filesystem.remove_dir_all(orphan_path)?;
+        }
+        Self::ok_with_state(
+            self.request,
+            RemovedOrphanDirectories {
+                previous_state: self.state,
+            },
+        )
+    }
+}
+
+impl CollectGarbageOperation<RemovedOrphanDirectories> {
+    pub fn as_removed_paths(&self) -> &[Absolute<PathBuf>] {
+        &self.as_state().previous_state.orphan_paths
+    }
+}
@@ -0,0 +1,161 @@
+use std::path::{Path, PathBuf};
+use crate::{
+    application::{
+        operation::{Compensate, New, Operation},
+        path::Absolute,
+        port::{Executor, FileSystem},
+    },
+    packaging::domain::{AsBundle, AsLocation, DeployLocation, Deployment, HookEvent},
+    Error, Result,
+};
+use super::{
+    LockedLocation,
+    execute_hooks, to_deployment_hook_environment, AsBundlePath, AsLocationPath, ParsedBundle,
+    ParsedLocation, GeneratedShims, PersistedDeployLocation,
+};
+
+pub struct DeleteBundleRequest {
+    bundle_path: Absolute<PathBuf>,
+    location_path: Absolute<PathBuf>,
+}
+
+impl DeleteBundleRequest {
+    pub fn new(bundle_path: Absolute<PathBuf>, location_path: Absolute<PathBuf>) -> Self {
+        DeleteBundleRequest {
+            bundle_path,
+            location_path,
+        }
+    }
+}
+
+pub type DeleteBundleOperation<S> = Operation<S, DeleteBundleRequest>;
+
+impl<S> AsBundlePath for DeleteBundleOperation<S> {
+    fn as_bundle_path(&self) -> Absolute<&Path> {
+        self.as_request().bundle_path.as_absolute_path()
+    }
+}
+
+impl<S> AsLocationPath for DeleteBundleOperation<S> {
+    fn as_location_path(&self) -> Absolute<&Path> {
+        self.as_request().location_path.as_absolute_path()
+    }
+}
+
+pub struct BundleInstalled {
+    previous_state: ParsedLocation<ParsedBundle<LockedLocation<New>>>,
+}
+
+impl DeleteBundleOperation<ParsedLocation<ParsedBundle<LockedLocation<New>>>> {
+    pub fn probe_bundle_installed_in_location(self) -> Result<DeleteBundleOperation<BundleInstalled>> {
+        let identifier = self.as_bundle().as_identifier();
+        if self.as_location().get_bundle(identifier).is_none() {
+            return Err(Error::BundleNotInstalledInLocation(identifier.to_string()));
+        }
+        Self::ok_with_state(
+            self.request,
+            BundleInstalled {
+                previous_state: self.state,
+            },
+        )
+    }
+}
+
+pub struct LocationWithoutBundle {
+    previous_state: BundleInstalled,
+    removed_deployments: Vec<Deployment>,
+}
+
+impl DeleteBundleOperation<BundleInstalled> {
+    pub fn remove_bundle_from_location(mut self) -> DeleteBundleOperation<LocationWithoutBundle> {
+        let identifier = self.as_state().previous_state.as_bundle().as_identifier().clone();
+        let removed_deployments = self
+            .as_mut_state()
+            .previous_state
+            .location
+            .remove_bundle(&identifier);
+
+        Self::with_state(
+            self.request,
+            LocationWithoutBundle {
+                previous_state: self.state,
+                removed_deployments,
+            },
+        )
+    }
+}
+
+pub struct HookedBundleRemoval {
+    previous_state: LocationWithoutBundle,
+}
+
+impl Compensate for HookedBundleRemoval {
+    fn compensate<F: FileSystem>(&self, _filesystem: &F) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl DeleteBundleOperation<LocationWithoutBundle> {
+    pub fn execute_bundle_undeploy_hooks<E: Executor>(self, executor: &E) -> Result<DeleteBundleOperation<HookedBundleRemoval>> {
+        for deployment in self.as_state().removed_deployments.iter() {
+            let deployment_path = self.to_deployment_path(deployment.as_id());
+            execute_hooks(
+                executor,
+                deployment.as_ref().as_hooks(),
+                HookEvent::Undeploy,
+                deployment_path.as_absolute_path(),
+                to_deployment_hook_environment(
+                    self.as_location_path(),
+                    deployment_path.as_absolute_path(),
+                    deployment,
+                ),
+            )?;
+        }
+        Self::ok_with_state(
+            self.request,
+            HookedBundleRemoval {
+                previous_state: self.state,
+            },
+        )
+    }
+}
+
+impl AsRef<DeployLocation> for HookedBundleRemoval {
+    fn as_ref(&self) -> &DeployLocation {
+        &self.previous_state.previous_state.previous_state.location
+    }
+}
+
+pub struct BundleDeploymentDirectoriesDeleted {
+    previous_state: GeneratedShims<PersistedDeployLocation<HookedBundleRemoval>>,
+}
+
+impl DeleteBundleOperation<GeneratedShims<PersistedDeployLocation<HookedBundleRemoval>>> {
+    fn as_removed_deployments(&self) -> &[Deployment] {
+        &self.as_state().previous_state.previous_state.previous_state.removed_deployments
+    }
+
+    pub fn delete_bundle_deployment_directories<F: FileSystem>(
+        self,
+        filesystem: &F,
+    ) -> Result<DeleteBundleOperation<BundleDeploymentDirectoriesDeleted>> {
+        for deployment in self.as_removed_deployments() {
+            let deployment_path = self.to_deployment_path(deployment.as_id());
+            if filesystem.exists(&deployment_path) {
+                filesystem.remove_dir_all(deployment_path)?;
+            }
+        }
+        Self::ok_with_state(
+            self.request,
+            BundleDeploymentDirectoriesDeleted {
+                previous_state: self.state,
+            },
+        )
+    }
+}
+
+impl DeleteBundleOperation<BundleDeploymentDirectoriesDeleted> {
+    pub fn as_removed_deployments(&self) -> &[Deployment] {
+        &self.as_state().previous_state.previous_state.previous_state.previous_state.removed_deployments
+    }
+}
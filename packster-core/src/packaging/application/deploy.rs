@@ -1,29 +1,35 @@
-use std::path::{Path, PathBuf};
+use std::{path::{Path, PathBuf}, str::FromStr};
 use crate::{
     Result,
     application::{
-        operation::{AsChecksum, New, Operation},
+        operation::{AsChecksum, Compensate, New, Operation},
         path::Absolute,
-        port::{Archiver, FileSystem},
+        port::{Archiver, Digester, Executor, FileSystem},
+    },
+    domain::entity::{Checksum, Parameters, Version},
+    packaging::domain::{
+        AsDeployment, DeployLocation, DeployedDependencies, DeployedFile, Deployment, DeploymentPath, HookEvent,
+        NoResourceConflict, NotYetDeployed, Package,
     },
-    domain::entity::Checksum,
-    packaging::domain::{DeployLocation, Deployment, DeploymentPath, NotYetDeployed, Package},
 };
 use super::{
-    AsLocationPath, AsPackage, AsPackagePath, MatchingChecksum, ParsedLocation, ParsedPackage,
-    PersistedDeployLocation,
+    LockedLocation,
+    execute_hooks, extract_package_payload, index_deployment_files, remove_deployment_directory, to_deployment_hook_environment, AsLocation, AsLocationPath, AsPackage, AsPackagePath, MatchingChecksum,
+    ParsedLocation, ParsedPackage, GeneratedShims, PersistedDeployLocation,
 };
 
 pub struct DeployRequest {
     package_path: Absolute<PathBuf>,
     location_path: Absolute<PathBuf>,
+    parameters: Parameters,
 }
 
 impl DeployRequest {
-    pub fn new(package_path: Absolute<PathBuf>, location_path: Absolute<PathBuf>) -> Self {
+    pub fn new(package_path: Absolute<PathBuf>, location_path: Absolute<PathBuf>, parameters: Parameters) -> Self {
         DeployRequest {
             package_path,
             location_path,
+            parameters,
         }
     }
 }
@@ -42,18 +48,74 @@ impl<S> AsLocationPath for DeployOperation<S> {
     }
 }
 
-impl<S: AsPackage> AsChecksum for DeployOperation<S> {
+// The deployment directory is named after the deployment identifier
+impl<S: AsDeployment> AsChecksum for DeployOperation<S> {
     fn as_checksum(&self) -> &Checksum {
-        self.as_state().as_package().as_checksum()
+        self.as_state().as_deployment().as_id()
+    }
+}
+
+pub struct IdentifiedDeployment<S> {
+    previous_state: S,
+    deployment: Deployment,
+}
+
+impl<S: AsPackage> AsPackage for IdentifiedDeployment<S> {
+    fn as_package(&self) -> &Package {
+        self.previous_state.as_package()
+    }
+}
+
+impl<S: AsLocation> AsLocation for IdentifiedDeployment<S> {
+    fn as_location(&self) -> &DeployLocation {
+        self.previous_state.as_location()
+    }
+}
+
+impl<S> AsDeployment for IdentifiedDeployment<S> {
+    fn as_deployment(&self) -> &Deployment {
+        &self.deployment
+    }
+}
+
+pub type DeployParsedState = ParsedLocation<ParsedPackage<LockedLocation<New>>>;
+
+impl DeployOperation<DeployParsedState> {
+    pub fn identify_deployment<D: Digester>(
+        self,
+        digester: &D,
+    ) -> Result<DeployOperation<IdentifiedDeployment<DeployParsedState>>> {
+        let deployment = Deployment::identify(
+            self.as_package().clone(),
+            self.as_request().parameters.clone(),
+            digester,
+        )?;
+        Self::ok_with_state(
+            self.request,
+            IdentifiedDeployment {
+                previous_state: self.state,
+                deployment,
+            },
+        )
     }
 }
 
-pub type DeployValidState =
-    DeploymentPath<MatchingChecksum<NotYetDeployed<ParsedLocation<ParsedPackage<New>>>>>;
+pub type DeployValidState = DeploymentPath<
+    MatchingChecksum<
+        NoResourceConflict<DeployedDependencies<NotYetDeployed<IdentifiedDeployment<DeployParsedState>>>>,
+    >,
+>;
 
 impl AsMut<DeployLocation> for DeployValidState {
     fn as_mut(&mut self) -> &mut DeployLocation {
-        &mut self.previous_state.previous_state.previous_state.location
+        &mut self
+            .previous_state
+            .previous_state
+            .previous_state
+            .previous_state
+            .previous_state
+            .previous_state
+            .location
     }
 }
 
@@ -67,10 +129,12 @@ impl DeployOperation<DeployValidState> {
         filesystem: &F,
         archiver: &A,
     ) -> Result<DeployOperation<ExtractedPackage>> {
-        archiver.extract(
+        extract_package_payload(
             filesystem,
+            archiver,
+            &self.as_request().package_path,
+            self.as_package(),
             self.as_state().deployment_path.as_absolute_path(),
-            self.as_request().package_path.as_absolute_path(),
         )?;
         Self::ok_with_state(
             self.request,
@@ -81,30 +145,120 @@ impl DeployOperation<DeployValidState> {
     }
 }
 
-pub struct LocationWithNewDeployment {
+impl Compensate for ExtractedPackage {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        remove_deployment_directory(filesystem, self.previous_state.deployment_path.as_absolute_path())
+    }
+}
+
+pub struct HookedDeployment {
     previous_state: ExtractedPackage,
-    deployment: Deployment,
 }
 
 impl DeployOperation<ExtractedPackage> {
+    pub fn execute_deploy_hooks<F: FileSystem, E: Executor>(
+        self,
+        filesystem: &F,
+        executor: &E,
+    ) -> Result<DeployOperation<HookedDeployment>> {
+        let deployment = self.as_state().previous_state.as_deployment();
+        let deployment_path = &self.as_state().previous_state.deployment_path;
+        let result = execute_hooks(
+            executor,
+            deployment.as_ref().as_hooks(),
+            HookEvent::Deploy,
+            deployment_path.as_absolute_path(),
+            to_deployment_hook_environment(
+                self.as_location_path(),
+                deployment_path.as_absolute_path(),
+                deployment,
+            ),
+        );
+        self.compensate_on_error(filesystem, result)?;
+        Self::ok_with_state(
+            self.request,
+            HookedDeployment {
+                previous_state: self.state,
+            },
+        )
+    }
+}
+
+impl Compensate for HookedDeployment {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        self.previous_state.compensate(filesystem)
+    }
+}
+
+/* Files are indexed once hooks are executed, the files they create or modify being part of the deployment */
+pub struct IndexedDeploymentFiles {
+    previous_state: HookedDeployment,
+    files: Vec<DeployedFile>,
+}
+
+impl DeployOperation<HookedDeployment> {
+    pub fn index_deployment_files<F: FileSystem, D: Digester>(
+        self,
+        filesystem: &F,
+        digester: &D,
+    ) -> Result<DeployOperation<IndexedDeploymentFiles>> {
+        let deployment_path = self.as_state().previous_state.previous_state.deployment_path.as_absolute_path();
+        let result = index_deployment_files(filesystem, digester, deployment_path);
+        let files = self.compensate_on_error(filesystem, result)?;
+        Self::ok_with_state(
+            self.request,
+            IndexedDeploymentFiles {
+                previous_state: self.state,
+                files,
+            },
+        )
+    }
+}
+
+impl Compensate for IndexedDeploymentFiles {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        self.previous_state.compensate(filesystem)
+    }
+}
+
+pub struct LocationWithNewDeployment {
+    previous_state: IndexedDeploymentFiles,
+    deployment: Deployment,
+}
+
+impl DeployOperation<IndexedDeploymentFiles> {
+    fn as_valid_state(&self) -> &DeployValidState {
+        &self.as_state().previous_state.previous_state.previous_state
+    }
+
     fn as_mut_location(&mut self) -> &mut DeployLocation {
-        &mut self
-            .as_mut_state()
-            .previous_state
+        self.as_mut_state().previous_state.previous_state.previous_state.as_mut()
+    }
+
+    pub fn add_deployment_to_location(mut self, packster_version: &str) -> Result<DeployOperation<LocationWithNewDeployment>> {
+        let deployment = self
+            .as_valid_state()
+            .as_deployment()
+            .clone()
+            .with_installed_packster_version(Version::from_str(packster_version)?)
+            .with_files(self.as_state().files.clone());
+        let dependents: Vec<_> = self
+            .as_valid_state()
             .previous_state
             .previous_state
             .previous_state
-            .location
-    }
-
-    pub fn add_deployment_to_location(mut self) -> DeployOperation<LocationWithNewDeployment> {
-        let package = self.as_state().previous_state.as_package();
-        let deployment: Deployment = Deployment::new(package.clone());
+            .resolution
+            .iter_dependents()
+            .map(|(id, dependent)| (id.clone(), dependent.clone()))
+            .collect();
 
         let location = self.as_mut_location();
         location.add_deployment(deployment.clone());
+        for (id, dependent) in dependents {
+            location.add_dependent(&id, dependent);
+        }
 
-        Self::with_state(
+        Self::ok_with_state(
             self.request,
             LocationWithNewDeployment {
                 previous_state: self.state,
@@ -114,36 +268,30 @@ impl DeployOperation<ExtractedPackage> {
     }
 }
 
-impl AsRef<DeployLocation> for DeployOperation<LocationWithNewDeployment> {
+impl Compensate for LocationWithNewDeployment {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        self.previous_state.compensate(filesystem)
+    }
+}
+
+impl AsRef<DeployLocation> for LocationWithNewDeployment {
     fn as_ref(&self) -> &DeployLocation {
-        &self
-            .as_state()
-            .previous_state
-            .previous_state
-            .previous_state
-            .previous_state
-            .previous_state
-            .location
+        self.previous_state.previous_state.previous_state.previous_state.as_location()
     }
 }
 
-impl DeployOperation<PersistedDeployLocation<LocationWithNewDeployment>> {
+impl DeployOperation<GeneratedShims<PersistedDeployLocation<LocationWithNewDeployment>>> {
+    fn as_valid_state(&self) -> &DeployValidState {
+        &self.as_state().previous_state.previous_state.previous_state.previous_state.previous_state.previous_state
+    }
+
     pub fn as_deploy_path(&self) -> Absolute<&Path> {
-        self.as_state()
-            .previous_state
-            .previous_state
-            .previous_state
-            .deployment_path
-            .as_absolute_path()
+        self.as_valid_state().deployment_path.as_absolute_path()
     }
     pub fn as_deployment(&self) -> &Deployment {
-        &self.as_state().previous_state.deployment
+        &self.as_state().previous_state.previous_state.deployment
     }
     pub fn as_package(&self) -> &Package {
-        self.as_state()
-            .previous_state
-            .previous_state
-            .previous_state
-            .as_package()
+        self.as_valid_state().as_package()
     }
 }
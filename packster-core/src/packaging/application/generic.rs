@@ -1,13 +1,53 @@
+use std::{collections::BTreeMap, io::{self, Read, Write}, path::{Path, PathBuf}, str::FromStr, time::Duration};
+use serde::Serialize;
 use crate::{
     application::{
-        operation::Operation,
-        port::{Digester, FileSystem, Parser, ReadOnlyFileSystem, Serializer},
+        operation::{Compensate, Operation},
+        path::Absolute,
+        port::{Archiver, Command, Digester, Executor, FileSystem, Lock, Locker, Parser, ReadOnlyFileSystem, Serializer},
+    },
+    domain::entity::{to_canonical_parameters, Checksum, Parameters, Version},
+    packaging::{
+        domain::{
+            is_shim_script, verify_deployment_files, AsBundle, AsDeployment, Bundle, DeployLocation, DeployedFile, Deployment, DeploymentIdentifier,
+            HookEvent, Hooks, IndexedPackage, LocationIssue, LockfileSchemaVersion, LockfileV0, LockfileV1, LockfileV2, LockfileV3, Package, PackageCache,
+            SourceIndex,
+            LOCKFILE_SCHEMA_VERSION,
+        },
+        PACKAGE_CACHE_INDEX_NAME, PACKAGE_EXTENSION, PACKAGE_HEADER_MAGIC,
     },
-    packaging::domain::{DeployLocation, Package},
     Error, Result,
 };
 
-use super::{AsLocation, AsLocationPath, AsPackage, AsPackagePath};
+use super::{AsBundlePath, AsCachePath, AsLocation, AsLocationPath, AsPackage, AsPackagePath, AsSourcePath};
+
+pub struct LockedLocation<P> {
+    pub previous_state: P,
+    _lock: Lock,
+}
+
+// Mutations of a location are exclusive, the lock being released when the operation is dropped
+impl<S, R> Operation<S, R>
+where
+    Self: AsLocationPath,
+{
+    pub fn lock_location<L: Locker>(self, locker: &L, wait: Duration) -> Result<Operation<LockedLocation<S>, R>> {
+        let lock = locker.lock(self.to_location_lock_path(), wait)?;
+        Self::ok_with_state(
+            self.request,
+            LockedLocation {
+                previous_state: self.state,
+                _lock: lock,
+            },
+        )
+    }
+}
+
+impl<S: Compensate> Compensate for LockedLocation<S> {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        self.previous_state.compensate(filesystem)
+    }
+}
 
 pub struct ParsedPackage<P> {
     pub previous_state: P,
@@ -31,6 +71,300 @@ where
     }
 }
 
+impl<S, R> Operation<S, R>
+where
+    Self: AsPackagePath,
+{
+    // Package metadata embedded in the package header is the source of truth, a well-formed file name must agree with it
+    pub fn parse_package_metadata<F: ReadOnlyFileSystem, P: Parser>(
+        self,
+        filesystem: &F,
+        parser: &P,
+    ) -> Result<Operation<ParsedPackage<S>, R>> {
+        let package_path = self.as_package_path();
+        let package = read_package_metadata(filesystem, parser, &package_path)?;
+
+        if let Ok(file_name_package) = Package::from_path(&package_path) {
+            if file_name_package.to_file_name() != package.to_file_name() {
+                return Err(Error::PackageMetadataDoNotMatchFileName {
+                    package_path: package_path.to_path_buf(),
+                    expected_file_name: package.to_file_name(),
+                });
+            }
+        }
+
+        Self::ok_with_state(
+            self.request,
+            ParsedPackage {
+                previous_state: self.state,
+                package,
+            },
+        )
+    }
+}
+
+/* Package file layout : magic | header length as u32 big endian | header | archive payload
+ * The package checksum is the digest of the archive payload only */
+pub fn write_package_header<W: Write>(writer: &mut W, header: &str) -> Result<()> {
+    let header_length = u32::try_from(header.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Package header too large"))?;
+    writer.write_all(PACKAGE_HEADER_MAGIC)?;
+    writer.write_all(&header_length.to_be_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    Ok(())
+}
+
+pub fn read_package_metadata<F: ReadOnlyFileSystem, P: Parser, Pa: AsRef<Path>>(
+    filesystem: &F,
+    parser: &P,
+    package_path: Pa,
+) -> Result<Package> {
+    let (header, _) = open_package(filesystem, package_path)?;
+    parser.parse(header)
+}
+
+pub fn validate_checksum<F: ReadOnlyFileSystem, D: Digester, P: AsRef<Path>>(
+    filesystem: &F,
+    digester: &D,
+    package_path: P,
+    package: &Package,
+) -> Result<()> {
+    let package_path = package_path.as_ref();
+    let (_, payload) = open_package(filesystem, package_path)?;
+    if digester.generate_checksum(payload)? == *package.as_checksum() {
+        Ok(())
+    } else {
+        Err(Error::PackageChecksumDoNotMatch {
+            package_path: package_path.to_path_buf(),
+            package_id: package.as_identifier().to_string(),
+            package_checksum: package.as_checksum().to_string(),
+        })
+    }
+}
+
+/* Environment given to hooks, parameters being exposed as PACKSTER_PARAMETER_<KEY> */
+pub fn to_hook_environment(identifier: &str, version: &Version, parameters: &Parameters) -> BTreeMap<String, String> {
+    let mut environment = BTreeMap::from([
+        (String::from("PACKSTER_IDENTIFIER"), identifier.to_string()),
+        (String::from("PACKSTER_VERSION"), version.to_string()),
+    ]);
+    for (key, value) in parameters {
+        let key = key.to_uppercase().replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+        environment.insert(format!("PACKSTER_PARAMETER_{key}"), value.to_string());
+    }
+    environment
+}
+
+pub fn to_deployment_hook_environment(
+    location_path: Absolute<&Path>,
+    deployment_path: Absolute<&Path>,
+    deployment: &Deployment,
+) -> BTreeMap<String, String> {
+    let package = deployment.as_ref();
+    let mut environment = to_hook_environment(package.as_identifier().as_ref(), package.as_version(), deployment.as_parameters());
+    environment.insert(String::from("PACKSTER_DEPLOYMENT_ID"), deployment.as_id().to_string());
+    environment.insert(String::from("PACKSTER_DEPLOYMENT_PATH"), deployment_path.to_string_lossy().to_string());
+    environment.insert(String::from("PACKSTER_LOCATION_PATH"), location_path.to_string_lossy().to_string());
+    environment
+}
+
+/* Executes the hooks of an event in order, failing on the first unsuccessful one. Relative command paths are relative to the working directory */
+pub fn execute_hooks<E: Executor>(
+    executor: &E,
+    hooks: &Hooks,
+    event: HookEvent,
+    working_directory: Absolute<&Path>,
+    mut environment: BTreeMap<String, String>,
+) -> Result<()> {
+    environment.insert(String::from("PACKSTER_EVENT"), event.to_string());
+    for hook in hooks.to_event_hooks(event) {
+        let program = Path::new(hook.as_command());
+        let program = if program.is_relative() && program.components().count() > 1 {
+            working_directory.join(program).to_normalized_absolute_path().to_string_lossy().to_string()
+        } else {
+            hook.as_command().to_string()
+        };
+        let command = Command::new(program, hook.as_arguments().to_vec(), working_directory.to_absolute_path())
+            .with_environment(environment.clone())
+            .with_timeout(hook.as_timeout().map(Duration::from_secs));
+
+        let output = executor.execute(&command)?;
+        if !output.is_success() {
+            return Err(Error::HookFailed {
+                event: event.to_string(),
+                command: command.to_command_line(),
+                status: output.as_status(),
+                output: format!("{}{}", output.as_stdout(), output.as_stderr()),
+            });
+        }
+    }
+    Ok(())
+}
+
+/* Returns the package header and a reader positioned at the start of the archive payload */
+pub fn open_package<F: ReadOnlyFileSystem, P: AsRef<Path>>(
+    filesystem: &F,
+    package_path: P,
+) -> Result<(String, Box<dyn Read + Send + Sync>)> {
+    const MAX_HEADER_LENGTH: usize = 1024 * 1024;
+
+    let package_path = package_path.as_ref();
+    let mut reader = filesystem.open_read(package_path)?;
+
+    let mut magic = vec![0; PACKAGE_HEADER_MAGIC.len()];
+    if reader.read_exact(&mut magic).is_err() || magic != PACKAGE_HEADER_MAGIC {
+        return Err(Error::MissingPackageHeader(package_path.to_path_buf()));
+    }
+
+    let mut header_length = [0; 4];
+    reader.read_exact(&mut header_length)
+        .map_err(|_| Error::InvalidPackageHeader("Truncated header length".into(), package_path.to_path_buf()))?;
+    let header_length = u32::from_be_bytes(header_length) as usize;
+    if header_length > MAX_HEADER_LENGTH {
+        return Err(Error::InvalidPackageHeader(format!("Header length {header_length} exceeds {MAX_HEADER_LENGTH}"), package_path.to_path_buf()));
+    }
+
+    let mut header = vec![0; header_length];
+    reader.read_exact(&mut header)
+        .map_err(|_| Error::InvalidPackageHeader("Truncated header".into(), package_path.to_path_buf()))?;
+
+    Ok((String::from_utf8(header)?, reader))
+}
+
+/* Extracts the package payload in a staging directory beside the deployment path, then renames it in place :
+ * a deployment directory is either complete or absent.
+ * Archives do not keep the file permissions, the shim targets are made executable again before the rename */
+pub fn extract_package_payload<F: FileSystem, A: Archiver, P: AsRef<Path>>(
+    filesystem: &F,
+    archiver: &A,
+    package_path: P,
+    package: &Package,
+    deployment_path: Absolute<&Path>,
+) -> Result<()> {
+    let staging_path = to_staging_path(&deployment_path);
+    if filesystem.exists(&staging_path) {
+        filesystem.remove_dir_all(&staging_path)?;
+    }
+
+    let result = filesystem.create_dir(&staging_path)
+        .and_then(|_| open_package(filesystem, package_path))
+        .and_then(|(_, payload)| archiver.extract_from(filesystem, staging_path.as_absolute_path(), payload))
+        .and_then(|_| set_shim_targets_executable(filesystem, package, staging_path.as_absolute_path()))
+        .and_then(|_| filesystem.rename(staging_path.as_ref(), deployment_path.as_ref()));
+
+    if result.is_err() && filesystem.exists(&staging_path) {
+        filesystem.remove_dir_all(&staging_path)?;
+    }
+    result
+}
+
+fn set_shim_targets_executable<F: FileSystem>(filesystem: &F, package: &Package, extraction_path: Absolute<&Path>) -> Result<()> {
+    for target in package.as_shims().values() {
+        let target_path = extraction_path.join(target.as_ref());
+        if filesystem.is_file(&target_path) {
+            filesystem.set_executable(&target_path)?;
+        }
+    }
+    Ok(())
+}
+
+fn to_staging_path(deployment_path: &Absolute<&Path>) -> Absolute<PathBuf> {
+    let file_name = deployment_path.file_name().unwrap_or_default().to_string_lossy();
+    deployment_path.with_file_name(format!(".{file_name}.staging"))
+}
+
+/* A damaged deployment directory is set aside there while its deployment is being repaired */
+pub fn to_damaged_path(deployment_path: Absolute<&Path>) -> Absolute<PathBuf> {
+    let file_name = deployment_path.file_name().unwrap_or_default().to_string_lossy();
+    deployment_path.with_file_name(format!(".{file_name}.damaged"))
+}
+
+/* Lists the files of a deployment directory, with their size and checksum */
+pub fn index_deployment_files<F: ReadOnlyFileSystem, D: Digester>(
+    filesystem: &F,
+    digester: &D,
+    deployment_path: Absolute<&Path>,
+) -> Result<Vec<DeployedFile>> {
+    let mut files = Vec::new();
+    for entry in filesystem.walk(&deployment_path) {
+        let entry = entry?;
+        if filesystem.is_file(entry.as_path()) {
+            let checksum = digester.generate_checksum(filesystem.open_read(entry.as_path())?)?;
+            files.push(DeployedFile::new(to_relative_file_path(&deployment_path, entry.as_path()), entry.size(), checksum));
+        }
+    }
+    files.sort_by(|a, b| a.as_path().cmp(b.as_path()));
+    Ok(files)
+}
+
+/* Path of a file relative to a directory, such as its deployment directory or its source, slash separated whatever the platform */
+pub fn to_relative_file_path(directory_path: &Path, file_path: &Path) -> String {
+    file_path
+        .strip_prefix(directory_path)
+        .unwrap_or(file_path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/* Compares a deployment directory with the files recorded at deployment, deployments recorded without files only being checked for their directory */
+pub fn inspect_deployment_directory<F: ReadOnlyFileSystem, D: Digester>(
+    filesystem: &F,
+    digester: &D,
+    deployment: &Deployment,
+    deployment_path: Absolute<&Path>,
+) -> Result<Vec<LocationIssue>> {
+    if !filesystem.is_directory(&deployment_path) {
+        return Ok(vec![LocationIssue::MissingDeploymentDirectory { deployment_id: deployment.as_id().clone() }]);
+    }
+    if deployment.as_files().is_empty() {
+        return Ok(Vec::new());
+    }
+    let found_files = index_deployment_files(filesystem, digester, deployment_path)?;
+    Ok(verify_deployment_files(deployment, &found_files))
+}
+
+/* Top level directories of a location left over by an interrupted operation : staging and damaged directories, and directories named after
+ * a deployment id the lockfile does not reference. Other directories are not packster's business and are left alone */
+pub fn find_orphan_deployment_directories<F: ReadOnlyFileSystem, D: Digester>(
+    filesystem: &F,
+    digester: &D,
+    location_path: Absolute<&Path>,
+    location: &DeployLocation,
+) -> Result<Vec<Absolute<PathBuf>>> {
+    // Deployment ids are digests, the one of nothing giving their length
+    let id_length = digester.generate_checksum(std::io::empty())?.as_ref().len();
+    let is_deployment_id = |name: &str| Checksum::from_str(name).is_ok_and(|checksum| checksum.as_ref().len() == id_length);
+    let mut orphan_paths = Vec::new();
+    for entry in filesystem.read_dir(&location_path) {
+        let entry = entry?;
+        let path = entry.as_path();
+        if !filesystem.is_directory(path) {
+            continue;
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let set_aside_name = name.strip_prefix('.')
+            .and_then(|name| name.strip_suffix(".staging").or_else(|| name.strip_suffix(".damaged")));
+        let is_orphan = match set_aside_name {
+            Some(staged_name) => is_deployment_id(staged_name),
+            None => is_deployment_id(&name) && !location.is_deployed(&Checksum::from_str(&name)?),
+        };
+        if is_orphan {
+            orphan_paths.push(entry.as_absolute_path().to_absolute_path());
+        }
+    }
+    Ok(orphan_paths)
+}
+
+/* Removes a deployment directory, if it has been created */
+pub fn remove_deployment_directory<F: FileSystem>(filesystem: &F, deployment_path: Absolute<&Path>) -> Result<()> {
+    if filesystem.exists(&deployment_path) {
+        filesystem.remove_dir_all(&deployment_path)?;
+    }
+    Ok(())
+}
+
 // Simple field accessor
 impl<S> AsPackage for ParsedPackage<S> {
     fn as_package(&self) -> &Package {
@@ -45,38 +379,105 @@ impl<S: AsLocation> AsLocation for ParsedPackage<S> {
     }
 }
 
+pub struct ParsedBundle<S> {
+    pub previous_state: S,
+    pub bundle: Bundle,
+}
+
+impl<S, R> Operation<S, R>
+where
+    Self: AsBundlePath,
+{
+    pub fn parse_bundle_manifest<F: ReadOnlyFileSystem, P: Parser>(
+        self,
+        filesystem: &F,
+        parser: &P,
+    ) -> Result<Operation<ParsedBundle<S>, R>> {
+        let bundle_manifest_content = filesystem.read_to_string(self.as_bundle_path())?;
+        Self::ok_with_state(
+            self.request,
+            ParsedBundle {
+                previous_state: self.state,
+                bundle: parser.parse(bundle_manifest_content)?,
+            },
+        )
+    }
+}
+
+impl<S> AsBundle for ParsedBundle<S> {
+    fn as_bundle(&self) -> &Bundle {
+        &self.bundle
+    }
+}
+
 pub struct ParsedLocation<S> {
     pub previous_state: S,
     pub location: DeployLocation,
+    pub lockfile_schema_version: u32,
 }
 
 impl<S, R> Operation<S, R>
 where
     Self: AsLocationPath,
 {
+    /* Lockfiles of an older schema are migrated in memory, they are written in the current schema once persisted */
     pub fn parse_location_lockfile<F: ReadOnlyFileSystem, P: Parser>(
         self,
         filesystem: &F,
         parser: &P,
     ) -> Result<Operation<ParsedLocation<S>, R>> {
         let lockfile_path = self.to_location_lockfile_path();
-        let lockfile_content = filesystem.read_to_string(lockfile_path)?;
+        let lockfile_content = filesystem.read_to_string(&lockfile_path)?;
+        let lockfile_schema_version = parser.parse::<_, LockfileSchemaVersion>(&lockfile_content)?.as_u32();
+        let location = match lockfile_schema_version {
+            0 => DeployLocation::from(LockfileV3::from(LockfileV2::from(LockfileV1::from(parser.parse::<_, LockfileV0>(lockfile_content)?)))),
+            1 => DeployLocation::from(LockfileV3::from(LockfileV2::from(parser.parse::<_, LockfileV1>(lockfile_content)?))),
+            2 => DeployLocation::from(LockfileV3::from(parser.parse::<_, LockfileV2>(lockfile_content)?)),
+            3 => DeployLocation::from(parser.parse::<_, LockfileV3>(lockfile_content)?),
+            LOCKFILE_SCHEMA_VERSION => parser.parse(lockfile_content)?,
+            version => {
+                return Err(Error::LockfileSchemaTooRecent {
+                    path: lockfile_path.into(),
+                    version,
+                    supported_version: LOCKFILE_SCHEMA_VERSION,
+                })
+            }
+        };
         Self::ok_with_state(
             self.request,
             ParsedLocation {
                 previous_state: self.state,
-                location: parser.parse(lockfile_content)?,
+                location,
+                lockfile_schema_version,
             },
         )
     }
 }
 
+impl<S> Compensate for ParsedLocation<S> {
+    fn compensate<F: FileSystem>(&self, _filesystem: &F) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<S> AsRef<DeployLocation> for ParsedLocation<S> {
+    fn as_ref(&self) -> &DeployLocation {
+        &self.location
+    }
+}
+
 impl<S> AsLocation for ParsedLocation<S> {
     fn as_location(&self) -> &DeployLocation {
         &self.location
     }
 }
 
+impl<S: AsBundle> AsBundle for ParsedLocation<S> {
+    fn as_bundle(&self) -> &Bundle {
+        self.previous_state.as_bundle()
+    }
+}
+
 impl<S: AsPackage> AsPackage for ParsedLocation<S> {
     fn as_package(&self) -> &Package {
         self.previous_state.as_package()
@@ -96,22 +497,13 @@ where
         filesystem: &F,
         digester: &D,
     ) -> Result<Operation<MatchingChecksum<S>, R>> {
-        let package_path = self.as_package_path();
-        let digest = digester.generate_checksum(filesystem.open_read(&package_path)?)?;
-        if digest == *self.as_package().as_checksum() {
-            Self::ok_with_state(
-                self.request,
-                MatchingChecksum {
-                    previous_state: self.state,
-                },
-            )
-        } else {
-            Err(Error::PackageChecksumDoNotMatch {
-                package_path: package_path.to_path_buf(),
-                package_id: self.as_package().as_identifier().to_string(),
-                package_checksum: self.as_package().as_checksum().to_string(),
-            })
-        }
+        validate_checksum(filesystem, digester, self.as_package_path(), self.as_package())?;
+        Self::ok_with_state(
+            self.request,
+            MatchingChecksum {
+                previous_state: self.state,
+            },
+        )
     }
 }
 
@@ -127,28 +519,549 @@ impl<S: AsLocation> AsLocation for MatchingChecksum<S> {
     }
 }
 
+impl<S: AsDeployment> AsDeployment for MatchingChecksum<S> {
+    fn as_deployment(&self) -> &Deployment {
+        self.previous_state.as_deployment()
+    }
+}
+
+impl<D: Digester> DeploymentIdentifier for D {
+    fn identify_deployment(&self, package: &Package, parameters: &Parameters) -> Result<Checksum> {
+        if parameters.is_empty() {
+            return Ok(package.as_checksum().clone());
+        }
+        let identity = [package.as_checksum().as_ref(), to_canonical_parameters(parameters).as_bytes()].concat();
+        self.generate_checksum(identity.as_slice())
+    }
+}
+
 pub struct PersistedDeployLocation<P> {
     pub previous_state: P,
+    lockfile_path: Absolute<PathBuf>,
+    previous_lockfile: String,
 }
 
 impl<S, R> Operation<S, R>
 where
-    Self: AsLocationPath + AsRef<DeployLocation>,
+    Self: AsLocationPath,
+    S: Compensate + AsRef<DeployLocation>,
 {
+    /* The lockfile is written beside and renamed in place, a failure compensating the previous steps */
     pub fn persist_location_lockfile<F: FileSystem, Sr: Serializer>(
         self,
         filesystem: &F,
         serializer: &Sr,
     ) -> Result<Operation<PersistedDeployLocation<S>, R>> {
-        let lockfile_location = self.to_location_lockfile_path();
-        let location = self.as_ref();
-        let deploy_location_file_content = serializer.serialize(location)?;
-        filesystem.write_all(lockfile_location, deploy_location_file_content.as_bytes())?;
+        let lockfile_path = self.to_location_lockfile_path();
+        let result = write_lockfile(filesystem, serializer, self.as_state().as_ref(), lockfile_path.as_absolute_path());
+        let previous_lockfile = self.compensate_on_error(filesystem, result)?;
         Self::ok_with_state(
             self.request,
             PersistedDeployLocation {
                 previous_state: self.state,
+                lockfile_path,
+                previous_lockfile,
+            },
+        )
+    }
+}
+
+/* Returns the previous lockfile content */
+fn write_lockfile<F: FileSystem, Sr: Serializer>(
+    filesystem: &F,
+    serializer: &Sr,
+    location: &DeployLocation,
+    lockfile_path: Absolute<&Path>,
+) -> Result<String> {
+    let previous_lockfile = filesystem.read_to_string(&lockfile_path)?;
+    let deploy_location_file_content = serializer.serialize(location)?;
+    let temporary_path = lockfile_path.with_extension("lock.tmp");
+    let result = filesystem.write_all(&temporary_path, deploy_location_file_content.as_bytes())
+        .and_then(|_| filesystem.rename(temporary_path.as_ref(), lockfile_path.as_ref()));
+    if result.is_err() && filesystem.exists(&temporary_path) {
+        filesystem.remove_file(&temporary_path)?;
+    }
+    result.map(|_| previous_lockfile)
+}
+
+impl<S: Compensate> Compensate for PersistedDeployLocation<S> {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        filesystem.write_all(&self.lockfile_path, self.previous_lockfile.as_bytes())?;
+        self.previous_state.compensate(filesystem)
+    }
+}
+
+impl<S: AsRef<DeployLocation>> AsRef<DeployLocation> for PersistedDeployLocation<S> {
+    fn as_ref(&self) -> &DeployLocation {
+        self.previous_state.as_ref()
+    }
+}
+
+pub struct GeneratedShims<P> {
+    pub previous_state: P,
+    changes: Vec<ShimChange>,
+}
+
+/* A shim script written or removed, along with its previous content to restore it */
+struct ShimChange {
+    path: Absolute<PathBuf>,
+    previous_script: Option<String>,
+}
+
+impl<S, R> Operation<S, R>
+where
+    Self: AsLocationPath,
+    S: Compensate + AsRef<DeployLocation>,
+{
+    /* The location bin directory gets a shim for each command of the active deployments, stale shims being removed.
+     * Files of the bin directory which are not shims are left alone, and never overwritten */
+    pub fn generate_location_shims<F: FileSystem>(self, filesystem: &F) -> Result<Operation<GeneratedShims<S>, R>> {
+        let bin_path = self.to_location_bin_path();
+        let result = self.as_state().as_ref().to_shims()
+            .and_then(|shims| {
+                let scripts = shims.iter()
+                    .map(|shim| {
+                        let deployment_path = self.to_deployment_path(shim.as_deployment_id());
+                        (bin_path.join(shim.as_name().as_ref()), shim.to_script(deployment_path.as_ref()))
+                    })
+                    .collect();
+                write_shims(filesystem, bin_path.as_absolute_path(), scripts)
+            });
+        let changes = self.compensate_on_error(filesystem, result)?;
+        Self::ok_with_state(
+            self.request,
+            GeneratedShims {
+                previous_state: self.state,
+                changes,
+            },
+        )
+    }
+}
+
+/* Shim scripts by path */
+type ShimScripts = BTreeMap<Absolute<PathBuf>, String>;
+
+/* Writes the expected shim scripts and removes the other ones, the changes being undone on failure */
+fn write_shims<F: FileSystem>(
+    filesystem: &F,
+    bin_path: Absolute<&Path>,
+    scripts: ShimScripts,
+) -> Result<Vec<ShimChange>> {
+    let mut existing_scripts = BTreeMap::new();
+    if filesystem.is_directory(&bin_path) {
+        for entry in filesystem.walk(&bin_path) {
+            let path = entry?.as_absolute_path().to_absolute_path();
+            if path.parent() == Some(bin_path.as_ref()) && filesystem.is_file(&path) {
+                let content = filesystem.read_to_string(&path)?;
+                if is_shim_script(&content) {
+                    existing_scripts.insert(path, content);
+                }
+            }
+        }
+    } else if !scripts.is_empty() {
+        filesystem.create_dir_recursively(&bin_path)?;
+    }
+
+    let mut changes = Vec::new();
+    let result = apply_shim_changes(filesystem, &scripts, &existing_scripts, &mut changes);
+    if let Err(error) = result {
+        return match restore_shims(filesystem, &changes) {
+            Ok(()) => Err(error),
+            Err(restoration_error) => Err(Error::CompensationFailed {
+                error: Box::new(error),
+                compensation_error: Box::new(restoration_error),
+            }),
+        };
+    }
+    Ok(changes)
+}
+
+fn apply_shim_changes<F: FileSystem>(
+    filesystem: &F,
+    scripts: &ShimScripts,
+    existing_scripts: &BTreeMap<Absolute<PathBuf>, String>,
+    changes: &mut Vec<ShimChange>,
+) -> Result<()> {
+    for (path, script) in scripts {
+        let previous_script = existing_scripts.get(path).cloned();
+        if previous_script.as_ref() == Some(script) {
+            continue;
+        }
+        if previous_script.is_none() && filesystem.exists(path) {
+            return Err(Error::ShimPathOccupied(path.to_path_buf()));
+        }
+        changes.push(ShimChange { path: path.clone(), previous_script });
+        filesystem.write_all(path, script.as_bytes())?;
+        filesystem.set_executable(path)?;
+    }
+    for (path, previous_script) in existing_scripts.iter().filter(|(path, _)| !scripts.contains_key(*path)) {
+        changes.push(ShimChange { path: path.clone(), previous_script: Some(previous_script.clone()) });
+        filesystem.remove_file(path)?;
+    }
+    Ok(())
+}
+
+fn restore_shims<F: FileSystem>(filesystem: &F, changes: &[ShimChange]) -> Result<()> {
+    for change in changes.iter().rev() {
+        match &change.previous_script {
+            Some(script) => {
+                filesystem.write_all(&change.path, script.as_bytes())?;
+                filesystem.set_executable(&change.path)?;
+            }
+            None if filesystem.exists(&change.path) => filesystem.remove_file(&change.path)?,
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+impl<S: Compensate> Compensate for GeneratedShims<S> {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        restore_shims(filesystem, &self.changes)?;
+        self.previous_state.compensate(filesystem)
+    }
+}
+
+impl<S: AsRef<DeployLocation>> AsRef<DeployLocation> for GeneratedShims<S> {
+    fn as_ref(&self) -> &DeployLocation {
+        self.previous_state.as_ref()
+    }
+}
+
+pub struct IndexedSource<P> {
+    pub previous_state: P,
+    index: SourceIndex,
+    digested_paths: Vec<String>,
+}
+
+impl<S, R> Operation<S, R>
+where
+    Self: AsSourcePath,
+{
+    /* Package files already indexed with the same size, and not modified since the index was written, are kept as is.
+     * The others have their header parsed and their payload digested.
+     * Indexed files which are gone are dropped, a source without index being indexed from scratch */
+    pub fn index_source<F: ReadOnlyFileSystem, P: Parser, D: Digester>(
+        self,
+        filesystem: &F,
+        parser: &P,
+        digester: &D,
+    ) -> Result<Operation<IndexedSource<S>, R>> {
+        let source_path = self.as_source_path();
+        let index_path = self.to_source_index_path();
+        let (previous_index, indexed_at): (SourceIndex, _) = if filesystem.exists(&index_path) {
+            (parser.parse(filesystem.read_to_string(&index_path)?)?, Some(filesystem.modified_at(&index_path)?))
+        } else {
+            (SourceIndex::default(), None)
+        };
+
+        let mut packages = Vec::new();
+        let mut digested_paths = Vec::new();
+        for entry in filesystem.walk(&source_path) {
+            let entry = entry?;
+            let is_package_file = filesystem.is_file(entry.as_path())
+                && entry.as_path().extension().is_some_and(|extension| extension == PACKAGE_EXTENSION);
+            if !is_package_file {
+                continue;
+            }
+
+            let path = to_relative_file_path(source_path.as_ref(), entry.as_path());
+            let modified_at = filesystem.modified_at(entry.as_path())?;
+            let unmodified_package = previous_index.find_by_path(&path)
+                .filter(|indexed_package| indexed_package.as_size() == entry.size())
+                .filter(|_| indexed_at.is_some_and(|indexed_at| modified_at < indexed_at));
+            match unmodified_package {
+                Some(indexed_package) => packages.push(indexed_package.clone()),
+                _ => {
+                    let package = read_package_metadata(filesystem, parser, entry.as_path())?;
+                    validate_checksum(filesystem, digester, entry.as_path(), &package)?;
+                    packages.push(IndexedPackage::new(&package, path.clone(), entry.size()));
+                    digested_paths.push(path);
+                }
+            }
+        }
+
+        Self::ok_with_state(
+            self.request,
+            IndexedSource {
+                previous_state: self.state,
+                index: SourceIndex::new(packages),
+                digested_paths,
             },
         )
     }
 }
+
+impl<S> IndexedSource<S> {
+    /* Package files which were not indexed yet, or changed since */
+    pub fn as_digested_paths(&self) -> &[String] {
+        &self.digested_paths
+    }
+}
+
+impl<S> Compensate for IndexedSource<S> {
+    fn compensate<F: FileSystem>(&self, _filesystem: &F) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<S> AsRef<SourceIndex> for IndexedSource<S> {
+    fn as_ref(&self) -> &SourceIndex {
+        &self.index
+    }
+}
+
+impl<S: AsPackage> AsPackage for IndexedSource<S> {
+    fn as_package(&self) -> &Package {
+        self.previous_state.as_package()
+    }
+}
+
+pub struct PersistedSourceIndex<P> {
+    pub previous_state: P,
+    index_path: Absolute<PathBuf>,
+    previous_index: Option<String>,
+}
+
+impl<S, R> Operation<S, R>
+where
+    Self: AsSourcePath,
+    S: Compensate + AsRef<SourceIndex>,
+{
+    /* The index is written beside and renamed in place, a failure compensating the previous steps */
+    pub fn persist_source_index<F: FileSystem, Sr: Serializer>(
+        self,
+        filesystem: &F,
+        serializer: &Sr,
+    ) -> Result<Operation<PersistedSourceIndex<S>, R>> {
+        let index_path = self.to_source_index_path();
+        let result = write_index(filesystem, serializer, self.as_state().as_ref(), index_path.as_absolute_path());
+        let previous_index = self.compensate_on_error(filesystem, result)?;
+        Self::ok_with_state(
+            self.request,
+            PersistedSourceIndex {
+                previous_state: self.state,
+                index_path,
+                previous_index,
+            },
+        )
+    }
+}
+
+/* Writes an index beside and renames it in place, returning the previous index content, if any */
+fn write_index<F: FileSystem, Sr: Serializer, T: Serialize>(
+    filesystem: &F,
+    serializer: &Sr,
+    index: &T,
+    index_path: Absolute<&Path>,
+) -> Result<Option<String>> {
+    let previous_index = if filesystem.exists(&index_path) {
+        Some(filesystem.read_to_string(&index_path)?)
+    } else {
+        None
+    };
+    let temporary_path = index_path.with_extension("json.tmp");
+    let result = filesystem.write_all(&temporary_path, serializer.serialize(index)?)
+        .and_then(|_| filesystem.rename(temporary_path.as_ref(), index_path.as_ref()));
+    if result.is_err() && filesystem.exists(&temporary_path) {
+        filesystem.remove_file(&temporary_path)?;
+    }
+    result.map(|_| previous_index)
+}
+
+impl<S: Compensate> Compensate for PersistedSourceIndex<S> {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        match &self.previous_index {
+            Some(previous_index) => filesystem.write_all(&self.index_path, previous_index.as_bytes())?,
+            None => filesystem.remove_file(&self.index_path)?,
+        }
+        self.previous_state.compensate(filesystem)
+    }
+}
+
+impl<S: AsRef<SourceIndex>> AsRef<SourceIndex> for PersistedSourceIndex<S> {
+    fn as_ref(&self) -> &SourceIndex {
+        self.previous_state.as_ref()
+    }
+}
+
+impl<S: AsPackage> AsPackage for PersistedSourceIndex<S> {
+    fn as_package(&self) -> &Package {
+        self.previous_state.as_package()
+    }
+}
+
+pub struct LockedCache<P> {
+    pub previous_state: P,
+    _lock: Lock,
+}
+
+// Reading and writing the cache, as well as deploying from it, are exclusive since the cache is shared by every location
+impl<S, R> Operation<S, R>
+where
+    Self: AsCachePath,
+{
+    pub fn lock_cache<F: FileSystem, L: Locker>(self, filesystem: &F, locker: &L, wait: Duration) -> Result<Operation<LockedCache<S>, R>> {
+        filesystem.create_dir_recursively(self.as_cache_path())?;
+        let lock = locker.lock(self.to_cache_lock_path(), wait)?;
+        Self::ok_with_state(
+            self.request,
+            LockedCache {
+                previous_state: self.state,
+                _lock: lock,
+            },
+        )
+    }
+}
+
+impl<S: Compensate> Compensate for LockedCache<S> {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        self.previous_state.compensate(filesystem)
+    }
+}
+
+impl<S: AsPackage> AsPackage for LockedCache<S> {
+    fn as_package(&self) -> &Package {
+        self.previous_state.as_package()
+    }
+}
+
+impl<S: AsLocation> AsLocation for LockedCache<S> {
+    fn as_location(&self) -> &DeployLocation {
+        self.previous_state.as_location()
+    }
+}
+
+pub struct ParsedPackageCache<P> {
+    pub previous_state: P,
+    cache: PackageCache,
+}
+
+impl<S, R> Operation<S, R>
+where
+    Self: AsCachePath,
+{
+    /* A cache without index is empty */
+    pub fn parse_package_cache<F: ReadOnlyFileSystem, P: Parser>(
+        self,
+        filesystem: &F,
+        parser: &P,
+    ) -> Result<Operation<ParsedPackageCache<S>, R>> {
+        let index_path = self.to_cache_index_path();
+        let cache = if filesystem.exists(&index_path) {
+            parser.parse(filesystem.read_to_string(&index_path)?)?
+        } else {
+            PackageCache::default()
+        };
+        Self::ok_with_state(
+            self.request,
+            ParsedPackageCache {
+                previous_state: self.state,
+                cache,
+            },
+        )
+    }
+}
+
+impl<S> Compensate for ParsedPackageCache<S> {
+    fn compensate<F: FileSystem>(&self, _filesystem: &F) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<S> AsRef<PackageCache> for ParsedPackageCache<S> {
+    fn as_ref(&self) -> &PackageCache {
+        &self.cache
+    }
+}
+
+impl<S: AsPackage> AsPackage for ParsedPackageCache<S> {
+    fn as_package(&self) -> &Package {
+        self.previous_state.as_package()
+    }
+}
+
+impl<S: AsLocation> AsLocation for ParsedPackageCache<S> {
+    fn as_location(&self) -> &DeployLocation {
+        self.previous_state.as_location()
+    }
+}
+
+pub struct PersistedPackageCache<P> {
+    pub previous_state: P,
+    index_path: Absolute<PathBuf>,
+    previous_index: Option<String>,
+}
+
+impl<S, R> Operation<S, R>
+where
+    Self: AsCachePath,
+    S: Compensate + AsRef<PackageCache>,
+{
+    /* The cache index is written beside and renamed in place, a failure compensating the previous steps */
+    pub fn persist_package_cache<F: FileSystem, Sr: Serializer>(
+        self,
+        filesystem: &F,
+        serializer: &Sr,
+    ) -> Result<Operation<PersistedPackageCache<S>, R>> {
+        let index_path = self.to_cache_index_path();
+        let result = filesystem.create_dir_recursively(self.as_cache_path())
+            .and_then(|_| write_index(filesystem, serializer, self.as_state().as_ref(), index_path.as_absolute_path()));
+        let previous_index = self.compensate_on_error(filesystem, result)?;
+        Self::ok_with_state(
+            self.request,
+            PersistedPackageCache {
+                previous_state: self.state,
+                index_path,
+                previous_index,
+            },
+        )
+    }
+}
+
+impl<S: Compensate> Compensate for PersistedPackageCache<S> {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        match &self.previous_index {
+            Some(previous_index) => filesystem.write_all(&self.index_path, previous_index.as_bytes())?,
+            None => filesystem.remove_file(&self.index_path)?,
+        }
+        self.previous_state.compensate(filesystem)
+    }
+}
+
+impl<S: AsRef<PackageCache>> AsRef<PackageCache> for PersistedPackageCache<S> {
+    fn as_ref(&self) -> &PackageCache {
+        self.previous_state.as_ref()
+    }
+}
+
+impl<S: AsPackage> AsPackage for PersistedPackageCache<S> {
+    fn as_package(&self) -> &Package {
+        self.previous_state.as_package()
+    }
+}
+
+impl<S: AsLocation> AsLocation for PersistedPackageCache<S> {
+    fn as_location(&self) -> &DeployLocation {
+        self.previous_state.as_location()
+    }
+}
+
+/* The cached package file of the given checksum, if the cache lists it and still holds its file */
+pub fn find_cached_package<F: ReadOnlyFileSystem, P: Parser>(
+    filesystem: &F,
+    parser: &P,
+    cache_path: Absolute<&Path>,
+    checksum: &Checksum,
+) -> Result<Option<Absolute<PathBuf>>> {
+    let index_path = cache_path.join(PACKAGE_CACHE_INDEX_NAME);
+    if !filesystem.exists(&index_path) {
+        return Ok(None);
+    }
+    let cache: PackageCache = parser.parse(filesystem.read_to_string(&index_path)?)?;
+    Ok(
+        cache.find(checksum)
+            .map(|package| cache_path.join(package.to_path()))
+            .filter(|package_path| filesystem.is_file(package_path))
+    )
+}
@@ -0,0 +1,35 @@
+use std::path::{Path, PathBuf};
+use crate::{
+    application::{operation::{New, Operation}, path::Absolute},
+    packaging::domain::SourceIndex,
+};
+use super::{AsSourcePath, IndexedSource, PersistedSourceIndex};
+
+pub struct IndexSourceRequest {
+    source_directory: Absolute<PathBuf>,
+}
+
+impl IndexSourceRequest {
+    pub fn new(source_directory: Absolute<PathBuf>) -> Self {
+        IndexSourceRequest { source_directory }
+    }
+}
+
+pub type IndexSourceOperation<S> = Operation<S, IndexSourceRequest>;
+
+impl<S> AsSourcePath for IndexSourceOperation<S> {
+    fn as_source_path(&self) -> Absolute<&Path> {
+        self.as_request().source_directory.as_absolute_path()
+    }
+}
+
+impl IndexSourceOperation<PersistedSourceIndex<IndexedSource<New>>> {
+    pub fn as_index(&self) -> &SourceIndex {
+        self.as_state().as_ref()
+    }
+
+    /* Package files which were not indexed yet, or changed since */
+    pub fn as_digested_paths(&self) -> &[String] {
+        self.as_state().previous_state.as_digested_paths()
+    }
+}
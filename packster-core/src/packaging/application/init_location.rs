@@ -1,23 +1,45 @@
-use std::path::{Path, PathBuf};
+use std::{path::{Path, PathBuf}, time::Duration};
 use crate::{
     Result, Error,
     application::{
-        operation::{New, Operation},
+        operation::{Compensate, New, Operation},
         path::Absolute,
-        port::{FileSystem, Serializer},
+        port::{FileSystem, Locker, Parser, ReadOnlyFileSystem, Serializer},
     },
-    packaging::domain::DeployLocation,
+    domain::entity::Identifier,
+    packaging::domain::{DeployLocation, UserConfig},
 };
-use super::AsLocationPath;
+use super::{lock_user_config, read_user_config, write_user_config, AsLocationPath, LockedLocation};
 
 pub struct InitLocationRequest {
     location_directory: Absolute<PathBuf>,
+    registration: Option<LocationRegistration>,
 }
 
 impl InitLocationRequest {
     pub fn new(location_directory: Absolute<PathBuf>) -> Self {
-        InitLocationRequest { location_directory }
+        InitLocationRequest { location_directory, registration: None }
     }
+
+    pub fn with_registration(mut self, registration: LocationRegistration) -> Self {
+        self.registration = Some(registration);
+        self
+    }
+}
+
+/* Name under which the location is recorded in the user config */
+pub struct LocationRegistration {
+    config_path: Absolute<PathBuf>,
+    name: Identifier,
+    is_default: bool,
+}
+
+impl LocationRegistration {
+    pub fn new(config_path: Absolute<PathBuf>, name: Identifier, is_default: bool) -> Self {
+        LocationRegistration { config_path, name, is_default }
+    }
+
+    pub fn as_name(&self) -> &Identifier { &self.name }
 }
 
 pub type InitLocationOperation<S> = Operation<S, InitLocationRequest>;
@@ -28,27 +50,142 @@ impl<S> AsLocationPath for InitLocationOperation<S> {
     }
 }
 
-pub struct LocationInitialized;
+/* The registration is validated before the location is initialized, then again once the user config is locked */
+pub struct ProbedRegistration;
 
 impl InitLocationOperation<New> {
-    pub fn initialize_lockfile<F: FileSystem, S: Serializer>(
+    /* Does nothing unless a registration is requested */
+    pub fn probe_location_registration<F: ReadOnlyFileSystem, P: Parser>(
         self,
         filesystem: &F,
-        serializer: &S,
-    ) -> Result<InitLocationOperation<LocationInitialized>> {
+        parser: &P,
+    ) -> Result<InitLocationOperation<ProbedRegistration>> {
+        self.to_registered_config(filesystem, parser)?;
+        Self::ok_with_state(self.request, ProbedRegistration)
+    }
+}
+
+impl<S> InitLocationOperation<S> {
+    fn to_registered_config<F: ReadOnlyFileSystem, P: Parser>(&self, filesystem: &F, parser: &P) -> Result<Option<UserConfig>> {
+        let InitLocationRequest { location_directory, registration } = self.as_request();
+        let Some(registration) = registration else {
+            return Ok(None);
+        };
+        let mut config = read_user_config(filesystem, parser, &registration.config_path)?;
+        config.register_location(registration.name.clone(), location_directory.to_path_buf(), registration.is_default)?;
+        Ok(Some(config))
+    }
+}
+
+pub struct CreatedLocationDirectory<S> {
+    pub previous_state: S,
+    created_directory: Option<Absolute<PathBuf>>,
+}
+
+impl<S> InitLocationOperation<S> {
+    /* The location directory is created before being locked, locking an uninitialized location being refused */
+    pub fn create_location_directory<F: FileSystem>(self, filesystem: &F) -> Result<InitLocationOperation<CreatedLocationDirectory<S>>> {
+        let location_directory = &self.as_request().location_directory;
+        let created_directory = if filesystem.is_directory(location_directory) {
+            None
+        } else {
+            filesystem.create_dir(location_directory)?;
+            Some(location_directory.clone())
+        };
+        Self::ok_with_state(
+            self.request,
+            CreatedLocationDirectory {
+                previous_state: self.state,
+                created_directory,
+            },
+        )
+    }
+}
+
+// A created location directory is removed
+impl<S> Compensate for CreatedLocationDirectory<S> {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        match &self.created_directory {
+            Some(directory) => filesystem.remove_dir_all(directory),
+            None => Ok(()),
+        }
+    }
+}
+
+pub struct LocationInitialized<S> {
+    previous_state: LockedLocation<CreatedLocationDirectory<S>>,
+    lockfile_path: Absolute<PathBuf>,
+}
+
+impl<S> InitLocationOperation<LockedLocation<CreatedLocationDirectory<S>>> {
+    pub fn initialize_lockfile<F: FileSystem, Sr: Serializer>(
+        self,
+        filesystem: &F,
+        serializer: &Sr,
+    ) -> Result<InitLocationOperation<LocationInitialized<S>>> {
         let lockfile_path = self.to_location_lockfile_path();
 
-        ensure_that_no_lockfile_is_present(&lockfile_path, filesystem)?;
+        let result = ensure_that_no_lockfile_is_present(&lockfile_path, filesystem)
+            .and_then(|_| serializer.serialize(&DeployLocation::default()))
+            .and_then(|deploy_location_file_content| filesystem.write_all(&lockfile_path, deploy_location_file_content.as_bytes()));
+        self.compensate_on_error(filesystem, result)?;
+        Self::ok_with_state(
+            self.request,
+            LocationInitialized {
+                previous_state: self.state,
+                lockfile_path,
+            },
+        )
+    }
+}
 
-        let is_free_slot = !filesystem.is_directory(&self.as_request().location_directory);
-        if is_free_slot {
-            filesystem.create_dir(&self.as_request().location_directory)?
+// An initialized location is removed when its registration fails
+impl<S> Compensate for LocationInitialized<S> {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        match &self.previous_state.previous_state.created_directory {
+            Some(directory) => filesystem.remove_dir_all(directory),
+            None => filesystem.remove_file(&self.lockfile_path),
         }
+    }
+}
+
+pub struct RegisteredLocation {
+    previous_state: LocationInitialized<ProbedRegistration>,
+}
 
-        let deploy_location = DeployLocation::default();
-        let deploy_location_file_content = serializer.serialize(&deploy_location)?;
-        filesystem.write_all(&lockfile_path, deploy_location_file_content.as_bytes())?;
-        Self::ok_with_state(self.request, LocationInitialized)
+impl InitLocationOperation<LocationInitialized<ProbedRegistration>> {
+    /* The user config is read again under its lock, another location may have been registered meanwhile */
+    pub fn register_location<F: FileSystem, P: Parser, Sr: Serializer, L: Locker>(
+        self,
+        filesystem: &F,
+        parser: &P,
+        serializer: &Sr,
+        locker: &L,
+        wait: Duration,
+    ) -> Result<InitLocationOperation<RegisteredLocation>> {
+        if let Some(registration) = &self.as_request().registration {
+            let config_path = &registration.config_path;
+            let result = lock_user_config(filesystem, locker, config_path.as_absolute_path(), wait).and_then(|_lock| {
+                match self.to_registered_config(filesystem, parser)? {
+                    Some(config) => write_user_config(filesystem, serializer, config_path.as_absolute_path(), &config),
+                    None => Ok(()),
+                }
+            });
+            self.compensate_on_error(filesystem, result)?;
+        }
+        Self::ok_with_state(self.request, RegisteredLocation { previous_state: self.state })
+    }
+}
+
+impl Compensate for RegisteredLocation {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        self.previous_state.compensate(filesystem)
+    }
+}
+
+impl InitLocationOperation<RegisteredLocation> {
+    pub fn as_registration(&self) -> Option<&LocationRegistration> {
+        self.as_request().registration.as_ref()
     }
 }
 
@@ -61,6 +198,3 @@ pub fn ensure_that_no_lockfile_is_present<F: FileSystem, P: AsRef<Path>>(
     }
     Ok(())
 }
-
-//TODO register in a user config file ?
-//TODO handle set as default if the first location created ?
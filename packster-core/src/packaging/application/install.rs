@@ -0,0 +1,192 @@
+use std::{collections::BTreeSet, path::{Path, PathBuf}};
+use crate::{
+    application::{
+        operation::{Compensate, New, Operation},
+        path::Absolute,
+        port::{Clock, FileSystem, ReadOnlyFileSystem, Source},
+    },
+    domain::entity::PackageRequirement,
+    packaging::domain::{plan_installation, select_indexed_package, CachedPackage, DeployLocation, IndexedPackage, Package, PackageCache},
+    Error, Result,
+};
+use super::{AsCachePath, AsLocation, AsLocationPath, LockedCache, LockedLocation, ParsedLocation, ParsedPackageCache, PersistedPackageCache};
+
+pub struct InstallRequest {
+    requirement: PackageRequirement,
+    location_path: Absolute<PathBuf>,
+    cache_directory: Absolute<PathBuf>,
+    labels: BTreeSet<String>,
+}
+
+impl InstallRequest {
+    pub fn new(requirement: PackageRequirement, location_path: Absolute<PathBuf>, cache_directory: Absolute<PathBuf>) -> Self {
+        InstallRequest {
+            requirement,
+            location_path,
+            cache_directory,
+            labels: BTreeSet::new(),
+        }
+    }
+
+    /* Only packages carrying every label are installed, dependencies included */
+    pub fn with_labels(mut self, labels: BTreeSet<String>) -> Self {
+        self.labels = labels;
+        self
+    }
+}
+
+pub type InstallOperation<S> = Operation<S, InstallRequest>;
+
+impl<S> AsCachePath for InstallOperation<S> {
+    fn as_cache_path(&self) -> Absolute<&Path> {
+        self.as_request().cache_directory.as_absolute_path()
+    }
+}
+
+impl<S> AsLocationPath for InstallOperation<S> {
+    fn as_location_path(&self) -> Absolute<&Path> {
+        self.as_request().location_path.as_absolute_path()
+    }
+}
+
+pub struct PlannedInstallation {
+    previous_state: ParsedLocation<LockedLocation<New>>,
+    packages: Vec<(usize, IndexedPackage)>,
+}
+
+impl InstallOperation<ParsedLocation<LockedLocation<New>>> {
+    /* The newest package matching the requirement among the sources, preceded by the dependencies it needs in the location */
+    pub fn plan_installation<F: ReadOnlyFileSystem, So: Source>(
+        self,
+        filesystem: &F,
+        sources: &[So],
+    ) -> Result<InstallOperation<PlannedInstallation>> {
+        if sources.is_empty() {
+            return Err(Error::NoSourceRegistered);
+        }
+        let InstallRequest { requirement, labels, .. } = self.as_request();
+        let indexes = sources.iter()
+            .map(|source| source.index(filesystem))
+            .collect::<Result<Vec<_>>>()?;
+        let selected = select_indexed_package(&indexes, requirement.as_identifier(), requirement.as_version_requirement(), labels)
+            .ok_or_else(|| Error::PackageNotFoundInSource {
+                requirement: requirement.to_string(),
+                source: sources.iter().map(Source::to_source_name).collect::<Vec<_>>().join(", "),
+            })?;
+        let packages = plan_installation(&self.as_state().location, &indexes, selected, labels)?;
+        Self::ok_with_state(
+            self.request,
+            PlannedInstallation {
+                previous_state: self.state,
+                packages,
+            },
+        )
+    }
+}
+
+impl AsLocation for PlannedInstallation {
+    fn as_location(&self) -> &DeployLocation {
+        self.previous_state.as_location()
+    }
+}
+
+pub struct DownloadedPackages {
+    previous_state: ParsedPackageCache<LockedCache<PlannedInstallation>>,
+    package_paths: Vec<Absolute<PathBuf>>,
+    downloaded_directories: Vec<Absolute<PathBuf>>,
+    cache: PackageCache,
+}
+
+impl InstallOperation<ParsedPackageCache<LockedCache<PlannedInstallation>>> {
+    /* Planned packages already cached are reused, the others are downloaded in the cache from the source they were planned from */
+    pub fn download_packages<F: FileSystem, So: Source, C: Clock>(
+        self,
+        filesystem: &F,
+        sources: &[So],
+        clock: &C,
+    ) -> Result<InstallOperation<DownloadedPackages>> {
+        let mut cache = self.as_state().as_ref().clone();
+        let mut package_paths = Vec::new();
+        let mut downloaded_directories = Vec::new();
+        for (source, package) in self.as_state().previous_state.previous_state.packages.iter() {
+            let cached_package = cache.find(package.as_checksum())
+                .filter(|cached_package| filesystem.is_file(self.to_cached_package_path(cached_package)))
+                .cloned();
+            let package_path = match cached_package {
+                Some(cached_package) => {
+                    cache = cache.with_package(cached_package.used_at(clock.now()));
+                    self.to_cached_package_path(&cached_package)
+                }
+                None => {
+                    let package_directory = self.to_cached_package_directory(package.as_checksum());
+                    let result = sources[*source].download(filesystem, package, package_directory.as_absolute_path())
+                        .and_then(|package_path| Ok((Package::from_path(&package_path)?, filesystem.file_size(&package_path)?, package_path)));
+                    downloaded_directories.push(package_directory);
+                    let (downloaded_package, size, package_path) = match result {
+                        Ok(downloaded) => downloaded,
+                        Err(error) => {
+                            remove_directories(filesystem, &downloaded_directories)?;
+                            return Err(error);
+                        }
+                    };
+                    cache = cache.with_package(CachedPackage::new(&downloaded_package, size, clock.now()));
+                    package_path
+                }
+            };
+            package_paths.push(package_path);
+        }
+
+        Self::ok_with_state(
+            self.request,
+            DownloadedPackages {
+                previous_state: self.state,
+                package_paths,
+                downloaded_directories,
+                cache,
+            },
+        )
+    }
+}
+
+fn remove_directories<F: FileSystem>(filesystem: &F, directories: &[Absolute<PathBuf>]) -> Result<()> {
+    for directory in directories {
+        if filesystem.exists(directory) {
+            filesystem.remove_dir_all(directory)?;
+        }
+    }
+    Ok(())
+}
+
+impl Compensate for DownloadedPackages {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        remove_directories(filesystem, &self.downloaded_directories)
+    }
+}
+
+impl AsRef<PackageCache> for DownloadedPackages {
+    fn as_ref(&self) -> &PackageCache {
+        &self.cache
+    }
+}
+
+impl AsLocation for DownloadedPackages {
+    fn as_location(&self) -> &DeployLocation {
+        self.previous_state.as_location()
+    }
+}
+
+impl InstallOperation<PersistedPackageCache<DownloadedPackages>> {
+    /* Package files to deploy in this order, the installed package being the last one */
+    pub fn as_package_paths(&self) -> &[Absolute<PathBuf>] {
+        &self.as_state().previous_state.package_paths
+    }
+
+    pub fn iter_planned_packages(&self) -> impl Iterator<Item = &IndexedPackage> {
+        self.as_state().previous_state.previous_state.previous_state.previous_state.packages.iter().map(|(_, package)| package)
+    }
+
+    /* Number of planned packages which were not cached yet */
+    pub fn count_downloaded_packages(&self) -> usize {
+        self.as_state().previous_state.downloaded_directories.len()
+    }
+}
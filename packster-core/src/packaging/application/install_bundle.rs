@@ -0,0 +1,353 @@
+use std::{path::{Path, PathBuf}, str::FromStr};
+use crate::{
+    application::{
+        operation::{Compensate, New, Operation},
+        path::Absolute,
+        port::{Archiver, Digester, Executor, FileSystem, Parser, ReadOnlyFileSystem},
+    },
+    domain::entity::{Checksum, Version},
+    packaging::{
+        domain::{plan_bundle, AsBundle, AsLocation, Bundle, DeployLocation, DeployedFile, Deployment, HookEvent, Package},
+        PACKAGE_EXTENSION,
+    },
+    Result,
+};
+use super::{
+    LockedLocation,
+    execute_hooks, extract_package_payload, index_deployment_files, remove_deployment_directory, to_deployment_hook_environment, read_package_metadata, validate_checksum, AsBundlePath, AsLocationPath,
+    ParsedBundle, ParsedLocation, GeneratedShims, PersistedDeployLocation,
+};
+
+pub struct InstallBundleRequest {
+    bundle_path: Absolute<PathBuf>,
+    location_path: Absolute<PathBuf>,
+}
+
+impl InstallBundleRequest {
+    pub fn new(bundle_path: Absolute<PathBuf>, location_path: Absolute<PathBuf>) -> Self {
+        InstallBundleRequest {
+            bundle_path,
+            location_path,
+        }
+    }
+}
+
+pub type InstallBundleOperation<S> = Operation<S, InstallBundleRequest>;
+
+impl<S> AsBundlePath for InstallBundleOperation<S> {
+    fn as_bundle_path(&self) -> Absolute<&Path> {
+        self.as_request().bundle_path.as_absolute_path()
+    }
+}
+
+impl<S> AsLocationPath for InstallBundleOperation<S> {
+    fn as_location_path(&self) -> Absolute<&Path> {
+        self.as_request().location_path.as_absolute_path()
+    }
+}
+
+pub type InstallBundleParsedState = ParsedLocation<ParsedBundle<LockedLocation<New>>>;
+
+pub struct IndexedBundleSource {
+    previous_state: InstallBundleParsedState,
+    packages: Vec<(Package, Absolute<PathBuf>)>,
+}
+
+impl InstallBundleOperation<InstallBundleParsedState> {
+    fn to_source_path(&self) -> Absolute<PathBuf> {
+        let bundle_directory = self.as_bundle_path().parent().map(Path::to_path_buf).unwrap_or_default();
+        Absolute::assume_absolute(bundle_directory.join(self.as_bundle().as_source()))
+            .to_normalized_absolute_path()
+            .to_absolute_path()
+    }
+
+    pub fn index_bundle_source<F: ReadOnlyFileSystem, P: Parser>(
+        self,
+        filesystem: &F,
+        parser: &P,
+    ) -> Result<InstallBundleOperation<IndexedBundleSource>> {
+        let source_path = self.to_source_path();
+        let mut packages = Vec::new();
+        for entry in filesystem.walk(&source_path) {
+            let entry = entry?;
+            let is_package_file = filesystem.is_file(entry.as_path())
+                && entry.as_path().extension().is_some_and(|extension| extension == PACKAGE_EXTENSION);
+            if is_package_file {
+                let package = read_package_metadata(filesystem, parser, entry.as_path())?;
+                packages.push((package, entry.as_absolute_path().to_absolute_path()));
+            }
+        }
+
+        Self::ok_with_state(
+            self.request,
+            IndexedBundleSource {
+                previous_state: self.state,
+                packages,
+            },
+        )
+    }
+}
+
+impl AsBundle for IndexedBundleSource {
+    fn as_bundle(&self) -> &Bundle {
+        self.previous_state.as_bundle()
+    }
+}
+
+pub struct ResolvedBundle {
+    previous_state: IndexedBundleSource,
+    deployments: Vec<(Deployment, Absolute<PathBuf>)>,
+    location: DeployLocation,
+}
+
+impl InstallBundleOperation<IndexedBundleSource> {
+    pub fn resolve_bundle<D: Digester>(self, digester: &D, packster_version: &str) -> Result<InstallBundleOperation<ResolvedBundle>> {
+        let packster_version = Version::from_str(packster_version)?;
+        let IndexedBundleSource { previous_state, packages } = &self.state;
+        let available_packages: Vec<Package> = packages.iter().map(|(package, _)| package.clone()).collect();
+        let (planned, mut location) = plan_bundle(previous_state.as_location(), previous_state.as_bundle(), &available_packages, digester)?;
+        for deployment in planned.iter() {
+            location.set_installed_packster_version(deployment.as_id(), packster_version.clone());
+        }
+
+        let deployments = planned
+            .into_iter()
+            .map(|deployment| deployment.with_installed_packster_version(packster_version.clone()))
+            .filter_map(|deployment| {
+                packages
+                    .iter()
+                    .find(|(package, _)| package.as_checksum() == deployment.as_checksum())
+                    .map(|(_, path)| path.clone())
+                    .map(|path| (deployment, path))
+            })
+            .collect();
+
+        Self::ok_with_state(
+            self.request,
+            ResolvedBundle {
+                previous_state: self.state,
+                deployments,
+                location,
+            },
+        )
+    }
+}
+
+impl AsBundle for ResolvedBundle {
+    fn as_bundle(&self) -> &Bundle {
+        self.previous_state.as_bundle()
+    }
+}
+
+pub struct MatchingBundleChecksums {
+    previous_state: ResolvedBundle,
+}
+
+impl InstallBundleOperation<ResolvedBundle> {
+    pub fn validate_bundle_checksums<F: ReadOnlyFileSystem, D: Digester>(
+        self,
+        filesystem: &F,
+        digester: &D,
+    ) -> Result<InstallBundleOperation<MatchingBundleChecksums>> {
+        for (deployment, package_path) in self.as_state().deployments.iter() {
+            validate_checksum(filesystem, digester, package_path, deployment.as_ref())?;
+        }
+        Self::ok_with_state(
+            self.request,
+            MatchingBundleChecksums {
+                previous_state: self.state,
+            },
+        )
+    }
+}
+
+impl AsBundle for MatchingBundleChecksums {
+    fn as_bundle(&self) -> &Bundle {
+        self.previous_state.as_bundle()
+    }
+}
+
+pub struct ExtractedBundle {
+    previous_state: MatchingBundleChecksums,
+    deployment_paths: Vec<Absolute<PathBuf>>,
+}
+
+impl InstallBundleOperation<MatchingBundleChecksums> {
+    pub fn extract_bundle_packages<F: FileSystem, A: Archiver>(
+        self,
+        filesystem: &F,
+        archiver: &A,
+    ) -> Result<InstallBundleOperation<ExtractedBundle>> {
+        let mut deployment_paths = Vec::new();
+        for (deployment, package_path) in self.as_state().previous_state.deployments.iter() {
+            let deployment_path = self.to_deployment_path(deployment.as_id());
+            if let Err(error) = extract_package_payload(filesystem, archiver, package_path, deployment.as_ref(), deployment_path.as_absolute_path()) {
+                remove_deployment_directories(filesystem, &deployment_paths)?;
+                return Err(error);
+            }
+            deployment_paths.push(deployment_path);
+        }
+        Self::ok_with_state(
+            self.request,
+            ExtractedBundle {
+                previous_state: self.state,
+                deployment_paths,
+            },
+        )
+    }
+}
+
+fn remove_deployment_directories<F: FileSystem>(filesystem: &F, deployment_paths: &[Absolute<PathBuf>]) -> Result<()> {
+    for deployment_path in deployment_paths {
+        remove_deployment_directory(filesystem, deployment_path.as_absolute_path())?;
+    }
+    Ok(())
+}
+
+impl Compensate for ExtractedBundle {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        remove_deployment_directories(filesystem, &self.deployment_paths)
+    }
+}
+
+impl AsBundle for ExtractedBundle {
+    fn as_bundle(&self) -> &Bundle {
+        self.previous_state.as_bundle()
+    }
+}
+
+pub struct HookedBundle {
+    previous_state: ExtractedBundle,
+}
+
+impl InstallBundleOperation<ExtractedBundle> {
+    fn as_resolved_bundle(&self) -> &ResolvedBundle {
+        &self.as_state().previous_state.previous_state
+    }
+
+    pub fn execute_bundle_deploy_hooks<F: FileSystem, E: Executor>(
+        self,
+        filesystem: &F,
+        executor: &E,
+    ) -> Result<InstallBundleOperation<HookedBundle>> {
+        let result = self.execute_each_deploy_hooks(executor);
+        self.compensate_on_error(filesystem, result)?;
+        Self::ok_with_state(
+            self.request,
+            HookedBundle {
+                previous_state: self.state,
+            },
+        )
+    }
+
+    fn execute_each_deploy_hooks<E: Executor>(&self, executor: &E) -> Result<()> {
+        for (deployment, _) in self.as_resolved_bundle().deployments.iter() {
+            let deployment_path = self.to_deployment_path(deployment.as_id());
+            execute_hooks(
+                executor,
+                deployment.as_ref().as_hooks(),
+                HookEvent::Deploy,
+                deployment_path.as_absolute_path(),
+                to_deployment_hook_environment(
+                    self.as_location_path(),
+                    deployment_path.as_absolute_path(),
+                    deployment,
+                ),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Compensate for HookedBundle {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        self.previous_state.compensate(filesystem)
+    }
+}
+
+impl AsBundle for HookedBundle {
+    fn as_bundle(&self) -> &Bundle {
+        self.previous_state.as_bundle()
+    }
+}
+
+pub struct IndexedBundleFiles {
+    previous_state: HookedBundle,
+}
+
+impl InstallBundleOperation<HookedBundle> {
+    pub fn index_bundle_deployment_files<F: FileSystem, D: Digester>(
+        mut self,
+        filesystem: &F,
+        digester: &D,
+    ) -> Result<InstallBundleOperation<IndexedBundleFiles>> {
+        let result = self.index_each_deployment_files(filesystem, digester);
+        let indexed_files = self.compensate_on_error(filesystem, result)?;
+        let location = &mut self.as_mut_state().previous_state.previous_state.previous_state.location;
+        for (id, files) in indexed_files {
+            location.set_deployment_files(&id, files);
+        }
+        Self::ok_with_state(
+            self.request,
+            IndexedBundleFiles {
+                previous_state: self.state,
+            },
+        )
+    }
+
+    fn index_each_deployment_files<F: FileSystem, D: Digester>(
+        &self,
+        filesystem: &F,
+        digester: &D,
+    ) -> Result<Vec<(Checksum, Vec<DeployedFile>)>> {
+        self.as_state()
+            .previous_state
+            .previous_state
+            .previous_state
+            .deployments
+            .iter()
+            .map(|(deployment, _)| {
+                let deployment_path = self.to_deployment_path(deployment.as_id());
+                index_deployment_files(filesystem, digester, deployment_path.as_absolute_path())
+                    .map(|files| (deployment.as_id().clone(), files))
+            })
+            .collect()
+    }
+}
+
+impl Compensate for IndexedBundleFiles {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        self.previous_state.compensate(filesystem)
+    }
+}
+
+impl AsBundle for IndexedBundleFiles {
+    fn as_bundle(&self) -> &Bundle {
+        self.previous_state.as_bundle()
+    }
+}
+
+impl AsRef<DeployLocation> for IndexedBundleFiles {
+    fn as_ref(&self) -> &DeployLocation {
+        &self.previous_state.previous_state.previous_state.previous_state.location
+    }
+}
+
+impl InstallBundleOperation<GeneratedShims<PersistedDeployLocation<IndexedBundleFiles>>> {
+    pub fn as_bundle(&self) -> &Bundle {
+        self.as_state().previous_state.previous_state.as_bundle()
+    }
+
+    pub fn iter_installed_deployments(&self) -> impl Iterator<Item = &Deployment> {
+        self.as_state()
+            .previous_state
+            .previous_state
+            .previous_state
+            .previous_state
+            .previous_state
+            .previous_state
+            .deployments
+            .iter()
+            .map(|(deployment, _)| deployment)
+    }
+}
@@ -0,0 +1,64 @@
+use std::path::{Path, PathBuf};
+use crate::{
+    application::{
+        operation::{New, Operation},
+        path::Absolute,
+    },
+    packaging::domain::{AsLocation, DeployLocation, LocationEnvironment},
+    Result,
+};
+use super::{AsLocationPath, ParsedLocation};
+
+pub struct LocationEnvironmentRequest {
+    location_path: Absolute<PathBuf>,
+}
+
+impl LocationEnvironmentRequest {
+    pub fn new(location_path: Absolute<PathBuf>) -> Self {
+        LocationEnvironmentRequest { location_path }
+    }
+}
+
+pub type LocationEnvironmentOperation<S> = Operation<S, LocationEnvironmentRequest>;
+
+impl<S> AsLocationPath for LocationEnvironmentOperation<S> {
+    fn as_location_path(&self) -> Absolute<&Path> {
+        self.as_request().location_path.as_absolute_path()
+    }
+}
+
+pub struct ResolvedEnvironment {
+    previous_state: ParsedLocation<New>,
+    environment: LocationEnvironment,
+}
+
+impl LocationEnvironmentOperation<ParsedLocation<New>> {
+    /* The location bin directory and the variables declared by its active deployments */
+    pub fn resolve_location_environment(self) -> Result<LocationEnvironmentOperation<ResolvedEnvironment>> {
+        let mut environment = LocationEnvironment::new(self.to_location_bin_path().as_ref());
+        for deployment in self.as_location().iter_active_deployments() {
+            let deployment_path = self.to_deployment_path(deployment.as_id());
+            environment.add_deployment(deployment, self.as_location_path().as_ref(), deployment_path.as_ref())?;
+        }
+
+        Self::ok_with_state(
+            self.request,
+            ResolvedEnvironment {
+                previous_state: self.state,
+                environment,
+            },
+        )
+    }
+}
+
+impl AsLocation for ResolvedEnvironment {
+    fn as_location(&self) -> &DeployLocation {
+        self.previous_state.as_location()
+    }
+}
+
+impl LocationEnvironmentOperation<ResolvedEnvironment> {
+    pub fn as_environment(&self) -> &LocationEnvironment {
+        &self.as_state().environment
+    }
+}
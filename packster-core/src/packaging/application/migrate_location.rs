@@ -0,0 +1,35 @@
+use std::path::{Path, PathBuf};
+use crate::application::{
+    operation::{New, Operation},
+    path::Absolute,
+};
+use super::{AsLocationPath, LockedLocation, ParsedLocation, PersistedDeployLocation};
+
+pub struct MigrateLocationRequest {
+    location_path: Absolute<PathBuf>,
+}
+
+impl MigrateLocationRequest {
+    pub fn new(location_path: Absolute<PathBuf>) -> Self {
+        MigrateLocationRequest { location_path }
+    }
+}
+
+pub type MigrateLocationOperation<S> = Operation<S, MigrateLocationRequest>;
+
+impl<S> AsLocationPath for MigrateLocationOperation<S> {
+    fn as_location_path(&self) -> Absolute<&Path> {
+        self.as_request().location_path.as_absolute_path()
+    }
+}
+
+// Parsing the lockfile migrates it, persisting it writes it in the current schema
+impl MigrateLocationOperation<PersistedDeployLocation<ParsedLocation<LockedLocation<New>>>> {
+    pub fn as_previous_schema_version(&self) -> u32 {
+        self.as_state().previous_state.lockfile_schema_version
+    }
+
+    pub fn as_schema_version(&self) -> u32 {
+        self.as_state().previous_state.location.as_schema_version()
+    }
+}
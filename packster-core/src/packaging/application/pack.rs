@@ -1,19 +1,21 @@
-use std::path::PathBuf;
+use std::{io, path::PathBuf, str::FromStr};
 use crate::{
     application::{
         operation::{New, Operation},
         path::Absolute,
         port::{
-            Archiver, Digester, FileSystem, Parser, ReadOnlyFileSystem, UniqueIdentifierGenerator,
+            Archiver, Digester, Executor, FileSystem, Parser, ReadOnlyFileSystem, Serializer,
+            UniqueIdentifierGenerator,
         },
     },
-    domain::entity::{Checksum, Version},
+    domain::entity::{Checksum, Parameters, Version},
     packaging::{
-        domain::{Package, Project},
+        domain::{HookEvent, Package, Project},
         PACKAGE_EXTENSION, PROJECT_MANIFEST_NAME,
     },
     Result,
 };
+use super::{execute_hooks, to_hook_environment, write_package_header};
 
 pub struct PackRequest {
     project_workspace: Absolute<PathBuf>,
@@ -51,12 +53,35 @@ impl PackOperation<New> {
     }
 }
 
+pub struct HookedProject {
+    pub previous_state: Project,
+}
+
+impl PackOperation<Project> {
+    pub fn execute_pack_hooks<E: Executor>(self, executor: &E) -> Result<PackOperation<HookedProject>> {
+        let project = self.as_state();
+        execute_hooks(
+            executor,
+            project.as_hooks(),
+            HookEvent::Pack,
+            self.as_request().project_workspace.as_absolute_path(),
+            to_hook_environment(project.as_identifier(), project.as_version(), &Parameters::new()),
+        )?;
+        Self::ok_with_state(
+            self.request,
+            HookedProject {
+                previous_state: self.state,
+            },
+        )
+    }
+}
+
 pub struct IdentifiedProject {
     pub project: Project,
     pub identifier: String,
 }
 
-impl PackOperation<Project> {
+impl PackOperation<HookedProject> {
     pub fn generate_unique_identity<I: UniqueIdentifierGenerator>(
         self,
         identifier_generator: &I,
@@ -65,7 +90,7 @@ impl PackOperation<Project> {
             self.request,
             IdentifiedProject {
                 identifier: identifier_generator.generate_identifier(),
-                project: self.state,
+                project: self.state.previous_state,
             },
         )
     }
@@ -128,9 +153,10 @@ impl PackOperation<ArchivedProject> {
 }
 
 impl PackOperation<DigestedArchivedProject> {
-    pub fn finalize<F: FileSystem>(
+    pub fn finalize<F: FileSystem, Sr: Serializer>(
         self,
         filesystem: &F,
+        serializer: &Sr,
         packster_version: &str,
     ) -> Result<PackOperation<Package>> {
         let DigestedArchivedProject {
@@ -142,10 +168,16 @@ impl PackOperation<DigestedArchivedProject> {
                 },
         } = self.state;
 
-        let package = Package::new(project, checksum, Version::new(packster_version));
+        let package = Package::new(project, checksum, Version::from_str(packster_version)?);
         let final_archive_path = archive_path.with_file_name(package.to_file_name());
 
-        filesystem.rename(archive_path, final_archive_path)?;
+        {
+            let mut writer = filesystem.open_write(&final_archive_path)?;
+            write_package_header(&mut writer, &serializer.serialize(&package)?)?;
+            io::copy(&mut filesystem.open_read(&archive_path)?, &mut writer)?;
+        }
+        filesystem.remove_file(archive_path)?;
+
         Self::ok_with_state(self.request, package)
     }
 }
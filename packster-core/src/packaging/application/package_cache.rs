@@ -0,0 +1,30 @@
+use std::path::{Path, PathBuf};
+use crate::{
+    application::{operation::{New, Operation}, path::Absolute},
+    packaging::domain::PackageCache,
+};
+use super::{AsCachePath, ParsedPackageCache};
+
+pub struct PackageCacheRequest {
+    cache_directory: Absolute<PathBuf>,
+}
+
+impl PackageCacheRequest {
+    pub fn new(cache_directory: Absolute<PathBuf>) -> Self {
+        PackageCacheRequest { cache_directory }
+    }
+}
+
+pub type PackageCacheOperation<S> = Operation<S, PackageCacheRequest>;
+
+impl<S> AsCachePath for PackageCacheOperation<S> {
+    fn as_cache_path(&self) -> Absolute<&Path> {
+        self.as_request().cache_directory.as_absolute_path()
+    }
+}
+
+impl PackageCacheOperation<ParsedPackageCache<New>> {
+    pub fn as_package_cache(&self) -> &PackageCache {
+        self.as_state().as_ref()
+    }
+}
@@ -0,0 +1,156 @@
+use std::{path::{Path, PathBuf}, str::FromStr, time::Duration};
+use crate::{
+    application::{
+        operation::{Compensate, New, Operation},
+        path::Absolute,
+        port::{Clock, FileSystem, ReadOnlyFileSystem},
+    },
+    domain::entity::Checksum,
+    packaging::domain::{CachedPackage, PackageCache},
+    Result,
+};
+use super::{AsCachePath, LockedCache, ParsedPackageCache, PersistedPackageCache};
+
+pub struct PruneCacheRequest {
+    cache_directory: Absolute<PathBuf>,
+    unused_for: Option<Duration>,
+    max_size: Option<u64>,
+}
+
+impl PruneCacheRequest {
+    /* Without limit, only the directories the cache does not list are pruned */
+    pub fn new(cache_directory: Absolute<PathBuf>) -> Self {
+        PruneCacheRequest { cache_directory, unused_for: None, max_size: None }
+    }
+
+    pub fn with_unused_for(mut self, unused_for: Duration) -> Self {
+        self.unused_for = Some(unused_for);
+        self
+    }
+
+    /* Size in bytes of the package files kept */
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+}
+
+pub type PruneCacheOperation<S> = Operation<S, PruneCacheRequest>;
+
+impl<S> AsCachePath for PruneCacheOperation<S> {
+    fn as_cache_path(&self) -> Absolute<&Path> {
+        self.as_request().cache_directory.as_absolute_path()
+    }
+}
+
+pub struct EvictedPackages {
+    previous_state: ParsedPackageCache<LockedCache<New>>,
+    evicted_packages: Vec<CachedPackage>,
+    orphan_paths: Vec<Absolute<PathBuf>>,
+    cache: PackageCache,
+}
+
+impl PruneCacheOperation<ParsedPackageCache<LockedCache<New>>> {
+    /* Packages unused for the given duration, then the least recently used ones until the cache fits in the given size,
+     * along with the directories left over by an interrupted operation */
+    pub fn select_evicted_packages<F: ReadOnlyFileSystem, C: Clock>(self, filesystem: &F, clock: &C) -> Result<PruneCacheOperation<EvictedPackages>> {
+        let PruneCacheRequest { unused_for, max_size, .. } = self.as_request();
+        let unused_since = unused_for.map(|unused_for| clock.now().saturating_sub(unused_for.as_secs()));
+        let cache = self.as_state().as_ref();
+        let evicted_packages = cache.to_evicted_packages(unused_since, *max_size);
+        let orphan_paths = find_orphan_cache_directories(filesystem, self.as_cache_path(), cache)?;
+        let cache = cache.without_packages(&evicted_packages);
+        Self::ok_with_state(
+            self.request,
+            EvictedPackages {
+                previous_state: self.state,
+                evicted_packages,
+                orphan_paths,
+                cache,
+            },
+        )
+    }
+}
+
+/* Top level directories named after a checksum the cache does not list */
+fn find_orphan_cache_directories<F: ReadOnlyFileSystem>(
+    filesystem: &F,
+    cache_path: Absolute<&Path>,
+    cache: &PackageCache,
+) -> Result<Vec<Absolute<PathBuf>>> {
+    if !filesystem.exists(&cache_path) {
+        return Ok(Vec::new());
+    }
+    let mut orphan_paths = Vec::new();
+    for entry in filesystem.walk(&cache_path) {
+        let entry = entry?;
+        let path = entry.as_path();
+        if path.parent() != Some(cache_path.as_ref()) || !filesystem.is_directory(path) {
+            continue;
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let is_orphan = !name.is_empty() && Checksum::from_str(&name).is_ok_and(|checksum| cache.find(&checksum).is_none());
+        if is_orphan {
+            orphan_paths.push(entry.as_absolute_path().to_absolute_path());
+        }
+    }
+    Ok(orphan_paths)
+}
+
+impl Compensate for EvictedPackages {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        self.previous_state.compensate(filesystem)
+    }
+}
+
+impl AsRef<PackageCache> for EvictedPackages {
+    fn as_ref(&self) -> &PackageCache {
+        &self.cache
+    }
+}
+
+// Stopping there is a dry run
+impl PruneCacheOperation<EvictedPackages> {
+    pub fn as_evicted_packages(&self) -> &[CachedPackage] {
+        &self.as_state().evicted_packages
+    }
+
+    pub fn as_orphan_paths(&self) -> &[Absolute<PathBuf>] {
+        &self.as_state().orphan_paths
+    }
+}
+
+pub struct DeletedEvictedPackages {
+    previous_state: PersistedPackageCache<EvictedPackages>,
+}
+
+impl PruneCacheOperation<PersistedPackageCache<EvictedPackages>> {
+    /* Package files are only removed once the cache no longer lists them : a failure leaves orphan directories, pruned next time */
+    pub fn delete_evicted_packages<F: FileSystem>(self, filesystem: &F) -> Result<PruneCacheOperation<DeletedEvictedPackages>> {
+        let evicted = &self.as_state().previous_state;
+        let package_directories = evicted.evicted_packages.iter()
+            .map(|package| self.to_cached_package_directory(package.as_checksum()))
+            .chain(evicted.orphan_paths.iter().cloned());
+        for package_directory in package_directories {
+            if filesystem.exists(&package_directory) {
+                filesystem.remove_dir_all(&package_directory)?;
+            }
+        }
+        Self::ok_with_state(
+            self.request,
+            DeletedEvictedPackages {
+                previous_state: self.state,
+            },
+        )
+    }
+}
+
+impl PruneCacheOperation<DeletedEvictedPackages> {
+    pub fn as_evicted_packages(&self) -> &[CachedPackage] {
+        &self.as_state().previous_state.previous_state.evicted_packages
+    }
+
+    pub fn as_orphan_paths(&self) -> &[Absolute<PathBuf>] {
+        &self.as_state().previous_state.previous_state.orphan_paths
+    }
+}
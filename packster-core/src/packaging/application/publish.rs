@@ -0,0 +1,152 @@
+use std::{io, path::{Path, PathBuf}};
+use crate::{
+    application::{
+        operation::{Compensate, New, Operation},
+        path::Absolute,
+        port::FileSystem,
+    },
+    packaging::domain::{AsPackage, IndexedPackage, Package, SourceIndex},
+    Error, Result,
+};
+use super::{AsPackagePath, AsSourcePath, IndexedSource, MatchingChecksum, ParsedPackage, PersistedSourceIndex};
+
+pub struct PublishRequest {
+    package_path: Absolute<PathBuf>,
+    source_directory: Absolute<PathBuf>,
+}
+
+impl PublishRequest {
+    pub fn new(package_path: Absolute<PathBuf>, source_directory: Absolute<PathBuf>) -> Self {
+        PublishRequest { package_path, source_directory }
+    }
+}
+
+pub type PublishOperation<S> = Operation<S, PublishRequest>;
+
+impl<S> AsPackagePath for PublishOperation<S> {
+    fn as_package_path(&self) -> Absolute<&Path> {
+        self.as_request().package_path.as_absolute_path()
+    }
+}
+
+impl<S> AsSourcePath for PublishOperation<S> {
+    fn as_source_path(&self) -> Absolute<&Path> {
+        self.as_request().source_directory.as_absolute_path()
+    }
+}
+
+pub type PublishValidState = IndexedSource<MatchingChecksum<ParsedPackage<New>>>;
+
+pub struct ProbedPublication {
+    previous_state: PublishValidState,
+    is_published: bool,
+}
+
+impl PublishOperation<PublishValidState> {
+    /* Published versions are immutable : a version can only be published again with the same checksum, which does nothing */
+    pub fn probe_package_publishable(self) -> Result<PublishOperation<ProbedPublication>> {
+        let package = self.as_package();
+        let is_published = match self.as_state().as_ref().find(package.as_identifier(), package.as_version()) {
+            Some(indexed_package) if indexed_package.as_checksum() != package.as_checksum() => {
+                return Err(Error::PackageVersionAlreadyPublished {
+                    package: format!("{} {}", package.as_identifier(), package.as_version()),
+                    source: self.as_source_path().to_string_lossy().to_string(),
+                    checksum: indexed_package.as_checksum().to_string(),
+                })
+            }
+            Some(_) => true,
+            None => false,
+        };
+        Self::ok_with_state(
+            self.request,
+            ProbedPublication {
+                previous_state: self.state,
+                is_published,
+            },
+        )
+    }
+}
+
+impl AsPackage for ProbedPublication {
+    fn as_package(&self) -> &Package {
+        self.previous_state.as_package()
+    }
+}
+
+pub struct CopiedPackage {
+    previous_state: ProbedPublication,
+    index: SourceIndex,
+    copied_path: Option<Absolute<PathBuf>>,
+}
+
+impl PublishOperation<ProbedPublication> {
+    /* The package file is copied at the root of the source, named after the package */
+    pub fn copy_package_to_source<F: FileSystem>(self, filesystem: &F) -> Result<PublishOperation<CopiedPackage>> {
+        if self.as_state().is_published {
+            let index = self.as_state().previous_state.as_ref().clone();
+            return Self::ok_with_state(
+                self.request,
+                CopiedPackage {
+                    previous_state: self.state,
+                    index,
+                    copied_path: None,
+                },
+            );
+        }
+
+        let file_name = self.as_package().to_file_name();
+        let copied_path = self.as_source_path().join(&file_name);
+        if let Err(error) = copy_file(filesystem, self.as_package_path(), copied_path.as_absolute_path()) {
+            if filesystem.exists(&copied_path) {
+                filesystem.remove_file(&copied_path)?;
+            }
+            return Err(error);
+        }
+
+        let indexed_package = IndexedPackage::new(self.as_package(), file_name, filesystem.file_size(&copied_path)?);
+        let index = self.as_state().previous_state.as_ref().with_package(indexed_package);
+        Self::ok_with_state(
+            self.request,
+            CopiedPackage {
+                previous_state: self.state,
+                index,
+                copied_path: Some(copied_path),
+            },
+        )
+    }
+}
+
+fn copy_file<F: FileSystem>(filesystem: &F, source: Absolute<&Path>, destination: Absolute<&Path>) -> Result<()> {
+    let mut reader = filesystem.open_read(source)?;
+    let mut writer = filesystem.open_write(destination)?;
+    io::copy(&mut reader, &mut writer)?;
+    Ok(())
+}
+
+impl Compensate for CopiedPackage {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        match &self.copied_path {
+            Some(copied_path) => filesystem.remove_file(copied_path),
+            None => Ok(()),
+        }
+    }
+}
+
+impl AsRef<SourceIndex> for CopiedPackage {
+    fn as_ref(&self) -> &SourceIndex {
+        &self.index
+    }
+}
+
+impl AsPackage for CopiedPackage {
+    fn as_package(&self) -> &Package {
+        self.previous_state.as_package()
+    }
+}
+
+impl PublishOperation<PersistedSourceIndex<CopiedPackage>> {
+    /* The same package was already published, so nothing was copied */
+    pub fn is_already_published(&self) -> bool {
+        self.as_state().previous_state.previous_state.is_published
+    }
+}
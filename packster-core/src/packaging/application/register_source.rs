@@ -0,0 +1,58 @@
+use std::{path::PathBuf, time::Duration};
+use crate::{
+    application::{
+        operation::{New, Operation},
+        path::Absolute,
+        port::{FileSystem, Locker, Parser, Serializer},
+    },
+    Result,
+};
+use super::{lock_user_config, read_user_config, write_user_config};
+
+pub struct RegisterSourceRequest {
+    config_path: Absolute<PathBuf>,
+    source: String,
+}
+
+impl RegisterSourceRequest {
+    pub fn new(config_path: Absolute<PathBuf>, source: String) -> Self {
+        RegisterSourceRequest { config_path, source }
+    }
+}
+
+pub type RegisterSourceOperation<S> = Operation<S, RegisterSourceRequest>;
+
+pub struct RegisteredSource {
+    is_new: bool,
+}
+
+impl RegisterSourceOperation<New> {
+    /* The user config is only written when the source was not registered yet */
+    pub fn register_source<F: FileSystem, P: Parser, Sr: Serializer, L: Locker>(
+        self,
+        filesystem: &F,
+        parser: &P,
+        serializer: &Sr,
+        locker: &L,
+        wait: Duration,
+    ) -> Result<RegisterSourceOperation<RegisteredSource>> {
+        let RegisterSourceRequest { config_path, source } = self.as_request();
+        let _lock = lock_user_config(filesystem, locker, config_path.as_absolute_path(), wait)?;
+        let mut config = read_user_config(filesystem, parser, config_path)?;
+        let is_new = config.register_source(source.clone());
+        if is_new {
+            write_user_config(filesystem, serializer, config_path.as_absolute_path(), &config)?;
+        }
+        Self::ok_with_state(self.request, RegisteredSource { is_new })
+    }
+}
+
+impl RegisterSourceOperation<RegisteredSource> {
+    pub fn as_source(&self) -> &str {
+        &self.as_request().source
+    }
+
+    pub fn is_new(&self) -> bool {
+        self.as_state().is_new
+    }
+}
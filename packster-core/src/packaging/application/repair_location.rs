@@ -0,0 +1,260 @@
+use std::path::{Path, PathBuf};
+use crate::{
+    application::{
+        operation::{Compensate, New, Operation},
+        path::Absolute,
+        port::{Archiver, Digester, FileSystem, Parser, ReadOnlyFileSystem},
+    },
+    domain::entity::Checksum,
+    packaging::{domain::{AsLocation, DeployLocation, DeployedFile, LocationIssue, Package}, PACKAGE_EXTENSION},
+    Error, Result,
+};
+use super::{
+    extract_package_payload, find_cached_package, index_deployment_files, inspect_deployment_directory, read_package_metadata, remove_deployment_directory, to_damaged_path,
+    validate_checksum, AsLocationPath, GeneratedShims, LockedLocation, ParsedLocation, PersistedDeployLocation,
+};
+
+pub struct RepairLocationRequest {
+    location_path: Absolute<PathBuf>,
+    package_paths: Vec<Absolute<PathBuf>>,
+    cache_directory: Option<Absolute<PathBuf>>,
+}
+
+impl RepairLocationRequest {
+    /* Package paths are either package files or directories searched for package files */
+    pub fn new(location_path: Absolute<PathBuf>, package_paths: Vec<Absolute<PathBuf>>) -> Self {
+        RepairLocationRequest { location_path, package_paths, cache_directory: None }
+    }
+
+    /* Packages not found among the package paths are looked up by checksum in the cache */
+    pub fn with_cache_directory(mut self, cache_directory: Absolute<PathBuf>) -> Self {
+        self.cache_directory = Some(cache_directory);
+        self
+    }
+}
+
+pub type RepairLocationOperation<S> = Operation<S, RepairLocationRequest>;
+
+impl<S> AsLocationPath for RepairLocationOperation<S> {
+    fn as_location_path(&self) -> Absolute<&Path> {
+        self.as_request().location_path.as_absolute_path()
+    }
+}
+
+pub struct DeploymentRepair {
+    deployment_id: Checksum,
+    package: Package,
+    package_path: Absolute<PathBuf>,
+}
+
+impl DeploymentRepair {
+    pub fn as_deployment_id(&self) -> &Checksum { &self.deployment_id }
+    pub fn as_package(&self) -> &Package { &self.package }
+    pub fn as_package_path(&self) -> Absolute<&Path> { self.package_path.as_absolute_path() }
+}
+
+pub struct DamagedDeployments {
+    pub previous_state: ParsedLocation<LockedLocation<New>>,
+    repairs: Vec<DeploymentRepair>,
+}
+
+impl RepairLocationOperation<ParsedLocation<LockedLocation<New>>> {
+    /* Every damaged deployment shall be matched with the package it was extracted from before anything is touched */
+    pub fn find_damaged_deployments<F: ReadOnlyFileSystem, P: Parser, D: Digester>(
+        self,
+        filesystem: &F,
+        parser: &P,
+        digester: &D,
+    ) -> Result<RepairLocationOperation<DamagedDeployments>> {
+        let packages = self.index_package_files(filesystem, parser)?;
+        let mut repairs = Vec::new();
+        for deployment in self.as_location().iter() {
+            let deployment_path = self.to_deployment_path(deployment.as_id());
+            if inspect_deployment_directory(filesystem, digester, deployment, deployment_path.as_absolute_path())?.is_empty() {
+                continue;
+            }
+            let found_package = packages
+                .iter()
+                .find(|(package, _)| package.as_checksum() == deployment.as_checksum())
+                .cloned();
+            let found_package = match found_package {
+                Some(found_package) => Some(found_package),
+                None => self.find_cached_package_file(filesystem, parser, deployment.as_checksum())?,
+            };
+            let (package, package_path) = found_package
+                .ok_or_else(|| Error::RepairPackageNotFound {
+                    deployment_id: deployment.as_id().to_string(),
+                    package_id: deployment.as_ref().as_identifier().to_string(),
+                    version: deployment.as_version().to_string(),
+                })?;
+            repairs.push(DeploymentRepair {
+                deployment_id: deployment.as_id().clone(),
+                package,
+                package_path,
+            });
+        }
+
+        Self::ok_with_state(
+            self.request,
+            DamagedDeployments {
+                previous_state: self.state,
+                repairs,
+            },
+        )
+    }
+
+    fn find_cached_package_file<F: ReadOnlyFileSystem, P: Parser>(
+        &self,
+        filesystem: &F,
+        parser: &P,
+        checksum: &Checksum,
+    ) -> Result<Option<(Package, Absolute<PathBuf>)>> {
+        let Some(cache_directory) = &self.as_request().cache_directory else {
+            return Ok(None);
+        };
+        match find_cached_package(filesystem, parser, cache_directory.as_absolute_path(), checksum)? {
+            Some(package_path) => Ok(Some((read_package_metadata(filesystem, parser, &package_path)?, package_path))),
+            None => Ok(None),
+        }
+    }
+
+    fn index_package_files<F: ReadOnlyFileSystem, P: Parser>(&self, filesystem: &F, parser: &P) -> Result<Vec<(Package, Absolute<PathBuf>)>> {
+        let mut packages = Vec::new();
+        for package_path in self.as_request().package_paths.iter() {
+            if filesystem.is_file(package_path) {
+                packages.push((read_package_metadata(filesystem, parser, package_path)?, package_path.clone()));
+                continue;
+            }
+            for entry in filesystem.walk(package_path) {
+                let entry = entry?;
+                let is_package_file = filesystem.is_file(entry.as_path())
+                    && entry.as_path().extension().is_some_and(|extension| extension == PACKAGE_EXTENSION);
+                if is_package_file {
+                    let package = read_package_metadata(filesystem, parser, entry.as_path())?;
+                    packages.push((package, entry.as_absolute_path().to_absolute_path()));
+                }
+            }
+        }
+        Ok(packages)
+    }
+}
+
+impl AsLocation for DamagedDeployments {
+    fn as_location(&self) -> &DeployLocation {
+        self.previous_state.as_location()
+    }
+}
+
+// Stopping there is a dry run
+impl RepairLocationOperation<DamagedDeployments> {
+    pub fn as_repairs(&self) -> &[DeploymentRepair] {
+        &self.as_state().repairs
+    }
+}
+
+pub struct RepairedDeployments {
+    pub previous_state: DamagedDeployments,
+    location: DeployLocation,
+    unrecoverable_files: Vec<LocationIssue>,
+}
+
+impl RepairLocationOperation<DamagedDeployments> {
+    /* Damaged deployment directories are replaced by a fresh extraction of their package, checked against the files recorded
+     * at deployment. Deploy hooks are not executed again : the intact files they generated are kept, the other ones are
+     * reported as unrecoverable. Shims are to be generated again once the location is persisted */
+    pub fn repair_deployments<F: FileSystem, A: Archiver, D: Digester>(
+        self,
+        filesystem: &F,
+        archiver: &A,
+        digester: &D,
+    ) -> Result<RepairLocationOperation<RepairedDeployments>> {
+        let mut location = self.as_location().clone();
+        let mut unrecoverable_files = Vec::new();
+        for repair in self.as_repairs() {
+            validate_checksum(filesystem, digester, &repair.package_path, &repair.package)?;
+            let deployment_path = self.to_deployment_path(&repair.deployment_id);
+            let damaged_path = to_damaged_path(deployment_path.as_absolute_path());
+            remove_deployment_directory(filesystem, damaged_path.as_absolute_path())?;
+            if filesystem.exists(&deployment_path) {
+                filesystem.rename(deployment_path.as_ref(), damaged_path.as_ref())?;
+            }
+            extract_package_payload(filesystem, archiver, &repair.package_path, &repair.package, deployment_path.as_absolute_path())?;
+            let extracted_files = index_deployment_files(filesystem, digester, deployment_path.as_absolute_path())?;
+            let recorded_files = self.as_location()
+                .get_deployment(&repair.deployment_id)
+                .map(|deployment| deployment.as_files().to_vec())
+                .unwrap_or_default();
+            if recorded_files.is_empty() {
+                location.set_deployment_files(&repair.deployment_id, extracted_files);
+            } else {
+                let generated_files = recorded_files.iter()
+                    .filter(|recorded_file| !extracted_files.iter().any(|file| file.as_path() == recorded_file.as_path()));
+                for generated_file in generated_files {
+                    if !restore_generated_file(filesystem, digester, &damaged_path, &deployment_path, generated_file)? {
+                        unrecoverable_files.push(LocationIssue::MissingFile {
+                            deployment_id: repair.deployment_id.clone(),
+                            path: generated_file.as_path().to_string(),
+                        });
+                    }
+                }
+            }
+            remove_deployment_directory(filesystem, damaged_path.as_absolute_path())?;
+        }
+
+        Self::ok_with_state(
+            self.request,
+            RepairedDeployments {
+                previous_state: self.state,
+                location,
+                unrecoverable_files,
+            },
+        )
+    }
+}
+
+/* Moves back a file generated by the deploy hooks from the damaged directory, provided it is intact */
+fn restore_generated_file<F: FileSystem, D: Digester>(
+    filesystem: &F,
+    digester: &D,
+    damaged_path: &Absolute<PathBuf>,
+    deployment_path: &Absolute<PathBuf>,
+    generated_file: &DeployedFile,
+) -> Result<bool> {
+    let damaged_file_path = damaged_path.join(generated_file.as_path());
+    if !filesystem.is_file(&damaged_file_path) || filesystem.file_size(&damaged_file_path)? != generated_file.as_size() {
+        return Ok(false);
+    }
+    if &digester.generate_checksum(filesystem.open_read(&damaged_file_path)?)? != generated_file.as_checksum() {
+        return Ok(false);
+    }
+    let restored_file_path = deployment_path.join(generated_file.as_path());
+    if let Some(parent_path) = restored_file_path.parent() {
+        filesystem.create_dir_recursively(parent_path)?;
+    }
+    filesystem.rename(damaged_file_path, restored_file_path)?;
+    Ok(true)
+}
+
+// Damaged files are not worth restoring
+impl Compensate for RepairedDeployments {
+    fn compensate<F: FileSystem>(&self, _filesystem: &F) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl AsRef<DeployLocation> for RepairedDeployments {
+    fn as_ref(&self) -> &DeployLocation {
+        &self.location
+    }
+}
+
+impl RepairLocationOperation<GeneratedShims<PersistedDeployLocation<RepairedDeployments>>> {
+    pub fn as_repairs(&self) -> &[DeploymentRepair] {
+        &self.as_state().previous_state.previous_state.previous_state.repairs
+    }
+
+    /* Files generated by the deploy hooks which could not be recovered, still recorded as part of their deployment */
+    pub fn as_unrecoverable_files(&self) -> &[LocationIssue] {
+        &self.as_state().previous_state.previous_state.unrecoverable_files
+    }
+}
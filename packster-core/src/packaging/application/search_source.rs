@@ -0,0 +1,48 @@
+use crate::{
+    application::{
+        operation::{New, Operation},
+        port::{ReadOnlyFileSystem, Source},
+    },
+    domain::entity::{Identifier, Version},
+    Result,
+};
+
+pub struct SearchSourceRequest {
+    pattern: Option<String>,
+}
+
+impl SearchSourceRequest {
+    /* Without a pattern, every package of the source is found */
+    pub fn new(pattern: Option<String>) -> Self {
+        SearchSourceRequest { pattern }
+    }
+}
+
+pub type SearchSourceOperation<S> = Operation<S, SearchSourceRequest>;
+
+pub struct FoundPackages {
+    packages: Vec<(Identifier, Vec<Version>)>,
+}
+
+impl SearchSourceOperation<New> {
+    /* Identifiers containing the pattern, along with their versions */
+    pub fn search_source<F: ReadOnlyFileSystem, S: Source>(
+        self,
+        filesystem: &F,
+        source: &S,
+    ) -> Result<SearchSourceOperation<FoundPackages>> {
+        let pattern = self.as_request().pattern.as_deref();
+        let packages = source.index(filesystem)?
+            .to_versions_by_identifier()
+            .into_iter()
+            .filter(|(identifier, _)| pattern.map(|pattern| identifier.to_string().contains(pattern)).unwrap_or(true))
+            .collect();
+        Self::ok_with_state(self.request, FoundPackages { packages })
+    }
+}
+
+impl SearchSourceOperation<FoundPackages> {
+    pub fn as_packages(&self) -> &[(Identifier, Vec<Version>)] {
+        &self.as_state().packages
+    }
+}
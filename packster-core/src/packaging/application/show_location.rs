@@ -1,17 +1,29 @@
 use std::path::{PathBuf, Path};
-use crate::application::path::Absolute;
+use crate::{application::path::Absolute, domain::entity::PackageRequirement, packaging::domain::{AsLocation, Deployment}};
 use super::{AsLocationPath, Operation};
 
 pub struct ShowLocationRequest {
-    location_directory: Absolute<PathBuf>
+    location_directory: Absolute<PathBuf>,
+    requirement: Option<PackageRequirement>
 }
 
 impl ShowLocationRequest {
-    pub fn new(location_directory: Absolute<PathBuf>) -> Self {
-        ShowLocationRequest { location_directory }
+    pub fn new(location_directory: Absolute<PathBuf>, requirement: Option<PackageRequirement>) -> Self {
+        ShowLocationRequest { location_directory, requirement }
     }
 }
 
 impl <S>AsLocationPath for Operation<S, ShowLocationRequest> {
     fn as_location_path(&self) -> Absolute<&Path> { self.as_request().location_directory.as_absolute_path() }
-}
\ No newline at end of file
+}
+
+impl <S: AsLocation>Operation<S, ShowLocationRequest> {
+    pub fn list_deployments(&self) -> Vec<&Deployment> {
+        match &self.as_request().requirement {
+            Some(requirement) => self.as_location()
+                .find_matching(requirement.as_identifier(), requirement.as_version_requirement())
+                .collect(),
+            None => self.as_location().iter().collect()
+        }
+    }
+}
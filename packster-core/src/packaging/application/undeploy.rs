@@ -1,19 +1,25 @@
 use std::path::{Path, PathBuf};
 use crate::{
     application::{
-        operation::{AsChecksum, New, Operation},
+        operation::{AsChecksum, Compensate, New, Operation},
         path::Absolute,
-        port::FileSystem,
+        port::{Executor, FileSystem},
     },
     domain::entity::Checksum,
-    packaging::domain::{AlreadyDeployed, DeployLocation, Deployment, DeploymentPath},
-    Result,
+    packaging::domain::{AlreadyDeployed, AsLocation, DeployLocation, Deployment, DeploymentPath, HookEvent},
+    Error, Result,
+};
+use super::{
+    LockedLocation,
+    execute_hooks, to_deployment_hook_environment, AsLocationPath, ParsedLocation,
+    GeneratedShims, PersistedDeployLocation,
 };
-use super::{AsLocationPath, ParsedLocation, PersistedDeployLocation};
 
 pub struct UndeployRequest {
     checksum: Checksum,
     location_path: Absolute<PathBuf>,
+    reassign_active: bool,
+    force: bool,
 }
 
 impl UndeployRequest {
@@ -21,8 +27,22 @@ impl UndeployRequest {
         UndeployRequest {
             checksum,
             location_path,
+            reassign_active: false,
+            force: false,
         }
     }
+
+    /* Undeploying the active deployment of an identifier is refused while other deployments of it remain, unless the newest of them is activated instead */
+    pub fn with_active_reassignment(mut self, reassign_active: bool) -> Self {
+        self.reassign_active = reassign_active;
+        self
+    }
+
+    /* Undeploying a deployment other deployments depend on is refused, unless forced, leaving their requirements unsatisfied */
+    pub fn with_force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
 }
 
 pub type UndeployOperation<S> = Operation<S, UndeployRequest>;
@@ -39,14 +59,113 @@ impl<S> AsLocationPath for UndeployOperation<S> {
     }
 }
 
-pub type UndeployValidState = DeploymentPath<AlreadyDeployed<ParsedLocation<New>>>;
+pub struct ActiveReassignment {
+    previous_state: AlreadyDeployed<ParsedLocation<LockedLocation<New>>>,
+    reassigned_deployment: Option<Deployment>,
+}
+
+impl UndeployOperation<AlreadyDeployed<ParsedLocation<LockedLocation<New>>>> {
+    pub fn probe_no_dependent_deployments(self) -> Result<UndeployOperation<AlreadyDeployed<ParsedLocation<LockedLocation<New>>>>> {
+        let deployment = &self.as_state().existing_deployment;
+        if !deployment.as_dependents().is_empty() && !self.as_request().force {
+            return Err(Error::RequiredDeploymentUndeployment {
+                deployment_id: deployment.as_id().to_string(),
+                package_id: deployment.as_ref().as_identifier().to_string(),
+                dependents: deployment.as_dependents().iter()
+                    .map(|dependent| format!("{} ({})", dependent.as_identifier(), dependent.as_deployment_id()))
+                    .collect(),
+            });
+        }
+        Ok(self)
+    }
+
+    pub fn probe_active_deployment_reassignment(self) -> Result<UndeployOperation<ActiveReassignment>> {
+        let deployment = &self.as_state().existing_deployment;
+        let location = self.as_location();
+        let mut reassigned_deployment = None;
+        if location.is_active(deployment.as_id()) {
+            reassigned_deployment = location
+                .iter_identifier(deployment.as_ref().as_identifier())
+                .filter(|candidate| candidate.as_id() != deployment.as_id())
+                .max_by(|left, right| left.as_version().cmp(right.as_version()))
+                .cloned();
+            if reassigned_deployment.is_some() && !self.as_request().reassign_active {
+                return Err(Error::ActiveDeploymentUndeployment {
+                    deployment_id: deployment.as_id().to_string(),
+                    package_id: deployment.as_ref().as_identifier().to_string(),
+                });
+            }
+        }
+
+        Self::ok_with_state(
+            self.request,
+            ActiveReassignment {
+                previous_state: self.state,
+                reassigned_deployment,
+            },
+        )
+    }
+}
+
+impl AsLocation for ActiveReassignment {
+    fn as_location(&self) -> &DeployLocation {
+        self.previous_state.as_location()
+    }
+}
+
+pub type UndeployValidState = DeploymentPath<ActiveReassignment>;
+
+impl UndeployValidState {
+    fn as_existing_deployment(&self) -> &Deployment {
+        &self.previous_state.previous_state.existing_deployment
+    }
+
+    fn as_mut_location(&mut self) -> &mut DeployLocation {
+        &mut self.previous_state.previous_state.previous_state.location
+    }
+}
+
+pub struct HookedUndeployment {
+    previous_state: UndeployValidState,
+}
+
+impl Compensate for HookedUndeployment {
+    fn compensate<F: FileSystem>(&self, _filesystem: &F) -> Result<()> {
+        Ok(())
+    }
+}
 
 impl UndeployOperation<UndeployValidState> {
+    pub fn execute_undeploy_hooks<E: Executor>(self, executor: &E) -> Result<UndeployOperation<HookedUndeployment>> {
+        let deployment = self.as_state().as_existing_deployment();
+        let deployment_path = &self.as_state().deployment_path;
+        execute_hooks(
+            executor,
+            deployment.as_ref().as_hooks(),
+            HookEvent::Undeploy,
+            deployment_path.as_absolute_path(),
+            to_deployment_hook_environment(
+                self.as_location_path(),
+                deployment_path.as_absolute_path(),
+                deployment,
+            ),
+        )?;
+        Self::ok_with_state(
+            self.request,
+            HookedUndeployment {
+                previous_state: self.state,
+            },
+        )
+    }
+}
+
+impl UndeployOperation<HookedUndeployment> {
     fn as_mut_location(&mut self) -> &mut DeployLocation {
-        &mut self.as_mut_state().previous_state.previous_state.location
+        self.as_mut_state().previous_state.as_mut_location()
     }
 
-    pub fn remove_deployment_from_location(mut self) -> UndeployOperation<UndeployValidState> {
+    /* The location activates the newest remaining deployment in place of a removed active one */
+    pub fn remove_deployment_from_location(mut self) -> UndeployOperation<HookedUndeployment> {
         let checksum = self.as_checksum().clone();
         let location = self.as_mut_location();
         location.remove_deployment(&checksum);
@@ -55,22 +174,22 @@ impl UndeployOperation<UndeployValidState> {
     }
 }
 
-impl AsRef<DeployLocation> for UndeployOperation<UndeployValidState> {
+impl AsRef<DeployLocation> for HookedUndeployment {
     fn as_ref(&self) -> &DeployLocation {
-        &self.as_state().previous_state.previous_state.location
+        self.previous_state.as_location()
     }
 }
 
 pub struct DeploymentDirectoryDeleted {
-    previous_state: PersistedDeployLocation<UndeployValidState>,
+    previous_state: GeneratedShims<PersistedDeployLocation<HookedUndeployment>>,
 }
 
-impl UndeployOperation<PersistedDeployLocation<UndeployValidState>> {
+impl UndeployOperation<GeneratedShims<PersistedDeployLocation<HookedUndeployment>>> {
     pub fn delete_deployment_directory<F: FileSystem>(
         self,
         filesystem: &F,
     ) -> Result<UndeployOperation<DeploymentDirectoryDeleted>> {
-        filesystem.remove_dir_all(&self.as_state().previous_state.deployment_path)?;
+        filesystem.remove_dir_all(&self.as_state().previous_state.previous_state.previous_state.deployment_path)?;
         Self::ok_with_state(
             self.request,
             DeploymentDirectoryDeleted {
@@ -81,12 +200,15 @@ impl UndeployOperation<PersistedDeployLocation<UndeployValidState>> {
 }
 
 impl UndeployOperation<DeploymentDirectoryDeleted> {
+    fn as_valid_state(&self) -> &UndeployValidState {
+        &self.as_state().previous_state.previous_state.previous_state.previous_state
+    }
+
     pub fn as_undeployed_deployment(&self) -> &Deployment {
-        &self
-            .as_state()
-            .previous_state
-            .previous_state
-            .previous_state
-            .existing_deployment
+        self.as_valid_state().as_existing_deployment()
+    }
+
+    pub fn as_reassigned_deployment(&self) -> Option<&Deployment> {
+        self.as_valid_state().previous_state.reassigned_deployment.as_ref()
     }
 }
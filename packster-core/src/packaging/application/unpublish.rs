@@ -0,0 +1,97 @@
+use std::path::{Path, PathBuf};
+use crate::{
+    application::{
+        operation::{Compensate, New, Operation},
+        path::Absolute,
+        port::FileSystem,
+    },
+    domain::entity::{Identifier, Version},
+    packaging::domain::{IndexedPackage, SourceIndex},
+    Error, Result,
+};
+use super::{AsSourcePath, IndexedSource, PersistedSourceIndex};
+
+pub struct UnpublishRequest {
+    source_directory: Absolute<PathBuf>,
+    identifier: Identifier,
+    version: Version,
+}
+
+impl UnpublishRequest {
+    pub fn new(source_directory: Absolute<PathBuf>, identifier: Identifier, version: Version) -> Self {
+        UnpublishRequest { source_directory, identifier, version }
+    }
+}
+
+pub type UnpublishOperation<S> = Operation<S, UnpublishRequest>;
+
+impl<S> AsSourcePath for UnpublishOperation<S> {
+    fn as_source_path(&self) -> Absolute<&Path> {
+        self.as_request().source_directory.as_absolute_path()
+    }
+}
+
+pub struct UnlistedPackage {
+    previous_state: IndexedSource<New>,
+    unpublished_package: IndexedPackage,
+    index: SourceIndex,
+}
+
+impl UnpublishOperation<IndexedSource<New>> {
+    pub fn unlist_package(self) -> Result<UnpublishOperation<UnlistedPackage>> {
+        let UnpublishRequest { identifier, version, .. } = self.as_request();
+        let index = self.as_state().as_ref();
+        let unpublished_package = index.find(identifier, version)
+            .ok_or_else(|| Error::PackageNotFoundInSource {
+                requirement: format!("{identifier}@={version}"),
+                source: self.as_source_path().to_string_lossy().to_string(),
+            })?
+            .clone();
+        let index = index.without_package(identifier, version);
+        Self::ok_with_state(
+            self.request,
+            UnlistedPackage {
+                previous_state: self.state,
+                unpublished_package,
+                index,
+            },
+        )
+    }
+}
+
+impl Compensate for UnlistedPackage {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        self.previous_state.compensate(filesystem)
+    }
+}
+
+impl AsRef<SourceIndex> for UnlistedPackage {
+    fn as_ref(&self) -> &SourceIndex {
+        &self.index
+    }
+}
+
+pub struct DeletedPackageFile {
+    previous_state: PersistedSourceIndex<UnlistedPackage>,
+}
+
+impl UnpublishOperation<PersistedSourceIndex<UnlistedPackage>> {
+    /* The package file is only removed once the index no longer lists it, the index being restored when it can not be */
+    pub fn delete_package_file<F: FileSystem>(self, filesystem: &F) -> Result<UnpublishOperation<DeletedPackageFile>> {
+        let package_path = self.as_source_path().join(self.as_state().previous_state.unpublished_package.to_path());
+        let result = filesystem.remove_file(&package_path);
+        self.compensate_on_error(filesystem, result)?;
+        Self::ok_with_state(
+            self.request,
+            DeletedPackageFile {
+                previous_state: self.state,
+            },
+        )
+    }
+}
+
+impl UnpublishOperation<DeletedPackageFile> {
+    pub fn as_unpublished_package(&self) -> &IndexedPackage {
+        &self.as_state().previous_state.previous_state.unpublished_package
+    }
+}
@@ -0,0 +1,438 @@
+use std::{path::{Path, PathBuf}, str::FromStr};
+use crate::{
+    application::{
+        operation::{AsChecksum, Compensate, New, Operation},
+        path::Absolute,
+        port::{Archiver, Digester, Executor, FileSystem},
+    },
+    domain::entity::{Checksum, Parameters, Version},
+    packaging::domain::{
+        AsDeployment, DeployLocation, DeployedDependencies, DeployedFile, Deployment, DeploymentPath, HookEvent, NoResourceConflict, NotYetDeployed,
+        Package,
+    },
+    Error, Result,
+};
+use super::{
+    execute_hooks, extract_package_payload, index_deployment_files, remove_deployment_directory, to_deployment_hook_environment,
+    AsLocation, AsLocationPath, AsPackage, AsPackagePath, LockedLocation, MatchingChecksum, ParsedLocation, ParsedPackage,
+    GeneratedShims, PersistedDeployLocation,
+};
+
+pub struct UpgradeRequest {
+    package_path: Absolute<PathBuf>,
+    location_path: Absolute<PathBuf>,
+    parameters: Parameters,
+}
+
+impl UpgradeRequest {
+    /* Given parameters override the ones of the upgraded deployment */
+    pub fn new(package_path: Absolute<PathBuf>, location_path: Absolute<PathBuf>, parameters: Parameters) -> Self {
+        UpgradeRequest {
+            package_path,
+            location_path,
+            parameters,
+        }
+    }
+}
+
+pub type UpgradeOperation<S> = Operation<S, UpgradeRequest>;
+
+impl<S> AsPackagePath for UpgradeOperation<S> {
+    fn as_package_path(&self) -> Absolute<&Path> {
+        self.as_request().package_path.as_absolute_path()
+    }
+}
+
+impl<S> AsLocationPath for UpgradeOperation<S> {
+    fn as_location_path(&self) -> Absolute<&Path> {
+        self.as_request().location_path.as_absolute_path()
+    }
+}
+
+// The deployment directory is named after the deployment identifier
+impl<S: AsDeployment> AsChecksum for UpgradeOperation<S> {
+    fn as_checksum(&self) -> &Checksum {
+        self.as_state().as_deployment().as_id()
+    }
+}
+
+pub type UpgradeParsedState = ParsedLocation<ParsedPackage<LockedLocation<New>>>;
+
+pub struct FoundPreviousDeployment {
+    previous_state: UpgradeParsedState,
+    previous_deployment: Deployment,
+    keep_previous_deployment: bool,
+}
+
+impl UpgradeOperation<UpgradeParsedState> {
+    /* The newest deployment of the package identifier is upgraded. It is kept along the new one when some of its dependents
+     * are not satisfied by the new version */
+    pub fn find_previous_deployment(self) -> Result<UpgradeOperation<FoundPreviousDeployment>> {
+        let package = self.as_package();
+        let previous_deployment = self
+            .as_location()
+            .get_latest_deployment(package.as_identifier())
+            .cloned()
+            .ok_or_else(|| Error::NoDeploymentToUpgrade(package.as_identifier().to_string()))?;
+        if package.as_version() <= previous_deployment.as_version() {
+            return Err(Error::UpgradeVersionNotGreater {
+                package_id: package.as_identifier().to_string(),
+                deployed_version: previous_deployment.as_version().to_string(),
+                version: package.as_version().to_string(),
+            });
+        }
+        let keep_previous_deployment = !previous_deployment.are_dependents_satisfied_by(package.as_version());
+
+        Self::ok_with_state(
+            self.request,
+            FoundPreviousDeployment {
+                previous_state: self.state,
+                previous_deployment,
+                keep_previous_deployment,
+            },
+        )
+    }
+}
+
+impl AsPackage for FoundPreviousDeployment {
+    fn as_package(&self) -> &Package {
+        self.previous_state.as_package()
+    }
+}
+
+impl AsLocation for FoundPreviousDeployment {
+    fn as_location(&self) -> &DeployLocation {
+        self.previous_state.as_location()
+    }
+}
+
+pub struct IdentifiedUpgrade {
+    previous_state: FoundPreviousDeployment,
+    deployment: Deployment,
+}
+
+impl UpgradeOperation<FoundPreviousDeployment> {
+    /* Parameters of the previous deployment still declared by the new package are migrated */
+    pub fn identify_deployment<D: Digester>(self, digester: &D) -> Result<UpgradeOperation<IdentifiedUpgrade>> {
+        let package = self.as_package().clone();
+        let mut parameters: Parameters = self
+            .as_state()
+            .previous_deployment
+            .as_parameters()
+            .iter()
+            .filter(|(key, _)| package.as_parameter_schema().iter().any(|(declared, _)| declared == *key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        parameters.extend(self.as_request().parameters.clone());
+        let deployment = Deployment::identify(package, parameters, digester)?;
+
+        Self::ok_with_state(
+            self.request,
+            IdentifiedUpgrade {
+                previous_state: self.state,
+                deployment,
+            },
+        )
+    }
+}
+
+impl AsPackage for IdentifiedUpgrade {
+    fn as_package(&self) -> &Package {
+        self.previous_state.as_package()
+    }
+}
+
+impl AsLocation for IdentifiedUpgrade {
+    fn as_location(&self) -> &DeployLocation {
+        self.previous_state.as_location()
+    }
+}
+
+impl AsDeployment for IdentifiedUpgrade {
+    fn as_deployment(&self) -> &Deployment {
+        &self.deployment
+    }
+}
+
+pub type UpgradeValidState = DeploymentPath<MatchingChecksum<NoResourceConflict<DeployedDependencies<NotYetDeployed<IdentifiedUpgrade>>>>>;
+
+impl UpgradeValidState {
+    fn as_previous_deployment_state(&self) -> &FoundPreviousDeployment {
+        &self.previous_state.previous_state.previous_state.previous_state.previous_state.previous_state
+    }
+}
+
+impl AsMut<DeployLocation> for UpgradeValidState {
+    fn as_mut(&mut self) -> &mut DeployLocation {
+        &mut self
+            .previous_state
+            .previous_state
+            .previous_state
+            .previous_state
+            .previous_state
+            .previous_state
+            .previous_state
+            .location
+    }
+}
+
+pub struct ExtractedUpgrade {
+    previous_state: UpgradeValidState,
+}
+
+impl UpgradeOperation<UpgradeValidState> {
+    pub fn extract_package<F: FileSystem, A: Archiver>(self, filesystem: &F, archiver: &A) -> Result<UpgradeOperation<ExtractedUpgrade>> {
+        extract_package_payload(
+            filesystem,
+            archiver,
+            &self.as_request().package_path,
+            self.as_package(),
+            self.as_state().deployment_path.as_absolute_path(),
+        )?;
+        Self::ok_with_state(
+            self.request,
+            ExtractedUpgrade {
+                previous_state: self.state,
+            },
+        )
+    }
+}
+
+impl Compensate for ExtractedUpgrade {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        remove_deployment_directory(filesystem, self.previous_state.deployment_path.as_absolute_path())
+    }
+}
+
+pub struct HookedUpgrade {
+    previous_state: ExtractedUpgrade,
+}
+
+impl UpgradeOperation<ExtractedUpgrade> {
+    /* The upgrade hook, or the deploy one when the package declares none, is told about the previous deployment */
+    pub fn execute_upgrade_hooks<F: FileSystem, E: Executor>(self, filesystem: &F, executor: &E) -> Result<UpgradeOperation<HookedUpgrade>> {
+        let valid_state = &self.as_state().previous_state;
+        let deployment = valid_state.as_deployment();
+        let deployment_path = valid_state.deployment_path.as_absolute_path();
+        let previous_deployment = &valid_state.as_previous_deployment_state().previous_deployment;
+        let mut environment = to_deployment_hook_environment(self.as_location_path(), deployment_path.clone(), deployment);
+        environment.insert(String::from("PACKSTER_PREVIOUS_VERSION"), previous_deployment.as_version().to_string());
+        environment.insert(String::from("PACKSTER_PREVIOUS_DEPLOYMENT_ID"), previous_deployment.as_id().to_string());
+        environment.insert(
+            String::from("PACKSTER_PREVIOUS_DEPLOYMENT_PATH"),
+            self.to_deployment_path(previous_deployment.as_id()).to_string_lossy().to_string(),
+        );
+
+        let hooks = deployment.as_ref().as_hooks();
+        let result = execute_hooks(executor, hooks, hooks.to_upgrade_event(), deployment_path, environment);
+        self.compensate_on_error(filesystem, result)?;
+        Self::ok_with_state(
+            self.request,
+            HookedUpgrade {
+                previous_state: self.state,
+            },
+        )
+    }
+}
+
+impl Compensate for HookedUpgrade {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        self.previous_state.compensate(filesystem)
+    }
+}
+
+pub struct IndexedUpgradeFiles {
+    previous_state: HookedUpgrade,
+    files: Vec<DeployedFile>,
+}
+
+impl UpgradeOperation<HookedUpgrade> {
+    pub fn index_deployment_files<F: FileSystem, D: Digester>(self, filesystem: &F, digester: &D) -> Result<UpgradeOperation<IndexedUpgradeFiles>> {
+        let deployment_path = self.as_state().previous_state.previous_state.deployment_path.as_absolute_path();
+        let result = index_deployment_files(filesystem, digester, deployment_path);
+        let files = self.compensate_on_error(filesystem, result)?;
+        Self::ok_with_state(
+            self.request,
+            IndexedUpgradeFiles {
+                previous_state: self.state,
+                files,
+            },
+        )
+    }
+}
+
+impl Compensate for IndexedUpgradeFiles {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        self.previous_state.compensate(filesystem)
+    }
+}
+
+pub struct HookedPreviousUndeployment {
+    previous_state: IndexedUpgradeFiles,
+}
+
+impl UpgradeOperation<IndexedUpgradeFiles> {
+    fn as_valid_state(&self) -> &UpgradeValidState {
+        &self.as_state().previous_state.previous_state.previous_state
+    }
+
+    /* Nothing is executed when the previous deployment is kept */
+    pub fn execute_previous_undeploy_hooks<F: FileSystem, E: Executor>(
+        self,
+        filesystem: &F,
+        executor: &E,
+    ) -> Result<UpgradeOperation<HookedPreviousUndeployment>> {
+        let FoundPreviousDeployment { previous_deployment, keep_previous_deployment, .. } = self.as_valid_state().as_previous_deployment_state();
+        if !keep_previous_deployment {
+            let previous_deployment_path = self.to_deployment_path(previous_deployment.as_id());
+            let result = execute_hooks(
+                executor,
+                previous_deployment.as_ref().as_hooks(),
+                HookEvent::Undeploy,
+                previous_deployment_path.as_absolute_path(),
+                to_deployment_hook_environment(self.as_location_path(), previous_deployment_path.as_absolute_path(), previous_deployment),
+            );
+            self.compensate_on_error(filesystem, result)?;
+        }
+        Self::ok_with_state(
+            self.request,
+            HookedPreviousUndeployment {
+                previous_state: self.state,
+            },
+        )
+    }
+}
+
+impl Compensate for HookedPreviousUndeployment {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        self.previous_state.compensate(filesystem)
+    }
+}
+
+pub struct LocationWithUpgrade {
+    previous_state: HookedPreviousUndeployment,
+    deployment: Deployment,
+}
+
+impl UpgradeOperation<HookedPreviousUndeployment> {
+    fn as_valid_state(&self) -> &UpgradeValidState {
+        &self.as_state().previous_state.previous_state.previous_state.previous_state
+    }
+
+    fn as_mut_location(&mut self) -> &mut DeployLocation {
+        self.as_mut_state().previous_state.previous_state.previous_state.previous_state.as_mut()
+    }
+
+    /* The new deployment is activated in place of the previous one when it was active, even if the previous one is kept */
+    pub fn replace_deployment_in_location(mut self, packster_version: &str) -> Result<UpgradeOperation<LocationWithUpgrade>> {
+        let deployment = self
+            .as_valid_state()
+            .as_deployment()
+            .clone()
+            .with_installed_packster_version(Version::from_str(packster_version)?)
+            .with_files(self.as_state().previous_state.files.clone());
+        let dependents: Vec<_> = self
+            .as_valid_state()
+            .previous_state
+            .previous_state
+            .previous_state
+            .resolution
+            .iter_dependents()
+            .map(|(id, dependent)| (id.clone(), dependent.clone()))
+            .collect();
+        let FoundPreviousDeployment { previous_deployment, keep_previous_deployment, .. } = self.as_valid_state().as_previous_deployment_state();
+        let previous_id = previous_deployment.as_id().clone();
+        let keep_previous_deployment = *keep_previous_deployment;
+
+        let location = self.as_mut_location();
+        location.add_deployment(deployment.clone());
+        for (id, dependent) in dependents {
+            location.add_dependent(&id, dependent);
+        }
+        if location.is_active(&previous_id) {
+            location.activate(deployment.as_id());
+        }
+        if !keep_previous_deployment {
+            location.replace_deployment(&previous_id, deployment.as_id());
+        }
+
+        Self::ok_with_state(
+            self.request,
+            LocationWithUpgrade {
+                previous_state: self.state,
+                deployment,
+            },
+        )
+    }
+}
+
+impl Compensate for LocationWithUpgrade {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        self.previous_state.compensate(filesystem)
+    }
+}
+
+impl AsRef<DeployLocation> for LocationWithUpgrade {
+    fn as_ref(&self) -> &DeployLocation {
+        self.previous_state.previous_state.previous_state.previous_state.previous_state.as_location()
+    }
+}
+
+pub struct DeletedPreviousDeployment {
+    previous_state: GeneratedShims<PersistedDeployLocation<LocationWithUpgrade>>,
+}
+
+impl UpgradeOperation<GeneratedShims<PersistedDeployLocation<LocationWithUpgrade>>> {
+    /* The previous deployment directory is only removed once the new deployment is persisted */
+    pub fn delete_previous_deployment_directory<F: FileSystem>(self, filesystem: &F) -> Result<UpgradeOperation<DeletedPreviousDeployment>> {
+        let FoundPreviousDeployment { previous_deployment, keep_previous_deployment, .. } = self.as_previous_deployment_state();
+        if !keep_previous_deployment {
+            remove_deployment_directory(filesystem, self.to_deployment_path(previous_deployment.as_id()).as_absolute_path())?;
+        }
+        Self::ok_with_state(
+            self.request,
+            DeletedPreviousDeployment {
+                previous_state: self.state,
+            },
+        )
+    }
+
+    fn as_previous_deployment_state(&self) -> &FoundPreviousDeployment {
+        self.as_state()
+            .previous_state
+            .previous_state
+            .previous_state
+            .previous_state
+            .previous_state
+            .previous_state
+            .previous_state
+            .as_previous_deployment_state()
+    }
+}
+
+impl UpgradeOperation<DeletedPreviousDeployment> {
+    fn as_previous_deployment_state(&self) -> &FoundPreviousDeployment {
+        self.as_state()
+            .previous_state
+            .previous_state
+            .previous_state
+            .previous_state
+            .previous_state
+            .previous_state
+            .previous_state
+            .previous_state
+            .as_previous_deployment_state()
+    }
+
+    pub fn as_deployment(&self) -> &Deployment {
+        &self.as_state().previous_state.previous_state.previous_state.deployment
+    }
+
+    pub fn as_previous_deployment(&self) -> &Deployment {
+        &self.as_previous_deployment_state().previous_deployment
+    }
+
+    pub fn is_previous_deployment_kept(&self) -> bool {
+        self.as_previous_deployment_state().keep_previous_deployment
+    }
+}
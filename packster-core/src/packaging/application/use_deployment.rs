@@ -0,0 +1,75 @@
+use std::path::{Path, PathBuf};
+use crate::{
+    application::{
+        operation::{Compensate, New, Operation},
+        path::Absolute,
+        port::FileSystem,
+    },
+    packaging::domain::{AsLocation, DeployLocation, Deployment, DeploymentSelector},
+    Result,
+};
+use super::{AsLocationPath, GeneratedShims, LockedLocation, ParsedLocation, PersistedDeployLocation};
+
+pub struct UseDeploymentRequest {
+    location_path: Absolute<PathBuf>,
+    selector: DeploymentSelector,
+}
+
+impl UseDeploymentRequest {
+    pub fn new(location_path: Absolute<PathBuf>, selector: DeploymentSelector) -> Self {
+        UseDeploymentRequest { location_path, selector }
+    }
+}
+
+pub type UseDeploymentOperation<S> = Operation<S, UseDeploymentRequest>;
+
+impl<S> AsLocationPath for UseDeploymentOperation<S> {
+    fn as_location_path(&self) -> Absolute<&Path> {
+        self.as_request().location_path.as_absolute_path()
+    }
+}
+
+pub struct ActivatedDeployment {
+    previous_state: ParsedLocation<LockedLocation<New>>,
+    deployment: Deployment,
+    previous_deployment: Option<Deployment>,
+}
+
+impl UseDeploymentOperation<ParsedLocation<LockedLocation<New>>> {
+    pub fn activate_deployment(mut self) -> Result<UseDeploymentOperation<ActivatedDeployment>> {
+        let deployment = self.as_location().select_deployment(&self.as_request().selector)?.clone();
+        let previous_deployment = self.as_location().get_active_deployment(deployment.as_ref().as_identifier()).cloned();
+        self.as_mut_state().location.activate(deployment.as_id());
+
+        Self::ok_with_state(
+            self.request,
+            ActivatedDeployment {
+                previous_state: self.state,
+                deployment,
+                previous_deployment,
+            },
+        )
+    }
+}
+
+impl Compensate for ActivatedDeployment {
+    fn compensate<F: FileSystem>(&self, _filesystem: &F) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl AsRef<DeployLocation> for ActivatedDeployment {
+    fn as_ref(&self) -> &DeployLocation {
+        self.previous_state.as_location()
+    }
+}
+
+impl UseDeploymentOperation<GeneratedShims<PersistedDeployLocation<ActivatedDeployment>>> {
+    pub fn as_deployment(&self) -> &Deployment {
+        &self.as_state().previous_state.previous_state.deployment
+    }
+
+    pub fn as_previous_deployment(&self) -> Option<&Deployment> {
+        self.as_state().previous_state.previous_state.previous_deployment.as_ref()
+    }
+}
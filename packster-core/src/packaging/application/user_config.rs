@@ -0,0 +1,87 @@
+use std::{path::{Path, PathBuf}, time::Duration};
+use crate::{
+    application::{
+        operation::{New, Operation},
+        path::Absolute,
+        port::{FileSystem, Lock, Locker, Parser, ReadOnlyFileSystem, Serializer},
+    },
+    packaging::{domain::UserConfig, LOCATION_LOCK_NAME},
+    Result,
+};
+
+pub struct UserConfigRequest {
+    config_path: Absolute<PathBuf>,
+}
+
+impl UserConfigRequest {
+    pub fn new(config_path: Absolute<PathBuf>) -> Self {
+        UserConfigRequest { config_path }
+    }
+}
+
+pub type UserConfigOperation<S> = Operation<S, UserConfigRequest>;
+
+pub struct ParsedUserConfig {
+    config: UserConfig,
+}
+
+impl UserConfigOperation<New> {
+    pub fn parse_user_config<F: ReadOnlyFileSystem, P: Parser>(
+        self,
+        filesystem: &F,
+        parser: &P,
+    ) -> Result<UserConfigOperation<ParsedUserConfig>> {
+        let config = read_user_config(filesystem, parser, &self.as_request().config_path)?;
+        Self::ok_with_state(self.request, ParsedUserConfig { config })
+    }
+}
+
+impl UserConfigOperation<ParsedUserConfig> {
+    pub fn as_user_config(&self) -> &UserConfig {
+        &self.as_state().config
+    }
+}
+
+/* A missing user config is an empty one, it is only written once a location is registered */
+pub fn read_user_config<F: ReadOnlyFileSystem, P: Parser, Q: AsRef<Path>>(
+    filesystem: &F,
+    parser: &P,
+    config_path: Q,
+) -> Result<UserConfig> {
+    if filesystem.exists(&config_path) {
+        parser.parse(filesystem.read_to_string(&config_path)?)
+    } else {
+        Ok(UserConfig::default())
+    }
+}
+
+/* Reading, modifying and writing the user config back is exclusive, the lock lying beside the user config */
+pub fn lock_user_config<F: FileSystem, L: Locker>(
+    filesystem: &F,
+    locker: &L,
+    config_path: Absolute<&Path>,
+    wait: Duration,
+) -> Result<Lock> {
+    let config_directory = Absolute::assume_absolute(config_path.parent().unwrap_or(&config_path).to_path_buf());
+    filesystem.create_dir_recursively(&config_directory)?;
+    locker.lock(config_directory.join(LOCATION_LOCK_NAME), wait)
+}
+
+/* The user config is written beside and renamed in place, so that it is never read partially written */
+pub fn write_user_config<F: FileSystem, Sr: Serializer>(
+    filesystem: &F,
+    serializer: &Sr,
+    config_path: Absolute<&Path>,
+    config: &UserConfig,
+) -> Result<()> {
+    if let Some(parent) = config_path.parent() {
+        filesystem.create_dir_recursively(parent)?;
+    }
+    let temporary_path = config_path.with_extension("toml.tmp");
+    let result = filesystem.write_all(&temporary_path, serializer.serialize(config)?)
+        .and_then(|_| filesystem.rename(temporary_path.as_ref(), config_path.as_ref()));
+    if result.is_err() && filesystem.exists(&temporary_path) {
+        filesystem.remove_file(&temporary_path)?;
+    }
+    result
+}
@@ -0,0 +1,122 @@
+use std::path::{Path, PathBuf};
+use crate::{
+    application::{
+        operation::{Compensate, New, Operation},
+        path::Absolute,
+        port::{Digester, FileSystem, ReadOnlyFileSystem},
+    },
+    packaging::domain::{CacheIssue, CachedPackage, PackageCache},
+    Result,
+};
+use super::{open_package, AsCachePath, LockedCache, ParsedPackageCache, PersistedPackageCache};
+
+pub struct VerifyCacheRequest {
+    cache_directory: Absolute<PathBuf>,
+}
+
+impl VerifyCacheRequest {
+    pub fn new(cache_directory: Absolute<PathBuf>) -> Self {
+        VerifyCacheRequest { cache_directory }
+    }
+}
+
+pub type VerifyCacheOperation<S> = Operation<S, VerifyCacheRequest>;
+
+impl<S> AsCachePath for VerifyCacheOperation<S> {
+    fn as_cache_path(&self) -> Absolute<&Path> {
+        self.as_request().cache_directory.as_absolute_path()
+    }
+}
+
+pub struct VerifiedCache {
+    previous_state: ParsedPackageCache<LockedCache<New>>,
+    issues: Vec<CacheIssue>,
+    cache: PackageCache,
+}
+
+impl VerifyCacheOperation<ParsedPackageCache<LockedCache<New>>> {
+    /* Every cached package file is digested again, a file whose header can not be read being a modified one */
+    pub fn verify_cached_packages<F: ReadOnlyFileSystem, D: Digester>(self, filesystem: &F, digester: &D) -> Result<VerifyCacheOperation<VerifiedCache>> {
+        let cache = self.as_state().as_ref();
+        let mut issues = Vec::new();
+        for package in cache.iter_packages() {
+            let package_path = self.to_cached_package_path(package);
+            let issue = if !filesystem.is_file(&package_path) {
+                Some(CacheIssue::MissingFile { checksum: package.as_checksum().clone(), path: package.to_path() })
+            } else if !is_matching_checksum(filesystem, digester, package_path.as_absolute_path(), package) {
+                Some(CacheIssue::ModifiedFile { checksum: package.as_checksum().clone(), path: package.to_path() })
+            } else {
+                None
+            };
+            issues.extend(issue);
+        }
+
+        let damaged_packages: Vec<CachedPackage> = cache.iter_packages()
+            .filter(|package| issues.iter().any(|issue| issue.as_checksum() == package.as_checksum()))
+            .cloned()
+            .collect();
+        let cache = cache.without_packages(&damaged_packages);
+        Self::ok_with_state(
+            self.request,
+            VerifiedCache {
+                previous_state: self.state,
+                issues,
+                cache,
+            },
+        )
+    }
+}
+
+fn is_matching_checksum<F: ReadOnlyFileSystem, D: Digester>(filesystem: &F, digester: &D, package_path: Absolute<&Path>, package: &CachedPackage) -> bool {
+    open_package(filesystem, package_path)
+        .and_then(|(_, payload)| digester.generate_checksum(payload))
+        .is_ok_and(|checksum| &checksum == package.as_checksum())
+}
+
+impl Compensate for VerifiedCache {
+    fn compensate<F: FileSystem>(&self, filesystem: &F) -> Result<()> {
+        self.previous_state.compensate(filesystem)
+    }
+}
+
+/* The cache without its damaged packages */
+impl AsRef<PackageCache> for VerifiedCache {
+    fn as_ref(&self) -> &PackageCache {
+        &self.cache
+    }
+}
+
+// Stopping there leaves the cache untouched
+impl VerifyCacheOperation<VerifiedCache> {
+    pub fn as_issues(&self) -> &[CacheIssue] {
+        &self.as_state().issues
+    }
+}
+
+pub struct DeletedDamagedPackages {
+    previous_state: PersistedPackageCache<VerifiedCache>,
+}
+
+impl VerifyCacheOperation<PersistedPackageCache<VerifiedCache>> {
+    /* Damaged package files are only removed once the cache no longer lists them, to be fetched again when needed */
+    pub fn delete_damaged_packages<F: FileSystem>(self, filesystem: &F) -> Result<VerifyCacheOperation<DeletedDamagedPackages>> {
+        for issue in &self.as_state().previous_state.issues {
+            let package_directory = self.to_cached_package_directory(issue.as_checksum());
+            if filesystem.exists(&package_directory) {
+                filesystem.remove_dir_all(&package_directory)?;
+            }
+        }
+        Self::ok_with_state(
+            self.request,
+            DeletedDamagedPackages {
+                previous_state: self.state,
+            },
+        )
+    }
+}
+
+impl VerifyCacheOperation<DeletedDamagedPackages> {
+    pub fn as_issues(&self) -> &[CacheIssue] {
+        &self.as_state().previous_state.previous_state.issues
+    }
+}
@@ -0,0 +1,72 @@
+use std::path::{Path, PathBuf};
+use crate::{
+    application::{
+        operation::{New, Operation},
+        path::Absolute,
+        port::{Digester, ReadOnlyFileSystem},
+    },
+    packaging::domain::{AsLocation, DeployLocation, LocationIssue, VerificationReport},
+    Result,
+};
+use super::{find_orphan_deployment_directories, inspect_deployment_directory, AsLocationPath, ParsedLocation};
+
+pub struct VerifyLocationRequest {
+    location_path: Absolute<PathBuf>,
+}
+
+impl VerifyLocationRequest {
+    pub fn new(location_path: Absolute<PathBuf>) -> Self {
+        VerifyLocationRequest { location_path }
+    }
+}
+
+pub type VerifyLocationOperation<S> = Operation<S, VerifyLocationRequest>;
+
+impl<S> AsLocationPath for VerifyLocationOperation<S> {
+    fn as_location_path(&self) -> Absolute<&Path> {
+        self.as_request().location_path.as_absolute_path()
+    }
+}
+
+pub struct VerifiedLocation {
+    previous_state: ParsedLocation<New>,
+    report: VerificationReport,
+}
+
+impl VerifyLocationOperation<ParsedLocation<New>> {
+    pub fn verify_location<F: ReadOnlyFileSystem, D: Digester>(
+        self,
+        filesystem: &F,
+        digester: &D,
+    ) -> Result<VerifyLocationOperation<VerifiedLocation>> {
+        let mut report = VerificationReport::default();
+        for deployment in self.as_location().iter() {
+            let deployment_path = self.to_deployment_path(deployment.as_id());
+            report.add_issues(inspect_deployment_directory(filesystem, digester, deployment, deployment_path.as_absolute_path())?);
+        }
+        let orphan_paths = find_orphan_deployment_directories(filesystem, digester, self.as_location_path(), self.as_location())?;
+        report.add_issues(orphan_paths.iter().map(|path| LocationIssue::OrphanDirectory {
+            path: path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+        }));
+
+        Self::ok_with_state(
+            self.request,
+            VerifiedLocation {
+                previous_state: self.state,
+                report,
+            },
+        )
+    }
+}
+
+impl AsLocation for VerifiedLocation {
+    fn as_location(&self) -> &DeployLocation {
+        self.previous_state.as_location()
+    }
+}
+
+impl VerifyLocationOperation<VerifiedLocation> {
+    pub fn as_report(&self) -> &VerificationReport {
+        &self.as_state().report
+    }
+}
@@ -4,6 +4,39 @@ pub use generic::*;
 mod entity;
 pub use entity::*;
 
+mod resolution;
+pub use resolution::*;
+
+mod bundle;
+pub use bundle::*;
+
+mod hook;
+pub use hook::*;
+
+mod lockfile;
+pub use lockfile::*;
+
+mod verification;
+pub use verification::*;
+
+mod shim;
+pub use shim::*;
+
+mod environment;
+pub use environment::*;
+
+mod resource;
+pub use resource::*;
+
+mod user_config;
+pub use user_config::*;
+
+mod source_index;
+pub use source_index::*;
+
+mod package_cache;
+pub use package_cache::*;
+
 
 pub trait AsPackage {
     fn as_package(&self) -> &Package;
@@ -12,3 +45,11 @@ pub trait AsPackage {
 pub trait AsLocation {
     fn as_location(&self) -> &DeployLocation;
 }
+
+pub trait AsDeployment {
+    fn as_deployment(&self) -> &Deployment;
+}
+
+pub trait AsBundle {
+    fn as_bundle(&self) -> &Bundle;
+}
@@ -0,0 +1,312 @@
+use std::{collections::BTreeMap, path::{Path, PathBuf}};
+use serde::{Deserialize, Serialize};
+use crate::{
+    domain::entity::{Checksum, Identifier, PackageRequirement, Parameters, VersionRequirement},
+    Error, Result,
+};
+use super::{resolve_dependencies, DeployLocation, Deployment, DeploymentIdentifier, Package};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawBundleEntry {
+    Requirement(VersionRequirement),
+    Detailed {
+        #[serde(default)]
+        requirement: VersionRequirement,
+        #[serde(default)]
+        parameters: Parameters,
+    },
+}
+
+/* Either `identifier = "^1.2"` or `[packages.identifier]` table with a requirement and parameters */
+#[derive(Deserialize, Clone, Debug)]
+#[serde(from = "RawBundleEntry")]
+pub struct BundleEntry {
+    requirement: VersionRequirement,
+    parameters: Parameters,
+}
+
+impl From<RawBundleEntry> for BundleEntry {
+    fn from(value: RawBundleEntry) -> Self {
+        match value {
+            RawBundleEntry::Requirement(requirement) => BundleEntry { requirement, parameters: Parameters::new() },
+            RawBundleEntry::Detailed { requirement, parameters } => BundleEntry { requirement, parameters },
+        }
+    }
+}
+
+impl BundleEntry {
+    pub fn as_requirement(&self) -> &VersionRequirement { &self.requirement }
+    pub fn as_parameters(&self) -> &Parameters { &self.parameters }
+}
+
+fn default_source() -> PathBuf { PathBuf::from(".") }
+
+#[derive(Deserialize)]
+pub struct Bundle {
+    identifier: Identifier,
+    /* Directory containing the package files, relative to the bundle manifest */
+    #[serde(default = "default_source")]
+    source: PathBuf,
+    #[serde(default)]
+    packages: BTreeMap<Identifier, BundleEntry>,
+}
+
+impl Bundle {
+    pub fn as_identifier(&self) -> &Identifier { &self.identifier }
+    pub fn as_source(&self) -> &Path { &self.source }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Identifier, &BundleEntry)> {
+        self.packages.iter()
+    }
+}
+
+/* Bundle installed in a location, with the deployments its installation brought */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InstalledBundle {
+    identifier: Identifier,
+    deployments: Vec<Checksum>,
+}
+
+impl InstalledBundle {
+    pub fn new(identifier: Identifier, deployments: Vec<Checksum>) -> Self {
+        InstalledBundle { identifier, deployments }
+    }
+
+    pub fn as_identifier(&self) -> &Identifier { &self.identifier }
+    pub fn as_deployment_ids(&self) -> &[Checksum] { &self.deployments }
+    pub fn into_deployment_ids(self) -> Vec<Checksum> { self.deployments }
+
+    pub fn contains(&self, id: &Checksum) -> bool {
+        self.deployments.contains(id)
+    }
+
+    pub fn remove(&mut self, id: &Checksum) {
+        self.deployments.retain(|deployment| deployment != id);
+    }
+
+    pub fn replace(&mut self, replaced_id: &Checksum, replacing_id: &Checksum) {
+        self.remove(replaced_id);
+        if !self.contains(replacing_id) {
+            self.deployments.push(replacing_id.clone());
+        }
+    }
+
+    pub fn merge(&mut self, other: InstalledBundle) {
+        for id in other.deployments {
+            if !self.contains(&id) {
+                self.deployments.push(id);
+            }
+        }
+    }
+}
+
+/* Every package of the bundle already satisfied by a deployment with the same parameters is kept as is, the others are
+ * satisfied by the newest matching available package, along with their dependencies. Both are recorded as members of the bundle.
+ * Returns the deployments to add and the resulting location */
+pub fn plan_bundle<I: DeploymentIdentifier>(
+    location: &DeployLocation,
+    bundle: &Bundle,
+    available_packages: &[Package],
+    identifier: &I,
+) -> Result<(Vec<Deployment>, DeployLocation)> {
+    let mut planned_location = location.clone();
+    let mut planned = Vec::new();
+    let mut members = Vec::new();
+
+    for (package_identifier, entry) in bundle.iter() {
+        let matching_deployment = planned_location.find_matching(package_identifier, entry.as_requirement())
+            .find(|deployment| {
+                deployment.as_ref().as_parameter_schema().apply(entry.as_parameters().clone())
+                    .is_ok_and(|parameters| &parameters == deployment.as_parameters())
+            });
+        if let Some(deployment) = matching_deployment {
+            members.push(deployment.as_id().clone());
+            continue;
+        }
+
+        let package = available_packages.iter()
+            .filter(|package| package.as_identifier() == package_identifier && entry.as_requirement().matches(package.as_version()))
+            .max_by(|left, right| left.as_version().cmp(right.as_version()))
+            .ok_or_else(|| Error::UnresolvableDependency {
+                dependent: format!("bundle {}", bundle.as_identifier()),
+                requirement: PackageRequirement::new(package_identifier.clone(), entry.as_requirement().clone()).to_string(),
+            })?;
+
+        let deployment = Deployment::identify(package.clone(), entry.as_parameters().clone(), identifier)?;
+        let resolution = resolve_dependencies(&planned_location, &deployment, available_packages, identifier)?;
+        let deployments = std::iter::once(deployment)
+            .chain(resolution.as_deployments().iter().cloned());
+
+        for deployment in deployments {
+            planned_location.probe_resource_conflict(&deployment)?;
+            planned_location.add_deployment(deployment.clone());
+            members.push(deployment.as_id().clone());
+            planned.push(deployment);
+        }
+        for (id, dependent) in resolution.iter_dependents() {
+            planned_location.add_dependent(id, dependent.clone());
+        }
+    }
+
+    // Deployments kept as is belong to the bundle as well, so that removing another bundle spares them
+    planned_location.add_bundle(InstalledBundle::new(bundle.as_identifier().clone(), members));
+
+    Ok((planned, planned_location))
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use pretty_assertions::assert_eq;
+    use crate::domain::entity::{to_canonical_parameters, ParameterValue};
+    use super::*;
+
+    fn package(identifier: &str, version: &str, checksum: &str, dependencies: &[(&str, &str)]) -> Package {
+        let dependencies: BTreeMap<&str, &str> = dependencies.iter().cloned().collect();
+        let parameters = match identifier {
+            "app" => serde_json::json!({
+                "port": { "type": "integer", "default": 80 },
+                "verbose": { "type": "boolean" },
+                "host": { "type": "string", "default": "localhost" }
+            }),
+            _ => serde_json::json!({}),
+        };
+        serde_json::from_value(serde_json::json!({
+            "identifier": identifier,
+            "version": version,
+            "checksum": checksum,
+            "packster_version": "0.1.0",
+            "dependencies": dependencies,
+            "parameter_schema": parameters
+        })).unwrap()
+    }
+
+    fn bundle() -> Bundle {
+        serde_json::from_value(serde_json::json!({
+            "identifier": "workstation",
+            "packages": {
+                "app": { "requirement": "^1", "parameters": { "port": 8080, "verbose": true } },
+                "tool": "~2.1"
+            }
+        })).unwrap()
+    }
+
+    /* Appends canonical parameters to the package checksum */
+    struct ConcatenatingIdentifier;
+
+    impl DeploymentIdentifier for ConcatenatingIdentifier {
+        fn identify_deployment(&self, package: &Package, parameters: &Parameters) -> Result<Checksum> {
+            Ok(Checksum::from([package.as_checksum().as_ref(), to_canonical_parameters(parameters).as_bytes()].concat()))
+        }
+    }
+
+    fn identifier(value: &str) -> Identifier {
+        Identifier::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_parse_bundle_entries() {
+        let bundle = bundle();
+        assert_eq!(bundle.as_source(), Path::new("."));
+
+        let (_, app) = bundle.iter().next().unwrap();
+        assert_eq!(app.as_requirement().to_string(), "^1");
+        assert_eq!(app.as_parameters().get("port"), Some(&ParameterValue::Integer(8080)));
+        assert_eq!(app.as_parameters().get("verbose"), Some(&ParameterValue::Boolean(true)));
+
+        let (_, tool) = bundle.iter().nth(1).unwrap();
+        assert_eq!(tool.as_requirement().to_string(), "~2.1");
+        assert!(tool.as_parameters().is_empty());
+    }
+
+    #[test]
+    fn test_plan_bundle_with_dependencies() -> Result<()> {
+        let available = [
+            package("app", "1.0.0", "a1", &[("lib", "^1")]),
+            package("app", "1.2.0", "a2", &[("lib", "^1")]),
+            package("lib", "1.0.0", "b1", &[]),
+            package("tool", "2.1.3", "c1", &[]),
+            package("tool", "2.2.0", "c2", &[]),
+        ];
+        let (planned, location) = plan_bundle(&DeployLocation::default(), &bundle(), &available, &ConcatenatingIdentifier)?;
+
+        let planned: Vec<String> = planned.iter()
+            .map(|deployment| format!("{}@{}", deployment.as_ref().as_identifier(), deployment.as_version()))
+            .collect();
+        assert_eq!(planned, vec!["app@1.2.0", "lib@1.0.0", "tool@2.1.3"]);
+
+        let app_identifier = identifier("app");
+        let app = location.iter_identifier(&app_identifier).next().unwrap();
+        assert_eq!(app.as_parameters().get("port"), Some(&ParameterValue::Integer(8080)));
+        assert_eq!(app.as_parameters().get("host"), Some(&ParameterValue::String("localhost".into())));
+        assert_ne!(app.as_id(), app.as_checksum());
+        assert_eq!(location.get_bundle(&identifier("workstation")).unwrap().as_deployment_ids().len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_bundle_deploys_again_with_other_parameters() -> Result<()> {
+        let app = package("app", "1.0.0", "a1", &[]);
+        let mut location = DeployLocation::default();
+        location.add_deployment(Deployment::identify(app.clone(), Parameters::new(), &ConcatenatingIdentifier)?);
+        let available = [app, package("tool", "2.1.3", "c1", &[])];
+
+        let (planned, location) = plan_bundle(&location, &bundle(), &available, &ConcatenatingIdentifier)?;
+        assert_eq!(planned.len(), 2);
+        assert_eq!(location.iter_identifier(&identifier("app")).count(), 2);
+
+        let (planned, _) = plan_bundle(&location, &bundle(), &available, &ConcatenatingIdentifier)?;
+        assert!(planned.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_bundle_keeps_matching_deployments() -> Result<()> {
+        let mut location = DeployLocation::default();
+        location.add_deployment(Deployment::new(package("tool", "2.1.0", "c0", &[])));
+        let available = [package("app", "1.0.0", "a1", &[]), package("tool", "2.1.3", "c1", &[])];
+
+        let (planned, location) = plan_bundle(&location, &bundle(), &available, &ConcatenatingIdentifier)?;
+        assert_eq!(planned.len(), 1);
+        assert_eq!(planned[0].as_checksum().to_string(), "a1");
+        assert!(location.get_bundle(&identifier("workstation")).unwrap().contains(&Checksum::from_str("c0").unwrap()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_bundle_missing_package() {
+        let available = [package("app", "1.0.0", "a1", &[])];
+        assert!(matches!(
+            plan_bundle(&DeployLocation::default(), &bundle(), &available, &ConcatenatingIdentifier),
+            Err(Error::UnresolvableDependency { .. })
+        ));
+    }
+
+    #[test]
+    fn test_remove_bundle_keeps_shared_deployments() -> Result<()> {
+        let available = [
+            package("app", "1.0.0", "a1", &[]),
+            package("tool", "2.1.3", "c1", &[]),
+        ];
+        let (_, mut location) = plan_bundle(&DeployLocation::default(), &bundle(), &available, &ConcatenatingIdentifier)?;
+
+        let other: Bundle = serde_json::from_value(serde_json::json!({
+            "identifier": "other",
+            "packages": { "tool": "^2" }
+        })).unwrap();
+        let (planned, mut location_with_other) = plan_bundle(&location, &other, &available, &ConcatenatingIdentifier)?;
+        assert!(planned.is_empty());
+        assert!(location_with_other.get_bundle(&identifier("other")).unwrap().contains(&Checksum::from_str("c1").unwrap()));
+
+        let removed = location_with_other.remove_bundle(&identifier("workstation"));
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].as_checksum().to_string(), "a1");
+        assert!(location_with_other.is_deployed(&Checksum::from_str("c1").unwrap()));
+
+        let removed = location.remove_bundle(&identifier("workstation"));
+        assert_eq!(removed.len(), 2);
+        assert!(location.as_slice().is_empty());
+        Ok(())
+    }
+}
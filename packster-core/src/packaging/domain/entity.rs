@@ -1,17 +1,36 @@
 
 use core::str::FromStr;
-use std::path::Path;
+use std::{collections::{BTreeMap, BTreeSet}, fmt, path::Path};
 use serde::{Deserialize, Serialize};
 use lazy_static::lazy_static;
 use regex::Regex;
 use hex;
 
-use crate::{ Result, Error, domain::entity::{Identifier, Version, Checksum}, packaging::PACKAGE_EXTENSION };
+use crate::{ Result, Error, domain::entity::{Identifier, Version, VersionRequirement, Checksum, Parameters, ParameterSchema}, packaging::PACKAGE_EXTENSION };
+use super::{Dependent, Environment, Hooks, IndexedPackage, InstalledBundle, LocationShim, Provides, Resource, ResourceClaim, ShimName, Shims, LOCKFILE_SCHEMA_VERSION};
+
+pub type Dependencies = BTreeMap<Identifier, VersionRequirement>;
 
 #[derive(Deserialize)]
 pub struct Project {
     identifier: Identifier,
-    version: Version
+    version: Version,
+    #[serde(default)]
+    dependencies: Dependencies,
+    #[serde(default)]
+    parameters: ParameterSchema,
+    #[serde(default)]
+    hooks: Hooks,
+    #[serde(default)]
+    shims: Shims,
+    #[serde(default)]
+    environment: Environment,
+    #[serde(default)]
+    provides: Provides,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    labels: BTreeSet<String>
 }
 
 impl Project {
@@ -19,17 +38,76 @@ impl Project {
         self.identifier.as_ref()
     }
 
-    pub fn as_version(&self) -> &str {
-        self.version.as_ref()
+    pub fn as_version(&self) -> &Version {
+        &self.version
+    }
+
+    pub fn as_dependencies(&self) -> &Dependencies {
+        &self.dependencies
+    }
+
+    pub fn as_parameter_schema(&self) -> &ParameterSchema {
+        &self.parameters
+    }
+
+    pub fn as_hooks(&self) -> &Hooks {
+        &self.hooks
+    }
+
+    pub fn as_shims(&self) -> &Shims {
+        &self.shims
+    }
+
+    pub fn as_environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    pub fn as_provides(&self) -> &Provides {
+        &self.provides
+    }
+
+    pub fn as_description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn as_labels(&self) -> &BTreeSet<String> {
+        &self.labels
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Package {
     identifier: Identifier,
     version: Version,
     checksum: Checksum,
-    packster_version: Version
+    packster_version: Version,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    dependencies: Dependencies,
+    #[serde(default, skip_serializing_if = "ParameterSchema::is_empty")]
+    parameter_schema: ParameterSchema,
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    hooks: Hooks,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    shims: Shims,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    environment: Environment,
+    #[serde(default, skip_serializing_if = "Provides::is_empty")]
+    provides: Provides,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    labels: BTreeSet<String>
+}
+
+pub fn to_package_file_name(identifier: &Identifier, version: &Version, checksum: &Checksum, packster_version: &Version) -> String {
+    format!(
+        "{}_{}_{}.{}.{}",
+        identifier,
+        version,
+        hex::encode(checksum),
+        hex::encode(packster_version.to_string()),
+        PACKAGE_EXTENSION
+    )
 }
 
 impl Package {
@@ -38,7 +116,15 @@ impl Package {
             identifier: project.identifier,
             version: project.version,
             checksum,
-            packster_version
+            packster_version,
+            dependencies: project.dependencies,
+            parameter_schema: project.parameters,
+            hooks: project.hooks,
+            shims: project.shims,
+            environment: project.environment,
+            provides: project.provides,
+            description: project.description,
+            labels: project.labels
         }
     }
 
@@ -46,29 +132,54 @@ impl Package {
     pub fn as_checksum(&self) -> &Checksum { &self.checksum }
     pub fn as_version(&self) -> &Version { &self.version }
     pub fn as_packster_version(&self) -> &Version { &self.packster_version }
+    pub fn as_dependencies(&self) -> &Dependencies { &self.dependencies }
+    pub fn as_parameter_schema(&self) -> &ParameterSchema { &self.parameter_schema }
+    pub fn as_hooks(&self) -> &Hooks { &self.hooks }
+    pub fn as_shims(&self) -> &Shims { &self.shims }
+    pub fn as_environment(&self) -> &Environment { &self.environment }
+    pub fn as_provides(&self) -> &Provides { &self.provides }
+    pub fn as_description(&self) -> Option<&str> { self.description.as_deref() }
+    pub fn as_labels(&self) -> &BTreeSet<String> { &self.labels }
+
+    pub fn to_resources(&self) -> BTreeSet<Resource> {
+        self.provides.to_resources(self.shims.keys(), self.environment.keys())
+    }
 
     pub fn to_file_name(&self) -> String {
-        format!(
-            "{}_{}_{}.{}.{}",
-            self.identifier,
-            self.version,
-            hex::encode(&self.checksum),
-            hex::encode(self.packster_version.as_bytes()),
-            PACKAGE_EXTENSION
-        )
+        to_package_file_name(&self.identifier, &self.version, &self.checksum, &self.packster_version)
+    }
+
+    /* Package as listed in a source index, the header metadata which is not indexed being unknown until it is downloaded */
+    pub fn from_indexed(indexed: &IndexedPackage) -> Self {
+        Package {
+            identifier: indexed.as_identifier().clone(),
+            version: indexed.as_version().clone(),
+            checksum: indexed.as_checksum().clone(),
+            packster_version: indexed.as_packster_version().clone(),
+            dependencies: indexed.as_dependencies().clone(),
+            parameter_schema: ParameterSchema::default(),
+            hooks: Hooks::default(),
+            shims: Shims::default(),
+            environment: Environment::default(),
+            provides: Provides::default(),
+            description: indexed.as_description().map(String::from),
+            labels: indexed.as_labels().clone()
+        }
     }
 
-    //TODO consider converting to From ?
+    /* Package file name grammar : {identifier}_{version}_{checksum}.{hexadecimal packster version}.packster
+     * Neither identifiers nor semantic versions can contain an underscore, and both hexadecimal parts cannot contain a dot, so no escaping is needed */
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
         lazy_static! {
-            static ref PACKAGE_FILENAME_REGEXP: Regex = Regex::new("(?P<identifier>[^_]+)_(?P<version>[^_]+)_(?P<checksum>[^.]+).(?P<packster_version>[^.]+)").unwrap();
+            static ref PACKAGE_FILENAME_REGEXP: Regex = Regex::new(
+                &format!(r"^(?P<identifier>[^_]+)_(?P<version>[^_]+)_(?P<checksum>[^._]+)\.(?P<packster_version>[^._]+)\.{PACKAGE_EXTENSION}$")
+            ).unwrap();
         }
         let path = path.as_ref();
-        let filename = path.file_stem()
+        let filename = path.file_name()
             .ok_or_else(|| Error::NoFileNameInPath(path.to_path_buf()))?
             .to_str()
-            .ok_or_else(|| Error::InvalidUtf8Path(path.to_path_buf()))
-        ?;
+            .ok_or_else(|| Error::InvalidUtf8Path(path.to_path_buf()))?;
 
         let captures = PACKAGE_FILENAME_REGEXP.captures(filename)
             .ok_or_else(|| Error::WrongFileNameFormat("No match".into(), path.to_path_buf()))
@@ -105,7 +216,15 @@ impl Package {
                 identifier,
                 version,
                 checksum,
-                packster_version
+                packster_version,
+                dependencies: Dependencies::default(),
+                parameter_schema: ParameterSchema::default(),
+                hooks: Hooks::default(),
+                shims: Shims::default(),
+                environment: Environment::default(),
+                provides: Provides::default(),
+                description: None,
+                labels: BTreeSet::new()
             }
         )
     }
@@ -116,72 +235,461 @@ impl Default for Package {
     fn default() -> Self {
         Package {
             identifier: Identifier(String::from("my-package")),
-            version: Version(String::from("0.0.1")),
+            version: Version::new(0, 0, 1),
             checksum: Checksum::from_str("d829752c10db8f7a98c939b5418beb0a360c6a6b818830e000f2c5a8dce35af4").unwrap(),
-            packster_version: Version(String::from("0.1.4"))
+            packster_version: Version::new(0, 1, 4),
+            dependencies: Dependencies::default(),
+            parameter_schema: ParameterSchema::default(),
+            hooks: Hooks::default(),
+            shims: Shims::default(),
+            environment: Environment::default(),
+            provides: Provides::default(),
+            description: None,
+            labels: BTreeSet::new()
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+/* Deployments without parameters are identified by their package checksum, the others by a digest of the package
+ * checksum and their canonical parameters, so the same package can be deployed several times with different settings */
+pub trait DeploymentIdentifier {
+    fn identify_deployment(&self, package: &Package, parameters: &Parameters) -> Result<Checksum>;
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Deployment {
+    id: Checksum,
     #[serde(flatten)]
-    package: Package
-    //TODO installed packster version
+    package: Package,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    dependents: Vec<Dependent>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    parameters: Parameters,
+    // Unknown for deployments migrated from a lockfile which did not record it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    installed_packster_version: Option<Version>,
+    // Empty for deployments migrated from a lockfile which did not record it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    files: Vec<DeployedFile>
+}
+
+/* A file of a deployment directory, as it was once deployed */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DeployedFile {
+    // Relative to the deployment directory, with slash separators
+    path: String,
+    size: u64,
+    checksum: Checksum,
+}
+
+impl DeployedFile {
+    pub fn new(path: String, size: u64, checksum: Checksum) -> Self {
+        DeployedFile { path, size, checksum }
+    }
+
+    pub fn as_path(&self) -> &str { &self.path }
+    pub fn as_size(&self) -> u64 { self.size }
+    pub fn as_checksum(&self) -> &Checksum { &self.checksum }
 }
 
 impl Deployment {
-    pub fn new( package: Package ) -> Self { Deployment { package } }
+    pub fn new( package: Package ) -> Self {
+        Deployment { id: package.as_checksum().clone(), package, dependents: Vec::new(), parameters: Parameters::new(), installed_packster_version: None, files: Vec::new() }
+    }
+
+    pub(crate) fn from_parts(id: Checksum, package: Package, dependents: Vec<Dependent>, parameters: Parameters) -> Self {
+        Deployment { id, package, dependents, parameters, installed_packster_version: None, files: Vec::new() }
+    }
 
+    pub fn with_installed_packster_version(mut self, packster_version: Version) -> Self {
+        self.installed_packster_version = Some(packster_version);
+        self
+    }
+
+    pub fn with_files(mut self, files: Vec<DeployedFile>) -> Self {
+        self.files = files;
+        self
+    }
+
+    /* Applies the package parameter schema to the given parameters before identifying the deployment */
+    pub fn identify<I: DeploymentIdentifier>(package: Package, parameters: Parameters, identifier: &I) -> Result<Self> {
+        let parameters = package.as_parameter_schema().apply(parameters)?;
+        let id = identifier.identify_deployment(&package, &parameters)?;
+        Ok(Deployment { id, package, dependents: Vec::new(), parameters, installed_packster_version: None, files: Vec::new() })
+    }
+
+    pub fn as_id(&self) -> &Checksum { &self.id }
     pub fn as_checksum(&self) -> &Checksum { self.package.as_checksum() }
+    pub fn as_version(&self) -> &Version { self.package.as_version() }
+    pub fn as_dependents(&self) -> &[Dependent] { &self.dependents }
+    pub fn as_parameters(&self) -> &Parameters { &self.parameters }
+    pub fn as_installed_packster_version(&self) -> Option<&Version> { self.installed_packster_version.as_ref() }
+    pub fn as_files(&self) -> &[DeployedFile] { &self.files }
+
+    pub fn add_dependent(&mut self, dependent: Dependent) {
+        if !self.dependents.contains(&dependent) {
+            self.dependents.push(dependent);
+        }
+    }
+
+    pub fn are_dependents_satisfied_by(&self, version: &Version) -> bool {
+        self.dependents.iter().all(|dependent| dependent.as_requirement().matches(version))
+    }
 }
 
 impl AsRef<Package> for Deployment {
     fn as_ref(&self) -> &Package { &self.package }
 }
 
-#[derive(Serialize, Deserialize, Default)]
+/* A deployment of an identifier, written as `identifier@version` or `identifier@checksum`, the checksum being either
+ * the deployment identifier or its package checksum */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeploymentSelector {
+    identifier: Identifier,
+    selection: DeploymentSelection,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DeploymentSelection {
+    Version(Version),
+    Checksum(Checksum),
+}
+
+impl DeploymentSelector {
+    pub fn as_identifier(&self) -> &Identifier { &self.identifier }
+
+    pub fn matches(&self, deployment: &Deployment) -> bool {
+        deployment.as_ref().as_identifier() == &self.identifier
+            && match &self.selection {
+                DeploymentSelection::Version(version) => deployment.as_version() == version,
+                DeploymentSelection::Checksum(checksum) => deployment.as_id() == checksum || deployment.as_checksum() == checksum,
+            }
+    }
+}
+
+impl From<&Deployment> for DeploymentSelector {
+    fn from(deployment: &Deployment) -> Self {
+        DeploymentSelector {
+            identifier: deployment.as_ref().as_identifier().clone(),
+            selection: DeploymentSelection::Checksum(deployment.as_id().clone()),
+        }
+    }
+}
+
+impl fmt::Display for DeploymentSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.selection {
+            DeploymentSelection::Version(version) => write!(f, "{}@{version}", self.identifier),
+            DeploymentSelection::Checksum(checksum) => write!(f, "{}@{checksum}", self.identifier),
+        }
+    }
+}
+
+impl FromStr for DeploymentSelector {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (identifier, selection) = s.split_once('@')
+            .ok_or_else(|| Error::InvalidDeploymentSelector(s.to_string()))?;
+        let selection = match Version::from_str(selection) {
+            Ok(version) => DeploymentSelection::Version(version),
+            Err(_) => DeploymentSelection::Checksum(
+                Checksum::from_str(selection).map_err(|_| Error::InvalidDeploymentSelector(s.to_string()))?
+            ),
+        };
+        Ok(DeploymentSelector { identifier: Identifier::from_str(identifier)?, selection })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct DeployLocation {
-    deployments: Vec<Deployment>
+    schema_version: u32,
+    deployments: Vec<Deployment>,
+    // Deployment used by default among the ones of an identifier
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    active: BTreeMap<Identifier, Checksum>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    bundles: Vec<InstalledBundle>,
+    // Resources of the deployments, each of them claimed by a single package identifier
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    claims: Vec<ResourceClaim>
+}
+
+impl Default for DeployLocation {
+    fn default() -> Self {
+        DeployLocation::new(Vec::new(), Vec::new())
+    }
 }
 
 impl DeployLocation {
+    pub(crate) fn new(deployments: Vec<Deployment>, bundles: Vec<InstalledBundle>) -> Self {
+        DeployLocation { schema_version: LOCKFILE_SCHEMA_VERSION, deployments, active: BTreeMap::new(), bundles, claims: Vec::new() }
+    }
+
+    /* The newest deployment of each identifier without an active one is activated */
+    pub(crate) fn with_latest_deployments_activated(mut self) -> Self {
+        let identifiers: Vec<Identifier> = self.deployments.iter().map(|deployment| deployment.as_ref().as_identifier().clone()).collect();
+        for identifier in identifiers {
+            if !self.active.contains_key(&identifier) {
+                self.activate_latest_deployment(&identifier);
+            }
+        }
+        self
+    }
+
+    /* Every deployment claims the resources of its package */
+    pub(crate) fn with_resources_claimed(mut self) -> Self {
+        self.claims = self.deployments.iter().flat_map(to_resource_claims).collect();
+        self
+    }
+
+    pub(crate) fn with_current_schema_version(mut self) -> Self {
+        self.schema_version = LOCKFILE_SCHEMA_VERSION;
+        self
+    }
+
+    pub fn as_schema_version(&self) -> u32 {
+        self.schema_version
+    }
+
     pub fn as_slice(&self) -> &[Deployment] {
         &self.deployments
     }
 
+    /* The first deployment of an identifier is activated */
     pub fn add_deployment(&mut self, deployment: Deployment) {
+        self.active
+            .entry(deployment.as_ref().as_identifier().clone())
+            .or_insert_with(|| deployment.as_id().clone());
+        self.claims.extend(to_resource_claims(&deployment));
         self.deployments.push(deployment);
     }
 
-    pub fn remove_deployment(&mut self, checksum: &Checksum) {
+    /* Removing the active deployment of an identifier activates the newest remaining one */
+    pub fn remove_deployment(&mut self, id: &Checksum) {
+        let identifier = self.get_deployment(id).map(|deployment| deployment.as_ref().as_identifier().clone());
         self.deployments
-            .retain(|deployment| deployment.as_checksum() != checksum)
+            .retain(|deployment| deployment.as_id() != id);
+        self.claims
+            .retain(|claim| claim.as_deployment_id() != id);
+        if let Some(identifier) = identifier.filter(|identifier| self.active.get(identifier) == Some(id)) {
+            self.activate_latest_deployment(&identifier);
+        }
+        for deployment in self.deployments.iter_mut() {
+            deployment.dependents.retain(|dependent| dependent.as_deployment_id() != id);
+        }
+        for bundle in self.bundles.iter_mut() {
+            bundle.remove(id);
+        }
+    }
+
+    /* The replacing deployment takes over the dependents and bundles of the replaced one, which is removed */
+    pub fn replace_deployment(&mut self, replaced_id: &Checksum, replacing_id: &Checksum) {
+        let dependents = self.get_deployment(replaced_id)
+            .map(|deployment| deployment.as_dependents().to_vec())
+            .unwrap_or_default();
+        for dependent in dependents {
+            self.add_dependent(replacing_id, dependent);
+        }
+        for bundle in self.bundles.iter_mut().filter(|bundle| bundle.contains(replaced_id)) {
+            bundle.replace(replaced_id, replacing_id);
+        }
+        if self.is_active(replaced_id) {
+            self.activate(replacing_id);
+        }
+        self.remove_deployment(replaced_id);
+    }
+
+    pub fn add_dependent(&mut self, id: &Checksum, dependent: Dependent) {
+        if let Some(deployment) = self.deployments.iter_mut().find(|deployment| deployment.as_id() == id) {
+            deployment.add_dependent(dependent);
+        }
+    }
+
+    pub fn set_installed_packster_version(&mut self, id: &Checksum, packster_version: Version) {
+        if let Some(deployment) = self.deployments.iter_mut().find(|deployment| deployment.as_id() == id) {
+            deployment.installed_packster_version = Some(packster_version);
+        }
     }
 
-    pub fn get_deployment(&self, checksum: &Checksum) -> Option<&Deployment> {
+    pub fn set_deployment_files(&mut self, id: &Checksum, files: Vec<DeployedFile>) {
+        if let Some(deployment) = self.deployments.iter_mut().find(|deployment| deployment.as_id() == id) {
+            deployment.files = files;
+        }
+    }
+
+    pub fn get_deployment(&self, id: &Checksum) -> Option<&Deployment> {
         self.deployments.iter()
-            .find(|deployment| deployment.as_checksum() == checksum)
+            .find(|deployment| deployment.as_id() == id)
     }
 
-    pub fn is_checksum_deployed(&self, checksum: &Checksum) -> bool {
+    pub fn is_deployed(&self, id: &Checksum) -> bool {
         self.deployments.iter()
-            .any(|deployment| deployment.as_checksum() == checksum)
+            .any(|deployment| deployment.as_id() == id)
     }
 
     pub fn iter(&self) -> impl Iterator<Item = &Deployment> {
         self.deployments.iter()
     }
+
+    pub fn iter_identifier<'a>(&'a self, identifier: &'a Identifier) -> impl Iterator<Item = &'a Deployment> {
+        self.deployments.iter()
+            .filter(move |deployment| deployment.as_ref().as_identifier() == identifier)
+    }
+
+    pub fn find_matching<'a>(&'a self, identifier: &'a Identifier, requirement: &'a VersionRequirement) -> impl Iterator<Item = &'a Deployment> {
+        self.iter_identifier(identifier)
+            .filter(move |deployment| requirement.matches(deployment.as_version()))
+    }
+
+    pub fn get_bundle(&self, identifier: &Identifier) -> Option<&InstalledBundle> {
+        self.bundles.iter()
+            .find(|bundle| bundle.as_identifier() == identifier)
+    }
+
+    pub fn add_bundle(&mut self, bundle: InstalledBundle) {
+        match self.bundles.iter_mut().find(|installed| installed.as_identifier() == bundle.as_identifier()) {
+            Some(installed) => installed.merge(bundle),
+            None => self.bundles.push(bundle),
+        }
+    }
+
+    /* Removes the bundle and every deployment it installed that is neither required by another deployment nor by another bundle */
+    pub fn remove_bundle(&mut self, identifier: &Identifier) -> Vec<Deployment> {
+        let Some(position) = self.bundles.iter().position(|bundle| bundle.as_identifier() == identifier) else {
+            return Vec::new();
+        };
+        let mut candidates = self.bundles.remove(position).into_deployment_ids();
+        let mut removed = Vec::new();
+
+        while let Some(position) = candidates.iter().position(|id| self.is_removable(id)) {
+            let id = candidates.remove(position);
+            if let Some(deployment) = self.get_deployment(&id).cloned() {
+                self.remove_deployment(&id);
+                removed.push(deployment);
+            }
+        }
+        removed
+    }
+
+    fn is_removable(&self, id: &Checksum) -> bool {
+        self.get_deployment(id)
+            .map(|deployment| deployment.as_dependents().is_empty())
+            .unwrap_or(true)
+        && !self.bundles.iter().any(|bundle| bundle.contains(id))
+    }
+
+    pub fn is_active(&self, id: &Checksum) -> bool {
+        self.active.values().any(|active_id| active_id == id)
+    }
+
+    pub fn get_active_deployment(&self, identifier: &Identifier) -> Option<&Deployment> {
+        self.active.get(identifier).and_then(|id| self.get_deployment(id))
+    }
+
+    pub fn activate(&mut self, id: &Checksum) {
+        if let Some(identifier) = self.get_deployment(id).map(|deployment| deployment.as_ref().as_identifier().clone()) {
+            self.active.insert(identifier, id.clone());
+        }
+    }
+
+    fn activate_latest_deployment(&mut self, identifier: &Identifier) {
+        match self.get_latest_deployment(identifier).map(|deployment| deployment.as_id().clone()) {
+            Some(id) => self.active.insert(identifier.clone(), id),
+            None => self.active.remove(identifier),
+        };
+    }
+
+    /* Exactly one deployment shall match the selector */
+    pub fn select_deployment(&self, selector: &DeploymentSelector) -> Result<&Deployment> {
+        let selected: Vec<&Deployment> = self.deployments.iter()
+            .filter(|deployment| selector.matches(deployment))
+            .collect();
+        match selected.as_slice() {
+            [deployment] => Ok(*deployment),
+            [] => Err(Error::DeploymentNotFound(selector.to_string())),
+            _ => Err(Error::AmbiguousDeploymentSelector {
+                selector: selector.to_string(),
+                deployment_ids: selected.iter().map(|deployment| deployment.as_id().to_string()).collect(),
+            }),
+        }
+    }
+
+    pub fn iter_active_deployments(&self) -> impl Iterator<Item = &Deployment> {
+        self.active.values().filter_map(|id| self.get_deployment(id))
+    }
+
+    /* Shims of the active deployments, a command claimed by two packages being refused */
+    pub fn to_shims(&self) -> Result<Vec<LocationShim>> {
+        let mut shims: BTreeMap<&ShimName, LocationShim> = BTreeMap::new();
+        for deployment in self.iter_active_deployments() {
+            let package = deployment.as_ref();
+            for (name, target) in package.as_shims() {
+                if let Some(shim) = shims.get(name) {
+                    return Err(Error::ShimCollision {
+                        command: name.to_string(),
+                        package: format!("{}@{}", shim.as_identifier(), shim.as_version()),
+                        other_package: format!("{}@{}", package.as_identifier(), package.as_version()),
+                    });
+                }
+                shims.insert(name, LocationShim::new(
+                    name.clone(),
+                    target.clone(),
+                    deployment.as_id().clone(),
+                    package.as_identifier().clone(),
+                    package.as_version().clone(),
+                ));
+            }
+        }
+        Ok(shims.into_values().collect())
+    }
+
+    pub fn as_claims(&self) -> &[ResourceClaim] {
+        &self.claims
+    }
+
+    /* The deployments of a same package identifier share their resources, being alternately active */
+    pub fn probe_resource_conflict(&self, deployment: &Deployment) -> Result<()> {
+        let package = deployment.as_ref();
+        let resources = package.to_resources();
+        let conflict = self.claims.iter()
+            .filter(|claim| resources.iter().any(|resource| resource.conflicts_with(claim.as_resource())))
+            .filter_map(|claim| self.get_deployment(claim.as_deployment_id()).map(|other| (claim, other)))
+            .find(|(_, other)| other.as_ref().as_identifier() != package.as_identifier());
+        match conflict {
+            Some((claim, other)) => Err(Error::ResourceConflict {
+                resource: claim.as_resource().to_string(),
+                deployment_id: deployment.as_id().to_string(),
+                package: format!("{}@{}", package.as_identifier(), package.as_version()),
+                other_deployment_id: other.as_id().to_string(),
+                other_package: format!("{}@{}", other.as_ref().as_identifier(), other.as_version()),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    pub fn get_latest_deployment(&self, identifier: &Identifier) -> Option<&Deployment> {
+        self.deployments.iter()
+            .filter(|deployment| deployment.as_ref().as_identifier() == identifier)
+            .max_by(|left, right| left.as_version().cmp(right.as_version()))
+    }
+}
+
+fn to_resource_claims(deployment: &Deployment) -> impl Iterator<Item = ResourceClaim> + '_ {
+    deployment.as_ref().to_resources()
+        .into_iter()
+        .map(|resource| ResourceClaim::new(resource, deployment.as_id().clone()))
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    pub use pretty_assertions::{assert_eq, assert_ne};
+    pub use pretty_assertions::assert_eq;
+    use proptest::prelude::*;
 
     #[test]
     fn test_extract_checksum_from_path() -> Result<()> {
-        let path = Path::new("C:\\Downloads\\static-package-a_0.0.1_d829752c10db8f7a98c939b5418beb0a360c6a6b818830e000f2c5a8dce35af4.302e312e30.packster");
+        let path = Path::new("/downloads/static-package-a_0.0.1_d829752c10db8f7a98c939b5418beb0a360c6a6b818830e000f2c5a8dce35af4.302e312e30.packster");
         let checksum = Package::from_path(path)?.as_checksum().to_string();
 
         assert_eq!(checksum, "d829752c10db8f7a98c939b5418beb0a360c6a6b818830e000f2c5a8dce35af4");
@@ -201,4 +709,212 @@ mod test {
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_from_path_reject_ambiguous_file_name() {
+        for file_name in [
+            "my_package_0.0.1_d829752c10db8f7a98c939b5418beb0a360c6a6b818830e000f2c5a8dce35af4.302e312e30.packster",
+            "my-package_0.0.1_d829752c10db8f7a98c939b5418beb0a360c6a6b818830e000f2c5a8dce35af4.302e312e30.packster.tar.gz",
+            "my-package_0.0.1_d829752c10db8f7a98c939b5418beb0a360c6a6b818830e000f2c5a8dce35af4.302e312e30",
+            "my-package_0.0.1_d829752c10db8f7a98c939b5418beb0a360c6a6b818830e000f2c5a8dce35af4.packster",
+        ] {
+            assert!(
+                matches!(Package::from_path(file_name), Err(Error::WrongFileNameFormat(..))),
+                "{file_name} should not be parsed"
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_path_reject_invalid_identifier() {
+        let path = Path::new("/My-Package_0.0.1_d829752c10db8f7a98c939b5418beb0a360c6a6b818830e000f2c5a8dce35af4.302e312e30.packster");
+        assert!(matches!(Package::from_path(path), Err(Error::InvalidIdentifier(_))));
+    }
+
+    #[test]
+    fn test_from_path_build_metadata() -> Result<()> {
+        let path = Path::new("/my-package_1.0.0-rc.1+build.5.sha.1f2e_d829752c10db8f7a98c939b5418beb0a360c6a6b818830e000f2c5a8dce35af4.302e312e30.packster");
+        let package = Package::from_path(path)?;
+
+        assert_eq!(package.as_version().to_string(), "1.0.0-rc.1+build.5.sha.1f2e");
+        assert_eq!(package.as_packster_version(), &Version::new(0, 1, 0));
+        Ok(())
+    }
+
+    prop_compose! {
+        fn arbitrary_version()(
+            major in 0..1000u64,
+            minor in 0..1000u64,
+            patch in 0..1000u64,
+            pre_release in proptest::option::of("(0|[1-9][0-9]{0,3}|[a-zA-Z-][0-9a-zA-Z-]{0,7})(\\.(0|[1-9][0-9]{0,3}|[a-zA-Z-][0-9a-zA-Z-]{0,7})){0,2}"),
+            build in proptest::option::of("[0-9a-zA-Z-]{1,8}(\\.[0-9a-zA-Z-]{1,8}){0,2}")
+        ) -> Version {
+            let mut version = format!("{major}.{minor}.{patch}");
+            if let Some(pre_release) = pre_release { version = format!("{version}-{pre_release}"); }
+            if let Some(build) = build { version = format!("{version}+{build}"); }
+            Version::from_str(&version).unwrap()
+        }
+    }
+
+    prop_compose! {
+        fn arbitrary_package()(
+            identifier in "[a-z0-9-]{1,64}",
+            version in arbitrary_version(),
+            checksum in proptest::collection::vec(any::<u8>(), 1..64),
+            packster_version in arbitrary_version()
+        ) -> Package {
+            Package {
+                identifier: Identifier::from_str(&identifier).unwrap(),
+                version,
+                checksum: Checksum::from(checksum),
+                packster_version,
+                dependencies: Dependencies::default(),
+                parameter_schema: ParameterSchema::default(),
+                hooks: Hooks::default(),
+                shims: Shims::default(),
+                environment: Environment::default(),
+                provides: Provides::default(),
+                description: None,
+                labels: BTreeSet::new()
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_arbitrary_filename_reciprocity(package in arbitrary_package()) {
+            let parsed_package = Package::from_path(Path::new("/some/directory").join(package.to_file_name())).unwrap();
+            prop_assert_eq!(package, parsed_package);
+        }
+    }
+
+    #[test]
+    fn test_from_path_reject_invalid_version() {
+        let path = Path::new("/latest-package_latest-ish_d829752c10db8f7a98c939b5418beb0a360c6a6b818830e000f2c5a8dce35af4.302e312e30.packster");
+        assert!(matches!(Package::from_path(path), Err(Error::InvalidVersion { .. })));
+    }
+
+    #[test]
+    fn test_get_latest_deployment() -> Result<()> {
+        let mut location = DeployLocation::default();
+        for (version, checksum) in [("1.2.0", "aa"), ("1.10.0", "bb"), ("1.10.0-rc.1", "cc")] {
+            location.add_deployment(Deployment::new(Package {
+                version: Version::from_str(version)?,
+                checksum: Checksum::from_str(checksum)?,
+                ..Package::default()
+            }));
+        }
+        location.add_deployment(Deployment::new(Package {
+            identifier: Identifier(String::from("another-package")),
+            version: Version::new(2, 0, 0),
+            ..Package::default()
+        }));
+
+        let latest = location.get_latest_deployment(&Identifier(String::from("my-package")));
+        assert_eq!(latest.map(Deployment::as_version), Some(&Version::new(1, 10, 0)));
+        assert!(location.get_latest_deployment(&Identifier(String::from("unknown"))).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_matching() -> Result<()> {
+        let mut location = DeployLocation::default();
+        for (version, checksum) in [("0.9.0", "aa"), ("1.2.0", "bb"), ("1.10.0", "cc"), ("2.0.0", "dd")] {
+            location.add_deployment(Deployment::new(Package {
+                version: Version::from_str(version)?,
+                checksum: Checksum::from_str(checksum)?,
+                ..Package::default()
+            }));
+        }
+
+        let identifier = Identifier(String::from("my-package"));
+        let requirement = VersionRequirement::from_str("^1")?;
+        let matching: Vec<String> = location.find_matching(&identifier, &requirement)
+            .map(|deployment| deployment.as_version().to_string())
+            .collect();
+
+        assert_eq!(matching, ["1.2.0", "1.10.0"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_deployment() -> Result<()> {
+        let mut location = DeployLocation::default();
+        let tool = |version: &str, checksum: &str| -> Result<Deployment> {
+            Ok(Deployment::new(Package {
+                identifier: Identifier(String::from("tool")),
+                version: Version::from_str(version)?,
+                checksum: Checksum::from_str(checksum)?,
+                ..Package::default()
+            }))
+        };
+        let app = Deployment::new(Package { checksum: Checksum::from_str("cc")?, ..Package::default() });
+        location.add_deployment(tool("1.0.0", "aa")?);
+        location.add_deployment(tool("1.1.0", "bb")?);
+        location.add_deployment(app.clone());
+        location.add_dependent(&Checksum::from_str("aa")?, Dependent::new(&app, VersionRequirement::from_str("^1")?));
+        location.add_bundle(InstalledBundle::new(Identifier(String::from("bundle")), vec![Checksum::from_str("aa")?]));
+
+        let replaced = location.get_deployment(&Checksum::from_str("aa")?).unwrap();
+        assert!(replaced.are_dependents_satisfied_by(&Version::new(1, 1, 0)));
+        assert!(!replaced.are_dependents_satisfied_by(&Version::new(2, 0, 0)));
+
+        location.replace_deployment(&Checksum::from_str("aa")?, &Checksum::from_str("bb")?);
+        assert!(!location.is_deployed(&Checksum::from_str("aa")?));
+        let replacing = location.get_deployment(&Checksum::from_str("bb")?).unwrap();
+        assert_eq!(replacing.as_dependents()[0].as_deployment_id(), app.as_id());
+        let bundle = location.get_bundle(&Identifier(String::from("bundle"))).unwrap();
+        assert_eq!(bundle.as_deployment_ids(), [Checksum::from_str("bb")?]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_active_deployment() -> Result<()> {
+        let mut location = DeployLocation::default();
+        let identifier = Identifier(String::from("my-package"));
+        for (version, checksum) in [("1.0.0", "aa"), ("1.1.0", "bb"), ("0.9.0", "cc")] {
+            location.add_deployment(Deployment::new(Package {
+                version: Version::from_str(version)?,
+                checksum: Checksum::from_str(checksum)?,
+                ..Package::default()
+            }));
+        }
+        let active_version = |location: &DeployLocation| location.get_active_deployment(&identifier).map(|deployment| deployment.as_version().to_string());
+        assert_eq!(active_version(&location).as_deref(), Some("1.0.0"));
+
+        location.activate(&Checksum::from_str("cc")?);
+        assert_eq!(active_version(&location).as_deref(), Some("0.9.0"));
+
+        location.remove_deployment(&Checksum::from_str("cc")?);
+        assert_eq!(active_version(&location).as_deref(), Some("1.1.0"));
+        location.remove_deployment(&Checksum::from_str("aa")?);
+        location.remove_deployment(&Checksum::from_str("bb")?);
+        assert!(active_version(&location).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_deployment() -> Result<()> {
+        let mut location = DeployLocation::default();
+        let package = |version: &str, checksum: &str| -> Result<Package> {
+            Ok(Package { version: Version::from_str(version)?, checksum: Checksum::from_str(checksum)?, ..Package::default() })
+        };
+        location.add_deployment(Deployment::new(package("1.0.0", "aa")?));
+        location.add_deployment(Deployment::from_parts(Checksum::from_str("bb")?, package("1.1.0", "cc")?, Vec::new(), Parameters::new()));
+        location.add_deployment(Deployment::from_parts(Checksum::from_str("dd")?, package("1.1.0", "cc")?, Vec::new(), Parameters::new()));
+
+        let select = |selector: &str| -> Result<String> {
+            Ok(location.select_deployment(&DeploymentSelector::from_str(selector)?)?.as_id().to_string())
+        };
+        assert_eq!(select("my-package@1.0.0")?, "aa");
+        assert_eq!(select("my-package@aa")?, "aa");
+        assert_eq!(select("my-package@dd")?, "dd");
+        assert!(matches!(select("my-package@1.1.0"), Err(Error::AmbiguousDeploymentSelector { .. })));
+        assert!(matches!(select("my-package@cc"), Err(Error::AmbiguousDeploymentSelector { .. })));
+        assert!(matches!(select("my-package@2.0.0"), Err(Error::DeploymentNotFound(_))));
+        assert!(matches!(select("another-package@1.0.0"), Err(Error::DeploymentNotFound(_))));
+        assert!(matches!(select("my-package"), Err(Error::InvalidDeploymentSelector(_))));
+        assert!(matches!(select("my-package@latest"), Err(Error::InvalidDeploymentSelector(_))));
+        Ok(())
+    }
+}
@@ -0,0 +1,338 @@
+use std::{collections::BTreeMap, fmt, path::Path, str::FromStr};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+use super::Deployment;
+
+pub const PATH_VARIABLE: &str = "PATH";
+
+/* Prefix of the variables saving the values replaced by an activation, for the deactivation to restore them */
+const SAVED_VARIABLE_PREFIX: &str = "_PACKSTER_OLD_";
+
+/* Prefix of the variables marking the ones set by an activation, so that activating again does not save its own values */
+const ACTIVATED_VARIABLE_PREFIX: &str = "_PACKSTER_ACTIVE_";
+
+const PLACEHOLDERS: [&str; 4] = ["deployment_path", "location_path", "identifier", "version"];
+
+/* Variables declared in the [environment] section of a project manifest, exported when the location is activated */
+pub type Environment = BTreeMap<VariableName, VariableValue>;
+
+/* An environment variable name, PATH and the PACKSTER_ prefix being reserved */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub struct VariableName(String);
+
+impl FromStr for VariableName {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        lazy_static! {
+            static ref VARIABLE_NAME_REGEXP: Regex = Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap();
+        }
+        if !VARIABLE_NAME_REGEXP.is_match(s) || s == PATH_VARIABLE || s.starts_with("PACKSTER_")
+            || s.starts_with(SAVED_VARIABLE_PREFIX) || s.starts_with(ACTIVATED_VARIABLE_PREFIX) {
+            return Err(Error::InvalidEnvironmentVariableName(s.to_string()));
+        }
+        Ok(VariableName(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for VariableName {
+    type Error = Error;
+    fn try_from(value: String) -> Result<Self> {
+        VariableName::from_str(&value)
+    }
+}
+
+impl From<VariableName> for String {
+    fn from(value: VariableName) -> Self {
+        value.0
+    }
+}
+
+impl fmt::Display for VariableName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl AsRef<str> for VariableName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/* A variable value, where ${deployment_path}, ${location_path}, ${identifier} and ${version} are expanded at activation */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct VariableValue(String);
+
+lazy_static! {
+    static ref PLACEHOLDER_REGEXP: Regex = Regex::new(r"\$\{([^}]*)\}").unwrap();
+}
+
+impl VariableValue {
+    pub fn expand(&self, placeholders: &BTreeMap<&str, String>) -> String {
+        PLACEHOLDER_REGEXP
+            .replace_all(&self.0, |captures: &regex::Captures| placeholders.get(&captures[1]).cloned().unwrap_or_default())
+            .to_string()
+    }
+}
+
+impl FromStr for VariableValue {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(captures) = PLACEHOLDER_REGEXP.captures_iter(s).find(|captures| !PLACEHOLDERS.contains(&&captures[1])) {
+            return Err(Error::UnknownEnvironmentPlaceholder(captures[1].to_string()));
+        }
+        Ok(VariableValue(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for VariableValue {
+    type Error = Error;
+    fn try_from(value: String) -> Result<Self> {
+        VariableValue::from_str(&value)
+    }
+}
+
+impl From<VariableValue> for String {
+    fn from(value: VariableValue) -> Self {
+        value.0
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VariableMode {
+    Set,
+    // The value is a directory prepended to the previous value of a search path
+    Prepend,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct EnvironmentVariable {
+    name: String,
+    value: String,
+    mode: VariableMode,
+}
+
+impl EnvironmentVariable {
+    pub fn as_name(&self) -> &str { &self.name }
+    pub fn as_value(&self) -> &str { &self.value }
+    pub fn as_mode(&self) -> VariableMode { self.mode }
+}
+
+/* Variables to export for using a location : its bin directory in the PATH, and the variables of its active deployments */
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct LocationEnvironment {
+    variables: Vec<EnvironmentVariable>,
+    #[serde(skip)]
+    owners: BTreeMap<String, String>,
+}
+
+impl LocationEnvironment {
+    pub fn new(bin_path: &Path) -> Self {
+        LocationEnvironment {
+            variables: vec![EnvironmentVariable {
+                name: PATH_VARIABLE.to_string(),
+                value: bin_path.to_string_lossy().to_string(),
+                mode: VariableMode::Prepend,
+            }],
+            owners: BTreeMap::new(),
+        }
+    }
+
+    pub fn as_variables(&self) -> &[EnvironmentVariable] {
+        &self.variables
+    }
+
+    /* Two deployments declaring the same variable are refused */
+    pub fn add_deployment(&mut self, deployment: &Deployment, location_path: &Path, deployment_path: &Path) -> Result<()> {
+        let package = deployment.as_ref();
+        let owner = format!("{}@{}", package.as_identifier(), package.as_version());
+        let placeholders = BTreeMap::from([
+            ("deployment_path", deployment_path.to_string_lossy().to_string()),
+            ("location_path", location_path.to_string_lossy().to_string()),
+            ("identifier", package.as_identifier().to_string()),
+            ("version", package.as_version().to_string()),
+        ]);
+        for (name, value) in package.as_environment() {
+            if let Some(other_owner) = self.owners.get(name.as_ref()) {
+                return Err(Error::EnvironmentVariableCollision {
+                    variable: name.to_string(),
+                    package: other_owner.clone(),
+                    other_package: owner,
+                });
+            }
+            self.owners.insert(name.to_string(), owner.clone());
+            self.variables.push(EnvironmentVariable {
+                name: name.to_string(),
+                value: value.expand(&placeholders),
+                mode: VariableMode::Set,
+            });
+        }
+        Ok(())
+    }
+}
+
+/* Shells whose scripts activate a location environment, saving the replaced values for the deactivation to restore them */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Posix,
+}
+
+impl Shell {
+    pub fn to_activation_script(&self, environment: &LocationEnvironment) -> String {
+        let script: String = environment.as_variables().iter()
+            .map(|variable| match self {
+                Shell::Fish => to_fish_activation(variable),
+                Shell::Bash | Shell::Zsh | Shell::Posix => to_posix_activation(variable),
+            })
+            .collect();
+        script + self.as_rehash_command()
+    }
+
+    pub fn to_deactivation_script(&self, environment: &LocationEnvironment) -> String {
+        let script: String = environment.as_variables().iter()
+            .map(|variable| match self {
+                Shell::Fish => to_fish_deactivation(variable.as_name()),
+                Shell::Bash | Shell::Zsh | Shell::Posix => to_posix_deactivation(variable.as_name()),
+            })
+            .collect();
+        script + self.as_rehash_command()
+    }
+
+    /* Commands previously found in the PATH are remembered by the shell */
+    fn as_rehash_command(&self) -> &'static str {
+        match self {
+            Shell::Bash | Shell::Posix => "hash -r 2>/dev/null || true\n",
+            Shell::Zsh => "rehash\n",
+            Shell::Fish => "",
+        }
+    }
+}
+
+/* Activating twice keeps the values saved the first time, and does not prepend a directory already in the search path */
+fn to_posix_activation(variable: &EnvironmentVariable) -> String {
+    let EnvironmentVariable { name, value, mode } = variable;
+    let saved = format!("{SAVED_VARIABLE_PREFIX}{name}");
+    let activated = format!("{ACTIVATED_VARIABLE_PREFIX}{name}");
+    let save = format!(
+        "if [ -z \"${{{activated}+x}}\" ]; then if [ -n \"${{{name}+x}}\" ]; then {saved}=\"${name}\"; export {saved}; fi; {activated}=1; export {activated}; fi\n"
+    );
+    let value = to_posix_quoted(value);
+    let assignment = match mode {
+        VariableMode::Set => format!("{name}={value}; export {name}\n"),
+        VariableMode::Prepend => format!("case \":${{{name}:-}}:\" in *:{value}:*) ;; *) {name}={value}\"${{{name}:+:${name}}}\"; export {name};; esac\n"),
+    };
+    save + &assignment
+}
+
+fn to_posix_deactivation(name: &str) -> String {
+    let saved = format!("{SAVED_VARIABLE_PREFIX}{name}");
+    let activated = format!("{ACTIVATED_VARIABLE_PREFIX}{name}");
+    format!(
+        "if [ -n \"${{{activated}+x}}\" ]; then if [ -n \"${{{saved}+x}}\" ]; then {name}=\"${saved}\"; export {name}; unset {saved}; else unset {name}; fi; unset {activated}; fi\n"
+    )
+}
+
+fn to_fish_activation(variable: &EnvironmentVariable) -> String {
+    let EnvironmentVariable { name, value, mode } = variable;
+    let saved = format!("{SAVED_VARIABLE_PREFIX}{name}");
+    let activated = format!("{ACTIVATED_VARIABLE_PREFIX}{name}");
+    let save = format!("if not set -q {activated}; if set -q {name}; set -gx {saved} ${name}; end; set -gx {activated} 1; end\n");
+    let value = to_fish_quoted(value);
+    let assignment = match mode {
+        VariableMode::Set => format!("set -gx {name} {value}\n"),
+        VariableMode::Prepend => format!("if not contains -- {value} ${name}; set -gx {name} {value} ${name}; end\n"),
+    };
+    save + &assignment
+}
+
+fn to_fish_deactivation(name: &str) -> String {
+    let saved = format!("{SAVED_VARIABLE_PREFIX}{name}");
+    let activated = format!("{ACTIVATED_VARIABLE_PREFIX}{name}");
+    format!("if set -q {activated}; if set -q {saved}; set -gx {name} ${saved}; set -e {saved}; else; set -e {name}; end; set -e {activated}; end\n")
+}
+
+fn to_posix_quoted(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+fn to_fish_quoted(s: &str) -> String {
+    format!("'{}'", s.replace('\\', r"\\").replace('\'', r"\'"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_variable_validation() {
+        assert!(VariableName::from_str("MY_TOOL_HOME").is_ok());
+        for name in ["", "PATH", "PACKSTER_EVENT", "_PACKSTER_OLD_HOME", "_PACKSTER_ACTIVE_HOME", "1TOOL", "MY-TOOL"] {
+            assert!(matches!(VariableName::from_str(name), Err(Error::InvalidEnvironmentVariableName(_))), "{name} shall be invalid");
+        }
+        assert!(VariableValue::from_str("${deployment_path}/lib:${location_path}").is_ok());
+        assert!(matches!(VariableValue::from_str("${home}/lib"), Err(Error::UnknownEnvironmentPlaceholder(placeholder)) if placeholder == "home"));
+    }
+
+    #[test]
+    fn test_variable_expansion() {
+        let value = VariableValue::from_str("${deployment_path}/lib:${deployment_path}/share ${identifier}").unwrap();
+        let placeholders = BTreeMap::from([("deployment_path", String::from("/location/aa")), ("identifier", String::from("tool"))]);
+        assert_eq!(value.expand(&placeholders), "/location/aa/lib:/location/aa/share tool");
+    }
+
+    #[test]
+    fn test_posix_scripts() {
+        let mut environment = LocationEnvironment::new(Path::new("/my location/bin"));
+        environment.variables.push(EnvironmentVariable { name: String::from("TOOL_HOME"), value: String::from("/it's"), mode: VariableMode::Set });
+
+        assert_eq!(Shell::Bash.to_activation_script(&environment), concat!(
+            "if [ -z \"${_PACKSTER_ACTIVE_PATH+x}\" ]; then if [ -n \"${PATH+x}\" ]; then _PACKSTER_OLD_PATH=\"$PATH\"; export _PACKSTER_OLD_PATH; fi; ",
+            "_PACKSTER_ACTIVE_PATH=1; export _PACKSTER_ACTIVE_PATH; fi\n",
+            "case \":${PATH:-}:\" in *:'/my location/bin':*) ;; *) PATH='/my location/bin'\"${PATH:+:$PATH}\"; export PATH;; esac\n",
+            "if [ -z \"${_PACKSTER_ACTIVE_TOOL_HOME+x}\" ]; then if [ -n \"${TOOL_HOME+x}\" ]; then _PACKSTER_OLD_TOOL_HOME=\"$TOOL_HOME\"; export _PACKSTER_OLD_TOOL_HOME; fi; ",
+            "_PACKSTER_ACTIVE_TOOL_HOME=1; export _PACKSTER_ACTIVE_TOOL_HOME; fi\n",
+            "TOOL_HOME='/it'\\''s'; export TOOL_HOME\n",
+            "hash -r 2>/dev/null || true\n",
+        ));
+        assert!(Shell::Zsh.to_activation_script(&environment).ends_with("export TOOL_HOME\nrehash\n"));
+        assert_eq!(Shell::Posix.to_deactivation_script(&environment), concat!(
+            "if [ -n \"${_PACKSTER_ACTIVE_PATH+x}\" ]; then ",
+            "if [ -n \"${_PACKSTER_OLD_PATH+x}\" ]; then PATH=\"$_PACKSTER_OLD_PATH\"; export PATH; unset _PACKSTER_OLD_PATH; else unset PATH; fi; ",
+            "unset _PACKSTER_ACTIVE_PATH; fi\n",
+            "if [ -n \"${_PACKSTER_ACTIVE_TOOL_HOME+x}\" ]; then ",
+            "if [ -n \"${_PACKSTER_OLD_TOOL_HOME+x}\" ]; then TOOL_HOME=\"$_PACKSTER_OLD_TOOL_HOME\"; export TOOL_HOME; unset _PACKSTER_OLD_TOOL_HOME; else unset TOOL_HOME; fi; ",
+            "unset _PACKSTER_ACTIVE_TOOL_HOME; fi\n",
+            "hash -r 2>/dev/null || true\n",
+        ));
+    }
+
+    #[test]
+    fn test_fish_scripts() {
+        let mut environment = LocationEnvironment::new(Path::new("/location/bin"));
+        environment.variables.push(EnvironmentVariable { name: String::from("TOOL_HOME"), value: String::from("/it's"), mode: VariableMode::Set });
+
+        assert_eq!(Shell::Fish.to_activation_script(&environment), concat!(
+            "if not set -q _PACKSTER_ACTIVE_PATH; if set -q PATH; set -gx _PACKSTER_OLD_PATH $PATH; end; set -gx _PACKSTER_ACTIVE_PATH 1; end\n",
+            "if not contains -- '/location/bin' $PATH; set -gx PATH '/location/bin' $PATH; end\n",
+            "if not set -q _PACKSTER_ACTIVE_TOOL_HOME; if set -q TOOL_HOME; set -gx _PACKSTER_OLD_TOOL_HOME $TOOL_HOME; end; set -gx _PACKSTER_ACTIVE_TOOL_HOME 1; end\n",
+            "set -gx TOOL_HOME '/it\\'s'\n",
+        ));
+        assert_eq!(Shell::Fish.to_deactivation_script(&environment), concat!(
+            "if set -q _PACKSTER_ACTIVE_PATH; ",
+            "if set -q _PACKSTER_OLD_PATH; set -gx PATH $_PACKSTER_OLD_PATH; set -e _PACKSTER_OLD_PATH; else; set -e PATH; end; ",
+            "set -e _PACKSTER_ACTIVE_PATH; end\n",
+            "if set -q _PACKSTER_ACTIVE_TOOL_HOME; ",
+            "if set -q _PACKSTER_OLD_TOOL_HOME; set -gx TOOL_HOME $_PACKSTER_OLD_TOOL_HOME; set -e _PACKSTER_OLD_TOOL_HOME; else; set -e TOOL_HOME; end; ",
+            "set -e _PACKSTER_ACTIVE_TOOL_HOME; end\n",
+        ));
+    }
+}
@@ -7,7 +7,7 @@ use crate::{
     packaging::application::AsLocationPath,
     Error, Result,
 };
-use super::{AsLocation, AsPackage, DeployLocation, Deployment, Package};
+use super::{resolve_deployed_dependencies, AsDeployment, AsLocation, AsPackage, DeployLocation, Deployment, Package, Resolution};
 
 pub struct NotYetDeployed<S> {
     pub previous_state: S,
@@ -15,12 +15,12 @@ pub struct NotYetDeployed<S> {
 
 impl<S, R> Operation<S, R>
 where
-    Self: AsPackage + AsLocation,
+    Self: AsPackage + AsDeployment + AsLocation,
 {
     pub fn probe_package_not_deployed_in_location(self) -> Result<Operation<NotYetDeployed<S>, R>> {
         if self
             .as_location()
-            .is_checksum_deployed(self.as_package().as_checksum())
+            .is_deployed(self.as_deployment().as_id())
         {
             Err(Error::PackageAlreadyDeployedInLocation(
                 self.as_package().as_identifier().to_string(),
@@ -48,6 +48,51 @@ impl<S: AsLocation> AsLocation for NotYetDeployed<S> {
     }
 }
 
+impl<S: AsDeployment> AsDeployment for NotYetDeployed<S> {
+    fn as_deployment(&self) -> &Deployment {
+        self.previous_state.as_deployment()
+    }
+}
+
+pub struct DeployedDependencies<S> {
+    pub previous_state: S,
+    pub resolution: Resolution,
+}
+
+impl<S, R> Operation<S, R>
+where
+    Self: AsDeployment + AsLocation,
+{
+    pub fn probe_dependencies_deployed_in_location(self) -> Result<Operation<DeployedDependencies<S>, R>> {
+        let resolution = resolve_deployed_dependencies(self.as_location(), self.as_deployment())?;
+        Self::ok_with_state(
+            self.request,
+            DeployedDependencies {
+                previous_state: self.state,
+                resolution,
+            },
+        )
+    }
+}
+
+impl<S: AsPackage> AsPackage for DeployedDependencies<S> {
+    fn as_package(&self) -> &Package {
+        self.previous_state.as_package()
+    }
+}
+
+impl<S: AsLocation> AsLocation for DeployedDependencies<S> {
+    fn as_location(&self) -> &DeployLocation {
+        self.previous_state.as_location()
+    }
+}
+
+impl<S: AsDeployment> AsDeployment for DeployedDependencies<S> {
+    fn as_deployment(&self) -> &Deployment {
+        self.previous_state.as_deployment()
+    }
+}
+
 pub struct AlreadyDeployed<S> {
     pub previous_state: S,
     pub existing_deployment: Deployment,
@@ -102,8 +147,7 @@ where
     Self: AsChecksum + AsLocationPath,
 {
     pub fn guess_deployment_path(self) -> Operation<DeploymentPath<S>, R> {
-        let checksum_string = self.as_checksum().to_string();
-        let deployment_path = self.as_location_path().join(checksum_string);
+        let deployment_path = self.to_deployment_path(self.as_checksum());
         Self::with_state(
             self.request,
             DeploymentPath {
@@ -125,3 +169,46 @@ impl<S: AsLocation> AsLocation for DeploymentPath<S> {
         self.previous_state.as_location()
     }
 }
+
+impl<S: AsDeployment> AsDeployment for DeploymentPath<S> {
+    fn as_deployment(&self) -> &Deployment {
+        self.previous_state.as_deployment()
+    }
+}
+
+pub struct NoResourceConflict<S> {
+    pub previous_state: S,
+}
+
+impl<S, R> Operation<S, R>
+where
+    Self: AsDeployment + AsLocation,
+{
+    pub fn probe_no_resource_conflict(self) -> Result<Operation<NoResourceConflict<S>, R>> {
+        self.as_location().probe_resource_conflict(self.as_deployment())?;
+        Self::ok_with_state(
+            self.request,
+            NoResourceConflict {
+                previous_state: self.state,
+            },
+        )
+    }
+}
+
+impl<S: AsPackage> AsPackage for NoResourceConflict<S> {
+    fn as_package(&self) -> &Package {
+        self.previous_state.as_package()
+    }
+}
+
+impl<S: AsLocation> AsLocation for NoResourceConflict<S> {
+    fn as_location(&self) -> &DeployLocation {
+        self.previous_state.as_location()
+    }
+}
+
+impl<S: AsDeployment> AsDeployment for NoResourceConflict<S> {
+    fn as_deployment(&self) -> &Deployment {
+        self.previous_state.as_deployment()
+    }
+}
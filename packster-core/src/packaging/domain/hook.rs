@@ -0,0 +1,162 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    Pack,
+    Deploy,
+    Upgrade,
+    Undeploy,
+}
+
+impl fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookEvent::Pack => "pack".fmt(f),
+            HookEvent::Deploy => "deploy".fmt(f),
+            HookEvent::Upgrade => "upgrade".fmt(f),
+            HookEvent::Undeploy => "undeploy".fmt(f),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawHook {
+    CommandLine(String),
+    Detailed {
+        command: String,
+        #[serde(default)]
+        arguments: Vec<String>,
+        timeout: Option<u64>,
+    },
+}
+
+/* Either `deploy = "sh hooks/deploy.sh"` ( split on whitespaces ) or a table with a command, its arguments and a timeout in seconds */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(from = "RawHook")]
+pub struct Hook {
+    command: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    arguments: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timeout: Option<u64>,
+}
+
+impl From<RawHook> for Hook {
+    fn from(value: RawHook) -> Self {
+        match value {
+            RawHook::CommandLine(command_line) => {
+                let mut words = command_line.split_whitespace().map(String::from);
+                Hook {
+                    command: words.next().unwrap_or_default(),
+                    arguments: words.collect(),
+                    timeout: None,
+                }
+            }
+            RawHook::Detailed { command, arguments, timeout } => Hook { command, arguments, timeout },
+        }
+    }
+}
+
+impl Hook {
+    pub fn as_command(&self) -> &str { &self.command }
+    pub fn as_arguments(&self) -> &[String] { &self.arguments }
+    pub fn as_timeout(&self) -> Option<u64> { self.timeout }
+
+    fn with_argument(&self, argument: String) -> Hook {
+        let mut hook = self.clone();
+        hook.arguments.push(argument);
+        hook
+    }
+}
+
+/* Hooks declared in the [hooks] section of a project manifest, executed with the package content as working directory */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
+pub struct Hooks {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pack: Option<Hook>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deploy: Option<Hook>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    upgrade: Option<Hook>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    undeploy: Option<Hook>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    always: Option<Hook>,
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self == &Hooks::default()
+    }
+
+    /* An upgrade executes the upgrade hook in place of the deploy one, when the package declares it */
+    pub fn to_upgrade_event(&self) -> HookEvent {
+        if self.upgrade.is_some() { HookEvent::Upgrade } else { HookEvent::Deploy }
+    }
+
+    /* The hook of the event followed by the `always` hook, which is given the event as last argument */
+    pub fn to_event_hooks(&self, event: HookEvent) -> Vec<Hook> {
+        let hook = match event {
+            HookEvent::Pack => &self.pack,
+            HookEvent::Deploy => &self.deploy,
+            HookEvent::Upgrade => &self.upgrade,
+            HookEvent::Undeploy => &self.undeploy,
+        };
+        hook.iter()
+            .cloned()
+            .chain(self.always.iter().map(|always| always.with_argument(event.to_string())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use super::*;
+
+    #[test]
+    fn test_event_hooks() {
+        let hooks: Hooks = serde_json::from_value(serde_json::json!({
+            "deploy": "sh hooks/deploy.sh --verbose",
+            "always": { "command": "python", "arguments": ["hooks/always.py"], "timeout": 10 }
+        })).unwrap();
+
+        let deploy_hooks = hooks.to_event_hooks(HookEvent::Deploy);
+        assert_eq!(deploy_hooks.len(), 2);
+        assert_eq!(deploy_hooks[0].as_command(), "sh");
+        assert_eq!(deploy_hooks[0].as_arguments(), ["hooks/deploy.sh", "--verbose"]);
+        assert_eq!(deploy_hooks[1].as_command(), "python");
+        assert_eq!(deploy_hooks[1].as_arguments(), ["hooks/always.py", "deploy"]);
+        assert_eq!(deploy_hooks[1].as_timeout(), Some(10));
+
+        let pack_hooks = hooks.to_event_hooks(HookEvent::Pack);
+        assert_eq!(pack_hooks.len(), 1);
+        assert_eq!(pack_hooks[0].as_arguments(), ["hooks/always.py", "pack"]);
+
+        assert!(Hooks::default().to_event_hooks(HookEvent::Undeploy).is_empty());
+        assert_eq!(hooks.to_upgrade_event(), HookEvent::Deploy);
+    }
+
+    #[test]
+    fn test_upgrade_hook_replaces_deploy_hook() {
+        let hooks: Hooks = serde_json::from_value(serde_json::json!({
+            "deploy": "sh hooks/deploy.sh",
+            "upgrade": "sh hooks/upgrade.sh"
+        })).unwrap();
+
+        assert_eq!(hooks.to_upgrade_event(), HookEvent::Upgrade);
+        let upgrade_hooks = hooks.to_event_hooks(HookEvent::Upgrade);
+        assert_eq!(upgrade_hooks.len(), 1);
+        assert_eq!(upgrade_hooks[0].as_arguments(), ["hooks/upgrade.sh"]);
+    }
+
+    #[test]
+    fn test_hooks_serialization_reciprocity() {
+        let hooks: Hooks = serde_json::from_value(serde_json::json!({ "undeploy": "sh hooks/undeploy.sh" })).unwrap();
+        let serialized = serde_json::to_value(&hooks).unwrap();
+        assert_eq!(serialized, serde_json::json!({ "undeploy": { "command": "sh", "arguments": ["hooks/undeploy.sh"] } }));
+        assert_eq!(serde_json::from_value::<Hooks>(serialized).unwrap(), hooks);
+    }
+}
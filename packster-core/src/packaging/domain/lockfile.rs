@@ -0,0 +1,154 @@
+use serde::Deserialize;
+
+use crate::domain::entity::{Checksum, Parameters};
+use super::{DeployLocation, Dependent, Deployment, InstalledBundle, Package};
+
+/* Version of the lockfile layout written by this packster, to be increased along with a migration from the previous one */
+pub const LOCKFILE_SCHEMA_VERSION: u32 = 4;
+
+/* Lockfiles written before versioning have no schema version, which is 0 */
+#[derive(Deserialize)]
+pub struct LockfileSchemaVersion {
+    #[serde(default)]
+    schema_version: u32,
+}
+
+impl LockfileSchemaVersion {
+    pub fn as_u32(&self) -> u32 {
+        self.schema_version
+    }
+}
+
+/* Each older lockfile layout is migrated into the next one, up to the current DeployLocation */
+
+/* Schema 0 : deployments of packages without parameters may be identified by their package checksum only */
+#[derive(Deserialize)]
+pub struct LockfileV0 {
+    deployments: Vec<DeploymentV0>,
+    #[serde(default)]
+    bundles: Vec<InstalledBundle>,
+}
+
+#[derive(Deserialize)]
+struct DeploymentV0 {
+    id: Option<Checksum>,
+    #[serde(flatten)]
+    package: Package,
+    #[serde(default)]
+    dependents: Vec<Dependent>,
+    #[serde(default)]
+    parameters: Parameters,
+}
+
+impl From<LockfileV0> for LockfileV1 {
+    fn from(lockfile: LockfileV0) -> Self {
+        let deployments = lockfile
+            .deployments
+            .into_iter()
+            .map(|deployment| {
+                let id = deployment.id.unwrap_or_else(|| deployment.package.as_checksum().clone());
+                Deployment::from_parts(id, deployment.package, deployment.dependents, deployment.parameters)
+            })
+            .collect();
+        LockfileV1(DeployLocation::new(deployments, lockfile.bundles))
+    }
+}
+
+/* Schema 1 : deployments have no file manifest, which is then left empty */
+#[derive(Deserialize)]
+#[serde(transparent)]
+pub struct LockfileV1(DeployLocation);
+
+impl From<LockfileV1> for LockfileV2 {
+    fn from(lockfile: LockfileV1) -> Self {
+        LockfileV2(lockfile.0)
+    }
+}
+
+/* Schema 2 : no deployment is active, the newest deployment of each identifier then is */
+#[derive(Deserialize)]
+#[serde(transparent)]
+pub struct LockfileV2(DeployLocation);
+
+impl From<LockfileV2> for LockfileV3 {
+    fn from(lockfile: LockfileV2) -> Self {
+        LockfileV3(lockfile.0.with_latest_deployments_activated())
+    }
+}
+
+/* Schema 3 : no resource is claimed, each deployment then claims the ones of its package */
+#[derive(Deserialize)]
+#[serde(transparent)]
+pub struct LockfileV3(DeployLocation);
+
+impl From<LockfileV3> for DeployLocation {
+    fn from(lockfile: LockfileV3) -> Self {
+        lockfile.0.with_resources_claimed().with_current_schema_version()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use super::*;
+    use crate::packaging::domain::Resource;
+
+    #[test]
+    fn test_lockfile_without_schema_version_is_version_0() {
+        let version: LockfileSchemaVersion = serde_json::from_str(r#"{"deployments":[]}"#).unwrap();
+        assert_eq!(version.as_u32(), 0);
+    }
+
+    #[test]
+    fn test_migrated_deployment_without_identifier_is_identified_by_its_checksum() {
+        let lockfile: LockfileV0 = serde_json::from_value(serde_json::json!({
+            "deployments": [
+                { "identifier": "lib", "version": "1.0.0", "checksum": "aa", "packster_version": "0.1.0",
+                  "dependents": [{ "identifier": "app", "checksum": "bb", "requirement": "^1" }] },
+                { "id": "cc", "identifier": "app", "version": "1.0.0", "checksum": "bb", "packster_version": "0.1.0" }
+            ]
+        })).unwrap();
+        let location = DeployLocation::from(LockfileV3::from(LockfileV2::from(LockfileV1::from(lockfile))));
+
+        let lib = &location.as_slice()[0];
+        assert_eq!(location.as_schema_version(), LOCKFILE_SCHEMA_VERSION);
+        assert_eq!(lib.as_id(), lib.as_checksum());
+        assert_eq!(lib.as_dependents()[0].as_deployment_id().to_string(), "bb");
+        assert_eq!(lib.as_installed_packster_version(), None);
+        assert!(lib.as_files().is_empty());
+        assert_eq!(location.as_slice()[1].as_id().to_string(), "cc");
+    }
+
+    #[test]
+    fn test_migrated_location_activates_newest_deployments() {
+        let lockfile: LockfileV2 = serde_json::from_value(serde_json::json!({
+            "schema_version": 2,
+            "deployments": [
+                { "id": "aa", "identifier": "lib", "version": "1.1.0", "checksum": "aa", "packster_version": "0.1.0" },
+                { "id": "bb", "identifier": "lib", "version": "1.2.0", "checksum": "bb", "packster_version": "0.1.0" },
+                { "id": "cc", "identifier": "app", "version": "1.0.0", "checksum": "cc", "packster_version": "0.1.0" }
+            ]
+        })).unwrap();
+        let location = DeployLocation::from(LockfileV3::from(lockfile));
+
+        assert!(!location.is_active(&Checksum::from_str("aa").unwrap()));
+        assert!(location.is_active(&Checksum::from_str("bb").unwrap()));
+        assert!(location.is_active(&Checksum::from_str("cc").unwrap()));
+    }
+
+    #[test]
+    fn test_migrated_location_claims_package_resources() {
+        let lockfile: LockfileV3 = serde_json::from_value(serde_json::json!({
+            "schema_version": 3,
+            "deployments": [
+                { "id": "aa", "identifier": "server", "version": "1.0.0", "checksum": "aa", "packster_version": "0.1.0",
+                  "shims": { "server": "bin/server" }, "provides": { "ports": [8080] } }
+            ]
+        })).unwrap();
+        let location = DeployLocation::from(lockfile);
+
+        let resources: Vec<&Resource> = location.as_claims().iter().map(|claim| claim.as_resource()).collect();
+        assert_eq!(resources, vec![&Resource::Shim("server".to_string()), &Resource::Port(8080)]);
+        assert!(location.as_claims().iter().all(|claim| claim.as_deployment_id().to_string() == "aa"));
+    }
+}
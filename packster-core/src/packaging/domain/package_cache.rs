@@ -0,0 +1,177 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::entity::{Checksum, Identifier, Version};
+use super::{to_package_file_name, Package};
+
+/* Package files kept on the host, each stored once in a directory named after its checksum,
+ * so that every location can reuse them without fetching them again */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
+pub struct PackageCache {
+    #[serde(default)]
+    packages: Vec<CachedPackage>,
+}
+
+impl PackageCache {
+    pub fn new(mut packages: Vec<CachedPackage>) -> Self {
+        packages.sort_by_key(|package| package.checksum.to_string());
+        PackageCache { packages }
+    }
+
+    pub fn find(&self, checksum: &Checksum) -> Option<&CachedPackage> {
+        self.packages.iter().find(|package| &package.checksum == checksum)
+    }
+
+    /* Replaces the package of the same checksum, if any */
+    pub fn with_package(&self, package: CachedPackage) -> PackageCache {
+        let mut packages: Vec<CachedPackage> = self.packages.iter()
+            .filter(|cached_package| cached_package.checksum != package.checksum)
+            .cloned()
+            .collect();
+        packages.push(package);
+        PackageCache::new(packages)
+    }
+
+    pub fn without_packages(&self, packages: &[CachedPackage]) -> PackageCache {
+        PackageCache::new(
+            self.packages.iter()
+                .filter(|cached_package| packages.iter().all(|package| package.checksum != cached_package.checksum))
+                .cloned()
+                .collect()
+        )
+    }
+
+    pub fn iter_packages(&self) -> impl Iterator<Item = &CachedPackage> {
+        self.packages.iter()
+    }
+
+    pub fn to_size(&self) -> u64 {
+        self.packages.iter().map(CachedPackage::as_size).sum()
+    }
+
+    /* Packages not used since the given time, then the least recently used ones until the cache fits in the given size */
+    pub fn to_evicted_packages(&self, unused_since: Option<u64>, max_size: Option<u64>) -> Vec<CachedPackage> {
+        let mut packages: Vec<&CachedPackage> = self.packages.iter().collect();
+        packages.sort_by_key(|package| (package.last_used_at, package.checksum.to_string()));
+        let mut size = self.to_size();
+        packages.into_iter()
+            .take_while(|package| {
+                let is_unused = unused_since.is_some_and(|time| package.last_used_at < time);
+                let is_oversized = max_size.is_some_and(|max_size| size > max_size);
+                if is_unused || is_oversized {
+                    size -= package.size;
+                }
+                is_unused || is_oversized
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/* A package file of the cache, along with the last time it was stored or reused */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CachedPackage {
+    identifier: Identifier,
+    version: Version,
+    checksum: Checksum,
+    packster_version: Version,
+    size: u64,
+    last_used_at: u64,
+}
+
+impl CachedPackage {
+    pub fn new(package: &Package, size: u64, last_used_at: u64) -> Self {
+        CachedPackage {
+            identifier: package.as_identifier().clone(),
+            version: package.as_version().clone(),
+            checksum: package.as_checksum().clone(),
+            packster_version: package.as_packster_version().clone(),
+            size,
+            last_used_at,
+        }
+    }
+
+    pub fn as_identifier(&self) -> &Identifier { &self.identifier }
+    pub fn as_version(&self) -> &Version { &self.version }
+    pub fn as_checksum(&self) -> &Checksum { &self.checksum }
+    pub fn as_size(&self) -> u64 { self.size }
+    pub fn as_last_used_at(&self) -> u64 { self.last_used_at }
+
+    pub fn used_at(&self, time: u64) -> CachedPackage {
+        CachedPackage { last_used_at: time, ..self.clone() }
+    }
+
+    pub fn to_file_name(&self) -> String {
+        to_package_file_name(&self.identifier, &self.version, &self.checksum, &self.packster_version)
+    }
+
+    /* Path of the package file relative to the cache, using slashes as separators */
+    pub fn to_path(&self) -> String {
+        format!("{}/{}", self.checksum, self.to_file_name())
+    }
+}
+
+/* A difference between the cache index and the package files it lists */
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "issue", rename_all = "snake_case")]
+pub enum CacheIssue {
+    MissingFile { checksum: Checksum, path: String },
+    ModifiedFile { checksum: Checksum, path: String },
+}
+
+impl CacheIssue {
+    pub fn as_checksum(&self) -> &Checksum {
+        match self {
+            CacheIssue::MissingFile { checksum, .. } | CacheIssue::ModifiedFile { checksum, .. } => checksum,
+        }
+    }
+}
+
+impl fmt::Display for CacheIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use CacheIssue::*;
+        match self {
+            MissingFile { path, .. } => write!(f, "Cached package {path} is missing"),
+            ModifiedFile { path, .. } => write!(f, "Cached package {path} does not match its checksum"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use crate::Result;
+    use super::*;
+
+    const CACHE: &str = r#"{"packages":[
+        {"identifier":"tool","version":"1.0.0","checksum":"bb","packster_version":"0.1.0","size":30,"last_used_at":100},
+        {"identifier":"tool","version":"1.2.0","checksum":"cc","packster_version":"0.1.0","size":20,"last_used_at":300},
+        {"identifier":"lib","version":"0.1.0","checksum":"aa","packster_version":"0.1.0","size":10,"last_used_at":200}
+    ]}"#;
+
+    fn to_checksums(packages: &[CachedPackage]) -> Vec<String> {
+        packages.iter().map(|package| package.as_checksum().to_string()).collect()
+    }
+
+    #[test]
+    fn test_evict_cached_packages() -> Result<()> {
+        let cache: PackageCache = serde_json::from_str(CACHE).unwrap();
+        assert_eq!(cache.to_size(), 60);
+        let checksum = Checksum::from_str("aa")?;
+        assert_eq!(cache.find(&checksum).unwrap().to_path(), "aa/lib_0.1.0_aa.302e312e30.packster");
+
+        assert!(cache.to_evicted_packages(None, None).is_empty());
+        assert_eq!(to_checksums(&cache.to_evicted_packages(Some(250), None)), ["bb", "aa"]);
+        assert_eq!(to_checksums(&cache.to_evicted_packages(None, Some(30))), ["bb"]);
+        assert_eq!(to_checksums(&cache.to_evicted_packages(None, Some(25))), ["bb", "aa"]);
+        assert_eq!(to_checksums(&cache.to_evicted_packages(Some(150), Some(60))), ["bb"]);
+
+        let cache = cache.without_packages(&cache.to_evicted_packages(Some(150), None));
+        assert_eq!(cache.to_size(), 30);
+        let checksum = Checksum::from_str("cc")?;
+        let cache = cache.with_package(cache.find(&checksum).unwrap().used_at(400));
+        assert_eq!(cache.iter_packages().count(), 2);
+        assert_eq!(cache.find(&checksum).map(CachedPackage::as_last_used_at), Some(400));
+        Ok(())
+    }
+}
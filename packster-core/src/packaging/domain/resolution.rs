@@ -0,0 +1,327 @@
+use std::collections::{BTreeSet, VecDeque};
+use serde::{Deserialize, Serialize};
+use crate::{
+    domain::entity::{Checksum, Identifier, PackageRequirement, Parameters, VersionRequirement},
+    Error, Result,
+};
+use super::{DeployLocation, Deployment, DeploymentIdentifier, IndexedPackage, Package, SourceIndex};
+
+/* A deployment requiring another one, recorded in the lockfile on the required deployment */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Dependent {
+    identifier: Identifier,
+    #[serde(alias = "checksum")]
+    deployment_id: Checksum,
+    requirement: VersionRequirement,
+}
+
+impl Dependent {
+    pub fn new(deployment: &Deployment, requirement: VersionRequirement) -> Self {
+        Dependent {
+            identifier: deployment.as_ref().as_identifier().clone(),
+            deployment_id: deployment.as_id().clone(),
+            requirement,
+        }
+    }
+
+    pub fn as_identifier(&self) -> &Identifier { &self.identifier }
+    pub fn as_deployment_id(&self) -> &Checksum { &self.deployment_id }
+    pub fn as_requirement(&self) -> &VersionRequirement { &self.requirement }
+}
+
+#[derive(Default, Debug)]
+pub struct Resolution {
+    deployments: Vec<Deployment>,
+    dependents: Vec<(Checksum, Dependent)>,
+}
+
+impl Resolution {
+    /* Deployments to add in addition to the resolved one */
+    pub fn as_deployments(&self) -> &[Deployment] { &self.deployments }
+
+    /* Dependents to record, keyed by the identifier of the deployment they require */
+    pub fn iter_dependents(&self) -> impl Iterator<Item = (&Checksum, &Dependent)> {
+        self.dependents.iter().map(|(id, dependent)| (id, dependent))
+    }
+}
+
+/* Each dependency is satisfied by the newest matching deployment already in the location ( or already planned ),
+ * otherwise by the newest matching available package, deployed with its default parameters. Requirements are never unified,
+ * so incompatible requirements on the same identifier lead to several versions deployed side by side */
+pub fn resolve_dependencies<I: DeploymentIdentifier>(
+    location: &DeployLocation,
+    deployment: &Deployment,
+    available_packages: &[Package],
+    identifier: &I,
+) -> Result<Resolution> {
+    let mut planned = vec![deployment.clone()];
+    let mut dependents = Vec::new();
+    let mut queue = VecDeque::from([deployment.clone()]);
+
+    while let Some(dependent_deployment) = queue.pop_front() {
+        let dependent_package = dependent_deployment.as_ref();
+        for (package_identifier, requirement) in dependent_package.as_dependencies() {
+            let dependent = Dependent::new(&dependent_deployment, requirement.clone());
+            let is_matching = |candidate: &&Package| candidate.as_identifier() == package_identifier && requirement.matches(candidate.as_version());
+
+            let satisfying = location.find_matching(package_identifier, requirement)
+                .chain(planned.iter().filter(|deployment| is_matching(&deployment.as_ref())))
+                .max_by(|left, right| left.as_version().cmp(right.as_version()));
+
+            if let Some(satisfying) = satisfying {
+                dependents.push((satisfying.as_id().clone(), dependent));
+                continue;
+            }
+
+            let candidate = available_packages.iter()
+                .filter(is_matching)
+                .max_by(|left, right| left.as_version().cmp(right.as_version()))
+                .ok_or_else(|| Error::UnresolvableDependency {
+                    dependent: format!("{}@{}", dependent_package.as_identifier(), dependent_package.as_version()),
+                    requirement: PackageRequirement::new(package_identifier.clone(), requirement.clone()).to_string(),
+                })?;
+            let candidate = Deployment::identify(candidate.clone(), Parameters::new(), identifier)?;
+
+            dependents.push((candidate.as_id().clone(), dependent));
+            planned.push(candidate.clone());
+            queue.push_back(candidate);
+        }
+    }
+
+    planned.remove(0);
+    Ok(Resolution { deployments: planned, dependents })
+}
+
+/* Deployments planned by a resolution without digester are identified by their package checksum */
+struct DeployedOnly;
+
+impl DeploymentIdentifier for DeployedOnly {
+    fn identify_deployment(&self, package: &Package, _parameters: &Parameters) -> Result<Checksum> {
+        Ok(package.as_checksum().clone())
+    }
+}
+
+/* Every dependency has to be satisfied by a deployment already in the location */
+pub fn resolve_deployed_dependencies(location: &DeployLocation, deployment: &Deployment) -> Result<Resolution> {
+    resolve_dependencies(location, deployment, &[], &DeployedOnly)
+}
+
+/* The newest indexed package matching the requirement and carrying every label, the first source winning between equal versions */
+pub fn select_indexed_package<'a>(
+    indexes: &'a [SourceIndex],
+    identifier: &Identifier,
+    requirement: &VersionRequirement,
+    labels: &BTreeSet<String>,
+) -> Option<(usize, &'a IndexedPackage)> {
+    let mut selected: Option<(usize, &IndexedPackage)> = None;
+    for (source, index) in indexes.iter().enumerate() {
+        let candidates = index.iter_packages().filter(|package| {
+            package.as_identifier() == identifier
+                && requirement.matches(package.as_version())
+                && labels.is_subset(package.as_labels())
+        });
+        for candidate in candidates {
+            if selected.is_none_or(|(_, package)| candidate.as_version() > package.as_version()) {
+                selected = Some((source, candidate));
+            }
+        }
+    }
+    selected
+}
+
+/* Packages to install, dependencies first, along with the source providing them : the selected package, and the dependencies
+ * resolved for it against the location and the packages carrying every label, the first source winning between equal versions */
+pub fn plan_installation(
+    location: &DeployLocation,
+    indexes: &[SourceIndex],
+    selected: (usize, &IndexedPackage),
+    labels: &BTreeSet<String>,
+) -> Result<Vec<(usize, IndexedPackage)>> {
+    let mut candidates: Vec<(usize, &IndexedPackage)> = Vec::new();
+    for (source, index) in indexes.iter().enumerate() {
+        for package in index.iter_packages().filter(|package| labels.is_subset(package.as_labels())) {
+            let is_listed = candidates.iter()
+                .any(|(_, candidate)| candidate.as_identifier() == package.as_identifier() && candidate.as_version() == package.as_version());
+            if !is_listed {
+                candidates.push((source, package));
+            }
+        }
+    }
+    let available_packages: Vec<Package> = candidates.iter().map(|(_, package)| Package::from_indexed(package)).collect();
+    let resolution = resolve_dependencies(location, &Deployment::new(Package::from_indexed(selected.1)), &available_packages, &DeployedOnly)?;
+
+    // A dependency is only deployed once every dependency it requires among the planned ones is, circular ones being kept in resolution order
+    let mut remaining: Vec<&Deployment> = resolution.as_deployments().iter().collect();
+    let mut planned = Vec::new();
+    while !remaining.is_empty() {
+        let position = remaining.iter()
+            .position(|deployment| !resolution.iter_dependents().any(|(required_id, dependent)| {
+                dependent.as_deployment_id() == deployment.as_id() && remaining.iter().any(|remaining| remaining.as_id() == required_id)
+            }))
+            .unwrap_or(0);
+        let deployment = remaining.remove(position);
+        let planned_package = candidates.iter().find(|(_, package)| package.as_checksum() == deployment.as_checksum());
+        if let Some((source, package)) = planned_package {
+            planned.push((*source, (*package).clone()));
+        }
+    }
+    planned.push((selected.0, selected.1.clone()));
+    Ok(planned)
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use pretty_assertions::assert_eq;
+    use super::{*, super::Dependencies};
+
+    fn package(identifier: &str, version: &str, checksum: &str, dependencies: &[(&str, &str)]) -> Package {
+        let dependencies: Dependencies = dependencies.iter()
+            .map(|(identifier, requirement)| (Identifier::from_str(identifier).unwrap(), VersionRequirement::from_str(requirement).unwrap()))
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "identifier": identifier,
+            "version": version,
+            "checksum": checksum,
+            "packster_version": "0.1.0",
+            "dependencies": dependencies
+        })).unwrap()
+    }
+
+    fn resolve(location: &DeployLocation, package: &Package, available_packages: &[Package]) -> Result<Resolution> {
+        resolve_dependencies(location, &Deployment::new(package.clone()), available_packages, &DeployedOnly)
+    }
+
+    fn describe(resolution: &Resolution) -> (Vec<String>, Vec<String>) {
+        (
+            resolution.as_deployments().iter()
+                .map(|deployment| format!("{}@{}", deployment.as_ref().as_identifier(), deployment.as_version()))
+                .collect(),
+            resolution.iter_dependents()
+                .map(|(id, dependent)| format!("{} -> {}", dependent.as_identifier(), id))
+                .collect()
+        )
+    }
+
+    #[test]
+    fn test_resolve_no_dependencies() -> Result<()> {
+        let resolution = resolve(&DeployLocation::default(), &package("app", "1.0.0", "aa", &[]), &[])?;
+        assert_eq!(describe(&resolution), (vec![], vec![]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_diamond_deploys_side_by_side() -> Result<()> {
+        let app = package("app", "1.0.0", "aa", &[("lib", "^1"), ("tool", "^1")]);
+        let available = [
+            package("lib", "1.1.0", "b1", &[]),
+            package("lib", "1.4.0", "b4", &[]),
+            package("lib", "2.0.0", "b2", &[]),
+            package("tool", "1.0.0", "cc", &[("lib", "^2")]),
+        ];
+
+        let resolution = resolve(&DeployLocation::default(), &app, &available)?;
+
+        assert_eq!(
+            describe(&resolution),
+            (
+                vec!["lib@1.4.0".to_string(), "tool@1.0.0".to_string(), "lib@2.0.0".to_string()],
+                vec!["app -> b4".to_string(), "app -> cc".to_string(), "tool -> b2".to_string()]
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_prefers_deployed_packages() -> Result<()> {
+        let mut location = DeployLocation::default();
+        location.add_deployment(Deployment::new(package("lib", "1.1.0", "b1", &[])));
+        let app = package("app", "1.0.0", "aa", &[("lib", "^1")]);
+
+        let resolution = resolve(&location, &app, &[package("lib", "1.4.0", "b4", &[])])?;
+
+        assert_eq!(describe(&resolution), (vec![], vec!["app -> b1".to_string()]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_circular_dependencies() -> Result<()> {
+        let app = package("app", "1.0.0", "aa", &[("lib", "^1")]);
+        let available = [package("lib", "1.0.0", "bb", &[("app", "^1")])];
+
+        let resolution = resolve(&DeployLocation::default(), &app, &available)?;
+
+        assert_eq!(
+            describe(&resolution),
+            (vec!["lib@1.0.0".to_string()], vec!["app -> bb".to_string(), "lib -> aa".to_string()])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_unresolvable_dependency() {
+        let app = package("app", "1.0.0", "aa", &[("lib", "^2")]);
+        let result = resolve(&DeployLocation::default(), &app, &[package("lib", "1.0.0", "bb", &[])]);
+
+        assert!(matches!(result, Err(Error::UnresolvableDependency { .. })));
+    }
+
+    /* identifier, version, labels, dependencies */
+    type IndexedFixture<'a> = (&'a str, &'a str, &'a [&'a str], &'a [(&'a str, &'a str)]);
+
+    fn index(packages: &[IndexedFixture]) -> SourceIndex {
+        SourceIndex::new(
+            packages.iter()
+                .map(|(identifier, version, labels, dependencies)| serde_json::from_value(serde_json::json!({
+                    "identifier": identifier,
+                    "version": version,
+                    "checksum": hex::encode(format!("{identifier}{version}")),
+                    "packster_version": "0.1.0",
+                    "size": 0,
+                    "labels": labels,
+                    "dependencies": dependencies.iter().cloned().collect::<std::collections::BTreeMap<_, _>>()
+                })).unwrap())
+                .collect()
+        )
+    }
+
+    fn plan(location: &DeployLocation, indexes: &[SourceIndex], requirement: &str, labels: &[&str]) -> Result<Vec<String>> {
+        let requirement = PackageRequirement::from_str(requirement)?;
+        let labels = labels.iter().map(ToString::to_string).collect();
+        let selected = select_indexed_package(indexes, requirement.as_identifier(), requirement.as_version_requirement(), &labels).unwrap();
+        Ok(
+            plan_installation(location, indexes, selected, &labels)?
+                .into_iter()
+                .map(|(source, package)| format!("{}@{} from {source}", package.as_identifier(), package.as_version()))
+                .collect()
+        )
+    }
+
+    #[test]
+    fn test_plan_installation_from_sources() -> Result<()> {
+        let indexes = [
+            index(&[
+                ("app", "2.1.0", &["linux"], &[("lib", "^1"), ("tool", "^1")]),
+                ("lib", "1.2.0", &[], &[]),
+                ("tool", "1.0.0", &["linux"], &[("lib", "^1.1")]),
+                ("cli", "1.0.0", &["linux"], &[("lib", "^2")]),
+            ]),
+            index(&[("app", "2.1.0", &[], &[]), ("app", "2.2.0", &["macos"], &[]), ("lib", "1.3.0", &["linux"], &[])]),
+        ];
+
+        assert_eq!(plan(&DeployLocation::default(), &indexes, "app@^2", &[])?, ["app@2.2.0 from 1"]);
+        assert_eq!(plan(&DeployLocation::default(), &indexes, "app@~2.1", &[])?, ["lib@1.3.0 from 1", "tool@1.0.0 from 0", "app@2.1.0 from 0"]);
+        assert_eq!(plan(&DeployLocation::default(), &indexes, "app@^2", &["linux"])?, ["lib@1.3.0 from 1", "tool@1.0.0 from 0", "app@2.1.0 from 0"]);
+        assert_eq!(
+            plan(&DeployLocation::default(), &indexes, "cli@^1", &["linux"]).map_err(|error| error.to_string()),
+            Err(String::from("No deployed nor available package satisfies lib@^2 required by cli@1.0.0"))
+        );
+
+        let mut location = DeployLocation::default();
+        location.add_deployment(Deployment::new(package("lib", "1.0.0", "b0", &[])));
+        assert_eq!(plan(&location, &indexes, "app@~2.1", &[])?, ["lib@1.3.0 from 1", "tool@1.0.0 from 0", "app@2.1.0 from 0"]);
+        location.add_deployment(Deployment::new(package("lib", "1.1.0", "b1", &[])));
+        assert_eq!(plan(&location, &indexes, "app@~2.1", &[])?, ["tool@1.0.0 from 0", "app@2.1.0 from 0"]);
+        Ok(())
+    }
+}
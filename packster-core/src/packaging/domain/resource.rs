@@ -0,0 +1,137 @@
+use std::{collections::BTreeSet, fmt, path::{Path, PathBuf}};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::entity::Checksum;
+use super::{ShimName, VariableName};
+
+/* A system resource which can only be claimed by a single package of a location */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(tag = "kind", content = "name", rename_all = "snake_case")]
+pub enum Resource {
+    EnvironmentVariable(String),
+    Shim(String),
+    Port(u16),
+    Directory(String),
+    File(String),
+}
+
+impl Resource {
+    /* Paths conflict when equal or nested, a file within a claimed directory belonging to it */
+    pub fn conflicts_with(&self, other: &Resource) -> bool {
+        use Resource::*;
+        match (self, other) {
+            (Directory(directory), Directory(other_directory)) => {
+                let (path, other_path) = (Path::new(directory), Path::new(other_directory));
+                path.starts_with(other_path) || other_path.starts_with(path)
+            }
+            (Directory(directory), File(file)) | (File(file), Directory(directory)) => Path::new(file).starts_with(directory),
+            (File(file), File(other_file)) => Path::new(file) == Path::new(other_file),
+            _ => self == other,
+        }
+    }
+}
+
+/* Trailing and repeated separators are dropped, so that a same path is always claimed under a same name */
+fn normalize_path(path: &str) -> String {
+    Path::new(path).components().collect::<PathBuf>().to_string_lossy().into_owned()
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Resource::*;
+        match self {
+            EnvironmentVariable(name) => write!(f, "Environment variable {name}"),
+            Shim(name) => write!(f, "Shim {name}"),
+            Port(port) => write!(f, "Port {port}"),
+            Directory(path) => write!(f, "Directory {path}"),
+            File(path) => write!(f, "File {path}"),
+        }
+    }
+}
+
+/* Resources declared in the [provides] section of a project manifest, in addition to the shims and environment variables it declares */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
+pub struct Provides {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    environment: Vec<VariableName>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    shims: Vec<ShimName>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    ports: Vec<u16>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    directories: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    files: Vec<String>,
+}
+
+impl Provides {
+    pub fn is_empty(&self) -> bool {
+        self == &Provides::default()
+    }
+
+    /* Every resource of a package, whether provided explicitly or through its shims and environment variables */
+    pub fn to_resources<'a>(
+        &'a self,
+        shims: impl Iterator<Item = &'a ShimName>,
+        environment: impl Iterator<Item = &'a VariableName>,
+    ) -> BTreeSet<Resource> {
+        let environment = self.environment.iter().chain(environment)
+            .map(|name| Resource::EnvironmentVariable(name.to_string()));
+        let shims = self.shims.iter().chain(shims)
+            .map(|name| Resource::Shim(name.to_string()));
+        environment
+            .chain(shims)
+            .chain(self.ports.iter().map(|port| Resource::Port(*port)))
+            .chain(self.directories.iter().map(|path| Resource::Directory(normalize_path(path))))
+            .chain(self.files.iter().map(|path| Resource::File(normalize_path(path))))
+            .collect()
+    }
+}
+
+/* A resource claimed by a deployment of a location */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ResourceClaim {
+    resource: Resource,
+    deployment_id: Checksum,
+}
+
+impl ResourceClaim {
+    pub fn new(resource: Resource, deployment_id: Checksum) -> Self {
+        ResourceClaim { resource, deployment_id }
+    }
+
+    pub fn as_resource(&self) -> &Resource { &self.resource }
+    pub fn as_deployment_id(&self) -> &Checksum { &self.deployment_id }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resource_path_normalization() {
+        let provides = Provides {
+            directories: vec!["/var/lib/server/".to_string(), "/var//lib/server".to_string()],
+            files: vec!["/etc/server.conf/".to_string()],
+            ..Provides::default()
+        };
+        let resources: Vec<Resource> = provides.to_resources(std::iter::empty(), std::iter::empty()).into_iter().collect();
+        assert_eq!(resources, [Resource::Directory("/var/lib/server".to_string()), Resource::File("/etc/server.conf".to_string())]);
+    }
+
+    #[test]
+    fn test_resource_nesting_conflict() {
+        let directory = |path: &str| Resource::Directory(path.to_string());
+        let file = |path: &str| Resource::File(path.to_string());
+
+        assert!(directory("/var/lib").conflicts_with(&directory("/var/lib/server/")));
+        assert!(directory("/var/lib/server/").conflicts_with(&directory("/var/lib")));
+        assert!(directory("/var/lib").conflicts_with(&file("/var/lib/server/data")));
+        assert!(file("/var/lib/server/data").conflicts_with(&directory("/var/lib/")));
+        assert!(file("/etc/server.conf").conflicts_with(&file("/etc/server.conf/")));
+
+        assert!(!directory("/var/lib/server").conflicts_with(&directory("/var/lib/server2")));
+        assert!(!file("/var/lib").conflicts_with(&file("/var/lib/server")));
+        assert!(!directory("/var/lib").conflicts_with(&Resource::Shim("/var/lib".to_string())));
+    }
+}
@@ -0,0 +1,177 @@
+use std::{collections::BTreeMap, fmt, path::{Component, Path}, str::FromStr};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result, domain::entity::{Checksum, Identifier, Version}};
+
+pub const SHIM_NAME_MAX_LENGTH: usize = 64;
+
+/* Second line of every shim script, telling the shims apart from the other files of the location bin directory */
+const SHIM_MARKER: &str = "# packster shim";
+
+/* Commands declared in the [shims] section of a project manifest, mapped to a file of the package */
+pub type Shims = BTreeMap<ShimName, ShimTarget>;
+
+/* The name of a command, and of its shim script in the location bin directory */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub struct ShimName(String);
+
+impl ShimName {
+    // ValidCharacter = Alphanumeric | Dash | Underscore | Dot
+    pub fn is_valid_character(character: char) -> bool {
+        character.is_ascii_alphanumeric() || character == '-' || character == '_' || character == '.'
+    }
+}
+
+impl FromStr for ShimName {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        if s.is_empty() || s.len() > SHIM_NAME_MAX_LENGTH || s.starts_with('.') || !s.chars().all(ShimName::is_valid_character) {
+            return Err(Error::InvalidShimName(s.to_string()));
+        }
+        Ok(ShimName(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for ShimName {
+    type Error = Error;
+    fn try_from(value: String) -> Result<Self> {
+        ShimName::from_str(&value)
+    }
+}
+
+impl From<ShimName> for String {
+    fn from(value: ShimName) -> Self {
+        value.0
+    }
+}
+
+impl fmt::Display for ShimName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl AsRef<str> for ShimName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/* A path relative to the deployment directory, which can not escape it */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct ShimTarget(String);
+
+impl FromStr for ShimTarget {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        let components: Vec<Component> = Path::new(s).components().collect();
+        if components.is_empty() || s.contains('\\') || !components.iter().all(|component| matches!(component, Component::Normal(_))) {
+            return Err(Error::InvalidShimTarget(s.to_string()));
+        }
+        Ok(ShimTarget(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for ShimTarget {
+    type Error = Error;
+    fn try_from(value: String) -> Result<Self> {
+        ShimTarget::from_str(&value)
+    }
+}
+
+impl From<ShimTarget> for String {
+    fn from(value: ShimTarget) -> Self {
+        value.0
+    }
+}
+
+impl AsRef<str> for ShimTarget {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/* A shim of the location bin directory, running a file of the active deployment of a package */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocationShim {
+    name: ShimName,
+    target: ShimTarget,
+    deployment_id: Checksum,
+    identifier: Identifier,
+    version: Version,
+}
+
+impl LocationShim {
+    pub fn new(name: ShimName, target: ShimTarget, deployment_id: Checksum, identifier: Identifier, version: Version) -> Self {
+        LocationShim { name, target, deployment_id, identifier, version }
+    }
+
+    pub fn as_name(&self) -> &ShimName { &self.name }
+    pub fn as_target(&self) -> &ShimTarget { &self.target }
+    pub fn as_deployment_id(&self) -> &Checksum { &self.deployment_id }
+    pub fn as_identifier(&self) -> &Identifier { &self.identifier }
+    pub fn as_version(&self) -> &Version { &self.version }
+
+    /* A POSIX shell script replacing itself with the target, given the shim arguments */
+    pub fn to_script(&self, deployment_path: &Path) -> String {
+        let target_path = deployment_path.join(self.target.as_ref());
+        format!(
+            "#!/bin/sh\n{SHIM_MARKER} of {}@{} ({})\nexec {} \"$@\"\n",
+            self.identifier,
+            self.version,
+            self.deployment_id,
+            to_shell_quoted(&target_path.to_string_lossy())
+        )
+    }
+}
+
+pub fn is_shim_script(content: &str) -> bool {
+    content.lines().nth(1).is_some_and(|line| line.starts_with(SHIM_MARKER))
+}
+
+fn to_shell_quoted(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_shim_name_validation() {
+        for name in ["tool", "my-tool", "tool_2", "tool.sh", "Tool"] {
+            assert!(ShimName::from_str(name).is_ok(), "{name} shall be valid");
+        }
+        for name in ["", ".hidden", "bin/tool", "my tool", "tool\\", "../tool"] {
+            assert!(matches!(ShimName::from_str(name), Err(Error::InvalidShimName(_))), "{name} shall be invalid");
+        }
+    }
+
+    #[test]
+    fn test_shim_target_validation() {
+        for target in ["tool", "bin/tool", "bin/tool.sh"] {
+            assert!(ShimTarget::from_str(target).is_ok(), "{target} shall be valid");
+        }
+        for target in ["", "/bin/tool", "../tool", "bin/../../tool", "./tool", "bin\\tool"] {
+            assert!(matches!(ShimTarget::from_str(target), Err(Error::InvalidShimTarget(_))), "{target} shall be invalid");
+        }
+    }
+
+    #[test]
+    fn test_shim_script() {
+        let shim = LocationShim::new(
+            ShimName::from_str("tool").unwrap(),
+            ShimTarget::from_str("bin/tool").unwrap(),
+            Checksum::from_str("aa").unwrap(),
+            Identifier::from_str("my-tool").unwrap(),
+            Version::new(1, 0, 0),
+        );
+        let script = shim.to_script(Path::new("/my/location/aa"));
+
+        assert_eq!(script, "#!/bin/sh\n# packster shim of my-tool@1.0.0 (aa)\nexec '/my/location/aa/bin/tool' \"$@\"\n");
+        assert!(is_shim_script(&script));
+        assert!(!is_shim_script("#!/bin/sh\nexec /usr/bin/tool \"$@\"\n"));
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::{BTreeMap, BTreeSet};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::entity::{Checksum, Identifier, PackageRequirement, Version};
+use super::{to_package_file_name, Dependencies, Package};
+
+/* Packages of a source, published at its root so that it can be listed without walking or downloading every package file.
+ * Its content only depends on the indexed files, so that it can be signed */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
+pub struct SourceIndex {
+    #[serde(default)]
+    packages: Vec<IndexedPackage>,
+}
+
+impl SourceIndex {
+    /* Packages are sorted, so that indexing the same files always gives the same index */
+    pub fn new(mut packages: Vec<IndexedPackage>) -> Self {
+        packages.sort_by(|left, right| (&left.identifier, &left.version, left.to_path()).cmp(&(&right.identifier, &right.version, right.to_path())));
+        SourceIndex { packages }
+    }
+
+    pub fn find_by_path(&self, path: &str) -> Option<&IndexedPackage> {
+        self.packages.iter().find(|package| package.to_path() == path)
+    }
+
+    pub fn find(&self, identifier: &Identifier, version: &Version) -> Option<&IndexedPackage> {
+        self.packages.iter().find(|package| &package.identifier == identifier && &package.version == version)
+    }
+
+    pub fn with_package(&self, package: IndexedPackage) -> SourceIndex {
+        SourceIndex::new(self.packages.iter().cloned().chain([package]).collect())
+    }
+
+    pub fn without_package(&self, identifier: &Identifier, version: &Version) -> SourceIndex {
+        SourceIndex::new(
+            self.packages.iter()
+                .filter(|package| &package.identifier != identifier || &package.version != version)
+                .cloned()
+                .collect()
+        )
+    }
+
+    pub fn iter_packages(&self) -> impl Iterator<Item = &IndexedPackage> {
+        self.packages.iter()
+    }
+
+    pub fn to_identifiers(&self) -> Vec<Identifier> {
+        self.packages.iter()
+            .map(|package| package.identifier.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    pub fn to_versions(&self, identifier: &Identifier) -> Vec<Version> {
+        self.packages.iter()
+            .filter(|package| &package.identifier == identifier)
+            .map(|package| package.version.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /* Versions of every identifier, sorted from the oldest to the newest */
+    pub fn to_versions_by_identifier(&self) -> BTreeMap<Identifier, Vec<Version>> {
+        let mut versions: BTreeMap<Identifier, BTreeSet<Version>> = BTreeMap::new();
+        for package in self.packages.iter() {
+            versions.entry(package.identifier.clone()).or_default().insert(package.version.clone());
+        }
+        versions.into_iter()
+            .map(|(identifier, versions)| (identifier, versions.into_iter().collect()))
+            .collect()
+    }
+
+    /* The most recent package matching the requirement */
+    pub fn find_latest(&self, requirement: &PackageRequirement) -> Option<&IndexedPackage> {
+        self.packages.iter()
+            .filter(|package| requirement.matches(&package.identifier, &package.version))
+            .max_by(|left, right| left.version.cmp(&right.version))
+    }
+}
+
+/* A package file of a source, along with the metadata of its header */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct IndexedPackage {
+    identifier: Identifier,
+    version: Version,
+    checksum: Checksum,
+    packster_version: Version,
+    size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    dependencies: Dependencies,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    labels: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+}
+
+impl IndexedPackage {
+    /* The path is relative to the source, and only kept when it differs from the package file name */
+    pub fn new(package: &Package, path: String, size: u64) -> Self {
+        IndexedPackage {
+            identifier: package.as_identifier().clone(),
+            version: package.as_version().clone(),
+            checksum: package.as_checksum().clone(),
+            packster_version: package.as_packster_version().clone(),
+            size,
+            path: Some(path).filter(|path| path != &package.to_file_name()),
+            dependencies: package.as_dependencies().clone(),
+            labels: package.as_labels().clone(),
+            description: package.as_description().map(String::from),
+        }
+    }
+
+    pub fn as_identifier(&self) -> &Identifier { &self.identifier }
+    pub fn as_version(&self) -> &Version { &self.version }
+    pub fn as_checksum(&self) -> &Checksum { &self.checksum }
+    pub fn as_packster_version(&self) -> &Version { &self.packster_version }
+    pub fn as_size(&self) -> u64 { self.size }
+    pub fn as_dependencies(&self) -> &Dependencies { &self.dependencies }
+    pub fn as_labels(&self) -> &BTreeSet<String> { &self.labels }
+    pub fn as_description(&self) -> Option<&str> { self.description.as_deref() }
+
+    /* Name of the package file, which is the one given by a packing */
+    pub fn to_file_name(&self) -> String {
+        to_package_file_name(&self.identifier, &self.version, &self.checksum, &self.packster_version)
+    }
+
+    /* Path of the package file relative to the source, using slashes as separators */
+    pub fn to_path(&self) -> String {
+        self.path.clone().unwrap_or_else(|| self.to_file_name())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use crate::Result;
+    use super::*;
+
+    const INDEX: &str = r#"{"packages":[
+        {"identifier":"tool","version":"1.0.0","checksum":"d829752c","packster_version":"0.1.0","size":16},
+        {"identifier":"tool","version":"1.2.0","checksum":"d829752c","packster_version":"0.1.0","size":16,"labels":["cli"]},
+        {"identifier":"lib","version":"0.1.0","checksum":"d829752c","packster_version":"0.1.0","size":16,"path":"nested/lib.packster"}
+    ]}"#;
+
+    #[test]
+    fn test_find_latest_indexed_package() -> Result<()> {
+        let index: SourceIndex = serde_json::from_str(INDEX).unwrap();
+
+        assert_eq!(index.to_identifiers(), vec![Identifier::from_str("lib")?, Identifier::from_str("tool")?]);
+        assert_eq!(index.to_versions(&Identifier::from_str("tool")?), vec![Version::new(1, 0, 0), Version::new(1, 2, 0)]);
+        assert_eq!(index.to_versions_by_identifier(), BTreeMap::from([
+            (Identifier::from_str("lib")?, vec![Version::new(0, 1, 0)]),
+            (Identifier::from_str("tool")?, vec![Version::new(1, 0, 0), Version::new(1, 2, 0)]),
+        ]));
+
+        let package = index.find_latest(&PackageRequirement::from_str("tool@^1")?).unwrap();
+        assert_eq!(package.as_version(), &Version::new(1, 2, 0));
+        assert_eq!(package.to_file_name(), "tool_1.2.0_d829752c.302e312e30.packster");
+        assert!(index.find_latest(&PackageRequirement::from_str("tool@^2")?).is_none());
+        assert_eq!(index.find_by_path("nested/lib.packster").map(IndexedPackage::as_identifier), Some(&Identifier::from_str("lib")?));
+        Ok(())
+    }
+}
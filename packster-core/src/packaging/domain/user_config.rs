@@ -0,0 +1,110 @@
+use std::{collections::BTreeMap, path::{Path, PathBuf}};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result, domain::entity::Identifier};
+
+/* Locations known by the user, named after the identifier grammar, one of them being used when no location is given,
+ * along with the sources packages are installed from, in the order they are queried */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
+pub struct UserConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default_location: Option<Identifier>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    locations: BTreeMap<Identifier, PathBuf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sources: Vec<String>,
+}
+
+impl UserConfig {
+    /* The first registered location is the default one, until another one is registered as such.
+     * Registering a name again is only allowed for the same path */
+    pub fn register_location(&mut self, name: Identifier, path: PathBuf, is_default: bool) -> Result<()> {
+        match self.locations.get(&name) {
+            Some(registered_path) if registered_path != &path => {
+                return Err(Error::LocationNameAlreadyRegistered {
+                    name: name.to_string(),
+                    path: registered_path.clone(),
+                })
+            }
+            Some(_) => {}
+            None => {
+                self.locations.insert(name.clone(), path);
+            }
+        }
+        if is_default || self.default_location.is_none() {
+            self.default_location = Some(name);
+        }
+        Ok(())
+    }
+
+    pub fn get_location(&self, name: &Identifier) -> Option<&Path> {
+        self.locations.get(name).map(PathBuf::as_path)
+    }
+
+    pub fn get_default_location(&self) -> Option<(&Identifier, &Path)> {
+        self.default_location
+            .as_ref()
+            .and_then(|name| self.get_location(name).map(|path| (name, path)))
+    }
+
+    pub fn is_default_location(&self, name: &Identifier) -> bool {
+        self.default_location.as_ref() == Some(name)
+    }
+
+    pub fn iter_locations(&self) -> impl Iterator<Item = (&Identifier, &Path)> {
+        self.locations.iter().map(|(name, path)| (name, path.as_path()))
+    }
+
+    /* Returns whether the source was not registered yet */
+    pub fn register_source(&mut self, source: String) -> bool {
+        let is_new = !self.sources.contains(&source);
+        if is_new {
+            self.sources.push(source);
+        }
+        is_new
+    }
+
+    pub fn iter_sources(&self) -> impl Iterator<Item = &str> {
+        self.sources.iter().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use super::*;
+
+    #[test]
+    fn test_first_registered_location_is_default() -> Result<()> {
+        let mut config = UserConfig::default();
+        config.register_location(Identifier::from_str("work")?, PathBuf::from("/work"), false)?;
+        config.register_location(Identifier::from_str("home")?, PathBuf::from("/home"), false)?;
+
+        assert_eq!(config.get_default_location(), Some((&Identifier::from_str("work")?, Path::new("/work"))));
+        config.register_location(Identifier::from_str("home")?, PathBuf::from("/home"), true)?;
+        assert!(config.is_default_location(&Identifier::from_str("home")?));
+        assert_eq!(config.iter_locations().count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_registered_name_can_not_change_path() -> Result<()> {
+        let mut config = UserConfig::default();
+        config.register_location(Identifier::from_str("work")?, PathBuf::from("/work"), false)?;
+
+        let result = config.register_location(Identifier::from_str("work")?, PathBuf::from("/other"), true);
+        assert!(matches!(result, Err(Error::LocationNameAlreadyRegistered { name, path }) if name == "work" && path == Path::new("/work")));
+        assert_eq!(config.get_location(&Identifier::from_str("work")?), Some(Path::new("/work")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sources_are_registered_once_in_order() {
+        let mut config = UserConfig::default();
+        assert!(config.register_source(String::from("https://packages.example.com")));
+        assert!(config.register_source(String::from("/srv/packages")));
+        assert!(!config.register_source(String::from("https://packages.example.com")));
+
+        assert_eq!(config.iter_sources().collect::<Vec<_>>(), ["https://packages.example.com", "/srv/packages"]);
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt;
+use serde::Serialize;
+
+use crate::domain::entity::Checksum;
+use super::{DeployedFile, Deployment};
+
+/* A difference between a location and its lockfile */
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "issue", rename_all = "snake_case")]
+pub enum LocationIssue {
+    MissingDeploymentDirectory { deployment_id: Checksum },
+    MissingFile { deployment_id: Checksum, path: String },
+    ModifiedFile { deployment_id: Checksum, path: String },
+    ExtraFile { deployment_id: Checksum, path: String },
+    OrphanDirectory { path: String },
+}
+
+impl fmt::Display for LocationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use LocationIssue::*;
+        match self {
+            MissingDeploymentDirectory { deployment_id } => write!(f, "Deployment {deployment_id} directory is missing"),
+            MissingFile { deployment_id, path } => write!(f, "Deployment {deployment_id} file {path} is missing"),
+            ModifiedFile { deployment_id, path } => write!(f, "Deployment {deployment_id} file {path} has been modified"),
+            ExtraFile { deployment_id, path } => write!(f, "Deployment {deployment_id} file {path} is not part of the deployment"),
+            OrphanDirectory { path } => write!(f, "Directory {path} is not a deployment of the location"),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct VerificationReport {
+    issues: Vec<LocationIssue>,
+}
+
+impl VerificationReport {
+    pub fn is_healthy(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn as_issues(&self) -> &[LocationIssue] {
+        &self.issues
+    }
+
+    pub fn add_issues<I: IntoIterator<Item = LocationIssue>>(&mut self, issues: I) {
+        self.issues.extend(issues)
+    }
+}
+
+/* Compares the files found in a deployment directory with the ones recorded at deployment.
+ * Deployments recorded without files can not be compared */
+pub fn verify_deployment_files(deployment: &Deployment, found_files: &[DeployedFile]) -> Vec<LocationIssue> {
+    let expected_files = deployment.as_files();
+    if expected_files.is_empty() {
+        return Vec::new();
+    }
+
+    let deployment_id = deployment.as_id();
+    let mut issues = Vec::new();
+    for expected_file in expected_files {
+        match found_files.iter().find(|found_file| found_file.as_path() == expected_file.as_path()) {
+            None => issues.push(LocationIssue::MissingFile {
+                deployment_id: deployment_id.clone(),
+                path: expected_file.as_path().to_string(),
+            }),
+            Some(found_file) if found_file != expected_file => issues.push(LocationIssue::ModifiedFile {
+                deployment_id: deployment_id.clone(),
+                path: expected_file.as_path().to_string(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for found_file in found_files {
+        if !expected_files.iter().any(|expected_file| expected_file.as_path() == found_file.as_path()) {
+            issues.push(LocationIssue::ExtraFile {
+                deployment_id: deployment_id.clone(),
+                path: found_file.as_path().to_string(),
+            });
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use super::*;
+    use crate::packaging::domain::Package;
+
+    fn file(path: &str, size: u64, checksum: &str) -> DeployedFile {
+        DeployedFile::new(path.to_string(), size, Checksum::from_str(checksum).unwrap())
+    }
+
+    fn deployment(files: Vec<DeployedFile>) -> Deployment {
+        let package: Package = serde_json::from_value(serde_json::json!({
+            "identifier": "tool", "version": "1.0.0", "checksum": "aa", "packster_version": "0.1.0"
+        })).unwrap();
+        Deployment::new(package).with_files(files)
+    }
+
+    #[test]
+    fn test_verify_deployment_files() {
+        let deployment = deployment(vec![file("bin/tool", 4, "01"), file("README", 2, "02"), file("LICENSE", 3, "03")]);
+        let found_files = [file("bin/tool", 4, "01"), file("README", 2, "ff"), file("config", 1, "04")];
+
+        let issues = verify_deployment_files(&deployment, &found_files);
+        let deployment_id = Checksum::from_str("aa").unwrap();
+        assert_eq!(issues, [
+            LocationIssue::ModifiedFile { deployment_id: deployment_id.clone(), path: String::from("README") },
+            LocationIssue::MissingFile { deployment_id: deployment_id.clone(), path: String::from("LICENSE") },
+            LocationIssue::ExtraFile { deployment_id, path: String::from("config") },
+        ]);
+    }
+
+    #[test]
+    fn test_deployment_without_recorded_files_is_not_verified() {
+        let issues = verify_deployment_files(&deployment(Vec::new()), &[file("config", 1, "04")]);
+        assert!(issues.is_empty());
+    }
+}
@@ -0,0 +1,65 @@
+use std::path::{Path, PathBuf};
+use packster_core::{
+    application::{path::Absolute, port::{FileSystem, ReadOnlyFileSystem, Source}},
+    packaging::domain::{IndexedPackage, SourceIndex},
+};
+
+use crate::{DirectorySource, HttpSource, Result};
+
+/* A source as configured by the user : an http(s) url for a web server, an absolute directory otherwise */
+pub enum ConfiguredSource {
+    Directory(DirectorySource),
+    Http(HttpSource),
+}
+
+impl ConfiguredSource {
+    pub fn new(source: &str) -> Result<Self> {
+        if source.starts_with("http://") || source.starts_with("https://") {
+            Ok(ConfiguredSource::Http(HttpSource::new(source)))
+        } else {
+            Ok(ConfiguredSource::Directory(DirectorySource::new(Absolute::try_absolute(PathBuf::from(source))?)))
+        }
+    }
+}
+
+impl Source for ConfiguredSource {
+    fn to_source_name(&self) -> String {
+        match self {
+            ConfiguredSource::Directory(source) => source.to_source_name(),
+            ConfiguredSource::Http(source) => source.to_source_name(),
+        }
+    }
+
+    fn index<F: ReadOnlyFileSystem>(&self, filesystem: &F) -> Result<SourceIndex> {
+        match self {
+            ConfiguredSource::Directory(source) => source.index(filesystem),
+            ConfiguredSource::Http(source) => source.index(filesystem),
+        }
+    }
+
+    fn download<F: FileSystem, P: AsRef<Path>>(
+        &self,
+        filesystem: &F,
+        package: &IndexedPackage,
+        destination_directory: Absolute<P>,
+    ) -> Result<Absolute<PathBuf>> {
+        match self {
+            ConfiguredSource::Directory(source) => source.download(filesystem, package, destination_directory),
+            ConfiguredSource::Http(source) => source.download(filesystem, package, destination_directory),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use packster_core::Error;
+    use super::*;
+
+    #[test]
+    fn test_configured_source_kind() -> Result<()> {
+        assert!(matches!(ConfiguredSource::new("https://packages.example.com/tools")?, ConfiguredSource::Http(_)));
+        assert!(matches!(ConfiguredSource::new("/srv/packages")?, ConfiguredSource::Directory(_)));
+        assert!(matches!(ConfiguredSource::new("packages"), Err(Error::PathIsRelative(_))));
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+use std::{io, path::{Path, PathBuf}};
+use packster_core::{
+    application::{path::Absolute, port::{FileSystem, ReadOnlyFileSystem, Source}},
+    packaging::{application::{read_package_metadata, to_relative_file_path}, domain::{IndexedPackage, Package, SourceIndex}, PACKAGE_EXTENSION, SOURCE_INDEX_NAME},
+};
+
+use crate::{Error, Json, Result};
+
+/* A directory of package files, listed by its index when it has one, by their files otherwise */
+pub struct DirectorySource {
+    path: Absolute<PathBuf>,
+}
+
+impl DirectorySource {
+    pub fn new(path: Absolute<PathBuf>) -> Self {
+        DirectorySource { path }
+    }
+
+    pub fn as_path(&self) -> Absolute<&Path> {
+        self.path.as_absolute_path()
+    }
+}
+
+impl Source for DirectorySource {
+    fn to_source_name(&self) -> String {
+        self.path.to_string_lossy().to_string()
+    }
+
+    /* Without index, files which are not named after a package are ignored, the others being described by their header, which has to be valid */
+    fn index<F: ReadOnlyFileSystem>(&self, filesystem: &F) -> Result<SourceIndex> {
+        let index_path = self.path.join(SOURCE_INDEX_NAME);
+        if filesystem.exists(&index_path) {
+            return Ok(serde_json::from_str(&filesystem.read_to_string(&index_path)?).map_err(Error::from)?);
+        }
+
+        let mut packages = Vec::new();
+        for entry in filesystem.walk(&self.path) {
+            let entry = entry?;
+            let is_package_file = filesystem.is_file(entry.as_path())
+                && entry.as_path().extension().is_some_and(|extension| extension == PACKAGE_EXTENSION);
+            if !is_package_file {
+                continue;
+            }
+            if Package::from_path(entry.as_path()).is_ok() {
+                let package = read_package_metadata(filesystem, &Json, entry.as_path())?;
+                let path = to_relative_file_path(self.path.as_ref(), entry.as_path());
+                packages.push(IndexedPackage::new(&package, path, entry.size()));
+            }
+        }
+        Ok(SourceIndex::new(packages))
+    }
+
+    fn download<F: FileSystem, P: AsRef<Path>>(
+        &self,
+        filesystem: &F,
+        package: &IndexedPackage,
+        destination_directory: Absolute<P>,
+    ) -> Result<Absolute<PathBuf>> {
+        let package_path = self.path.join(package.to_path());
+        let destination_path = destination_directory.join(package.to_file_name());
+        if destination_path != package_path {
+            filesystem.create_dir_recursively(destination_directory.as_ref())?;
+            let mut reader = filesystem.open_read(&package_path)?;
+            let mut writer = filesystem.open_write(&destination_path)?;
+            io::copy(&mut reader, &mut writer).map_err(Error::from)?;
+        }
+        Ok(destination_path)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "test")]
+mod test {
+    use std::str::FromStr;
+    use packster_core::application::port::ReadOnlyFileSystem;
+
+    use packster_core::{Error as CoreError, domain::entity::{Identifier, PackageRequirement, Version}, packaging::application::write_package_header};
+
+    use crate::InMemoryFileSystem;
+
+    use super::*;
+
+    const CHECKSUM: &str = "d829752c10db8f7a98c939b5418beb0a360c6a6b818830e000f2c5a8dce35af4";
+
+    fn source(filesystem: &InMemoryFileSystem) -> Result<DirectorySource> {
+        filesystem.create_dir_recursively("/source/nested")?;
+        for file_name in [
+            format!("tool_1.0.0_{CHECKSUM}.302e312e30.packster"),
+            format!("tool_1.2.0_{CHECKSUM}.302e312e30.packster"),
+            format!("nested/lib_0.1.0_{CHECKSUM}.302e312e30.packster"),
+        ] {
+            let package = Package::from_path(&file_name)?;
+            let mut content = Vec::new();
+            write_package_header(&mut content, &serde_json::to_string(&package).unwrap())?;
+            content.extend_from_slice(file_name.as_bytes());
+            filesystem.write_all(Path::new("/source").join(&file_name), content)?;
+        }
+        for file_name in ["readme.packster", "notes.txt"] {
+            filesystem.write_all(Path::new("/source").join(file_name), file_name.as_bytes())?;
+        }
+        Ok(DirectorySource::new(Absolute::assume_absolute(PathBuf::from("/source"))))
+    }
+
+    #[test]
+    fn test_list_directory_source() -> Result<()> {
+        let filesystem = InMemoryFileSystem::default();
+        let source = source(&filesystem)?;
+
+        assert_eq!(
+            source.list_identifiers(&filesystem)?,
+            vec![Identifier::from_str("lib")?, Identifier::from_str("tool")?]
+        );
+        assert_eq!(
+            source.list_versions(&filesystem, &Identifier::from_str("tool")?)?,
+            vec![Version::new(1, 0, 0), Version::new(1, 2, 0)]
+        );
+        assert!(source.list_versions(&filesystem, &Identifier::from_str("other")?)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fetch_from_directory_source() -> Result<()> {
+        let filesystem = InMemoryFileSystem::default();
+        let source = source(&filesystem)?;
+        let destination = Absolute::assume_absolute(PathBuf::from("/cache"));
+
+        let fetched_path = source.fetch(&filesystem, &PackageRequirement::from_str("tool@^1")?, destination.as_absolute_path())?;
+        let file_name = format!("tool_1.2.0_{CHECKSUM}.302e312e30.packster");
+        assert_eq!(fetched_path.as_ref(), Path::new("/cache").join(&file_name));
+        assert_eq!(filesystem.file_size(fetched_path)?, filesystem.file_size(Path::new("/source").join(&file_name))?);
+
+        let result = source.fetch(&filesystem, &PackageRequirement::from_str("tool@^2")?, destination);
+        assert!(matches!(result, Err(CoreError::PackageNotFoundInSource { .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn test_directory_source_rejects_package_without_header() -> Result<()> {
+        let filesystem = InMemoryFileSystem::default();
+        let source = source(&filesystem)?;
+        filesystem.write_all(format!("/source/tool_2.0.0_{CHECKSUM}.302e312e30.packster"), "No header")?;
+
+        assert!(matches!(source.index(&filesystem), Err(CoreError::MissingPackageHeader(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_directory_source_prefers_its_index() -> Result<()> {
+        let filesystem = InMemoryFileSystem::default();
+        let source = source(&filesystem)?;
+        let package = Package::from_path(format!("/source/nested/lib_0.1.0_{CHECKSUM}.302e312e30.packster"))?;
+        let index = SourceIndex::new(vec![IndexedPackage::new(&package, format!("nested/{}", package.to_file_name()), 53)]);
+        filesystem.write_all("/source/packster-index.json", serde_json::to_string(&index).unwrap())?;
+
+        assert_eq!(source.list_identifiers(&filesystem)?, vec![Identifier::from_str("lib")?]);
+        let fetched_path = source.fetch(
+            &filesystem,
+            &PackageRequirement::from_str("lib@0.1")?,
+            Absolute::assume_absolute(PathBuf::from("/cache")),
+        )?;
+        assert_eq!(fetched_path.as_ref(), Path::new("/cache").join(package.to_file_name()));
+        Ok(())
+    }
+}
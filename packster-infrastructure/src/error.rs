@@ -10,7 +10,9 @@ pub enum Error {
     WalkDir(walkdir::Error),
     TomlDeserialize(toml::de::Error),
     TomlSerialize(toml::ser::Error),
-    JsonSerde(serde_json::Error)
+    JsonSerde(serde_json::Error),
+    Spawn(String, io::Error),
+    Http(Box<ureq::Error>),
 }
 
 impl fmt::Display for Error {
@@ -21,7 +23,9 @@ impl fmt::Display for Error {
             WalkDir(e) => write!(f, "WalkDir error : {e}"),
             TomlDeserialize(e) => write!(f, "Toml deserialize error : {e}"),
             TomlSerialize(e) => write!(f, "Toml serialize error : {e}"),
-            JsonSerde(e) => write!(f, "Json deserialize error: {e}")
+            JsonSerde(e) => write!(f, "Json deserialize error: {e}"),
+            Spawn(command, e) => write!(f, "Unable to execute \"{command}\" : {e}"),
+            Http(e) => write!(f, "Http error : {e}"),
         }
     }
 }
@@ -35,6 +39,8 @@ impl error::Error for Error {
             TomlDeserialize(e) => Some(e),
             TomlSerialize(e) => Some(e),
             JsonSerde(e) => Some(e),
+            Spawn(_, e) => Some(e),
+            Http(e) => Some(e.as_ref()),
         }
     }
 }
@@ -64,3 +70,7 @@ impl From<toml::ser::Error> for Error {
 impl From<serde_json::Error> for Error {
     fn from(error: serde_json::Error) -> Self { Error::JsonSerde(error) }
 }
+
+impl From<ureq::Error> for Error {
+    fn from(error: ureq::Error) -> Self { Error::Http(Box::new(error)) }
+}
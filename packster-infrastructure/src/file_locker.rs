@@ -0,0 +1,233 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, Write},
+    path::{Path, PathBuf},
+    process,
+    thread,
+    time::{Duration, Instant},
+};
+use packster_core::{
+    application::{path::Absolute, port::{Lock, Locker}},
+    Error as CoreError,
+};
+use crate::{Error, Result};
+
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/**
+ * Lock file holding the PID and hostname of its owner, the exclusion itself relying on an advisory lock of the file,
+ * released by the system when its owner is gone. The lock file of an owner which is gone is thus taken over,
+ * whether it could write its identity or not, unless it was written from another host.
+ */
+#[derive(Default)]
+pub struct FileLocker;
+
+impl Locker for FileLocker {
+    fn lock<P: AsRef<Path>>(&self, path: Absolute<P>, wait: Duration) -> Result<Lock> {
+        let path = path.as_ref();
+        let deadline = Instant::now() + wait;
+        loop {
+            let mut file = open_lock_file(path)?;
+            let owner = match file.try_lock() {
+                Ok(()) if !is_same_file(&file, path)? => continue,
+                Ok(()) => match LockOwner::read_from(&mut file) {
+                    Some(owner) if !owner.is_local() => Some(owner),
+                    _ => {
+                        write_lock_owner(&mut file).map_err(Error::from)?;
+                        return Ok(Lock::new(path, FileLockGuard(path.to_path_buf(), file)));
+                    }
+                },
+                // The owner may not have written its identity yet
+                Err(fs::TryLockError::WouldBlock) => LockOwner::read_from(&mut file),
+                Err(fs::TryLockError::Error(error)) => return Err(Error::from(error).into()),
+            };
+            drop(file);
+
+            if Instant::now() >= deadline {
+                return Err(match owner {
+                    Some(owner) => CoreError::LocationLocked {
+                        path: path.to_path_buf(),
+                        pid: owner.pid,
+                        hostname: owner.hostname,
+                    },
+                    None => CoreError::LocationLockedByUnknownOwner(path.to_path_buf()),
+                });
+            }
+            thread::sleep(RETRY_INTERVAL);
+        }
+    }
+}
+
+// Only the initialization of a location creates its directory
+fn open_lock_file(path: &Path) -> Result<File> {
+    match OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            let location_path = path.parent().unwrap_or(path).to_path_buf();
+            Err(CoreError::LocationNotInitialized(location_path))
+        }
+        result => result.map_err(|error| Error::from(error).into()),
+    }
+}
+
+// A lock file removed by its previous owner, between its opening and its locking, is no longer the lock file
+#[cfg(unix)]
+fn is_same_file(file: &File, path: &Path) -> Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    let file_metadata = file.metadata().map_err(Error::from)?;
+    Ok(match fs::metadata(path) {
+        Ok(metadata) => metadata.dev() == file_metadata.dev() && metadata.ino() == file_metadata.ino(),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => false,
+        Err(error) => return Err(Error::from(error).into()),
+    })
+}
+
+// An opened file can not be removed
+#[cfg(not(unix))]
+fn is_same_file(_file: &File, _path: &Path) -> Result<bool> {
+    Ok(true)
+}
+
+fn write_lock_owner(file: &mut File) -> io::Result<()> {
+    file.set_len(0)?;
+    file.rewind()?;
+    file.write_all(LockOwner::current().to_string().as_bytes())
+}
+
+// The lock file is removed before being unlocked, for a waiter to notice it is no longer the lock file
+struct FileLockGuard(PathBuf, File);
+
+impl Drop for FileLockGuard {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+        let _ = self.1.unlock();
+    }
+}
+
+struct LockOwner {
+    pid: u32,
+    hostname: String,
+}
+
+impl LockOwner {
+    fn current() -> Self {
+        LockOwner {
+            pid: process::id(),
+            hostname: current_hostname(),
+        }
+    }
+
+    fn read_from(file: &mut File) -> Option<Self> {
+        let mut content = String::new();
+        file.rewind().ok()?;
+        file.read_to_string(&mut content).ok()?;
+        let (pid, hostname) = content.trim_end().split_once('\n')?;
+        Some(LockOwner {
+            pid: pid.parse().ok()?,
+            hostname: hostname.to_string(),
+        })
+    }
+
+    // The lock of a process of another host may not be visible from the current host
+    fn is_local(&self) -> bool {
+        self.hostname == current_hostname()
+    }
+}
+
+impl std::fmt::Display for LockOwner {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "{}\n{}", self.pid, self.hostname)
+    }
+}
+
+fn current_hostname() -> String {
+    gethostname::gethostname().to_string_lossy().to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn to_lock_path(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("packster-file-locker-{}", process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        directory.join(name)
+    }
+
+    #[test]
+    fn test_lock_is_exclusive_and_released_on_drop() {
+        let path = to_lock_path("exclusive");
+        let lock = FileLocker.lock(Absolute::assume_absolute(&path), Duration::ZERO).unwrap();
+        let result = FileLocker.lock(Absolute::assume_absolute(&path), Duration::from_millis(100));
+        assert!(matches!(result, Err(CoreError::LocationLocked { pid, .. }) if pid == process::id()));
+
+        drop(lock);
+        assert!(!path.exists());
+        FileLocker.lock(Absolute::assume_absolute(&path), Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_stale_lock_is_taken_over() {
+        let path = to_lock_path("stale");
+        fs::write(&path, format!("999999999\n{}\n", current_hostname())).unwrap();
+
+        let _lock = FileLocker.lock(Absolute::assume_absolute(&path), Duration::ZERO).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), LockOwner::current().to_string());
+    }
+
+    #[test]
+    fn test_lock_of_another_host_is_never_stale() {
+        let path = to_lock_path("remote");
+        fs::write(&path, "999999999\nanother-host\n").unwrap();
+
+        let result = FileLocker.lock(Absolute::assume_absolute(&path), Duration::ZERO);
+        assert!(matches!(result, Err(CoreError::LocationLocked { hostname, .. }) if hostname == "another-host"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_lock_of_missing_location_is_refused() {
+        let location_path = to_lock_path("missing");
+        let result = FileLocker.lock(Absolute::assume_absolute(location_path.join(".packster.lck")), Duration::ZERO);
+        assert!(matches!(result, Err(CoreError::LocationNotInitialized(path)) if path == location_path));
+        assert!(!location_path.exists());
+    }
+
+    #[test]
+    fn test_unidentified_lock_is_taken_over_once_released() {
+        let path = to_lock_path("unidentified");
+        fs::write(&path, "").unwrap();
+        let file = File::open(&path).unwrap();
+        file.lock().unwrap();
+
+        let start = Instant::now();
+        let result = FileLocker.lock(Absolute::assume_absolute(&path), Duration::from_millis(100));
+        assert!(matches!(result, Err(CoreError::LocationLockedByUnknownOwner(_))));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        drop(file);
+        let _lock = FileLocker.lock(Absolute::assume_absolute(&path), Duration::ZERO).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), LockOwner::current().to_string());
+    }
+
+    #[test]
+    fn test_lock_is_held_by_a_single_thread_at_once() {
+        let path = to_lock_path("contended");
+        let holders = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let (path, holders) = (path.clone(), holders.clone());
+                thread::spawn(move || {
+                    for _ in 0..5 {
+                        let _lock = FileLocker.lock(Absolute::assume_absolute(&path), Duration::from_secs(30)).unwrap();
+                        assert_eq!(holders.fetch_add(1, std::sync::atomic::Ordering::SeqCst), 0);
+                        thread::sleep(Duration::from_millis(1));
+                        holders.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+                    }
+                })
+            })
+            .collect();
+        threads.into_iter().for_each(|thread| thread.join().unwrap());
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,22 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use packster_core::application::port::Clock;
+
+/* A clock only moving when told to */
+#[derive(Default)]
+pub struct FixedClock(AtomicU64);
+
+impl FixedClock {
+    pub fn new(now: u64) -> Self {
+        FixedClock(AtomicU64::new(now))
+    }
+
+    pub fn advance(&self, seconds: u64) {
+        self.0.fetch_add(seconds, Ordering::SeqCst);
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,211 @@
+use std::{io, path::{Path, PathBuf}, time::Duration};
+use packster_core::{
+    application::{path::Absolute, port::{FileSystem, ReadOnlyFileSystem, Source}},
+    packaging::{application::validate_checksum, domain::{IndexedPackage, Package, SourceIndex}, SOURCE_INDEX_NAME},
+};
+
+use crate::{Error, Result, Sha2Digester};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/* Bounds the wait for each read, not the whole download */
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/* A web server exposing a packster-index.json and the package files it lists, both relative to its base url */
+pub struct HttpSource {
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+impl HttpSource {
+    pub fn new<S: Into<String>>(base_url: S) -> Self {
+        HttpSource {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            agent: ureq::AgentBuilder::new()
+                .timeout_connect(CONNECT_TIMEOUT)
+                .timeout_read(READ_TIMEOUT)
+                .build(),
+        }
+    }
+
+    pub fn as_base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn get(&self, path: &str) -> Result<Box<dyn io::Read + Send + Sync>> {
+        let response = self.agent
+            .get(&format!("{}/{path}", self.base_url))
+            .call()
+            .map_err(Error::from)?;
+        Ok(response.into_reader())
+    }
+}
+
+impl Source for HttpSource {
+    fn to_source_name(&self) -> String {
+        self.base_url.clone()
+    }
+
+    fn index<F: ReadOnlyFileSystem>(&self, _: &F) -> Result<SourceIndex> {
+        Ok(serde_json::from_reader(self.get(SOURCE_INDEX_NAME)?).map_err(Error::from)?)
+    }
+
+    /* The downloaded file is removed unless it is completely downloaded and its payload matches the checksum it is indexed with */
+    fn download<F: FileSystem, P: AsRef<Path>>(
+        &self,
+        filesystem: &F,
+        package: &IndexedPackage,
+        destination_directory: Absolute<P>,
+    ) -> Result<Absolute<PathBuf>> {
+        let destination_path = destination_directory.join(package.to_file_name());
+        filesystem.create_dir_recursively(destination_directory.as_ref())?;
+        let result = self.get(&package.to_path())
+            .and_then(|mut reader| {
+                let mut writer = filesystem.open_write(&destination_path)?;
+                io::copy(&mut reader, &mut writer).map_err(Error::from)?;
+                Ok(())
+            })
+            .and_then(|_| Package::from_path(&destination_path))
+            .and_then(|downloaded_package| validate_checksum(filesystem, &Sha2Digester::Sha256, &destination_path, &downloaded_package));
+        if let Err(error) = result {
+            if filesystem.exists(&destination_path) {
+                filesystem.remove_file(&destination_path)?;
+            }
+            return Err(error);
+        }
+        Ok(destination_path)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "test")]
+mod test {
+    use std::{collections::BTreeMap, io::{Read, Write}, net::TcpListener, str::FromStr, thread};
+    use tiny_http::{Response, Server};
+    use packster_core::{
+        Error as CoreError,
+        application::port::Digester,
+        domain::entity::{Checksum, Identifier, PackageRequirement, Version},
+        packaging::{application::write_package_header, domain::to_package_file_name},
+    };
+
+    use crate::InMemoryFileSystem;
+
+    use super::*;
+
+    const PAYLOAD: &[u8] = b"archive payload";
+
+    fn to_package_file(payload: &[u8]) -> Vec<u8> {
+        let mut file = Vec::new();
+        write_package_header(&mut file, "{}").unwrap();
+        file.extend_from_slice(payload);
+        file
+    }
+
+    fn to_indexed_package(identifier: &str, version: Version, checksum: Checksum) -> IndexedPackage {
+        let file_name = to_package_file_name(&Identifier::from_str(identifier).unwrap(), &version, &checksum, &Version::new(0, 1, 0));
+        let package = Package::from_path(&file_name).unwrap();
+        IndexedPackage::new(&package, file_name, to_package_file(PAYLOAD).len() as u64)
+    }
+
+    /* Serves the given files from their name until the test process ends */
+    fn serve(files: BTreeMap<String, Vec<u8>>) -> HttpSource {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}/", server.server_addr().to_ip().unwrap());
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let response = match files.get(request.url().trim_start_matches('/')) {
+                    Some(content) => Response::from_data(content.clone()),
+                    None => Response::from_data(Vec::new()).with_status_code(404),
+                };
+                let _ = request.respond(response);
+            }
+        });
+        HttpSource::new(base_url)
+    }
+
+    fn source(tampered_checksum: Checksum) -> HttpSource {
+        let checksum = Sha2Digester::Sha256.generate_checksum(PAYLOAD).unwrap();
+        let packages = vec![
+            to_indexed_package("tool", Version::new(1, 0, 0), checksum.clone()),
+            to_indexed_package("tool", Version::new(1, 2, 0), checksum),
+            to_indexed_package("tool", Version::new(2, 0, 0), tampered_checksum),
+        ];
+        let mut files: BTreeMap<String, Vec<u8>> = packages.iter()
+            .map(|package| (package.to_path(), to_package_file(PAYLOAD)))
+            .collect();
+        files.insert(SOURCE_INDEX_NAME.to_string(), serde_json::to_vec(&SourceIndex::new(packages)).unwrap());
+        serve(files)
+    }
+
+    #[test]
+    fn test_list_http_source() -> Result<()> {
+        let filesystem = InMemoryFileSystem::default();
+        let source = source(Checksum::from_str("d829752c")?);
+
+        assert_eq!(source.list_identifiers(&filesystem)?, vec![Identifier::from_str("tool")?]);
+        assert_eq!(
+            source.list_versions(&filesystem, &Identifier::from_str("tool")?)?,
+            vec![Version::new(1, 0, 0), Version::new(1, 2, 0), Version::new(2, 0, 0)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_fetch_from_http_source() -> Result<()> {
+        let filesystem = InMemoryFileSystem::default();
+        let source = source(Checksum::from_str("d829752c")?);
+        let destination = Absolute::assume_absolute(PathBuf::from("/cache"));
+
+        let fetched_path = source.fetch(&filesystem, &PackageRequirement::from_str("tool@^1")?, destination.as_absolute_path())?;
+        assert_eq!(Package::from_path(&fetched_path)?.as_version(), &Version::new(1, 2, 0));
+        assert!(filesystem.exists(&fetched_path));
+
+        let result = source.fetch(&filesystem, &PackageRequirement::from_str("tool@^3")?, destination.as_absolute_path());
+        assert!(matches!(result, Err(CoreError::PackageNotFoundInSource { .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fetch_tampered_package_from_http_source() -> Result<()> {
+        let filesystem = InMemoryFileSystem::default();
+        let source = source(Checksum::from_str("d829752c")?);
+        let destination = Absolute::assume_absolute(PathBuf::from("/cache"));
+
+        let result = source.fetch(&filesystem, &PackageRequirement::from_str("tool@^2")?, destination.as_absolute_path());
+        assert!(matches!(result, Err(CoreError::PackageChecksumDoNotMatch { .. })));
+        assert!(!filesystem.exists(destination.join("tool_2.0.0_d829752c.302e312e30.packster")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fetch_interrupted_download_from_http_source() -> Result<()> {
+        let filesystem = InMemoryFileSystem::default();
+        let checksum = Sha2Digester::Sha256.generate_checksum(PAYLOAD).unwrap();
+        let package = to_indexed_package("tool", Version::new(1, 0, 0), checksum);
+        let index = serde_json::to_vec(&SourceIndex::new(vec![package.clone()])).unwrap();
+        let package_file = to_package_file(PAYLOAD);
+
+        // The package file response announces more bytes than it sends before closing the connection
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}/", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = [0; 1024];
+                let length = stream.read(&mut request).unwrap_or_default();
+                let (body, content_length) = match String::from_utf8_lossy(&request[..length]).contains(SOURCE_INDEX_NAME) {
+                    true => (&index[..], index.len()),
+                    false => (&package_file[..package_file.len() / 2], package_file.len()),
+                };
+                let _ = write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {content_length}\r\n\r\n");
+                let _ = stream.write_all(body);
+            }
+        });
+        let destination = Absolute::assume_absolute(PathBuf::from("/cache"));
+
+        let result = HttpSource::new(base_url).fetch(&filesystem, &PackageRequirement::from_str("tool@^1")?, destination.as_absolute_path());
+        assert!(result.is_err());
+        assert!(!filesystem.exists(destination.join(package.to_file_name())));
+        Ok(())
+    }
+}
@@ -2,7 +2,8 @@ use std::{
     collections::BTreeMap,
     io::{self, Cursor, Read, Write},
     path::{Path, PathBuf},
-    sync::RwLock,
+    sync::{atomic::{AtomicU64, Ordering}, RwLock},
+    time::{Duration, SystemTime},
 };
 use packster_core::application::{
     path::{Absolute, NormalizedPathBuf},
@@ -12,7 +13,8 @@ use crate::{Error, Result};
 
 #[derive(Clone, Debug)]
 pub enum Node {
-    File(Vec<u8>),
+    // Content, along with the modification it was last written by
+    File(Vec<u8>, u64),
     Directory,
 }
 
@@ -23,7 +25,14 @@ pub enum Node {
  * - it propably doesn't have all the wanted behaviours for a virtual in-memory file system
  */
 #[derive(Default, Debug)]
-pub struct InMemoryFileSystem(RwLock<BTreeMap<NormalizedPathBuf, Node>>);
+pub struct InMemoryFileSystem(RwLock<BTreeMap<NormalizedPathBuf, Node>>, AtomicU64);
+
+impl InMemoryFileSystem {
+    /* Modifications are counted rather than timed, so that each one is strictly after the previous ones */
+    fn next_modification(&self) -> u64 {
+        self.1.fetch_add(1, Ordering::SeqCst) + 1
+    }
+}
 
 impl ReadOnlyFileSystem for InMemoryFileSystem {
     fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
@@ -44,7 +53,7 @@ impl ReadOnlyFileSystem for InMemoryFileSystem {
             .read()
             .unwrap()
             .get(&NormalizedPathBuf::from(path.as_ref()))
-            .filter(|node| matches!(node, Node::File(_)))
+            .filter(|node| matches!(node, Node::File(..)))
             .is_some()
     }
 
@@ -75,7 +84,7 @@ impl ReadOnlyFileSystem for InMemoryFileSystem {
             .unwrap()
             .get(&NormalizedPathBuf::from(path.as_ref()))
             .map(|node| match node {
-                Node::File(content) => Ok(String::from_utf8(content.to_vec()).unwrap()),
+                Node::File(content, _) => Ok(String::from_utf8(content.to_vec()).unwrap()),
                 _ => {
                     panic!("Path is not a file {:?}", path.as_ref());
                 }
@@ -99,7 +108,7 @@ impl ReadOnlyFileSystem for InMemoryFileSystem {
             .unwrap()
             .get(&NormalizedPathBuf::from(path.as_ref()))
             .map(|node| match node {
-                Node::File(content) => {
+                Node::File(content, _) => {
                     Ok(Box::new(Cursor::new(content.to_vec())) as Box<dyn Read + Send + Sync>)
                 }
                 _ => {
@@ -131,6 +140,17 @@ impl ReadOnlyFileSystem for InMemoryFileSystem {
         Box::new(buf.into_iter())
     }
 
+    fn read_dir<'a>(
+        &'a self,
+        target_path: &'a Path,
+    ) -> Box<dyn Iterator<Item = Result<DirEntry>> + 'a> {
+        let normalized_target_path = NormalizedPathBuf::from(target_path);
+        Box::new(
+            self.walk(target_path)
+                .filter(move |entry| !matches!(entry, Ok(entry) if entry.as_path().parent() != Some(normalized_target_path.as_ref())))
+        )
+    }
+
     fn file_size<P: AsRef<Path>>(&self, path: P) -> Result<u64> {
         let mut buffer = Vec::new();
         if self.is_directory(&path) {
@@ -142,6 +162,13 @@ impl ReadOnlyFileSystem for InMemoryFileSystem {
             Ok(buffer.len() as u64)
         }
     }
+
+    fn modified_at<P: AsRef<Path>>(&self, path: P) -> Result<SystemTime> {
+        match self.0.read().unwrap().get(&NormalizedPathBuf::from(path.as_ref())) {
+            Some(Node::File(_, modification)) => Ok(SystemTime::UNIX_EPOCH + Duration::from_nanos(*modification)),
+            _ => panic!("modified_at: Path is not a file ! {:?}", path.as_ref()),
+        }
+    }
 }
 
 impl FileSystem for InMemoryFileSystem {
@@ -157,7 +184,7 @@ impl FileSystem for InMemoryFileSystem {
 
         self.0.write().unwrap().insert(
             NormalizedPathBuf::from(path.as_ref()),
-            Node::File(Vec::new()),
+            Node::File(Vec::new(), self.next_modification()),
         );
         Ok(())
     }
@@ -188,7 +215,7 @@ impl FileSystem for InMemoryFileSystem {
 
         self.0.write().unwrap().insert(
             NormalizedPathBuf::from(path.as_ref()),
-            Node::File(buf.as_ref().to_vec()),
+            Node::File(buf.as_ref().to_vec(), self.next_modification()),
         );
         Ok(())
     }
@@ -199,9 +226,20 @@ impl FileSystem for InMemoryFileSystem {
         }
         let source_path = NormalizedPathBuf::from(source.as_ref());
         let destination_path = NormalizedPathBuf::from(destination.as_ref());
-        let node = { self.0.write().unwrap().remove(&source_path).unwrap() };
+        let mut btree_lock = self.0.write().unwrap();
+        let moved_paths: Vec<NormalizedPathBuf> = btree_lock
+            .keys()
+            .filter(|node_path| source_path.as_ref().is_ancestor_of(node_path))
+            .cloned()
+            .collect();
 
-        self.0.write().unwrap().insert(destination_path, node);
+        for moved_path in moved_paths {
+            let node = btree_lock.remove(&moved_path).unwrap();
+            let relative_path = moved_path.strip_prefix(&*source_path).unwrap();
+            btree_lock.insert(NormalizedPathBuf::from(destination_path.join(relative_path).as_path()), node);
+        }
+        let node = btree_lock.remove(&source_path).unwrap();
+        btree_lock.insert(destination_path, node);
         Ok(())
     }
 
@@ -213,18 +251,20 @@ impl FileSystem for InMemoryFileSystem {
         }
 
         let len = buf.as_ref().len();
+        let modification = self.next_modification();
         self.0
             .write()
             .unwrap()
             .entry(NormalizedPathBuf::from(path.as_ref()))
             .and_modify(|node| {
-                if let Node::File(content) = node {
-                    content.extend(buf.as_ref())
+                if let Node::File(content, modified_by) = node {
+                    content.extend(buf.as_ref());
+                    *modified_by = modification;
                 } else {
                     panic!("Path is not a file {:?}", path.as_ref());
                 }
             })
-            .or_insert(Node::File(buf.as_ref().to_vec()));
+            .or_insert(Node::File(buf.as_ref().to_vec(), modification));
 
         Ok(len)
     }
@@ -266,6 +306,24 @@ impl FileSystem for InMemoryFileSystem {
 
         Ok(())
     }
+
+    fn remove_file<P: AsRef<Path>>(&self, path: P) -> packster_core::Result<()> {
+        if !self.is_file(path.as_ref()) {
+            panic!("remove_file: Path is not a file ! {:?}", path.as_ref());
+        }
+        self.0
+            .write()
+            .unwrap()
+            .remove(&NormalizedPathBuf::from(path.as_ref()));
+        Ok(())
+    }
+
+    fn set_executable<P: AsRef<Path>>(&self, path: P) -> packster_core::Result<()> {
+        if !self.is_file(path.as_ref()) {
+            panic!("set_executable: Path is not a file ! {:?}", path.as_ref());
+        }
+        Ok(())
+    }
 }
 
 pub struct InMemoryFile<'a> {
@@ -309,11 +367,11 @@ impl Archiver for InMemoryFileSystem {
         Ok(())
     }
 
-    fn extract<F: FileSystem, P1: AsRef<Path>, P2: AsRef<Path>>(
+    fn extract_from<F: FileSystem, P: AsRef<Path>, R: Read>(
         &self,
         _filesystem: &F,
-        _expand_path: Absolute<P1>,
-        _archive_path: Absolute<P2>,
+        _expand_path: Absolute<P>,
+        _reader: R,
     ) -> Result<()> {
         unimplemented!()
     }
@@ -0,0 +1,50 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    process,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use packster_core::{
+    application::{path::Absolute, port::{Lock, Locker}},
+    Error,
+};
+use crate::Result;
+
+/* Locks shared by the clones of an InMemoryLocker, as the processes of a same host would */
+#[derive(Default, Clone)]
+pub struct InMemoryLocker(Arc<Mutex<BTreeSet<PathBuf>>>);
+
+impl InMemoryLocker {
+    pub fn is_locked<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.0.lock().unwrap().contains(path.as_ref())
+    }
+}
+
+impl Locker for InMemoryLocker {
+    fn lock<P: AsRef<Path>>(&self, path: Absolute<P>, wait: Duration) -> Result<Lock> {
+        let deadline = Instant::now() + wait;
+        loop {
+            if self.0.lock().unwrap().insert(path.to_path_buf()) {
+                return Ok(Lock::new(&path, InMemoryLockGuard(self.clone(), path.to_path_buf())));
+            }
+            if Instant::now() >= deadline {
+                return Err(Error::LocationLocked {
+                    path: path.to_path_buf(),
+                    pid: process::id(),
+                    hostname: String::from("localhost"),
+                });
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+struct InMemoryLockGuard(InMemoryLocker, PathBuf);
+
+impl Drop for InMemoryLockGuard {
+    fn drop(&mut self) {
+        self.0 .0.lock().unwrap().remove(&self.1);
+    }
+}
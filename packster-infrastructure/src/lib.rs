@@ -24,8 +24,41 @@ pub use crate::toml::Toml;
 mod json;
 pub use crate::json::Json;
 
+mod process_executor;
+pub use process_executor::ProcessExecutor;
+
+mod file_locker;
+pub use file_locker::FileLocker;
+
+mod directory_source;
+pub use directory_source::DirectorySource;
+
+mod http_source;
+pub use http_source::HttpSource;
+
+mod configured_source;
+pub use configured_source::ConfiguredSource;
+
+mod system_clock;
+pub use system_clock::SystemClock;
+
 
 #[cfg(feature = "test")]
 mod in_memory_filesystem;
 #[cfg(feature = "test")]
-pub use in_memory_filesystem::*;
\ No newline at end of file
+pub use in_memory_filesystem::*;
+
+#[cfg(feature = "test")]
+mod in_memory_locker;
+#[cfg(feature = "test")]
+pub use in_memory_locker::InMemoryLocker;
+
+#[cfg(feature = "test")]
+mod recording_executor;
+#[cfg(feature = "test")]
+pub use recording_executor::RecordingExecutor;
+
+#[cfg(feature = "test")]
+mod fixed_clock;
+#[cfg(feature = "test")]
+pub use fixed_clock::FixedClock;
@@ -0,0 +1,115 @@
+use std::{
+    io::Read,
+    process::{Command as ProcessCommand, Stdio},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use packster_core::application::port::{Command, ExecutionOutput, Executor};
+use crate::{Error, Result};
+
+const TIMEOUT_POLLING_INTERVAL: Duration = Duration::from_millis(10);
+
+/* Executes commands as child processes of the current one, capturing their outputs */
+#[derive(Default)]
+pub struct ProcessExecutor;
+
+fn capture<R: Read + Send + 'static>(reader: Option<R>) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut reader) = reader {
+            let _ = reader.read_to_end(&mut buffer);
+        }
+        String::from_utf8_lossy(&buffer).into_owned()
+    })
+}
+
+impl Executor for ProcessExecutor {
+    fn execute(&self, command: &Command) -> Result<ExecutionOutput> {
+        let mut child = ProcessCommand::new(command.as_program())
+            .args(command.as_arguments())
+            .envs(command.as_environment())
+            .current_dir(command.as_working_directory())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|error| Error::Spawn(command.to_command_line(), error))?;
+
+        let stdout = capture(child.stdout.take());
+        let stderr = capture(child.stderr.take());
+
+        let status = match command.as_timeout() {
+            None => child.wait().map_err(Error::from)?,
+            Some(timeout) => {
+                let start = Instant::now();
+                loop {
+                    if let Some(status) = child.try_wait().map_err(Error::from)? {
+                        break status;
+                    }
+                    if start.elapsed() >= timeout {
+                        child.kill().map_err(Error::from)?;
+                        child.wait().map_err(Error::from)?;
+                        return Err(packster_core::Error::ExecutionTimedOut {
+                            command: command.to_command_line(),
+                            timeout,
+                        });
+                    }
+                    thread::sleep(TIMEOUT_POLLING_INTERVAL);
+                }
+            }
+        };
+
+        Ok(ExecutionOutput::new(
+            status.code(),
+            stdout.join().unwrap_or_default(),
+            stderr.join().unwrap_or_default(),
+        ))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use std::{collections::BTreeMap, path::PathBuf};
+    use packster_core::application::path::Absolute;
+    use super::*;
+
+    fn shell(script: &str) -> Command {
+        Command::new(
+            String::from("sh"),
+            vec![String::from("-c"), String::from(script)],
+            Absolute::assume_absolute(PathBuf::from("/")),
+        )
+    }
+
+    #[test]
+    fn test_execute_captures_outputs() -> Result<()> {
+        let command = shell("echo \"$GREETING\" && pwd && echo oops >&2 && exit 3")
+            .with_environment(BTreeMap::from([(String::from("GREETING"), String::from("hello"))]));
+        let output = ProcessExecutor.execute(&command)?;
+
+        assert_eq!(output.as_status(), Some(3));
+        assert!(!output.is_success());
+        assert_eq!(output.as_stdout(), "hello\n/\n");
+        assert_eq!(output.as_stderr(), "oops\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_timeout() {
+        let command = shell("sleep 5").with_timeout(Some(Duration::from_millis(50)));
+        assert!(matches!(
+            ProcessExecutor.execute(&command),
+            Err(packster_core::Error::ExecutionTimedOut { .. })
+        ));
+    }
+
+    #[test]
+    fn test_execute_unknown_program() {
+        let command = Command::new(
+            String::from("/this/program/does/not/exist"),
+            Vec::new(),
+            Absolute::assume_absolute(PathBuf::from("/")),
+        );
+        assert!(ProcessExecutor.execute(&command).is_err());
+    }
+}
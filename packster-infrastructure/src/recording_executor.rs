@@ -0,0 +1,34 @@
+use std::{collections::BTreeMap, sync::Mutex};
+use packster_core::application::port::{Command, ExecutionOutput, Executor};
+use crate::Result;
+
+/* Records executed commands without executing them, succeeding unless told otherwise for a given program */
+#[derive(Default, Debug)]
+pub struct RecordingExecutor {
+    commands: Mutex<Vec<Command>>,
+    outputs: BTreeMap<String, ExecutionOutput>,
+}
+
+impl RecordingExecutor {
+    /* Commands whose program ends with the given one will output the given result */
+    pub fn with_output<S: Into<String>>(mut self, program: S, output: ExecutionOutput) -> Self {
+        self.outputs.insert(program.into(), output);
+        self
+    }
+
+    pub fn to_commands(&self) -> Vec<Command> {
+        self.commands.lock().unwrap().clone()
+    }
+}
+
+impl Executor for RecordingExecutor {
+    fn execute(&self, command: &Command) -> Result<ExecutionOutput> {
+        self.commands.lock().unwrap().push(command.clone());
+        Ok(self
+            .outputs
+            .iter()
+            .find(|(program, _)| command.as_program().ends_with(program.as_str()))
+            .map(|(_, output)| output.clone())
+            .unwrap_or_else(|| ExecutionOutput::new(Some(0), String::new(), String::new())))
+    }
+}
@@ -2,6 +2,7 @@ use std::{
     fs::{self, File},
     io::{Read, Write},
     path::Path,
+    time::SystemTime,
 };
 use walkdir::WalkDir;
 use packster_core::application::{
@@ -41,24 +42,37 @@ impl ReadOnlyFileSystem for StdFileSystem {
         &'a self,
         target_path: &'a Path,
     ) -> Box<dyn Iterator<Item = Result<DirEntry>> + 'a> {
-        Box::new(WalkDir::new(target_path).into_iter().map(|entry| {
-            entry
-                .and_then(|entry| {
-                    entry
-                        .metadata()
-                        .map(|metadata| (metadata.len(), entry.path().to_normalized_path()))
-                })
-                .map_err(|e| Error::from(e).into())
-                .and_then(|(len, normalized_path)| {
-                    Absolute::try_absolute(normalized_path)
-                        .map(|absolute_path| DirEntry::new(absolute_path, len))
-                })
-        }))
+        Box::new(WalkDir::new(target_path).into_iter().map(to_dir_entry))
+    }
+
+    fn read_dir<'a>(
+        &'a self,
+        target_path: &'a Path,
+    ) -> Box<dyn Iterator<Item = Result<DirEntry>> + 'a> {
+        Box::new(WalkDir::new(target_path).min_depth(1).max_depth(1).into_iter().map(to_dir_entry))
     }
 
     fn file_size<P: AsRef<Path>>(&self, path: P) -> Result<u64> {
         Ok(path.as_ref().metadata().map_err(Error::from)?.len())
     }
+
+    fn modified_at<P: AsRef<Path>>(&self, path: P) -> Result<SystemTime> {
+        Ok(path.as_ref().metadata().and_then(|metadata| metadata.modified()).map_err(Error::from)?)
+    }
+}
+
+fn to_dir_entry(entry: walkdir::Result<walkdir::DirEntry>) -> Result<DirEntry> {
+    entry
+        .and_then(|entry| {
+            entry
+                .metadata()
+                .map(|metadata| (metadata.len(), entry.path().to_normalized_path()))
+        })
+        .map_err(|e| Error::from(e).into())
+        .and_then(|(len, normalized_path)| {
+            Absolute::try_absolute(normalized_path)
+                .map(|absolute_path| DirEntry::new(absolute_path, len))
+        })
 }
 
 impl FileSystem for StdFileSystem {
@@ -68,9 +82,7 @@ impl FileSystem for StdFileSystem {
     }
 
     fn create_dir<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        fs::create_dir(path)
-            .map_err(Error::from)
-            .map_err(Error::from)?;
+        fs::create_dir(path).map_err(Error::from)?;
         Ok(())
     }
 
@@ -101,4 +113,22 @@ impl FileSystem for StdFileSystem {
     fn remove_dir_all<P: AsRef<Path>>(&self, path: P) -> packster_core::Result<()> {
         Ok(fs::remove_dir_all(path).map_err(Error::from)?)
     }
+
+    fn remove_file<P: AsRef<Path>>(&self, path: P) -> packster_core::Result<()> {
+        Ok(fs::remove_file(path).map_err(Error::from)?)
+    }
+
+    #[cfg(unix)]
+    fn set_executable<P: AsRef<Path>>(&self, path: P) -> packster_core::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+        let mut permissions = fs::metadata(&path).map_err(Error::from)?.permissions();
+        permissions.set_mode(permissions.mode() | 0o111);
+        Ok(fs::set_permissions(path, permissions).map_err(Error::from)?)
+    }
+
+    /* Executability is not a permission on other platforms */
+    #[cfg(not(unix))]
+    fn set_executable<P: AsRef<Path>>(&self, _path: P) -> packster_core::Result<()> {
+        Ok(())
+    }
 }
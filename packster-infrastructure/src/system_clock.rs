@@ -0,0 +1,15 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use packster_core::application::port::Clock;
+
+#[derive(Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    /* A system clock set before the unix epoch gives the epoch */
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default()
+    }
+}
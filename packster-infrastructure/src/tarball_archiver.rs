@@ -1,5 +1,5 @@
 use std::{
-    io::{self, empty},
+    io::{self, empty, Read},
     path::Path,
 };
 use flate2::{read::GzDecoder, write::GzEncoder, Compression};
@@ -62,13 +62,12 @@ impl Archiver for TarballArchiver {
         Ok(())
     }
 
-    fn extract<F: FileSystem, P1: AsRef<Path>, P2: AsRef<Path>>(
+    fn extract_from<F: FileSystem, P: AsRef<Path>, R: Read>(
         &self,
         filesystem: &F,
-        expand_path: Absolute<P1>,
-        archive_path: Absolute<P2>,
+        expand_path: Absolute<P>,
+        reader: R,
     ) -> Result<()> {
-        let reader = filesystem.open_read(archive_path)?;
         let decoder = GzDecoder::new(reader);
         let mut archive = Archive::new(decoder);
 
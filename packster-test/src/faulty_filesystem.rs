@@ -0,0 +1,128 @@
+use std::{
+    io::{self, Read, Write},
+    path::Path,
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use packster_core::{
+    application::port::{DirEntry, FileSystem, ReadOnlyFileSystem},
+    Error, Result,
+};
+
+/* Fails the nth modifying operation on the underlying filesystem, once, the following ones succeeding again */
+pub struct FaultyFileSystem<'a, F> {
+    filesystem: &'a F,
+    faulty_operation: usize,
+    operation_count: Mutex<usize>,
+}
+
+impl<'a, F: FileSystem> FaultyFileSystem<'a, F> {
+    pub fn new(filesystem: &'a F, faulty_operation: usize) -> Self {
+        FaultyFileSystem {
+            filesystem,
+            faulty_operation,
+            operation_count: Mutex::new(0),
+        }
+    }
+
+    pub fn is_fault_injected(&self) -> bool {
+        *self.operation_count.lock().unwrap() > self.faulty_operation
+    }
+
+    fn inject_fault(&self, operation: &str, path: &Path) -> Result<()> {
+        let mut operation_count = self.operation_count.lock().unwrap();
+        let current_operation = *operation_count;
+        *operation_count += 1;
+        if current_operation == self.faulty_operation {
+            Err(Error::Io(io::Error::other(format!("Injected fault on {operation} {}", path.to_string_lossy()))))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<'a, F: FileSystem> ReadOnlyFileSystem for FaultyFileSystem<'a, F> {
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.filesystem.exists(path)
+    }
+
+    fn is_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.filesystem.is_file(path)
+    }
+
+    fn is_directory<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.filesystem.is_directory(path)
+    }
+
+    fn read_to_string<P: AsRef<Path>>(&self, path: P) -> Result<String> {
+        self.filesystem.read_to_string(path)
+    }
+
+    fn open_read<P: AsRef<Path>>(&self, path: P) -> Result<Box<dyn Read + Send + Sync>> {
+        self.filesystem.open_read(path)
+    }
+
+    fn walk<'b>(&'b self, target_path: &'b Path) -> Box<dyn Iterator<Item = Result<DirEntry>> + 'b> {
+        self.filesystem.walk(target_path)
+    }
+
+    fn read_dir<'b>(&'b self, target_path: &'b Path) -> Box<dyn Iterator<Item = Result<DirEntry>> + 'b> {
+        self.filesystem.read_dir(target_path)
+    }
+
+    fn file_size<P: AsRef<Path>>(&self, path: P) -> Result<u64> {
+        self.filesystem.file_size(path)
+    }
+
+    fn modified_at<P: AsRef<Path>>(&self, path: P) -> Result<SystemTime> {
+        self.filesystem.modified_at(path)
+    }
+}
+
+impl<'a, F: FileSystem> FileSystem for FaultyFileSystem<'a, F> {
+    fn create<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.inject_fault("create", path.as_ref())?;
+        self.filesystem.create(path)
+    }
+
+    fn create_dir<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.inject_fault("create_dir", path.as_ref())?;
+        self.filesystem.create_dir(path)
+    }
+
+    fn write_all<P: AsRef<Path>, B: AsRef<[u8]>>(&self, path: P, buf: B) -> Result<()> {
+        self.inject_fault("write_all", path.as_ref())?;
+        self.filesystem.write_all(path, buf)
+    }
+
+    fn rename<P: AsRef<Path>>(&self, source: P, destination: P) -> Result<()> {
+        self.inject_fault("rename", source.as_ref())?;
+        self.filesystem.rename(source, destination)
+    }
+
+    fn append<P: AsRef<Path>, B: AsRef<[u8]>>(&self, path: P, buf: B) -> Result<usize> {
+        self.inject_fault("append", path.as_ref())?;
+        self.filesystem.append(path, buf)
+    }
+
+    fn open_write<'b, P: AsRef<Path>>(&'b self, path: P) -> Result<Box<dyn Write + Send + Sync + 'b>> {
+        self.inject_fault("open_write", path.as_ref())?;
+        self.filesystem.open_write(path)
+    }
+
+    fn remove_dir_all<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.inject_fault("remove_dir_all", path.as_ref())?;
+        self.filesystem.remove_dir_all(path)
+    }
+
+    fn remove_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.inject_fault("remove_file", path.as_ref())?;
+        self.filesystem.remove_file(path)
+    }
+
+    fn set_executable<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.inject_fault("set_executable", path.as_ref())?;
+        self.filesystem.set_executable(path)
+    }
+}
@@ -1,4 +1,5 @@
 #![cfg(test)]
 pub mod stub;
+pub mod faulty_filesystem;
 
 mod packaging;
\ No newline at end of file
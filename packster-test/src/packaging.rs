@@ -5,22 +5,27 @@ use std::{
     matches,
     path::{Path, PathBuf},
     str::FromStr,
+    thread,
+    time::Duration,
 };
 
 use packster_core::{
     application::{
         operation::Operation,
         path::Absolute,
-        port::{Digester, FileSystem, ReadOnlyFileSystem, UniqueIdentifierGenerator},
+        port::{Command, Digester, ExecutionOutput, Executor, FileSystem, Locker, ReadOnlyFileSystem, Source, UniqueIdentifierGenerator},
     },
-    domain::entity::Checksum,
-    packaging::{application::*, LOCKFILE_NAME},
+    domain::entity::{Checksum, Identifier, PackageRequirement, ParameterValue, Parameters, Version},
+    packaging::{application::*, domain::{AsLocation, AsPackage, CacheIssue, CachedPackage, Deployment, DeploymentSelector, IndexedPackage, LocationIssue, Package, PackageCache, SourceIndex}, LOCKFILE_NAME, SOURCE_INDEX_NAME},
     Error, Result,
 };
 
-use packster_infrastructure::{InMemoryFileSystem, Json, Sha2Digester, TarballArchiver, Toml};
+use packster_infrastructure::{
+    DirectorySource, FileLocker, FixedClock, HttpSource, InMemoryFileSystem, InMemoryLocker, Json, RecordingExecutor, Sha2Digester, StdFileSystem,
+    TarballArchiver, Toml, UniqidIdentifierGenerator,
+};
 
-use crate::stub;
+use crate::{faulty_filesystem::FaultyFileSystem, stub};
 
 #[test]
 fn test_static_packing() -> Result<()> {
@@ -64,10 +69,11 @@ fn test_static_packing() -> Result<()> {
     let request = PackRequest::new(project_workspace, output_directory);
     Operation::new(request)
         .parse_project(&filesystem, &Toml)?
+        .execute_pack_hooks(&RecordingExecutor::default())?
         .generate_unique_identity(&UniqueIdentifierGeneratorMock)
         .archive(&filesystem, &filesystem_as_archiver)?
         .digest(&filesystem, &DigesterMock)?
-        .finalize(&filesystem, APP_VERSION)?;
+        .finalize(&filesystem, &Json, APP_VERSION)?;
 
     let package_path = format!("/repo/static-package-a_0.0.1_ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad.{}.packster", hex::encode(APP_VERSION.as_bytes()));
 
@@ -81,6 +87,46 @@ fn test_static_packing() -> Result<()> {
         String::from("Hello world !")
     );
 
+    filesystem.rename(package_path.as_str(), "/repo/renamed.packster")?;
+    let request = DeployRequest::new(
+        Absolute::assume_absolute(PathBuf::from("/repo/renamed.packster")),
+        Absolute::assume_absolute(PathBuf::from("/location")),
+        Parameters::new(),
+    );
+    let operation = Operation::new(request).parse_package_metadata(&filesystem, &Json)?;
+    let package = operation.as_package();
+
+    assert_eq!(package.as_identifier().to_string(), "static-package-a");
+    assert_eq!(package.as_version().to_string(), "0.0.1");
+    assert_eq!(package.as_packster_version().to_string(), APP_VERSION);
+    assert_eq!(
+        package.as_checksum().to_string(),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_packing_invalid_manifests() -> Result<()> {
+    for (manifest, reason) in [
+        ("identifier = \"static-package-a\"\nversion = \"v1.2\"\n", "version is not semantic"),
+        ("identifier = \"static_package_a\"\nversion = \"0.0.1\"\n", "identifier contains an underscore"),
+    ] {
+        let filesystem = InMemoryFileSystem::default();
+        filesystem.create_dir("/project")?;
+        filesystem.create_dir("/repo")?;
+        filesystem.write_all("/project/packster.toml", manifest.as_bytes())?;
+
+        let project_workspace = Absolute::assume_absolute(PathBuf::from("/project"));
+        let output_directory = Absolute::assume_absolute(PathBuf::from("/repo"));
+        let request = PackRequest::new(project_workspace, output_directory);
+        let result = Operation::new(request).parse_project(&filesystem, &Toml);
+
+        assert!(result.is_err(), "packing shall fail when the {reason}");
+        assert_eq!(filesystem.walk(Path::new("/repo")).count(), 0, "no package file should be written when the {reason}");
+    }
+
     Ok(())
 }
 
@@ -91,12 +137,15 @@ fn test_init_location_initialization_case() -> Result<()> {
 
     let request =
         InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/my/location")));
-    Operation::new(request).initialize_lockfile(&filesystem, &Json)?;
+    Operation::new(request)
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
 
     let expected_lockfile_path = Path::new("/my/location").join(LOCKFILE_NAME);
     assert!(filesystem.exists(&expected_lockfile_path));
 
-    let expected_lockfile_content = "{\"deployments\":[]}";
+    let expected_lockfile_content = "{\"schema_version\":4,\"deployments\":[]}";
     assert_eq!(
         filesystem.read_to_string(expected_lockfile_path)?,
         expected_lockfile_content
@@ -105,6 +154,82 @@ fn test_init_location_initialization_case() -> Result<()> {
     Ok(())
 }
 
+fn init_registered_location(filesystem: &InMemoryFileSystem, location_path: &str, name: &str, is_default: bool) -> Result<()> {
+    let registration = LocationRegistration::new(
+        Absolute::assume_absolute(PathBuf::from("/config/packster/packster-config.toml")),
+        Identifier::from_str(name)?,
+        is_default,
+    );
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from(location_path))).with_registration(registration))
+        .probe_location_registration(filesystem, &Toml)?
+        .create_location_directory(filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(filesystem, &Json)?
+        .register_location(filesystem, &Toml, &Toml, &InMemoryLocker::default(), Duration::ZERO)?;
+    Ok(())
+}
+
+#[test]
+fn test_init_location_registration() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    let config_request = || UserConfigRequest::new(Absolute::assume_absolute(PathBuf::from("/config/packster/packster-config.toml")));
+    assert_eq!(Operation::new(config_request()).parse_user_config(&filesystem, &Toml)?.as_user_config().iter_locations().count(), 0);
+
+    init_registered_location(&filesystem, "/work", "work", false)?;
+    init_registered_location(&filesystem, "/home", "home", false)?;
+    let operation = Operation::new(config_request()).parse_user_config(&filesystem, &Toml)?;
+    let config = operation.as_user_config();
+    assert_eq!(config.get_default_location(), Some((&Identifier::from_str("work")?, Path::new("/work"))));
+    assert_eq!(config.get_location(&Identifier::from_str("home")?), Some(Path::new("/home")));
+
+    init_registered_location(&filesystem, "/other", "home", true)
+        .expect_err("a registered name shall not be registered again for another location");
+    assert!(!filesystem.exists("/other"));
+
+    init_registered_location(&filesystem, "/office", "office", true)?;
+    let operation = Operation::new(config_request()).parse_user_config(&filesystem, &Toml)?;
+    assert!(operation.as_user_config().is_default_location(&Identifier::from_str("office")?));
+    assert!(filesystem.exists(Path::new("/office").join(LOCKFILE_NAME)));
+    Ok(())
+}
+
+#[test]
+fn test_init_location_registration_race() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    let locker = InMemoryLocker::default();
+    let probe_registration = |location_path: &str, name: &str| -> Result<_> {
+        let config_path = Absolute::assume_absolute(PathBuf::from("/config/packster/packster-config.toml"));
+        let registration = LocationRegistration::new(config_path, Identifier::from_str(name)?, false);
+        Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from(location_path))).with_registration(registration))
+            .probe_location_registration(&filesystem, &Toml)?
+            .create_location_directory(&filesystem)?
+            .lock_location(&locker, Duration::ZERO)?
+            .initialize_lockfile(&filesystem, &Json)
+    };
+
+    let operation = probe_registration("/second", "work")?;
+    init_registered_location(&filesystem, "/first", "work", false)?;
+    let result = operation.register_location(&filesystem, &Toml, &Toml, &locker, Duration::ZERO);
+    assert!(matches!(result, Err(Error::LocationNameAlreadyRegistered { .. })));
+    assert!(!filesystem.exists("/second"), "a failed registration shall remove the created location directory");
+
+    filesystem.create_dir("/third")?;
+    let operation = probe_registration("/third", "third")?;
+    let config_lock = locker.lock(Absolute::assume_absolute("/config/packster/.packster.lck"), Duration::ZERO)?;
+    let result = operation.register_location(&filesystem, &Toml, &Toml, &locker, Duration::ZERO);
+    assert!(matches!(result, Err(Error::LocationLocked { .. })));
+    assert!(filesystem.is_directory("/third"), "a location directory which existed beforehand shall be kept");
+    assert!(!filesystem.exists(Path::new("/third").join(LOCKFILE_NAME)));
+
+    drop(config_lock);
+    probe_registration("/third", "third")?.register_location(&filesystem, &Toml, &Toml, &locker, Duration::ZERO)?;
+    let config_request = UserConfigRequest::new(Absolute::assume_absolute(PathBuf::from("/config/packster/packster-config.toml")));
+    let operation = Operation::new(config_request).parse_user_config(&filesystem, &Toml)?;
+    assert_eq!(operation.as_user_config().iter_locations().count(), 2);
+    assert!(!filesystem.exists("/config/packster/packster-config.toml.tmp"));
+    Ok(())
+}
+
 #[test]
 fn test_deployment_new_package() -> Result<()> {
     let filesystem = InMemoryFileSystem::default();
@@ -122,17 +247,25 @@ fn test_deployment_new_package() -> Result<()> {
     let request = DeployRequest::new(
         Absolute::assume_absolute(PathBuf::from("/my/my-simple-package_0.0.1_d829752c10db8f7a98c939b5418beb0a360c6a6b818830e000f2c5a8dce35af4.302e312e30.packster")),
         Absolute::assume_absolute(PathBuf::from("/my/location")),
+        Parameters::new(),
     );
 
     Operation::new(request)
-        .parse_package_path()?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .parse_package_metadata(&filesystem, &Json)?
         .parse_location_lockfile(&filesystem, &Json)?
+        .identify_deployment(&Sha2Digester::Sha256)?
         .probe_package_not_deployed_in_location()?
+        .probe_dependencies_deployed_in_location()?
+        .probe_no_resource_conflict()?
         .validate_package_checksum(&filesystem, &Sha2Digester::Sha256)?
         .guess_deployment_path()
         .extract_package(&filesystem, &TarballArchiver)?
-        .add_deployment_to_location()
-        .persist_location_lockfile(&filesystem, &Json)?;
+        .execute_deploy_hooks(&filesystem, &RecordingExecutor::default())?
+        .index_deployment_files(&filesystem, &Sha2Digester::Sha256)?
+        .add_deployment_to_location("0.1.4")?
+        .persist_location_lockfile(&filesystem, &Json)?
+        .generate_location_shims(&filesystem)?;
 
     assert!(filesystem
         .exists("/my/location/d829752c10db8f7a98c939b5418beb0a360c6a6b818830e000f2c5a8dce35af4"));
@@ -170,6 +303,49 @@ fn test_deployment_new_package() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_deployment_file_name_do_not_match_metadata() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    filesystem.create_dir_recursively("/my/location")?;
+    let package_bytes = stub::get_simple_package_bytes();
+    let renamed_package_path = "/my/my-simple-package_0.0.2_d829752c10db8f7a98c939b5418beb0a360c6a6b818830e000f2c5a8dce35af4.302e312e30.packster";
+    filesystem.open_write(renamed_package_path)?.write_all(&package_bytes).unwrap();
+
+    let request = DeployRequest::new(
+        Absolute::assume_absolute(PathBuf::from(renamed_package_path)),
+        Absolute::assume_absolute(PathBuf::from("/my/location")),
+        Parameters::new(),
+    );
+
+    let result = Operation::new(request).parse_package_metadata(&filesystem, &Json);
+
+    assert!(matches!(
+        result,
+        Err(Error::PackageMetadataDoNotMatchFileName { .. })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_deployment_package_without_header() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    filesystem.create_dir_recursively("/my/location")?;
+    filesystem.write_all("/my/not-a-package.packster", b"\x1f\x8b\x08\x00")?;
+
+    let request = DeployRequest::new(
+        Absolute::assume_absolute(PathBuf::from("/my/not-a-package.packster")),
+        Absolute::assume_absolute(PathBuf::from("/my/location")),
+        Parameters::new(),
+    );
+
+    let result = Operation::new(request).parse_package_metadata(&filesystem, &Json);
+
+    assert!(matches!(result, Err(Error::MissingPackageHeader(_))));
+
+    Ok(())
+}
+
 #[test]
 fn test_deployment_already_existing_package() -> Result<()> {
     let filesystem = InMemoryFileSystem::default();
@@ -197,11 +373,14 @@ fn test_deployment_already_existing_package() -> Result<()> {
     let request = DeployRequest::new(
         Absolute::assume_absolute(PathBuf::from("/my/my-simple-package_0.0.1_d829752c10db8f7a98c939b5418beb0a360c6a6b818830e000f2c5a8dce35af4.302e312e30.packster")),
         Absolute::assume_absolute(PathBuf::from("/my/location")),
+        Parameters::new(),
     );
 
     let result = Operation::new(request)
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
         .parse_package_path()?
         .parse_location_lockfile(&filesystem, &Json)?
+        .identify_deployment(&Sha2Digester::Sha256)?
         .probe_package_not_deployed_in_location();
 
     assert!(matches!(
@@ -221,7 +400,10 @@ fn test_init_location_lockfile_present_error_case() -> Result<()> {
 
     let request =
         InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/my/location")));
-    let result = Operation::new(request).initialize_lockfile(&filesystem, &Json);
+    let result = Operation::new(request)
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json);
 
     assert!(matches!(
         result,
@@ -261,11 +443,16 @@ fn test_undeploy_existing_deployment() -> Result<()> {
     );
 
     Operation::new(request)
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
         .parse_location_lockfile(&filesystem, &Json)?
         .probe_package_already_deployed_in_location()?
+        .probe_no_dependent_deployments()?
+        .probe_active_deployment_reassignment()?
         .guess_deployment_path()
+        .execute_undeploy_hooks(&RecordingExecutor::default())?
         .remove_deployment_from_location()
         .persist_location_lockfile(&filesystem, &Json)?
+        .generate_location_shims(&filesystem)?
         .delete_deployment_directory(&filesystem)?;
 
     assert!(!filesystem
@@ -273,7 +460,7 @@ fn test_undeploy_existing_deployment() -> Result<()> {
     assert!(!filesystem.exists("/my/location/d829752c10db8f7a98c939b5418beb0a360c6a6b818830e000f2c5a8dce35af4/packster.toml"));
     assert_eq!(
         filesystem.read_to_string(lockfile_path)?,
-        r#"{"deployments":[]}"#
+        r#"{"schema_version":4,"deployments":[]}"#
     );
 
     Ok(())
@@ -299,6 +486,7 @@ fn test_undeploy_not_existing_deployment() -> Result<()> {
     );
 
     let result = Operation::new(request)
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
         .parse_location_lockfile(&filesystem, &Json)?
         .probe_package_already_deployed_in_location();
 
@@ -309,3 +497,1733 @@ fn test_undeploy_not_existing_deployment() -> Result<()> {
 
     Ok(())
 }
+
+fn pack_project(filesystem: &InMemoryFileSystem, project_path: &str, manifest: &str) -> Result<Absolute<PathBuf>> {
+    pack_project_with_executor(filesystem, project_path, manifest, &RecordingExecutor::default())
+}
+
+fn pack_project_with_executor(filesystem: &InMemoryFileSystem, project_path: &str, manifest: &str, executor: &RecordingExecutor) -> Result<Absolute<PathBuf>> {
+    filesystem.create_dir_recursively(project_path)?;
+    filesystem.create_dir_recursively("/repo")?;
+    filesystem.write_all(Path::new(project_path).join("packster.toml"), manifest.as_bytes())?;
+
+    let request = PackRequest::new(
+        Absolute::assume_absolute(PathBuf::from(project_path)),
+        Absolute::assume_absolute(PathBuf::from("/repo")),
+    );
+    let operation = Operation::new(request)
+        .parse_project(filesystem, &Toml)?
+        .execute_pack_hooks(executor)?
+        .generate_unique_identity(&UniqidIdentifierGenerator::default())
+        .archive(filesystem, &TarballArchiver)?
+        .digest(filesystem, &Sha2Digester::Sha256)?
+        .finalize(filesystem, &Json, "0.1.4")?;
+
+    Ok(Absolute::assume_absolute(Path::new("/repo").join(operation.as_state().to_file_name())))
+}
+
+fn deploy_package(filesystem: &InMemoryFileSystem, package_path: Absolute<PathBuf>, location_path: &str) -> Result<()> {
+    deploy_package_with_parameters(filesystem, package_path, location_path, Parameters::new())
+}
+
+fn deploy_package_with_parameters(filesystem: &InMemoryFileSystem, package_path: Absolute<PathBuf>, location_path: &str, parameters: Parameters) -> Result<()> {
+    deploy_package_with_executor(filesystem, package_path, location_path, parameters, &RecordingExecutor::default())
+}
+
+fn deploy_package_with_executor<F: FileSystem, E: Executor>(
+    filesystem: &F,
+    package_path: Absolute<PathBuf>,
+    location_path: &str,
+    parameters: Parameters,
+    executor: &E,
+) -> Result<()> {
+    deploy_package_with_locker(filesystem, &InMemoryLocker::default(), Duration::ZERO, package_path, location_path, parameters, executor)?;
+    Ok(())
+}
+
+fn deploy_package_with_locker<F: FileSystem, L: Locker, E: Executor>(
+    filesystem: &F,
+    locker: &L,
+    wait: Duration,
+    package_path: Absolute<PathBuf>,
+    location_path: &str,
+    parameters: Parameters,
+    executor: &E,
+) -> Result<Deployment> {
+    let request = DeployRequest::new(package_path, Absolute::assume_absolute(PathBuf::from(location_path)), parameters);
+    let operation = Operation::new(request)
+        .lock_location(locker, wait)?
+        .parse_package_metadata(filesystem, &Json)?
+        .parse_location_lockfile(filesystem, &Json)?
+        .identify_deployment(&Sha2Digester::Sha256)?
+        .probe_package_not_deployed_in_location()?
+        .probe_dependencies_deployed_in_location()?
+        .probe_no_resource_conflict()?
+        .validate_package_checksum(filesystem, &Sha2Digester::Sha256)?
+        .guess_deployment_path()
+        .extract_package(filesystem, &TarballArchiver)?
+        .execute_deploy_hooks(filesystem, executor)?
+        .index_deployment_files(filesystem, &Sha2Digester::Sha256)?
+        .add_deployment_to_location("0.1.4")?
+        .persist_location_lockfile(filesystem, &Json)?
+        .generate_location_shims(filesystem)?;
+    Ok(operation.as_deployment().clone())
+}
+
+#[test]
+fn test_deployment_with_dependencies() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
+
+    let tool_path = pack_project(&filesystem, "/tool", indoc! {r#"
+        identifier = "tool"
+        version = "1.2.0"
+    "#})?;
+    let app_path = pack_project(&filesystem, "/app", indoc! {r#"
+        identifier = "app"
+        version = "0.1.0"
+
+        [dependencies]
+        tool = "^1"
+    "#})?;
+
+    let result = deploy_package(&filesystem, app_path.clone(), "/location");
+    assert!(matches!(result, Err(Error::UnresolvableDependency { .. })));
+
+    deploy_package(&filesystem, tool_path, "/location")?;
+    deploy_package(&filesystem, app_path, "/location")?;
+
+    let lockfile: serde_json::Value = serde_json::from_str(&filesystem.read_to_string("/location/packster.lock")?).unwrap();
+    let deployments = lockfile["deployments"].as_array().unwrap();
+    assert_eq!(deployments.len(), 2);
+    assert_eq!(deployments[0]["identifier"], "tool");
+    assert_eq!(deployments[0]["dependents"][0]["identifier"], "app");
+    assert_eq!(deployments[0]["dependents"][0]["requirement"], "^1");
+    assert_eq!(deployments[0]["dependents"][0]["deployment_id"], deployments[1]["id"]);
+    assert_eq!(deployments[1]["dependencies"]["tool"], "^1");
+
+    /* A required deployment is only undeployed when forced */
+    let tool_id = deployments[0]["id"].as_str().unwrap();
+    let result = undeploy_deployment(&filesystem, tool_id, "/location", &RecordingExecutor::default());
+    assert!(matches!(result, Err(Error::RequiredDeploymentUndeployment { dependents, .. }) if dependents.len() == 1));
+    assert!(filesystem.is_directory(format!("/location/{tool_id}")));
+
+    let request = UndeployRequest::new(Checksum::from_str(tool_id)?, Absolute::assume_absolute(PathBuf::from("/location"))).with_force(true);
+    undeploy_with_request(&filesystem, request, &RecordingExecutor::default())?;
+    let lockfile: serde_json::Value = serde_json::from_str(&filesystem.read_to_string("/location/packster.lock")?).unwrap();
+    assert_eq!(lockfile["deployments"].as_array().unwrap().len(), 1);
+    assert_eq!(lockfile["deployments"][0]["identifier"], "app");
+
+    Ok(())
+}
+
+#[test]
+fn test_bundle_install_and_delete() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
+
+    pack_project(&filesystem, "/tool", indoc! {r#"
+        identifier = "tool"
+        version = "1.2.0"
+    "#})?;
+    pack_project(&filesystem, "/app", indoc! {r#"
+        identifier = "app"
+        version = "0.1.0"
+
+        [dependencies]
+        tool = "^1"
+
+        [parameters.port]
+        type = "integer"
+    "#})?;
+    filesystem.write_all("/bundle.toml", indoc! {r#"
+        identifier = "workstation"
+        source = "repo"
+
+        [packages.app]
+        requirement = "^0.1"
+        parameters = { port = 8080 }
+    "#}.as_bytes())?;
+
+    let bundle_path = Absolute::assume_absolute(PathBuf::from("/bundle.toml"));
+    let location_path = Absolute::assume_absolute(PathBuf::from("/location"));
+    Operation::new(InstallBundleRequest::new(bundle_path.clone(), location_path.clone()))
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .parse_bundle_manifest(&filesystem, &Toml)?
+        .parse_location_lockfile(&filesystem, &Json)?
+        .index_bundle_source(&filesystem, &Json)?
+        .resolve_bundle(&Sha2Digester::Sha256, "0.1.4")?
+        .validate_bundle_checksums(&filesystem, &Sha2Digester::Sha256)?
+        .extract_bundle_packages(&filesystem, &TarballArchiver)?
+        .execute_bundle_deploy_hooks(&filesystem, &RecordingExecutor::default())?
+        .index_bundle_deployment_files(&filesystem, &Sha2Digester::Sha256)?
+        .persist_location_lockfile(&filesystem, &Json)?
+        .generate_location_shims(&filesystem)?;
+
+    let lockfile: serde_json::Value = serde_json::from_str(&filesystem.read_to_string("/location/packster.lock")?).unwrap();
+    let deployments = lockfile["deployments"].as_array().unwrap();
+    assert_eq!(deployments.len(), 2);
+    assert_eq!(deployments[0]["identifier"], "app");
+    assert_eq!(deployments[0]["parameters"]["port"], 8080);
+    assert_eq!(deployments[1]["identifier"], "tool");
+    assert_eq!(lockfile["bundles"][0]["identifier"], "workstation");
+    assert_ne!(deployments[0]["id"], deployments[0]["checksum"]);
+    for deployment in deployments {
+        let id = deployment["id"].as_str().unwrap();
+        assert!(filesystem.exists(Path::new("/location").join(id).join("packster.toml")));
+    }
+
+    let operation = Operation::new(DeleteBundleRequest::new(bundle_path.clone(), location_path.clone()))
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .parse_bundle_manifest(&filesystem, &Toml)?
+        .parse_location_lockfile(&filesystem, &Json)?
+        .probe_bundle_installed_in_location()?
+        .remove_bundle_from_location()
+        .execute_bundle_undeploy_hooks(&RecordingExecutor::default())?
+        .persist_location_lockfile(&filesystem, &Json)?
+        .generate_location_shims(&filesystem)?
+        .delete_bundle_deployment_directories(&filesystem)?;
+    assert_eq!(operation.as_removed_deployments().len(), 2);
+
+    let lockfile: serde_json::Value = serde_json::from_str(&filesystem.read_to_string("/location/packster.lock")?).unwrap();
+    assert_eq!(lockfile["deployments"].as_array().unwrap().len(), 0);
+    assert!(lockfile.get("bundles").is_none());
+    for deployment in deployments {
+        let id = deployment["id"].as_str().unwrap();
+        assert!(!filesystem.exists(Path::new("/location").join(id)));
+    }
+
+    let result = Operation::new(DeleteBundleRequest::new(bundle_path, location_path))
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .parse_bundle_manifest(&filesystem, &Toml)?
+        .parse_location_lockfile(&filesystem, &Json)?
+        .probe_bundle_installed_in_location();
+    assert!(matches!(result, Err(Error::BundleNotInstalledInLocation(_))));
+
+    Ok(())
+}
+
+#[test]
+fn test_deployment_with_parameters() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
+
+    let server_path = pack_project(&filesystem, "/server", indoc! {r#"
+        identifier = "server"
+        version = "1.0.0"
+
+        [parameters.port]
+        type = "integer"
+        default = 80
+
+        [parameters.name]
+        type = "string"
+        required = true
+    "#})?;
+
+    let parameters = |port: &str| Parameters::from([
+        ("name".to_string(), ParameterValue::String("front".into())),
+        ("port".to_string(), ParameterValue::String(port.into())),
+    ]);
+
+    let result = deploy_package(&filesystem, server_path.clone(), "/location");
+    assert!(matches!(result, Err(Error::MissingParameter(_))));
+
+    let result = deploy_package_with_parameters(&filesystem, server_path.clone(), "/location", parameters("http"));
+    assert!(matches!(result, Err(Error::InvalidParameterType { .. })));
+
+    deploy_package_with_parameters(&filesystem, server_path.clone(), "/location", parameters("8080"))?;
+    deploy_package_with_parameters(&filesystem, server_path.clone(), "/location", parameters("8081"))?;
+
+    let result = deploy_package_with_parameters(&filesystem, server_path, "/location", parameters("8080"));
+    assert!(matches!(result, Err(Error::PackageAlreadyDeployedInLocation(_))));
+
+    let lockfile: serde_json::Value = serde_json::from_str(&filesystem.read_to_string("/location/packster.lock")?).unwrap();
+    let deployments = lockfile["deployments"].as_array().unwrap();
+    assert_eq!(deployments.len(), 2);
+    assert_eq!(deployments[0]["checksum"], deployments[1]["checksum"]);
+    assert_ne!(deployments[0]["id"], deployments[1]["id"]);
+    assert_ne!(deployments[0]["id"], deployments[0]["checksum"]);
+    assert_eq!(deployments[0]["parameters"], json!({ "name": "front", "port": 8080 }));
+    assert_eq!(deployments[1]["parameters"], json!({ "name": "front", "port": 8081 }));
+    for deployment in deployments {
+        let id = deployment["id"].as_str().unwrap();
+        assert!(filesystem.exists(Path::new("/location").join(id).join("packster.toml")));
+    }
+
+    Ok(())
+}
+
+fn undeploy_deployment(filesystem: &InMemoryFileSystem, deployment_id: &str, location_path: &str, executor: &RecordingExecutor) -> Result<()> {
+    undeploy_deployment_with_reassignment(filesystem, deployment_id, location_path, false, executor)
+}
+
+fn undeploy_deployment_with_reassignment(
+    filesystem: &InMemoryFileSystem,
+    deployment_id: &str,
+    location_path: &str,
+    reassign_active: bool,
+    executor: &RecordingExecutor,
+) -> Result<()> {
+    let request = UndeployRequest::new(Checksum::from_str(deployment_id)?, Absolute::assume_absolute(PathBuf::from(location_path)))
+        .with_active_reassignment(reassign_active);
+    undeploy_with_request(filesystem, request, executor)
+}
+
+fn undeploy_with_request(filesystem: &InMemoryFileSystem, request: UndeployRequest, executor: &RecordingExecutor) -> Result<()> {
+    Operation::new(request)
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .parse_location_lockfile(filesystem, &Json)?
+        .probe_package_already_deployed_in_location()?
+        .probe_no_dependent_deployments()?
+        .probe_active_deployment_reassignment()?
+        .guess_deployment_path()
+        .execute_undeploy_hooks(executor)?
+        .remove_deployment_from_location()
+        .persist_location_lockfile(filesystem, &Json)?
+        .generate_location_shims(filesystem)?
+        .delete_deployment_directory(filesystem)?;
+    Ok(())
+}
+
+#[test]
+fn test_hooks_execution() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
+
+    let executor = RecordingExecutor::default();
+    let package_path = pack_project_with_executor(&filesystem, "/hooked", indoc! {r#"
+        identifier = "hooked"
+        version = "1.0.0"
+
+        [parameters.port]
+        type = "integer"
+        default = 80
+
+        [hooks]
+        pack = "sh hooks/pack.sh"
+        deploy = { command = "./hooks/deploy.sh", arguments = ["--verbose"], timeout = 30 }
+        undeploy = "sh hooks/undeploy.sh"
+        always = "logger"
+    "#}, &executor)?;
+
+    deploy_package_with_executor(&filesystem, package_path, "/location", Parameters::new(), &executor)?;
+
+    let lockfile: serde_json::Value = serde_json::from_str(&filesystem.read_to_string("/location/packster.lock")?).unwrap();
+    let deployment_id = lockfile["deployments"][0]["id"].as_str().unwrap().to_string();
+    let deployment_path = format!("/location/{deployment_id}");
+    undeploy_deployment(&filesystem, &deployment_id, "/location", &executor)?;
+
+    let commands = executor.to_commands();
+    let command_lines: Vec<String> = commands.iter().map(|command| command.to_command_line()).collect();
+    assert_eq!(command_lines, vec![
+        "sh hooks/pack.sh".to_string(),
+        "logger pack".to_string(),
+        format!("{deployment_path}/hooks/deploy.sh --verbose"),
+        "logger deploy".to_string(),
+        "sh hooks/undeploy.sh".to_string(),
+        "logger undeploy".to_string(),
+    ]);
+
+    assert_eq!(commands[0].as_working_directory().to_string_lossy(), "/hooked");
+    assert_eq!(commands[0].as_environment()["PACKSTER_EVENT"], "pack");
+    assert_eq!(commands[0].as_environment()["PACKSTER_IDENTIFIER"], "hooked");
+
+    let deploy = &commands[2];
+    assert_eq!(deploy.as_working_directory().to_string_lossy(), deployment_path);
+    assert_eq!(deploy.as_timeout(), Some(std::time::Duration::from_secs(30)));
+    assert_eq!(deploy.as_environment()["PACKSTER_EVENT"], "deploy");
+    assert_eq!(deploy.as_environment()["PACKSTER_VERSION"], "1.0.0");
+    assert_eq!(deploy.as_environment()["PACKSTER_DEPLOYMENT_ID"], deployment_id);
+    assert_eq!(deploy.as_environment()["PACKSTER_LOCATION_PATH"], "/location");
+    assert_eq!(deploy.as_environment()["PACKSTER_PARAMETER_PORT"], "80");
+
+    assert_eq!(commands[4].as_environment()["PACKSTER_EVENT"], "undeploy");
+    Ok(())
+}
+
+#[test]
+fn test_failing_hook() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
+    let manifest = indoc! {r#"
+        identifier = "hooked"
+        version = "1.0.0"
+
+        [hooks]
+        deploy = "sh hooks/deploy.sh"
+    "#};
+
+    let failing_executor = RecordingExecutor::default()
+        .with_output("sh", ExecutionOutput::new(Some(2), String::from("out\n"), String::from("err\n")));
+    let result = pack_project_with_executor(&filesystem, "/hooked", &format!("{manifest}pack = \"sh hooks/pack.sh\""), &failing_executor);
+    assert!(matches!(result, Err(Error::HookFailed { .. })));
+
+    let package_path = pack_project(&filesystem, "/hooked", manifest)?;
+    let result = deploy_package_with_executor(&filesystem, package_path, "/location", Parameters::new(), &failing_executor);
+    match result {
+        Err(Error::HookFailed { event, command, status, output }) => {
+            assert_eq!(event, "deploy");
+            assert_eq!(command, "sh hooks/deploy.sh");
+            assert_eq!(status, Some(2));
+            assert_eq!(output, "out\nerr\n");
+        }
+        _ => panic!("deploy hook failure expected"),
+    }
+
+    assert_eq!(filesystem.read_to_string("/location/packster.lock")?, r#"{"schema_version":4,"deployments":[]}"#);
+    assert_eq!(snapshot_directory(&filesystem, "/location")?.len(), 1);
+    Ok(())
+}
+
+fn snapshot_directory<F: FileSystem>(filesystem: &F, path: &str) -> Result<Vec<(PathBuf, Option<Vec<u8>>)>> {
+    let mut snapshot = Vec::new();
+    for entry in filesystem.walk(Path::new(path)) {
+        let entry = entry?;
+        let content = if filesystem.is_file(entry.as_path()) {
+            let mut content = Vec::new();
+            filesystem.open_read(entry.as_path())?.read_to_end(&mut content)?;
+            Some(content)
+        } else {
+            None
+        };
+        snapshot.push((entry.as_path().to_path_buf(), content));
+    }
+    Ok(snapshot)
+}
+
+#[test]
+fn test_failing_deployment_leaves_location_untouched() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
+    let tool_path = pack_project(&filesystem, "/tool", indoc! {r#"
+        identifier = "tool"
+        version = "1.0.0"
+    "#})?;
+    deploy_package(&filesystem, tool_path, "/location")?;
+
+    filesystem.create_dir_recursively("/app/bin")?;
+    filesystem.write_all("/app/bin/app.sh", b"echo app")?;
+    filesystem.write_all("/app/README.md", b"app")?;
+    let app_path = pack_project(&filesystem, "/app", indoc! {r#"
+        identifier = "app"
+        version = "1.0.0"
+
+        [dependencies]
+        tool = "^1"
+    "#})?;
+
+    let location_snapshot = snapshot_directory(&filesystem, "/location")?;
+    let mut faulty_operation = 0;
+    loop {
+        let faulty_filesystem = FaultyFileSystem::new(&filesystem, faulty_operation);
+        let result = deploy_package_with_executor(&faulty_filesystem, app_path.clone(), "/location", Parameters::new(), &RecordingExecutor::default());
+        if !faulty_filesystem.is_fault_injected() {
+            result?;
+            break;
+        }
+        assert!(result.is_err(), "operation {faulty_operation} failure shall fail the deployment");
+        assert_eq!(snapshot_directory(&filesystem, "/location")?, location_snapshot, "operation {faulty_operation} failure shall be compensated");
+        faulty_operation += 1;
+    }
+
+    assert!(faulty_operation > 5);
+    assert_ne!(snapshot_directory(&filesystem, "/location")?, location_snapshot);
+    Ok(())
+}
+
+#[test]
+fn test_locked_location_refuses_deployment() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    let locker = InMemoryLocker::default();
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(&filesystem)?
+        .lock_location(&locker, Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
+    let package_path = pack_project(&filesystem, "/tool", indoc! {r#"
+        identifier = "tool"
+        version = "1.0.0"
+    "#})?;
+
+    let lock = locker.lock(Absolute::assume_absolute("/location/.packster.lck"), Duration::ZERO)?;
+    let result = deploy_package_with_locker(&filesystem, &locker, Duration::from_millis(50), package_path.clone(), "/location", Parameters::new(), &RecordingExecutor::default());
+    assert!(matches!(result, Err(Error::LocationLocked { .. })));
+
+    drop(lock);
+    deploy_package_with_locker(&filesystem, &locker, Duration::ZERO, package_path, "/location", Parameters::new(), &RecordingExecutor::default())?;
+    assert!(!locker.is_locked("/location/.packster.lck"));
+    Ok(())
+}
+
+#[test]
+fn test_concurrent_deployments() -> Result<()> {
+    const DEPLOYMENT_COUNT: usize = 4;
+    let directory = tempfile::tempdir()?;
+    let location_path = directory.path().join("location");
+    let location_path = location_path.to_str().unwrap();
+
+    let filesystem = InMemoryFileSystem::default();
+    let mut package_paths = Vec::new();
+    for index in 0..DEPLOYMENT_COUNT {
+        let manifest = format!("identifier = \"tool-{index}\"\nversion = \"1.0.0\"\n");
+        let package_path = pack_project(&filesystem, &format!("/tool-{index}"), &manifest)?;
+        let mut package_bytes = Vec::new();
+        filesystem.open_read(&package_path)?.read_to_end(&mut package_bytes)?;
+        let package_path = directory.path().join(package_path.file_name().unwrap());
+        StdFileSystem.write_all(&package_path, package_bytes)?;
+        package_paths.push(Absolute::assume_absolute(package_path));
+    }
+
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from(location_path))))
+        .create_location_directory(&StdFileSystem)?
+        .lock_location(&FileLocker, Duration::ZERO)?
+        .initialize_lockfile(&StdFileSystem, &Json)?;
+
+    std::thread::scope(|scope| {
+        let deployments: Vec<_> = package_paths
+            .into_iter()
+            .map(|package_path| {
+                scope.spawn(move || {
+                    deploy_package_with_locker(&StdFileSystem, &FileLocker, Duration::from_secs(30), package_path, location_path, Parameters::new(), &RecordingExecutor::default())
+                        .map_err(|error| error.to_string())
+                })
+            })
+            .collect();
+        for deployment in deployments {
+            deployment.join().unwrap().expect("concurrent deployment shall wait for the location lock");
+        }
+    });
+
+    let lockfile: serde_json::Value = serde_json::from_str(&StdFileSystem.read_to_string(Path::new(location_path).join(LOCKFILE_NAME))?).unwrap();
+    assert_eq!(lockfile["deployments"].as_array().unwrap().len(), DEPLOYMENT_COUNT);
+    assert!(!Path::new(location_path).join(".packster.lck").exists());
+    Ok(())
+}
+
+fn migrate_location(filesystem: &InMemoryFileSystem, location_path: &str) -> Result<(u32, u32)> {
+    let operation = Operation::new(MigrateLocationRequest::new(Absolute::assume_absolute(PathBuf::from(location_path))))
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .parse_location_lockfile(filesystem, &Json)?
+        .persist_location_lockfile(filesystem, &Json)?;
+    Ok((operation.as_previous_schema_version(), operation.as_schema_version()))
+}
+
+#[test]
+fn test_lockfile_schema_migration() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    filesystem.create_dir("/location")?;
+    let package_path = pack_project(&filesystem, "/tool", indoc! {r#"
+        identifier = "tool"
+        version = "1.0.0"
+    "#})?;
+    let checksum = Package::from_path(&package_path)?.as_checksum().to_string();
+    let legacy_lockfile = json!({
+        "deployments": [
+            { "identifier": "tool", "version": "1.0.0", "checksum": checksum, "packster_version": "0.1.4" }
+        ]
+    });
+    filesystem.write_all("/location/packster.lock", legacy_lockfile.to_string())?;
+    filesystem.create_dir(format!("/location/{checksum}"))?;
+
+    assert_eq!(migrate_location(&filesystem, "/location")?, (0, 4));
+    let lockfile: serde_json::Value = serde_json::from_str(&filesystem.read_to_string("/location/packster.lock")?).unwrap();
+    assert_eq!(lockfile["schema_version"], 4);
+    assert_eq!(lockfile["deployments"][0]["id"], checksum);
+    assert!(lockfile["deployments"][0].get("installed_packster_version").is_none());
+    assert_eq!(migrate_location(&filesystem, "/location")?, (4, 4));
+
+    undeploy_deployment(&filesystem, &checksum, "/location", &RecordingExecutor::default())?;
+    deploy_package(&filesystem, package_path, "/location")?;
+    let lockfile: serde_json::Value = serde_json::from_str(&filesystem.read_to_string("/location/packster.lock")?).unwrap();
+    assert_eq!(lockfile["deployments"][0]["installed_packster_version"], "0.1.4");
+
+    filesystem.write_all("/location/packster.lock", r#"{"schema_version":5,"deployments":[]}"#)?;
+    let result = migrate_location(&filesystem, "/location");
+    assert!(matches!(result, Err(Error::LockfileSchemaTooRecent { version: 5, supported_version: 4, .. })));
+    Ok(())
+}
+
+fn verify_location(filesystem: &InMemoryFileSystem, location_path: &str) -> Result<Vec<LocationIssue>> {
+    let operation = Operation::new(VerifyLocationRequest::new(Absolute::assume_absolute(PathBuf::from(location_path))))
+        .parse_location_lockfile(filesystem, &Json)?
+        .verify_location(filesystem, &Sha2Digester::Sha256)?;
+    Ok(operation.as_report().as_issues().to_vec())
+}
+
+#[test]
+fn test_location_verification() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
+
+    filesystem.create_dir_recursively("/tool/bin")?;
+    filesystem.write_all("/tool/bin/tool", "#!/bin/sh")?;
+    filesystem.write_all("/tool/README", "Some tool")?;
+    let package_path = pack_project(&filesystem, "/tool", indoc! {r#"
+        identifier = "tool"
+        version = "1.0.0"
+    "#})?;
+    deploy_package(&filesystem, package_path, "/location")?;
+    assert!(verify_location(&filesystem, "/location")?.is_empty());
+
+    let lockfile: serde_json::Value = serde_json::from_str(&filesystem.read_to_string("/location/packster.lock")?).unwrap();
+    let deployment_id = Checksum::from_str(lockfile["deployments"][0]["id"].as_str().unwrap())?;
+    let files: Vec<&str> = lockfile["deployments"][0]["files"].as_array().unwrap().iter().map(|file| file["path"].as_str().unwrap()).collect();
+    assert_eq!(files, ["README", "bin/tool", "packster.toml"]);
+
+    let deployment_path = format!("/location/{deployment_id}");
+    filesystem.write_all(format!("{deployment_path}/README"), "Tampered")?;
+    filesystem.remove_file(format!("{deployment_path}/bin/tool"))?;
+    filesystem.write_all(format!("{deployment_path}/extra"), "")?;
+    let staging_name = format!(".{}.staging", "0bad".repeat(16));
+    filesystem.create_dir(format!("/location/{staging_name}"))?;
+    filesystem.create_dir("/location/notes")?;
+
+    let issues = verify_location(&filesystem, "/location")?;
+    assert_eq!(
+        issues,
+        [
+            LocationIssue::ModifiedFile { deployment_id: deployment_id.clone(), path: "README".to_string() },
+            LocationIssue::MissingFile { deployment_id: deployment_id.clone(), path: "bin/tool".to_string() },
+            LocationIssue::ExtraFile { deployment_id: deployment_id.clone(), path: "extra".to_string() },
+            LocationIssue::OrphanDirectory { path: staging_name },
+        ]
+    );
+
+    filesystem.remove_dir_all(&deployment_path)?;
+    let issues = verify_location(&filesystem, "/location")?;
+    assert!(issues.contains(&LocationIssue::MissingDeploymentDirectory { deployment_id }));
+    Ok(())
+}
+
+fn deploy_tool(filesystem: &InMemoryFileSystem) -> Result<Checksum> {
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(filesystem, &Json)?;
+    filesystem.create_dir_recursively("/tool/bin")?;
+    filesystem.write_all("/tool/bin/tool", "#!/bin/sh")?;
+    let package_path = pack_project(filesystem, "/tool", indoc! {r#"
+        identifier = "tool"
+        version = "1.0.0"
+
+        [shims]
+        tool = "bin/tool"
+    "#})?;
+    deploy_package(filesystem, package_path.clone(), "/location")?;
+    Ok(Package::from_path(&package_path)?.as_checksum().clone())
+}
+
+#[test]
+fn test_location_garbage_collection() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    let deployment_id = deploy_tool(&filesystem)?;
+    let orphan_id = "0bad".repeat(16);
+    filesystem.create_dir(format!("/location/{orphan_id}"))?;
+    filesystem.create_dir(format!("/location/.{deployment_id}.staging"))?;
+    filesystem.create_dir(format!("/location/.{deployment_id}.damaged"))?;
+    filesystem.create_dir("/location/notes")?;
+    /* Hexadecimal names are only deployment ids at the top level of the location, and when as long as a digest */
+    filesystem.create_dir("/location/beef")?;
+    filesystem.create_dir(format!("/location/{deployment_id}/{orphan_id}"))?;
+
+    let operation = Operation::new(CollectGarbageRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .parse_location_lockfile(&filesystem, &Json)?
+        .find_orphan_directories(&filesystem, &Sha2Digester::Sha256)?;
+    let orphan_paths: Vec<PathBuf> = operation.as_orphan_paths().iter().map(|path| path.to_path_buf()).collect();
+    assert_eq!(orphan_paths, [
+        PathBuf::from(format!("/location/.{deployment_id}.damaged")),
+        PathBuf::from(format!("/location/.{deployment_id}.staging")),
+        PathBuf::from(format!("/location/{orphan_id}")),
+    ]);
+    assert!(filesystem.is_directory(format!("/location/{orphan_id}")));
+
+    operation.remove_orphan_directories(&filesystem)?;
+    assert!(!filesystem.exists(format!("/location/{orphan_id}")));
+    assert!(!filesystem.exists(format!("/location/.{deployment_id}.staging")));
+    assert!(!filesystem.exists(format!("/location/.{deployment_id}.damaged")));
+    assert!(filesystem.is_directory("/location/notes"));
+    assert!(filesystem.is_directory("/location/beef"));
+    assert!(filesystem.is_directory(format!("/location/{deployment_id}/{orphan_id}")));
+    assert!(filesystem.is_file(format!("/location/{deployment_id}/bin/tool")));
+    Ok(())
+}
+
+fn find_damaged_deployments(filesystem: &InMemoryFileSystem, package_paths: &[&str]) -> Result<RepairLocationOperation<DamagedDeployments>> {
+    let request = RepairLocationRequest::new(
+        Absolute::assume_absolute(PathBuf::from("/location")),
+        package_paths.iter().map(|path| Absolute::assume_absolute(PathBuf::from(path))).collect(),
+    );
+    Operation::new(request)
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .parse_location_lockfile(filesystem, &Json)?
+        .find_damaged_deployments(filesystem, &Json, &Sha2Digester::Sha256)
+}
+
+#[test]
+fn test_location_repair() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    let deployment_id = deploy_tool(&filesystem)?;
+    assert!(find_damaged_deployments(&filesystem, &[])?.as_repairs().is_empty());
+
+    let tool_path = format!("/location/{deployment_id}/bin/tool");
+    filesystem.write_all(&tool_path, "tampered")?;
+    filesystem.write_all(format!("/location/{deployment_id}/extra"), "")?;
+    filesystem.remove_file("/location/bin/tool")?;
+    let result = find_damaged_deployments(&filesystem, &[]);
+    assert!(matches!(result, Err(Error::RepairPackageNotFound { .. })));
+
+    let operation = find_damaged_deployments(&filesystem, &["/repo"])?;
+    assert_eq!(operation.as_repairs().len(), 1);
+    assert_eq!(operation.as_repairs()[0].as_deployment_id(), &deployment_id);
+    assert_eq!(filesystem.read_to_string(&tool_path)?, "tampered");
+
+    operation
+        .repair_deployments(&filesystem, &TarballArchiver, &Sha2Digester::Sha256)?
+        .persist_location_lockfile(&filesystem, &Json)?
+        .generate_location_shims(&filesystem)?;
+    assert_eq!(filesystem.read_to_string(&tool_path)?, "#!/bin/sh");
+    assert!(filesystem.read_to_string("/location/bin/tool")?.contains(&format!("{deployment_id}/bin/tool")));
+    assert!(verify_location(&filesystem, "/location")?.is_empty());
+
+    filesystem.remove_dir_all(format!("/location/{deployment_id}"))?;
+    let package_file = filesystem.walk(Path::new("/repo")).next().unwrap()?.as_path().to_string_lossy().to_string();
+    find_damaged_deployments(&filesystem, &[&package_file])?
+        .repair_deployments(&filesystem, &TarballArchiver, &Sha2Digester::Sha256)?
+        .persist_location_lockfile(&filesystem, &Json)?;
+    assert!(verify_location(&filesystem, "/location")?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_location_repair_hook_generated_files() -> Result<()> {
+    /* Deploy hooks generate a file within the deployment directory */
+    struct GeneratingExecutor<'a>(&'a InMemoryFileSystem);
+
+    impl Executor for GeneratingExecutor<'_> {
+        fn execute(&self, command: &Command) -> Result<ExecutionOutput> {
+            let deployment_path = &command.as_environment()["PACKSTER_DEPLOYMENT_PATH"];
+            self.0.write_all(format!("{deployment_path}/generated.conf"), "generated")?;
+            Ok(ExecutionOutput::new(Some(0), String::new(), String::new()))
+        }
+    }
+
+    let filesystem = InMemoryFileSystem::default();
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
+    filesystem.create_dir_recursively("/tool/bin")?;
+    filesystem.write_all("/tool/bin/tool", "#!/bin/sh")?;
+    let package_path = pack_project(&filesystem, "/tool", indoc! {r#"
+        identifier = "tool"
+        version = "1.0.0"
+
+        [hooks]
+        deploy = "generate"
+    "#})?;
+    let deployment_id = Package::from_path(&package_path)?.as_checksum().clone();
+    deploy_package_with_executor(&filesystem, package_path, "/location", Parameters::new(), &GeneratingExecutor(&filesystem))?;
+    let generated_path = format!("/location/{deployment_id}/generated.conf");
+
+    // An intact generated file is kept along the extracted ones
+    filesystem.write_all(format!("/location/{deployment_id}/bin/tool"), "tampered")?;
+    let operation = find_damaged_deployments(&filesystem, &["/repo"])?
+        .repair_deployments(&filesystem, &TarballArchiver, &Sha2Digester::Sha256)?
+        .persist_location_lockfile(&filesystem, &Json)?
+        .generate_location_shims(&filesystem)?;
+    assert!(operation.as_unrecoverable_files().is_empty());
+    assert_eq!(filesystem.read_to_string(&generated_path)?, "generated");
+    assert!(verify_location(&filesystem, "/location")?.is_empty());
+    assert!(!filesystem.exists(format!("/location/.{deployment_id}.damaged")));
+
+    // A damaged one can not be generated again, it is reported and still recorded
+    filesystem.write_all(&generated_path, "tampered")?;
+    let operation = find_damaged_deployments(&filesystem, &["/repo"])?
+        .repair_deployments(&filesystem, &TarballArchiver, &Sha2Digester::Sha256)?
+        .persist_location_lockfile(&filesystem, &Json)?
+        .generate_location_shims(&filesystem)?;
+    assert_eq!(
+        operation.as_unrecoverable_files(),
+        [LocationIssue::MissingFile { deployment_id: deployment_id.clone(), path: "generated.conf".to_string() }]
+    );
+    assert!(!filesystem.exists(&generated_path));
+    assert_eq!(
+        verify_location(&filesystem, "/location")?,
+        [LocationIssue::MissingFile { deployment_id, path: "generated.conf".to_string() }]
+    );
+    Ok(())
+}
+
+fn upgrade_package(
+    filesystem: &InMemoryFileSystem,
+    package_path: Absolute<PathBuf>,
+    location_path: &str,
+    parameters: Parameters,
+    executor: &RecordingExecutor,
+) -> Result<UpgradeOperation<DeletedPreviousDeployment>> {
+    let request = UpgradeRequest::new(package_path, Absolute::assume_absolute(PathBuf::from(location_path)), parameters);
+    Operation::new(request)
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .parse_package_metadata(filesystem, &Json)?
+        .parse_location_lockfile(filesystem, &Json)?
+        .find_previous_deployment()?
+        .identify_deployment(&Sha2Digester::Sha256)?
+        .probe_package_not_deployed_in_location()?
+        .probe_dependencies_deployed_in_location()?
+        .probe_no_resource_conflict()?
+        .validate_package_checksum(filesystem, &Sha2Digester::Sha256)?
+        .guess_deployment_path()
+        .extract_package(filesystem, &TarballArchiver)?
+        .execute_upgrade_hooks(filesystem, executor)?
+        .index_deployment_files(filesystem, &Sha2Digester::Sha256)?
+        .execute_previous_undeploy_hooks(filesystem, executor)?
+        .replace_deployment_in_location("0.1.4")?
+        .persist_location_lockfile(filesystem, &Json)?
+        .generate_location_shims(filesystem)?
+        .delete_previous_deployment_directory(filesystem)
+}
+
+#[test]
+fn test_upgrade_replaces_previous_deployment() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
+
+    let server_v1_path = pack_project(&filesystem, "/server", indoc! {r#"
+        identifier = "server"
+        version = "1.0.0"
+
+        [parameters.port]
+        type = "integer"
+        default = 80
+
+        [parameters.legacy]
+        type = "boolean"
+        default = true
+
+        [hooks]
+        undeploy = "sh hooks/undeploy.sh"
+    "#})?;
+    let server_v2_path = pack_project(&filesystem, "/server", indoc! {r#"
+        identifier = "server"
+        version = "1.1.0"
+
+        [parameters.port]
+        type = "integer"
+        default = 80
+
+        [parameters.workers]
+        type = "integer"
+        default = 2
+
+        [hooks]
+        deploy = "sh hooks/deploy.sh"
+        upgrade = "sh hooks/upgrade.sh"
+    "#})?;
+
+    let executor = RecordingExecutor::default();
+    let result = upgrade_package(&filesystem, server_v2_path.clone(), "/location", Parameters::new(), &executor);
+    assert!(matches!(result, Err(Error::NoDeploymentToUpgrade(_))));
+
+    deploy_package_with_parameters(&filesystem, server_v1_path.clone(), "/location", Parameters::from([
+        ("port".to_string(), ParameterValue::String("8080".into())),
+    ]))?;
+    let lockfile: serde_json::Value = serde_json::from_str(&filesystem.read_to_string("/location/packster.lock")?).unwrap();
+    let previous_id = lockfile["deployments"][0]["id"].as_str().unwrap().to_string();
+
+    let operation = upgrade_package(&filesystem, server_v2_path, "/location", Parameters::from([
+        ("workers".to_string(), ParameterValue::String("4".into())),
+    ]), &executor)?;
+    assert!(!operation.is_previous_deployment_kept());
+    assert_eq!(operation.as_previous_deployment().as_id().to_string(), previous_id);
+    let deployment_id = operation.as_deployment().as_id().to_string();
+
+    let lockfile: serde_json::Value = serde_json::from_str(&filesystem.read_to_string("/location/packster.lock")?).unwrap();
+    let deployments = lockfile["deployments"].as_array().unwrap();
+    assert_eq!(deployments.len(), 1);
+    assert_eq!(deployments[0]["version"], "1.1.0");
+    assert_eq!(deployments[0]["parameters"], json!({ "port": 8080, "workers": 4 }));
+    assert!(filesystem.is_directory(format!("/location/{deployment_id}")));
+    assert!(!filesystem.exists(format!("/location/{previous_id}")));
+
+    let commands = executor.to_commands();
+    let command_lines: Vec<String> = commands.iter().map(|command| command.to_command_line()).collect();
+    assert_eq!(command_lines, ["sh hooks/upgrade.sh", "sh hooks/undeploy.sh"]);
+    assert_eq!(commands[0].as_environment()["PACKSTER_EVENT"], "upgrade");
+    assert_eq!(commands[0].as_environment()["PACKSTER_PREVIOUS_VERSION"], "1.0.0");
+    assert_eq!(commands[0].as_environment()["PACKSTER_PREVIOUS_DEPLOYMENT_PATH"], format!("/location/{previous_id}"));
+    assert_eq!(commands[1].as_working_directory().to_string_lossy(), format!("/location/{previous_id}"));
+
+    let result = upgrade_package(&filesystem, server_v1_path, "/location", Parameters::new(), &executor);
+    assert!(matches!(result, Err(Error::UpgradeVersionNotGreater { .. })));
+    Ok(())
+}
+
+#[test]
+fn test_upgrade_keeps_depended_upon_deployment() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
+
+    let tool_path = |version: &str| pack_project(&filesystem, "/tool", &format!("identifier = \"tool\"\nversion = \"{version}\"\n"));
+    let tool_v1_path = tool_path("1.2.0")?;
+    let tool_v1_3_path = tool_path("1.3.0")?;
+    let tool_v2_path = tool_path("2.0.0")?;
+    let app_path = pack_project(&filesystem, "/app", indoc! {r#"
+        identifier = "app"
+        version = "0.1.0"
+
+        [dependencies]
+        tool = "^1"
+    "#})?;
+    deploy_package(&filesystem, tool_v1_path, "/location")?;
+    deploy_package(&filesystem, app_path, "/location")?;
+
+    let operation = upgrade_package(&filesystem, tool_v1_3_path, "/location", Parameters::new(), &RecordingExecutor::default())?;
+    assert!(!operation.is_previous_deployment_kept());
+    let lockfile: serde_json::Value = serde_json::from_str(&filesystem.read_to_string("/location/packster.lock")?).unwrap();
+    let deployments = lockfile["deployments"].as_array().unwrap();
+    assert_eq!(deployments.len(), 2);
+    assert_eq!(deployments[1]["version"], "1.3.0");
+    assert_eq!(deployments[1]["dependents"][0]["identifier"], "app");
+
+    let operation = upgrade_package(&filesystem, tool_v2_path, "/location", Parameters::new(), &RecordingExecutor::default())?;
+    assert!(operation.is_previous_deployment_kept());
+    let lockfile: serde_json::Value = serde_json::from_str(&filesystem.read_to_string("/location/packster.lock")?).unwrap();
+    let versions: Vec<&str> = lockfile["deployments"].as_array().unwrap().iter().map(|deployment| deployment["version"].as_str().unwrap()).collect();
+    assert_eq!(versions, ["0.1.0", "1.3.0", "2.0.0"]);
+    assert_eq!(lockfile["active"]["tool"], operation.as_deployment().as_id().to_string());
+    assert!(filesystem.is_directory(format!("/location/{}", operation.as_previous_deployment().as_id())));
+    Ok(())
+}
+
+fn use_deployment(filesystem: &InMemoryFileSystem, selector: &str) -> Result<()> {
+    let request = UseDeploymentRequest::new(Absolute::assume_absolute(PathBuf::from("/location")), DeploymentSelector::from_str(selector)?);
+    Operation::new(request)
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .parse_location_lockfile(filesystem, &Json)?
+        .activate_deployment()?
+        .persist_location_lockfile(filesystem, &Json)?
+        .generate_location_shims(filesystem)?;
+    Ok(())
+}
+
+#[test]
+fn test_active_deployment_selection() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
+
+    let tool_path = |version: &str| pack_project(&filesystem, "/tool", &format!("identifier = \"tool\"\nversion = \"{version}\"\n"));
+    let tool_v1_path = tool_path("1.0.0")?;
+    let tool_v2_path = tool_path("1.1.0")?;
+    let v1_id = Package::from_path(&tool_v1_path)?.as_checksum().to_string();
+    let v2_id = Package::from_path(&tool_v2_path)?.as_checksum().to_string();
+    deploy_package(&filesystem, tool_v1_path, "/location")?;
+    deploy_package(&filesystem, tool_v2_path, "/location")?;
+
+    let active_tool = || -> Result<serde_json::Value> {
+        let lockfile: serde_json::Value = serde_json::from_str(&filesystem.read_to_string("/location/packster.lock")?).unwrap();
+        Ok(lockfile["active"]["tool"].clone())
+    };
+    assert_eq!(active_tool()?, v1_id.as_str());
+
+    use_deployment(&filesystem, "tool@1.1.0")?;
+    assert_eq!(active_tool()?, v2_id.as_str());
+    use_deployment(&filesystem, &format!("tool@{v1_id}"))?;
+    assert_eq!(active_tool()?, v1_id.as_str());
+    assert!(matches!(use_deployment(&filesystem, "tool@2.0.0"), Err(Error::DeploymentNotFound(_))));
+
+    let result = undeploy_deployment(&filesystem, &v1_id, "/location", &RecordingExecutor::default());
+    assert!(matches!(result, Err(Error::ActiveDeploymentUndeployment { .. })));
+    undeploy_deployment_with_reassignment(&filesystem, &v1_id, "/location", true, &RecordingExecutor::default())?;
+    assert_eq!(active_tool()?, v2_id.as_str());
+
+    undeploy_deployment(&filesystem, &v2_id, "/location", &RecordingExecutor::default())?;
+    assert!(active_tool()?.is_null());
+    Ok(())
+}
+
+#[test]
+fn test_location_shims() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
+
+    filesystem.create_dir_recursively("/tool/bin")?;
+    filesystem.write_all("/tool/bin/tool", "#!/bin/sh")?;
+    let tool_path = |version: &str| pack_project(&filesystem, "/tool", &format!(
+        "identifier = \"tool\"\nversion = \"{version}\"\n\n[shims]\ntool = \"bin/tool\"\n"
+    ));
+    let tool_v1_path = tool_path("1.0.0")?;
+    let tool_v2_path = tool_path("1.1.0")?;
+    let v1_id = Package::from_path(&tool_v1_path)?.as_checksum().to_string();
+    let v2_id = Package::from_path(&tool_v2_path)?.as_checksum().to_string();
+
+    filesystem.create_dir_recursively("/location/bin")?;
+    filesystem.write_all("/location/bin/mine", "#!/bin/sh")?;
+    deploy_package(&filesystem, tool_v1_path, "/location")?;
+    deploy_package(&filesystem, tool_v2_path, "/location")?;
+    let shim = || filesystem.read_to_string("/location/bin/tool");
+    assert_eq!(shim()?, format!("#!/bin/sh\n# packster shim of tool@1.0.0 ({v1_id})\nexec '/location/{v1_id}/bin/tool' \"$@\"\n"));
+
+    use_deployment(&filesystem, "tool@1.1.0")?;
+    assert!(shim()?.contains(&format!("exec '/location/{v2_id}/bin/tool'")));
+
+    let other_path = pack_project(&filesystem, "/other", indoc! {r#"
+        identifier = "other"
+        version = "1.0.0"
+
+        [shims]
+        tool = "tool"
+    "#})?;
+    let other_id = Package::from_path(&other_path)?.as_checksum().to_string();
+    let lockfile = filesystem.read_to_string("/location/packster.lock")?;
+    let result = deploy_package(&filesystem, other_path, "/location");
+    assert!(matches!(
+        result,
+        Err(Error::ResourceConflict { resource, package, other_package, .. })
+            if resource == "Shim tool" && package == "other@1.0.0" && other_package == "tool@1.0.0"
+    ));
+    assert_eq!(filesystem.read_to_string("/location/packster.lock")?, lockfile);
+    assert!(!filesystem.exists(format!("/location/{other_id}")));
+
+    let mine_path = pack_project(&filesystem, "/mine", indoc! {r#"
+        identifier = "mine"
+        version = "1.0.0"
+
+        [shims]
+        mine = "mine"
+    "#})?;
+    assert!(matches!(deploy_package(&filesystem, mine_path, "/location"), Err(Error::ShimPathOccupied(_))));
+    assert_eq!(filesystem.read_to_string("/location/packster.lock")?, lockfile);
+
+    undeploy_deployment_with_reassignment(&filesystem, &v2_id, "/location", true, &RecordingExecutor::default())?;
+    assert!(shim()?.contains(&format!("exec '/location/{v1_id}/bin/tool'")));
+    undeploy_deployment(&filesystem, &v1_id, "/location", &RecordingExecutor::default())?;
+    assert!(!filesystem.exists("/location/bin/tool"));
+    assert_eq!(filesystem.read_to_string("/location/bin/mine")?, "#!/bin/sh");
+    Ok(())
+}
+
+#[test]
+fn test_packing_invalid_shim() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    let result = pack_project(&filesystem, "/tool", indoc! {r#"
+        identifier = "tool"
+        version = "1.0.0"
+
+        [shims]
+        tool = "../tool"
+    "#});
+    assert!(result.is_err());
+    Ok(())
+}
+
+fn resolve_location_environment(filesystem: &InMemoryFileSystem) -> Result<Vec<(String, String)>> {
+    let operation = Operation::new(LocationEnvironmentRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .parse_location_lockfile(filesystem, &Json)?
+        .resolve_location_environment()?;
+    Ok(operation.as_environment().as_variables().iter()
+        .map(|variable| (variable.as_name().to_string(), variable.as_value().to_string()))
+        .collect())
+}
+
+#[test]
+fn test_location_environment() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
+    assert_eq!(resolve_location_environment(&filesystem)?, [(String::from("PATH"), String::from("/location/bin"))]);
+
+    let tool_path = |version: &str| pack_project(&filesystem, "/tool", &format!(
+        "identifier = \"tool\"\nversion = \"{version}\"\n\n[environment]\nTOOL_HOME = \"${{deployment_path}}\"\nTOOL_VERSION = \"${{identifier}}@${{version}}\"\n"
+    ));
+    let tool_v1_path = tool_path("1.0.0")?;
+    let tool_v2_path = tool_path("1.1.0")?;
+    let v2_id = Package::from_path(&tool_v2_path)?.as_checksum().to_string();
+    deploy_package(&filesystem, tool_v1_path, "/location")?;
+    deploy_package(&filesystem, tool_v2_path, "/location")?;
+    use_deployment(&filesystem, "tool@1.1.0")?;
+
+    assert_eq!(resolve_location_environment(&filesystem)?, [
+        (String::from("PATH"), String::from("/location/bin")),
+        (String::from("TOOL_HOME"), format!("/location/{v2_id}")),
+        (String::from("TOOL_VERSION"), String::from("tool@1.1.0")),
+    ]);
+
+    let other_path = pack_project(&filesystem, "/other", indoc! {r#"
+        identifier = "other"
+        version = "1.0.0"
+
+        [environment]
+        TOOL_HOME = "${location_path}"
+    "#})?;
+    assert!(matches!(
+        deploy_package(&filesystem, other_path, "/location"),
+        Err(Error::ResourceConflict { resource, package, other_package, .. })
+            if resource == "Environment variable TOOL_HOME" && package == "other@1.0.0" && other_package == "tool@1.0.0"
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_resource_conflict() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
+
+    let server_path = |version: &str| pack_project(&filesystem, "/server", &format!(
+        "identifier = \"server\"\nversion = \"{version}\"\n\n[provides]\nports = [8080]\ndirectories = [\"/var/lib/server\"]\n"
+    ));
+    let server_v1_path = server_path("1.0.0")?;
+    let server_v2_path = server_path("1.1.0")?;
+    let server_v1_id = Package::from_path(&server_v1_path)?.as_checksum().to_string();
+    let server_v2_id = Package::from_path(&server_v2_path)?.as_checksum().to_string();
+    deploy_package(&filesystem, server_v1_path, "/location")?;
+
+    let proxy_path = pack_project(&filesystem, "/proxy", indoc! {r#"
+        identifier = "proxy"
+        version = "1.0.0"
+
+        [provides]
+        ports = [8080]
+    "#})?;
+    let proxy_id = Package::from_path(&proxy_path)?.as_checksum().to_string();
+    let lockfile = filesystem.read_to_string("/location/packster.lock")?;
+    assert!(matches!(
+        deploy_package(&filesystem, proxy_path.clone(), "/location"),
+        Err(Error::ResourceConflict { resource, deployment_id, package, other_deployment_id, other_package })
+            if resource == "Port 8080" && deployment_id == proxy_id && package == "proxy@1.0.0"
+                && other_deployment_id == server_v1_id && other_package == "server@1.0.0"
+    ));
+    assert_eq!(filesystem.read_to_string("/location/packster.lock")?, lockfile);
+    assert!(!filesystem.exists(format!("/location/{proxy_id}")));
+
+    // Nested paths conflict, whatever their trailing separators
+    let backup_path = pack_project(&filesystem, "/backup", indoc! {r#"
+        identifier = "backup"
+        version = "1.0.0"
+
+        [provides]
+        directories = ["/var/lib/server/backups/"]
+    "#})?;
+    assert!(matches!(
+        deploy_package(&filesystem, backup_path, "/location"),
+        Err(Error::ResourceConflict { resource, .. }) if resource == "Directory /var/lib/server"
+    ));
+
+    // The deployments of a same package take over the resources of each other
+    upgrade_package(&filesystem, server_v2_path, "/location", Parameters::new(), &RecordingExecutor::default())?;
+    let claims = |filesystem: &InMemoryFileSystem| -> Result<serde_json::Value> {
+        let lockfile: serde_json::Value = serde_json::from_str(&filesystem.read_to_string("/location/packster.lock")?).unwrap();
+        Ok(lockfile["claims"].clone())
+    };
+    assert_eq!(claims(&filesystem)?, json!([
+        { "resource": { "kind": "port", "name": 8080 }, "deployment_id": server_v2_id },
+        { "resource": { "kind": "directory", "name": "/var/lib/server" }, "deployment_id": server_v2_id },
+    ]));
+
+    undeploy_deployment(&filesystem, &server_v2_id, "/location", &RecordingExecutor::default())?;
+    assert!(claims(&filesystem)?.is_null());
+    deploy_package(&filesystem, proxy_path, "/location")?;
+    Ok(())
+}
+
+fn search_source(filesystem: &InMemoryFileSystem, pattern: Option<&str>) -> Result<Vec<(String, Vec<String>)>> {
+    let operation = Operation::new(SearchSourceRequest::new(pattern.map(String::from)))
+        .search_source(filesystem, &DirectorySource::new(Absolute::assume_absolute(PathBuf::from("/repo"))))?;
+    Ok(
+        operation.as_packages().iter()
+            .map(|(identifier, versions)| (identifier.to_string(), versions.iter().map(ToString::to_string).collect()))
+            .collect()
+    )
+}
+
+#[test]
+fn test_directory_source() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
+
+    for (identifier, version) in [("my-tool", "1.0.0"), ("my-tool", "2.1.0"), ("my-tool", "2.0.0"), ("library", "0.1.0")] {
+        pack_project(&filesystem, &format!("/{identifier}"), &format!("identifier = \"{identifier}\"\nversion = \"{version}\"\n"))?;
+    }
+
+    assert_eq!(search_source(&filesystem, None)?, [
+        (String::from("library"), vec![String::from("0.1.0")]),
+        (String::from("my-tool"), vec![String::from("1.0.0"), String::from("2.0.0"), String::from("2.1.0")]),
+    ]);
+    assert_eq!(search_source(&filesystem, Some("tool"))?.len(), 1);
+    assert!(search_source(&filesystem, Some("server"))?.is_empty());
+
+    let source = DirectorySource::new(Absolute::assume_absolute(PathBuf::from("/repo")));
+    let package_path = source.fetch(
+        &filesystem,
+        &PackageRequirement::from_str("my-tool@~2.0")?,
+        Absolute::assume_absolute(Path::new("/downloads")),
+    )?;
+    deploy_package(&filesystem, package_path, "/location")?;
+    let lockfile: serde_json::Value = serde_json::from_str(&filesystem.read_to_string("/location/packster.lock")?).unwrap();
+    assert_eq!(lockfile["deployments"][0]["version"], "2.0.0");
+    Ok(())
+}
+
+/* Serves the packages of /repo along with their index, as a web server hosting a source would */
+fn serve_repository(filesystem: &InMemoryFileSystem) -> Result<HttpSource> {
+    let mut files = std::collections::BTreeMap::new();
+    let mut packages = Vec::new();
+    for entry in filesystem.walk(Path::new("/repo")) {
+        let entry = entry?;
+        if !filesystem.is_file(entry.as_path()) {
+            continue;
+        }
+        let package = read_package_metadata(filesystem, &Json, entry.as_path())?;
+        let mut content = Vec::new();
+        filesystem.open_read(entry.as_path())?.read_to_end(&mut content)?;
+        files.insert(package.to_file_name(), content);
+        packages.push(IndexedPackage::new(&package, package.to_file_name(), entry.size()));
+    }
+    files.insert(SOURCE_INDEX_NAME.to_string(), serde_json::to_vec(&SourceIndex::new(packages)).unwrap());
+
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", server.server_addr().to_ip().unwrap());
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let response = match files.get(request.url().trim_start_matches('/')) {
+                Some(content) => tiny_http::Response::from_data(content.clone()),
+                None => tiny_http::Response::from_data(Vec::new()).with_status_code(404),
+            };
+            let _ = request.respond(response);
+        }
+    });
+    Ok(HttpSource::new(base_url))
+}
+
+#[test]
+fn test_http_source() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
+
+    for version in ["1.0.0", "1.1.0", "2.0.0"] {
+        pack_project(&filesystem, "/my-tool", &format!("identifier = \"my-tool\"\nversion = \"{version}\"\n"))?;
+    }
+    let source = serve_repository(&filesystem)?;
+
+    let operation = Operation::new(SearchSourceRequest::new(None)).search_source(&filesystem, &source)?;
+    assert_eq!(operation.as_packages().len(), 1);
+    assert_eq!(operation.as_packages()[0].1.len(), 3);
+
+    let package_path = source.fetch(
+        &filesystem,
+        &PackageRequirement::from_str("my-tool@^1")?,
+        Absolute::assume_absolute(Path::new("/downloads")),
+    )?;
+    deploy_package(&filesystem, package_path, "/location")?;
+    let lockfile: serde_json::Value = serde_json::from_str(&filesystem.read_to_string("/location/packster.lock")?).unwrap();
+    assert_eq!(lockfile["deployments"][0]["version"], "1.1.0");
+    Ok(())
+}
+
+fn index_source(filesystem: &InMemoryFileSystem) -> Result<Vec<String>> {
+    let operation = Operation::new(IndexSourceRequest::new(Absolute::assume_absolute(PathBuf::from("/repo"))))
+        .index_source(filesystem, &Json, &Sha2Digester::Sha256)?
+        .persist_source_index(filesystem, &Json)?;
+    Ok(operation.as_digested_paths().to_vec())
+}
+
+#[test]
+fn test_index_source() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    let tool_path = pack_project(&filesystem, "/my-tool", indoc!{r#"
+        identifier = "my-tool"
+        version = "1.0.0"
+        description = "A tool"
+        labels = ["cli", "stable"]
+
+        [dependencies]
+        library = "^0.1"
+    "#})?;
+    pack_project(&filesystem, "/library", "identifier = \"library\"\nversion = \"0.1.0\"\n")?;
+
+    assert_eq!(index_source(&filesystem)?.len(), 2);
+    let index = filesystem.read_to_string("/repo/packster-index.json")?;
+    let json: serde_json::Value = serde_json::from_str(&index).unwrap();
+    assert_eq!(json["packages"][0]["identifier"], "library");
+    assert_eq!(json["packages"][1]["description"], "A tool");
+    assert_eq!(json["packages"][1]["labels"], json!(["cli", "stable"]));
+    assert_eq!(json["packages"][1]["dependencies"], json!({"library": "^0.1"}));
+    assert_eq!(json["packages"][1]["size"], filesystem.file_size(&tool_path)?);
+
+    assert!(index_source(&filesystem)?.is_empty());
+    assert_eq!(filesystem.read_to_string("/repo/packster-index.json")?, index);
+
+    /* A package file rewritten with the same size is digested again */
+    let library_path = filesystem.walk(Path::new("/repo")).map(|entry| entry.unwrap().as_path().to_path_buf())
+        .find(|path| path.to_string_lossy().contains("library_"))
+        .unwrap();
+    let mut content = Vec::new();
+    filesystem.open_read(&library_path)?.read_to_end(&mut content).unwrap();
+    *content.last_mut().unwrap() ^= 0xff;
+    filesystem.write_all(&library_path, &content)?;
+    assert!(matches!(index_source(&filesystem), Err(Error::PackageChecksumDoNotMatch { .. })));
+    *content.last_mut().unwrap() ^= 0xff;
+    filesystem.write_all(&library_path, &content)?;
+    assert_eq!(index_source(&filesystem)?.len(), 1);
+
+    pack_project(&filesystem, "/my-tool", "identifier = \"my-tool\"\nversion = \"1.1.0\"\n")?;
+    filesystem.remove_file("/repo/".to_string() + &Package::from_path(&tool_path)?.to_file_name())?;
+    let digested_paths = index_source(&filesystem)?;
+    assert_eq!(digested_paths.len(), 1);
+    assert!(digested_paths[0].starts_with("my-tool_1.1.0_"));
+
+    /* Once indexed, a source is listed from its index */
+    filesystem.write_all("/repo/unindexed_1.0.0_d829752c.302e312e30.packster", b"")?;
+    let operation = Operation::new(SearchSourceRequest::new(None))
+        .search_source(&filesystem, &DirectorySource::new(Absolute::assume_absolute(PathBuf::from("/repo"))))?;
+    assert_eq!(operation.as_packages().iter().map(|(identifier, _)| identifier.to_string()).collect::<Vec<_>>(), ["library", "my-tool"]);
+    Ok(())
+}
+
+/* Returns whether the same package was already published */
+fn publish_package<F: FileSystem>(filesystem: &F, package_path: Absolute<PathBuf>) -> Result<bool> {
+    let operation = Operation::new(PublishRequest::new(package_path, Absolute::assume_absolute(PathBuf::from("/source"))))
+        .parse_package_metadata(filesystem, &Json)?
+        .validate_package_checksum(filesystem, &Sha2Digester::Sha256)?
+        .index_source(filesystem, &Json, &Sha2Digester::Sha256)?
+        .probe_package_publishable()?
+        .copy_package_to_source(filesystem)?
+        .persist_source_index(filesystem, &Json)?;
+    Ok(operation.is_already_published())
+}
+
+fn unpublish_package<F: FileSystem>(filesystem: &F, identifier: &str, version: &str) -> Result<()> {
+    Operation::new(UnpublishRequest::new(
+        Absolute::assume_absolute(PathBuf::from("/source")),
+        Identifier::from_str(identifier)?,
+        Version::from_str(version)?,
+    ))
+        .index_source(filesystem, &Json, &Sha2Digester::Sha256)?
+        .unlist_package()?
+        .persist_source_index(filesystem, &Json)?
+        .delete_package_file(filesystem)?;
+    Ok(())
+}
+
+#[test]
+fn test_publish_package() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    filesystem.create_dir_recursively("/source")?;
+    let tool_path = pack_project(&filesystem, "/my-tool", "identifier = \"my-tool\"\nversion = \"1.0.0\"\n")?;
+    let file_name = tool_path.file_name().unwrap().to_string_lossy().to_string();
+
+    assert!(!publish_package(&filesystem, tool_path.clone())?);
+    let published_path = Absolute::assume_absolute(Path::new("/source").join(&file_name));
+    assert_eq!(Package::from_path(&published_path)?.to_file_name(), file_name);
+    validate_checksum(&filesystem, &Sha2Digester::Sha256, &published_path, &read_package_metadata(&filesystem, &Json, &published_path)?)?;
+    let index: serde_json::Value = serde_json::from_str(&filesystem.read_to_string("/source/packster-index.json")?).unwrap();
+    assert_eq!(index["packages"][0]["identifier"], "my-tool");
+    assert_eq!(index["packages"][0]["version"], "1.0.0");
+
+    assert!(publish_package(&filesystem, tool_path)?);
+
+    /* Packing the same version with other content gives another checksum */
+    filesystem.write_all("/my-tool/README.md", b"my-tool")?;
+    let repacked_path = pack_project(&filesystem, "/my-tool", "identifier = \"my-tool\"\nversion = \"1.0.0\"\n")?;
+    let result = publish_package(&filesystem, repacked_path.clone());
+    assert!(matches!(result, Err(Error::PackageVersionAlreadyPublished { package, .. }) if package == "my-tool 1.0.0"));
+    assert!(!filesystem.exists(Path::new("/source").join(repacked_path.file_name().unwrap())));
+
+    let source = DirectorySource::new(Absolute::assume_absolute(PathBuf::from("/source")));
+    assert_eq!(source.list_versions(&filesystem, &Identifier::from_str("my-tool")?)?, [Version::new(1, 0, 0)]);
+    Ok(())
+}
+
+#[test]
+fn test_unpublish_package() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    filesystem.create_dir_recursively("/source")?;
+    let tool_path = pack_project(&filesystem, "/my-tool", "identifier = \"my-tool\"\nversion = \"1.0.0\"\n")?;
+    publish_package(&filesystem, tool_path.clone())?;
+    publish_package(&filesystem, pack_project(&filesystem, "/my-tool", "identifier = \"my-tool\"\nversion = \"1.1.0\"\n")?)?;
+
+    unpublish_package(&filesystem, "my-tool", "1.0.0")?;
+    assert!(!filesystem.exists(Path::new("/source").join(tool_path.file_name().unwrap())));
+    let source = DirectorySource::new(Absolute::assume_absolute(PathBuf::from("/source")));
+    assert_eq!(source.list_versions(&filesystem, &Identifier::from_str("my-tool")?)?, [Version::new(1, 1, 0)]);
+
+    let result = unpublish_package(&filesystem, "my-tool", "1.0.0");
+    assert!(matches!(result, Err(Error::PackageNotFoundInSource { .. })));
+    Ok(())
+}
+
+#[test]
+fn test_failed_publication_is_compensated() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    filesystem.create_dir_recursively("/source")?;
+    publish_package(&filesystem, pack_project(&filesystem, "/my-tool", "identifier = \"my-tool\"\nversion = \"1.0.0\"\n")?)?;
+    let tool_path = pack_project(&filesystem, "/my-tool", "identifier = \"my-tool\"\nversion = \"1.1.0\"\n")?;
+
+    for (operation, is_publication) in [("publication", true), ("unpublication", false)] {
+        let source_snapshot = snapshot_directory(&filesystem, "/source")?;
+        let mut faulty_operation = 0;
+        loop {
+            let faulty_filesystem = FaultyFileSystem::new(&filesystem, faulty_operation);
+            let result = if is_publication {
+                publish_package(&faulty_filesystem, tool_path.clone()).map(|_| ())
+            } else {
+                unpublish_package(&faulty_filesystem, "my-tool", "1.0.0")
+            };
+            if !faulty_filesystem.is_fault_injected() {
+                result?;
+                break;
+            }
+            assert!(result.is_err(), "operation {faulty_operation} failure shall fail the {operation}");
+            assert_eq!(snapshot_directory(&filesystem, "/source")?, source_snapshot, "operation {faulty_operation} failure shall be compensated");
+            faulty_operation += 1;
+        }
+        assert!(faulty_operation > 1);
+        assert_ne!(snapshot_directory(&filesystem, "/source")?, source_snapshot);
+    }
+    Ok(())
+}
+
+fn install_package<So: Source>(filesystem: &InMemoryFileSystem, clock: &FixedClock, requirement: &str, labels: &[&str], sources: &[So]) -> Result<Vec<String>> {
+    let request = InstallRequest::new(
+        PackageRequirement::from_str(requirement)?,
+        Absolute::assume_absolute(PathBuf::from("/location")),
+        Absolute::assume_absolute(PathBuf::from("/cache")),
+    )
+    .with_labels(labels.iter().map(ToString::to_string).collect());
+    let locker = InMemoryLocker::default();
+    let operation = Operation::new(request);
+    let location_lock = locker.lock(operation.to_location_lock_path(), Duration::ZERO)?;
+    let operation = operation
+        .lock_location(&location_lock, Duration::ZERO)?
+        .parse_location_lockfile(filesystem, &Json)?
+        .plan_installation(filesystem, sources)?
+        .lock_cache(filesystem, &InMemoryLocker::default(), Duration::ZERO)?
+        .parse_package_cache(filesystem, &Json)?
+        .download_packages(filesystem, sources, clock)?
+        .persist_package_cache(filesystem, &Json)?;
+    let active_deployments: Vec<DeploymentSelector> = operation.as_location().iter_active_deployments().map(DeploymentSelector::from).collect();
+    let mut deployments = Vec::new();
+    for package_path in operation.as_package_paths() {
+        match deploy_package_with_locker(filesystem, &location_lock, Duration::ZERO, package_path.clone(), "/location", Parameters::new(), &RecordingExecutor::default()) {
+            Ok(deployment) => deployments.push(deployment),
+            Err(error) => {
+                undeploy_installed_packages(filesystem, &location_lock, &deployments, &active_deployments)?;
+                return Err(error);
+            }
+        }
+        assert!(locker.lock(operation.to_location_lock_path(), Duration::ZERO).is_err());
+    }
+    Ok(operation.iter_planned_packages().map(|package| format!("{} {}", package.as_identifier(), package.as_version())).collect())
+}
+
+/* Undeploys the packages of a failed installation, then activates again the deployments which were active before it */
+fn undeploy_installed_packages<L: Locker>(filesystem: &InMemoryFileSystem, locker: &L, deployments: &[Deployment], active_deployments: &[DeploymentSelector]) -> Result<()> {
+    for deployment in deployments.iter().rev() {
+        let request = UndeployRequest::new(deployment.as_id().clone(), Absolute::assume_absolute(PathBuf::from("/location")))
+            .with_active_reassignment(true);
+        Operation::new(request)
+            .lock_location(locker, Duration::ZERO)?
+            .parse_location_lockfile(filesystem, &Json)?
+            .probe_package_already_deployed_in_location()?
+            .probe_no_dependent_deployments()?
+            .probe_active_deployment_reassignment()?
+            .guess_deployment_path()
+            .execute_undeploy_hooks(&RecordingExecutor::default())?
+            .remove_deployment_from_location()
+            .persist_location_lockfile(filesystem, &Json)?
+            .generate_location_shims(filesystem)?
+            .delete_deployment_directory(filesystem)?;
+    }
+    let reactivated_deployments = active_deployments.iter()
+        .filter(|selector| deployments.iter().any(|deployment| deployment.as_ref().as_identifier() == selector.as_identifier()));
+    for selector in reactivated_deployments {
+        Operation::new(UseDeploymentRequest::new(Absolute::assume_absolute(PathBuf::from("/location")), selector.clone()))
+            .lock_location(locker, Duration::ZERO)?
+            .parse_location_lockfile(filesystem, &Json)?
+            .activate_deployment()?
+            .persist_location_lockfile(filesystem, &Json)?
+            .generate_location_shims(filesystem)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_install_package_from_sources() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
+    filesystem.create_dir_recursively("/empty")?;
+
+    pack_project(&filesystem, "/library", "identifier = \"library\"\nversion = \"0.1.0\"\nlabels = [\"linux\"]\n")?;
+    pack_project(&filesystem, "/library", "identifier = \"library\"\nversion = \"0.2.0\"\n")?;
+    for (version, labels) in [("1.0.0", "[\"linux\"]"), ("2.0.0", "[\"linux\"]"), ("2.1.0", "[]")] {
+        pack_project(&filesystem, "/my-tool", &format!("identifier = \"my-tool\"\nversion = \"{version}\"\nlabels = {labels}\n\n[dependencies]\nlibrary = \"^0.1\"\n"))?;
+    }
+    let clock = FixedClock::new(1000);
+    let sources = [
+        DirectorySource::new(Absolute::assume_absolute(PathBuf::from("/empty"))),
+        DirectorySource::new(Absolute::assume_absolute(PathBuf::from("/repo"))),
+    ];
+
+    let result = install_package(&filesystem, &clock, "my-tool@^3", &[], &sources);
+    assert!(matches!(result, Err(Error::PackageNotFoundInSource { source, .. }) if source == "/empty, /repo"));
+    let result = install_package(&filesystem, &clock, "my-tool@^2", &[], &[] as &[DirectorySource]);
+    assert!(matches!(result, Err(Error::NoSourceRegistered)));
+
+    assert_eq!(install_package(&filesystem, &clock, "my-tool@^2", &["linux"], &sources)?, ["library 0.1.0", "my-tool 2.0.0"]);
+    assert_eq!(read_package_cache(&filesystem)?.iter_packages().count(), 2);
+    let lockfile: serde_json::Value = serde_json::from_str(&filesystem.read_to_string("/location/packster.lock")?).unwrap();
+    assert_eq!(lockfile["deployments"][0]["version"], "0.1.0");
+    assert_eq!(lockfile["deployments"][1]["version"], "2.0.0");
+
+    /* Dependencies already deployed in the location are not installed again */
+    assert_eq!(install_package(&filesystem, &clock, "my-tool@^2.1", &[], &sources)?, ["my-tool 2.1.0"]);
+    Ok(())
+}
+
+#[test]
+fn test_failed_install_restores_location() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    Operation::new(InitLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location"))))
+        .create_location_directory(&filesystem)?
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .initialize_lockfile(&filesystem, &Json)?;
+
+    let library_path = pack_project(&filesystem, "/library", "identifier = \"library\"\nversion = \"0.1.0\"\n")?;
+    pack_project(&filesystem, "/library", "identifier = \"library\"\nversion = \"0.2.0\"\n")?;
+    let server_path = pack_project(&filesystem, "/server", "identifier = \"server\"\nversion = \"1.0.0\"\n\n[provides]\nports = [8080]\n")?;
+    pack_project(&filesystem, "/my-tool", indoc! {r#"
+        identifier = "my-tool"
+        version = "1.0.0"
+
+        [dependencies]
+        library = "^0.2"
+
+        [provides]
+        ports = [8080]
+    "#})?;
+    deploy_package(&filesystem, library_path, "/location")?;
+    deploy_package(&filesystem, server_path, "/location")?;
+    let lockfile = filesystem.read_to_string("/location/packster.lock")?;
+
+    /* The newer library deployed for the installation is undeployed once the installed package conflicts with the server */
+    let sources = [DirectorySource::new(Absolute::assume_absolute(PathBuf::from("/repo")))];
+    let result = install_package(&filesystem, &FixedClock::new(1000), "my-tool@^1", &[], &sources);
+    assert!(matches!(result, Err(Error::ResourceConflict { .. })));
+    assert_eq!(filesystem.read_to_string("/location/packster.lock")?, lockfile);
+    assert_eq!(verify_location(&filesystem, "/location")?.len(), 0);
+    Ok(())
+}
+
+fn read_package_cache(filesystem: &InMemoryFileSystem) -> Result<PackageCache> {
+    let operation = Operation::new(PackageCacheRequest::new(Absolute::assume_absolute(PathBuf::from("/cache"))))
+        .parse_package_cache(filesystem, &Json)?;
+    Ok(operation.as_package_cache().clone())
+}
+
+fn cache_package(filesystem: &InMemoryFileSystem, clock: &FixedClock, package_path: &Absolute<PathBuf>) -> Result<(Absolute<PathBuf>, bool)> {
+    let operation = Operation::new(CachePackageRequest::new(package_path.clone(), Absolute::assume_absolute(PathBuf::from("/cache"))))
+        .parse_package_metadata(filesystem, &Json)?
+        .lock_cache(filesystem, &InMemoryLocker::default(), Duration::ZERO)?
+        .parse_package_cache(filesystem, &Json)?
+        .store_package(filesystem, &Sha2Digester::Sha256, clock)?
+        .persist_package_cache(filesystem, &Json)?;
+    Ok((operation.as_cached_path().to_absolute_path(), operation.is_already_cached()))
+}
+
+#[test]
+fn test_package_cache_reuse() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    let clock = FixedClock::new(1000);
+    let deployment_id = deploy_tool(&filesystem)?;
+    let package_path = Absolute::assume_absolute(filesystem.walk(Path::new("/repo")).next().unwrap()?.as_path().to_path_buf());
+
+    let (cached_path, is_already_cached) = cache_package(&filesystem, &clock, &package_path)?;
+    assert!(!is_already_cached);
+    assert_eq!(cached_path.to_path_buf(), Path::new("/cache").join(deployment_id.to_string()).join(package_path.file_name().unwrap()));
+    clock.advance(500);
+    assert_eq!(cache_package(&filesystem, &clock, &package_path)?, (cached_path.clone(), true));
+    assert_eq!(read_package_cache(&filesystem)?.find(&deployment_id).map(CachedPackage::as_last_used_at), Some(1500));
+
+    /* The source index still lists the package, whose file is gone */
+    index_source(&filesystem)?;
+    filesystem.remove_file(&package_path)?;
+
+    filesystem.remove_dir_all(format!("/location/{deployment_id}"))?;
+    let request = RepairLocationRequest::new(Absolute::assume_absolute(PathBuf::from("/location")), Vec::new())
+        .with_cache_directory(Absolute::assume_absolute(PathBuf::from("/cache")));
+    let operation = Operation::new(request)
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .parse_location_lockfile(&filesystem, &Json)?
+        .find_damaged_deployments(&filesystem, &Json, &Sha2Digester::Sha256)?;
+    assert_eq!(operation.as_repairs()[0].as_package_path().to_path_buf(), cached_path.to_path_buf());
+    operation
+        .repair_deployments(&filesystem, &TarballArchiver, &Sha2Digester::Sha256)?
+        .persist_location_lockfile(&filesystem, &Json)?;
+    assert!(verify_location(&filesystem, "/location")?.is_empty());
+
+    clock.advance(500);
+    let sources = [DirectorySource::new(Absolute::assume_absolute(PathBuf::from("/repo")))];
+    let request = InstallRequest::new(
+        PackageRequirement::from_str("tool@^1")?,
+        Absolute::assume_absolute(PathBuf::from("/location")),
+        Absolute::assume_absolute(PathBuf::from("/cache")),
+    );
+    let operation = Operation::new(request)
+        .lock_location(&InMemoryLocker::default(), Duration::ZERO)?
+        .parse_location_lockfile(&filesystem, &Json)?
+        .plan_installation(&filesystem, &sources)?
+        .lock_cache(&filesystem, &InMemoryLocker::default(), Duration::ZERO)?
+        .parse_package_cache(&filesystem, &Json)?
+        .download_packages(&filesystem, &sources, &clock)?
+        .persist_package_cache(&filesystem, &Json)?;
+    assert_eq!(operation.count_downloaded_packages(), 0);
+    assert_eq!(operation.as_package_paths(), [cached_path]);
+    assert_eq!(read_package_cache(&filesystem)?.find(&deployment_id).map(CachedPackage::as_last_used_at), Some(2000));
+    Ok(())
+}
+
+fn prune_cache(filesystem: &InMemoryFileSystem, clock: &FixedClock, request: PruneCacheRequest) -> Result<Vec<String>> {
+    let operation = Operation::new(request)
+        .lock_cache(filesystem, &InMemoryLocker::default(), Duration::ZERO)?
+        .parse_package_cache(filesystem, &Json)?
+        .select_evicted_packages(filesystem, clock)?
+        .persist_package_cache(filesystem, &Json)?
+        .delete_evicted_packages(filesystem)?;
+    Ok(operation.as_evicted_packages().iter().map(|package| format!("{} {}", package.as_identifier(), package.as_version())).collect())
+}
+
+#[test]
+fn test_locked_cache_refuses_pruning() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    let clock = FixedClock::new(1000);
+    let locker = InMemoryLocker::default();
+    let package_path = pack_project(&filesystem, "/my-tool", "identifier = \"my-tool\"\nversion = \"1.0.0\"\n")?;
+    let cache_request = || CachePackageRequest::new(package_path.clone(), Absolute::assume_absolute(PathBuf::from("/cache")));
+
+    let operation = Operation::new(cache_request())
+        .parse_package_metadata(&filesystem, &Json)?
+        .lock_cache(&filesystem, &locker, Duration::ZERO)?;
+    let prune_request = || PruneCacheRequest::new(Absolute::assume_absolute(PathBuf::from("/cache")));
+    let result = Operation::new(prune_request()).lock_cache(&filesystem, &locker, Duration::from_millis(50));
+    assert!(matches!(result, Err(Error::LocationLocked { .. })));
+
+    let operation = operation
+        .parse_package_cache(&filesystem, &Json)?
+        .store_package(&filesystem, &Sha2Digester::Sha256, &clock)?
+        .persist_package_cache(&filesystem, &Json)?;
+    drop(operation);
+    assert!(!locker.is_locked("/cache/.packster.lck"));
+    let operation = Operation::new(prune_request())
+        .lock_cache(&filesystem, &locker, Duration::ZERO)?
+        .parse_package_cache(&filesystem, &Json)?
+        .select_evicted_packages(&filesystem, &clock)?;
+    assert!(operation.as_orphan_paths().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_package_cache_maintenance() -> Result<()> {
+    let filesystem = InMemoryFileSystem::default();
+    let clock = FixedClock::new(1000);
+    let cache_path = Absolute::assume_absolute(PathBuf::from("/cache"));
+    let package_paths = [
+        pack_project(&filesystem, "/library", "identifier = \"library\"\nversion = \"0.1.0\"\n")?,
+        pack_project(&filesystem, "/my-tool", "identifier = \"my-tool\"\nversion = \"1.0.0\"\n")?,
+        pack_project(&filesystem, "/my-tool", "identifier = \"my-tool\"\nversion = \"1.1.0\"\n")?,
+    ];
+    let mut cached_paths = Vec::new();
+    for package_path in &package_paths {
+        cached_paths.push(cache_package(&filesystem, &clock, package_path)?.0);
+        clock.advance(1000);
+    }
+
+    let verify_cache = || Operation::new(VerifyCacheRequest::new(cache_path.clone()))
+        .lock_cache(&filesystem, &InMemoryLocker::default(), Duration::ZERO)?
+        .parse_package_cache(&filesystem, &Json)?
+        .verify_cached_packages(&filesystem, &Sha2Digester::Sha256);
+    assert!(verify_cache()?.as_issues().is_empty());
+    filesystem.write_all(&cached_paths[0], "tampered")?;
+    filesystem.remove_file(&cached_paths[1])?;
+    let operation = verify_cache()?;
+    assert!(matches!(operation.as_issues(), [CacheIssue::ModifiedFile { .. }, CacheIssue::MissingFile { .. }] | [CacheIssue::MissingFile { .. }, CacheIssue::ModifiedFile { .. }]));
+    operation
+        .persist_package_cache(&filesystem, &Json)?
+        .delete_damaged_packages(&filesystem)?;
+    assert_eq!(read_package_cache(&filesystem)?.iter_packages().count(), 1);
+    assert!(!filesystem.exists(cached_paths[0].parent().unwrap()));
+    assert!(verify_cache()?.as_issues().is_empty());
+
+    /* my-tool 1.1.0 was used at 3000, library at 4000 and my-tool 1.0.0 at 5000 */
+    cache_package(&filesystem, &clock, &package_paths[0])?;
+    clock.advance(1000);
+    cache_package(&filesystem, &clock, &package_paths[1])?;
+    filesystem.create_dir("/cache/0bad")?;
+    filesystem.create_dir("/cache/notes")?;
+
+    let unused_request = || PruneCacheRequest::new(cache_path.clone()).with_unused_for(Duration::from_secs(1500));
+    let operation = Operation::new(unused_request())
+        .lock_cache(&filesystem, &InMemoryLocker::default(), Duration::ZERO)?
+        .parse_package_cache(&filesystem, &Json)?
+        .select_evicted_packages(&filesystem, &clock)?;
+    assert_eq!(operation.as_evicted_packages().len(), 1);
+    assert_eq!(operation.as_orphan_paths().iter().map(|path| path.to_path_buf()).collect::<Vec<_>>(), [PathBuf::from("/cache/0bad")]);
+    assert!(filesystem.is_file(&cached_paths[2]));
+
+    assert_eq!(prune_cache(&filesystem, &clock, unused_request())?, ["my-tool 1.1.0"]);
+    assert!(!filesystem.exists(&cached_paths[2]));
+    assert!(!filesystem.exists("/cache/0bad"));
+    assert!(filesystem.is_directory("/cache/notes"));
+
+    let max_size = filesystem.file_size(&package_paths[1])?;
+    assert_eq!(prune_cache(&filesystem, &clock, PruneCacheRequest::new(cache_path.clone()).with_max_size(max_size))?, ["library 0.1.0"]);
+    assert_eq!(read_package_cache(&filesystem)?.to_size(), max_size);
+    assert!(filesystem.is_file(&cached_paths[1]));
+    Ok(())
+}